@@ -724,7 +724,7 @@ impl<T: Config> Pallet<T> {
         CurrentSlot::<T>::put(pre_digest.slot);
 
         {
-            let farmer_public_key = pre_digest.solution.public_key.clone();
+            let farmer_public_key = pre_digest.solution.public_key().clone();
 
             // Optional restriction for block authoring to the root user
             if !AllowAuthoringByAnyone::<T>::get() {
@@ -748,7 +748,7 @@ impl<T: Config> Pallet<T> {
 
             let key = (
                 farmer_public_key,
-                pre_digest.solution.sector_index,
+                pre_digest.solution.sector_index(),
                 pre_digest.slot,
             );
             if ParentBlockVoters::<T>::get().contains_key(&key) {
@@ -775,7 +775,7 @@ impl<T: Config> Pallet<T> {
                     public_key,
                     sector_index,
                     slot,
-                    pre_digest.solution.reward_address,
+                    pre_digest.solution.reward_address().clone(),
                 ));
             }
         }
@@ -810,9 +810,9 @@ impl<T: Config> Pallet<T> {
         // Extract PoR randomness from pre-digest.
         // Tag signature is validated by the client and is always valid here.
         let por_randomness: Randomness = derive_randomness(
-            &PublicKey::from(&pre_digest.solution.public_key),
-            &pre_digest.solution.chunk,
-            &pre_digest.solution.chunk_signature,
+            &PublicKey::from(pre_digest.solution.public_key()),
+            &pre_digest.solution.chunk(),
+            &pre_digest.solution.chunk_signature(),
         )
         .expect("Tag signature is verified by the client and is always valid; qed");
         // Store PoR randomness for block duration as it might be useful.
@@ -945,12 +945,12 @@ impl<T: Config> Pallet<T> {
             ..
         } = signed_vote.vote;
 
-        if BlockList::<T>::contains_key(&solution.public_key) {
+        if BlockList::<T>::contains_key(solution.public_key()) {
             Err(DispatchError::Other("Equivocated"))
         } else {
             Self::deposit_event(Event::FarmerVote {
-                public_key: solution.public_key,
-                reward_address: solution.reward_address,
+                public_key: solution.public_key().clone(),
+                reward_address: solution.reward_address().clone(),
                 height,
                 parent_hash,
             });
@@ -1006,7 +1006,7 @@ impl<T: Config> Pallet<T> {
     }
 
     pub fn chain_constants() -> ChainConstants {
-        ChainConstants::V0 {
+        ChainConstants::V1 {
             confirmation_depth_k: T::ConfirmationDepthK::get()
                 .try_into()
                 .unwrap_or_else(|_| panic!("Block number always fits in BlockNumber; qed")),
@@ -1017,6 +1017,7 @@ impl<T: Config> Pallet<T> {
                 .try_into()
                 .unwrap_or_else(|_| panic!("Block number always fits in BlockNumber; qed")),
             slot_probability: T::SlotProbability::get(),
+            kzg_public_parameters: kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO,
         }
     }
 }
@@ -1203,7 +1204,7 @@ fn check_vote<T: Config>(
     let height = *height;
     let slot = *slot;
 
-    if BlockList::<T>::contains_key(&solution.public_key) {
+    if BlockList::<T>::contains_key(solution.public_key()) {
         return Err(CheckVoteError::BlockListed);
     }
 
@@ -1295,7 +1296,7 @@ fn check_vote<T: Config>(
     if let Err(error) = check_reward_signature(
         signed_vote.vote.hash().as_bytes(),
         &RewardSignature::from(&signed_vote.signature),
-        &PublicKey::from(&solution.public_key),
+        &PublicKey::from(solution.public_key()),
         &schnorrkel::signing_context(REWARD_SIGNING_CONTEXT),
     ) {
         debug!(
@@ -1311,9 +1312,10 @@ fn check_vote<T: Config>(
         parent_vote_verification_data
     };
 
-    let sector_id = SectorId::new(&(&solution.public_key).into(), solution.sector_index);
+    let sector_id = SectorId::new(&solution.public_key().into(), solution.sector_index());
 
-    let piece_index = sector_id.derive_piece_index(solution.piece_offset, solution.total_pieces);
+    let piece_index =
+        sector_id.derive_piece_index(solution.piece_offset(), solution.total_pieces());
     let pieces_in_segment = vote_verification_data.pieces_in_segment;
     let position = u32::try_from(piece_index % u64::from(pieces_in_segment))
         .expect("Position within segment always fits into u32; qed");
@@ -1329,7 +1331,7 @@ fn check_vote<T: Config>(
         return Err(CheckVoteError::UnknownRecordsRoot);
     };
 
-    let kzg = Kzg::new(kzg::test_public_parameters());
+    let kzg = Kzg::new(kzg::embedded_public_parameters_unchecked());
 
     if let Err(error) = verify_solution::<FarmerPublicKey, T::AccountId>(
         solution,
@@ -1352,7 +1354,7 @@ fn check_vote<T: Config>(
         return Err(CheckVoteError::InvalidSolution(error));
     }
 
-    let key = (solution.public_key.clone(), solution.sector_index, slot);
+    let key = (solution.public_key().clone(), solution.sector_index(), slot);
     // Check that farmer didn't use solution from this vote yet in:
     // * parent block
     // * current block
@@ -1415,7 +1417,7 @@ fn check_vote<T: Config>(
                 .insert(
                     key,
                     (
-                        solution.reward_address.clone(),
+                        solution.reward_address().clone(),
                         signed_vote.signature.clone(),
                     ),
                 );
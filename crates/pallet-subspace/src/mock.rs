@@ -40,7 +40,7 @@ use subspace_core_primitives::crypto::kzg::{Kzg, Witness};
 use subspace_core_primitives::crypto::{blake2b_256_254_hash, kzg};
 use subspace_core_primitives::{
     ArchivedBlockProgress, Blake2b256Hash, Chunk, LastArchivedBlock, Piece, Randomness,
-    RecordsRoot, RootBlock, SegmentIndex, Solution, SolutionRange, PIECE_SIZE,
+    RecordsRoot, RootBlock, SegmentIndex, Solution, SolutionRange, SolutionV0, PIECE_SIZE,
     RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
 };
 use subspace_solving::{create_chunk_signature, derive_global_challenge, REWARD_SIGNING_CONTEXT};
@@ -188,7 +188,7 @@ pub fn go_to_block(
 
     let pre_digest = make_pre_digest(
         slot.into(),
-        Solution {
+        Solution::V0(SolutionV0 {
             public_key: FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
             reward_address,
             sector_index: 0,
@@ -198,7 +198,7 @@ pub fn go_to_block(
             piece_witness: Default::default(),
             chunk,
             chunk_signature: create_chunk_signature(keypair, &chunk),
-        },
+        }),
     );
 
     System::reset_events();
@@ -266,7 +266,7 @@ pub fn generate_equivocation_proof(
         let parent_hash = System::parent_hash();
         let pre_digest = make_pre_digest(
             slot,
-            Solution {
+            Solution::V0(SolutionV0 {
                 public_key: public_key.clone(),
                 reward_address,
                 sector_index: 0,
@@ -276,7 +276,7 @@ pub fn generate_equivocation_proof(
                 piece_witness: Default::default(),
                 chunk,
                 chunk_signature: create_chunk_signature(keypair, &chunk),
-            },
+            }),
         );
         System::reset_events();
         System::initialize(&current_block, &parent_hash, &pre_digest);
@@ -365,7 +365,7 @@ pub fn create_signed_vote(
         height,
         parent_hash,
         slot,
-        solution: Solution {
+        solution: Solution::V0(SolutionV0 {
             public_key: FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
             reward_address,
             sector_index: 0,
@@ -378,7 +378,7 @@ pub fn create_signed_vote(
             .unwrap(),
             chunk,
             chunk_signature: create_chunk_signature(keypair, &chunk),
-        },
+        }),
     };
 
     let signature = FarmerSignature::unchecked_from(
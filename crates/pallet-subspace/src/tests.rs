@@ -43,7 +43,7 @@ use sp_runtime::transaction_validity::{
 use sp_runtime::DispatchError;
 use std::assert_matches::assert_matches;
 use std::collections::BTreeMap;
-use subspace_core_primitives::Piece;
+use subspace_core_primitives::{Piece, Solution};
 use subspace_runtime_primitives::{FindBlockRewardAddress, FindVotingRewardAddresses};
 use subspace_solving::REWARD_SIGNING_CONTEXT;
 use subspace_verification::Error as VerificationError;
@@ -1042,6 +1042,7 @@ fn vote_invalid_solution_signature() {
         );
 
         let Vote::V0 { solution, .. } = &mut signed_vote.vote;
+        let Solution::V0(solution) = solution;
         solution.chunk_signature.output = rand::random();
 
         // Fix signed vote signature after changed contents
@@ -1282,7 +1283,7 @@ fn vote_equivocation_current_voters_duplicate() {
             map.insert(
                 (
                     FarmerPublicKey::unchecked_from(voter_keypair.public.to_bytes()),
-                    signed_vote.vote.solution().sector_index,
+                    signed_vote.vote.solution().sector_index(),
                     slot,
                 ),
                 (reward_address, signed_vote.signature.clone()),
@@ -1301,7 +1302,7 @@ fn vote_equivocation_current_voters_duplicate() {
             map.insert(
                 (
                     FarmerPublicKey::unchecked_from(voter_keypair.public.to_bytes()),
-                    signed_vote.vote.solution().sector_index,
+                    signed_vote.vote.solution().sector_index(),
                     slot,
                 ),
                 (reward_address, FarmerSignature::unchecked_from([0; 64])),
@@ -1359,7 +1360,7 @@ fn vote_equivocation_parent_voters_duplicate() {
             map.insert(
                 (
                     FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
-                    signed_vote.vote.solution().sector_index,
+                    signed_vote.vote.solution().sector_index(),
                     slot,
                 ),
                 (reward_address, signed_vote.signature.clone()),
@@ -1378,7 +1379,7 @@ fn vote_equivocation_parent_voters_duplicate() {
             map.insert(
                 (
                     FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
-                    signed_vote.vote.solution().sector_index,
+                    signed_vote.vote.solution().sector_index(),
                     slot,
                 ),
                 (reward_address, FarmerSignature::unchecked_from([0; 64])),
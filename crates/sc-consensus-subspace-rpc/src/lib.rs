@@ -48,8 +48,8 @@ use std::sync::Arc;
 use std::time::Duration;
 use subspace_archiving::archiver::ArchivedSegment;
 use subspace_core_primitives::{
-    Piece, PieceIndex, RecordsRoot, SegmentIndex, Solution, RECORDED_HISTORY_SEGMENT_SIZE,
-    RECORD_SIZE,
+    Piece, PieceIndex, RecordsRoot, SegmentIndex, Solution, SolutionV0,
+    RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
 };
 use subspace_rpc_primitives::{
     FarmerProtocolInfo, RewardSignatureResponse, RewardSigningInfo, SlotInfo, SolutionResponse,
@@ -205,6 +205,9 @@ where
                 space_l: NonZeroU16::new(20).expect("Not zero; qed"),
                 // TODO: Fetch this from the runtime
                 sector_expiration: 100,
+                kzg_public_parameters: runtime_api
+                    .chain_constants(&best_block_id)?
+                    .kzg_public_parameters(),
             }
         };
 
@@ -264,22 +267,27 @@ where
                     let forward_solution_fut = async move {
                         if let Ok(solution_response) = response_receiver.await {
                             for solution in solution_response.solutions {
-                                let public_key = FarmerPublicKey::from_slice(&solution.public_key)
-                                    .expect("Always correct length; qed");
-                                let reward_address =
-                                    FarmerPublicKey::from_slice(&solution.reward_address)
-                                        .expect("Always correct length; qed");
-
-                                let solution = Solution {
-                                    public_key,
-                                    reward_address,
-                                    sector_index: solution.sector_index,
-                                    total_pieces: solution.total_pieces,
-                                    piece_offset: solution.piece_offset,
-                                    piece_record_hash: solution.piece_record_hash,
-                                    piece_witness: solution.piece_witness,
-                                    chunk: solution.chunk,
-                                    chunk_signature: solution.chunk_signature,
+                                let solution = match solution {
+                                    Solution::V0(solution) => {
+                                        let public_key =
+                                            FarmerPublicKey::from_slice(&solution.public_key)
+                                                .expect("Always correct length; qed");
+                                        let reward_address =
+                                            FarmerPublicKey::from_slice(&solution.reward_address)
+                                                .expect("Always correct length; qed");
+
+                                        Solution::V0(SolutionV0 {
+                                            public_key,
+                                            reward_address,
+                                            sector_index: solution.sector_index,
+                                            total_pieces: solution.total_pieces,
+                                            piece_offset: solution.piece_offset,
+                                            piece_record_hash: solution.piece_record_hash,
+                                            piece_witness: solution.piece_witness,
+                                            chunk: solution.chunk,
+                                            chunk_signature: solution.chunk_signature,
+                                        })
+                                    }
                                 };
 
                                 let _ = solution_sender.send(solution).await;
@@ -14,9 +14,13 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
+#[cfg(test)]
+mod tests;
+
+use crate::aux_schema::ArchiverCheckpoint;
 use crate::{
-    get_chain_constants, ArchivedSegmentNotification, ImportedBlockNotification, SubspaceLink,
-    SubspaceNotificationSender,
+    aux_schema, get_chain_constants, ArchivedSegmentNotification, ImportedBlockNotification,
+    SubspaceLink, SubspaceNotificationSender,
 };
 use codec::Encode;
 use futures::{SinkExt, StreamExt};
@@ -38,6 +42,198 @@ use subspace_core_primitives::{
     BlockNumber, RootBlock, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
 };
 
+/// Number of the block where root blocks produced by archiving of the block with `block_number` are
+/// expected to be included.
+fn root_blocks_inclusion_block_number<Block>(
+    block_number: NumberFor<Block>,
+    confirmation_depth_k: BlockNumber,
+) -> NumberFor<Block>
+where
+    Block: BlockT,
+{
+    if block_number.is_zero() {
+        // Special case for genesis block whose root block should be included in the first block in
+        // order for further validation to work properly.
+        One::one()
+    } else {
+        block_number + confirmation_depth_k.into() + One::one()
+    }
+}
+
+/// Get object mappings of the block.
+fn block_object_mappings<Block, Client>(
+    client: &Client,
+    block: &SignedBlock<Block>,
+) -> BlockObjectMapping
+where
+    Block: BlockT,
+    Client: ProvideRuntimeApi<Block>,
+    Client::Api: ObjectsApi<Block>,
+{
+    client
+        .runtime_api()
+        .validated_object_call_hashes(&BlockId::Hash(block.block.hash()))
+        .and_then(|calls| {
+            client.runtime_api().extract_block_object_mapping(
+                &BlockId::Hash(*block.block.header().parent_hash()),
+                block.block.clone(),
+                calls,
+            )
+        })
+        .unwrap_or_default()
+}
+
+/// Load archiver checkpoint from aux storage.
+///
+/// Returns `None` if there is no checkpoint or it is not consistent with the current state of the
+/// blockchain, in which case the blockchain needs to be searched for the last archived block
+/// instead (see [`find_last_archived_block()`]).
+fn load_archiver_checkpoint<Block, Client>(
+    client: &Client,
+    best_block_id: BlockId<Block>,
+) -> Option<ArchiverCheckpoint<Block::Hash, NumberFor<Block>>>
+where
+    Block: BlockT,
+    Client: ProvideRuntimeApi<Block> + HeaderBackend<Block> + AuxStore,
+    Client::Api: SubspaceApi<Block, FarmerPublicKey>,
+{
+    let archiver_checkpoint =
+        match aux_schema::load_archiver_checkpoint::<Block::Hash, NumberFor<Block>, _>(client) {
+            Ok(Some(archiver_checkpoint)) => archiver_checkpoint,
+            Ok(None) => {
+                return None;
+            }
+            Err(error) => {
+                warn!(
+                    target: "subspace",
+                    "Failed to load archiver checkpoint, ignoring: {}",
+                    error,
+                );
+                return None;
+            }
+        };
+
+    let segment_index = archiver_checkpoint.archiver_state.segment_index();
+
+    // Checkpoint must correspond to the last root block included in the blockchain, otherwise
+    // segments that follow it (and were not yet confirmed) will not be expected by block import
+    // after restart
+    let last_records_root_matches = match archiver_checkpoint.last_root_block {
+        Some(last_root_block) if last_root_block.segment_index() + 1 == segment_index => client
+            .runtime_api()
+            .records_root(&best_block_id, last_root_block.segment_index())
+            .map(|records_root| records_root == Some(last_root_block.records_root())),
+        None if segment_index == 0 => Ok(true),
+        _ => {
+            warn!(
+                target: "subspace",
+                "Archiver checkpoint root block doesn't match archiver state with segment index {}, \
+                ignoring",
+                segment_index,
+            );
+            return None;
+        }
+    };
+    let next_records_root = client
+        .runtime_api()
+        .records_root(&best_block_id, segment_index);
+    match (last_records_root_matches, next_records_root) {
+        (Ok(true), Ok(None)) => {
+            // Root block is the last one in the blockchain
+        }
+        (Ok(_), Ok(_)) => {
+            warn!(
+                target: "subspace",
+                "Archiver checkpoint root block before segment {} doesn't match the last root \
+                block in the blockchain, ignoring",
+                segment_index,
+            );
+            return None;
+        }
+        (Err(error), _) | (_, Err(error)) => {
+            warn!(
+                target: "subspace",
+                "Failed to check archiver checkpoint root block before segment {}, ignoring: {}",
+                segment_index,
+                error,
+            );
+            return None;
+        }
+    }
+
+    let (best_archived_block_hash, best_archived_block_number) =
+        archiver_checkpoint.best_archived_block;
+
+    match client.hash(best_archived_block_number) {
+        Ok(Some(block_hash)) if block_hash == best_archived_block_hash => {
+            // Best archived block is in canonical chain
+        }
+        Ok(_) => {
+            warn!(
+                target: "subspace",
+                "Archiver checkpoint block {} ({}) is not in canonical chain, ignoring",
+                best_archived_block_number,
+                best_archived_block_hash,
+            );
+            return None;
+        }
+        Err(error) => {
+            warn!(
+                target: "subspace",
+                "Failed to check archiver checkpoint block {} ({}), ignoring: {}",
+                best_archived_block_number,
+                best_archived_block_hash,
+                error,
+            );
+            return None;
+        }
+    }
+
+    Some(archiver_checkpoint)
+}
+
+/// Write archiver checkpoint to aux storage, failure to do so is not critical and will only result
+/// in searching the blockchain for the last archived block on restart.
+///
+/// Checkpoint is supposed to be written after every block added to the archiver,
+/// `best_archived_block` is the hash and number of that block.
+fn write_archiver_checkpoint<Block, Client>(
+    client: &Client,
+    archiver: &Archiver,
+    last_root_block: Option<RootBlock>,
+    best_archived_block: (Block::Hash, NumberFor<Block>),
+) where
+    Block: BlockT,
+    Client: AuxStore,
+{
+    let archiver_checkpoint = ArchiverCheckpoint {
+        archiver_state: archiver.export_state(),
+        last_root_block,
+        best_archived_block,
+    };
+
+    let result = aux_schema::write_archiver_checkpoint(&archiver_checkpoint, |values| {
+        client.insert_aux(
+            &values
+                .iter()
+                .map(|(key, value)| (key.as_slice(), *value))
+                .collect::<Vec<_>>(),
+            &[],
+        )
+    });
+
+    if let Err(error) = result {
+        let (best_archived_block_hash, best_archived_block_number) = best_archived_block;
+        warn!(
+            target: "subspace",
+            "Failed to write archiver checkpoint at block {} ({}): {}",
+            best_archived_block_number,
+            best_archived_block_hash,
+            error,
+        );
+    }
+}
+
 fn find_last_archived_block<Block, Client>(
     client: &Client,
     best_block_id: BlockId<Block>,
@@ -100,19 +296,7 @@ where
         block_to_check = BlockId::Hash(*block.block.header().parent_hash());
     };
 
-    let last_archived_block_hash = block_to_check;
-
-    let block_object_mappings = client
-        .runtime_api()
-        .validated_object_call_hashes(&last_archived_block_hash)
-        .and_then(|calls| {
-            client.runtime_api().extract_block_object_mapping(
-                &BlockId::Hash(*last_archived_block.block.header().parent_hash()),
-                last_archived_block.block.clone(),
-                calls,
-            )
-        })
-        .unwrap_or_default();
+    let block_object_mappings = block_object_mappings(client, &last_archived_block);
 
     Some((last_root_block, last_archived_block, block_object_mappings))
 }
@@ -175,6 +359,7 @@ where
     archiver: Archiver,
     older_archived_segments: Vec<ArchivedSegment>,
     best_archived_block: (Block::Hash, NumberFor<Block>),
+    last_root_block: Option<RootBlock>,
 }
 
 fn initialize_archiver<Block, Client>(
@@ -194,15 +379,38 @@ where
         .expect("Must always be able to get chain constants")
         .confirmation_depth_k();
 
-    let maybe_last_archived_block = find_last_archived_block(client, best_block_id);
-    let have_last_root_block = maybe_last_archived_block.is_some();
     let mut best_archived_block = None;
+    let mut last_root_block = None;
+
+    // Checkpoint allows to restore archiver directly, only blocks imported after it was written
+    // need to be archived below
+    let mut archiver = if let Some(ArchiverCheckpoint {
+        archiver_state,
+        last_root_block: checkpoint_last_root_block,
+        best_archived_block: checkpoint_best_archived_block,
+    }) = load_archiver_checkpoint(client, best_block_id)
+    {
+        info!(
+            target: "subspace",
+            "Restoring archiver from checkpoint at block {}",
+            checkpoint_best_archived_block.1,
+        );
+
+        best_archived_block.replace(checkpoint_best_archived_block);
+        last_root_block = checkpoint_last_root_block;
 
-    let mut archiver = if let Some((last_root_block, last_archived_block, block_object_mappings)) =
-        maybe_last_archived_block
+        Archiver::from_exported_state(
+            RECORD_SIZE,
+            RECORDED_HISTORY_SEGMENT_SIZE,
+            kzg,
+            archiver_state,
+        )
+        .expect("Incorrect parameters for archiver")
+    } else if let Some((last_root_block_found, last_archived_block, block_object_mappings)) =
+        find_last_archived_block(client, best_block_id)
     {
         // Continuing from existing initial state
-        let last_archived_block_number = last_root_block.last_archived_block().number;
+        let last_archived_block_number = last_root_block_found.last_archived_block().number;
         info!(
             target: "subspace",
             "Last archived block {}",
@@ -215,12 +423,13 @@ where
             last_archived_block.block.hash(),
             *last_archived_block.block.header().number(),
         ));
+        last_root_block.replace(last_root_block_found);
 
         Archiver::with_initial_state(
             RECORD_SIZE,
             RECORDED_HISTORY_SEGMENT_SIZE,
             kzg,
-            last_root_block,
+            last_root_block_found,
             &last_archived_block.encode(),
             block_object_mappings,
         )
@@ -231,15 +440,15 @@ where
         Archiver::new(RECORD_SIZE, RECORDED_HISTORY_SEGMENT_SIZE, kzg)
             .expect("Incorrect parameters for archiver")
     };
+    let is_continuation = best_archived_block.is_some();
 
     let mut older_archived_segments = Vec::new();
 
-    // Process blocks since last fully archived block (or genesis) up to the current head minus K
+    // Process blocks since last archived block (or genesis) up to the current head minus K
     {
-        let blocks_to_archive_from = archiver
-            .last_archived_block_number()
-            .map(|n| n + 1)
-            .unwrap_or_default();
+        let blocks_to_archive_from = best_archived_block
+            .map(|(_block_hash, block_number)| block_number + One::one())
+            .unwrap_or_else(Zero::zero);
         let blocks_to_archive_to = TryInto::<BlockNumber>::try_into(best_block_number)
             .unwrap_or_else(|_| {
                 panic!(
@@ -249,13 +458,15 @@ where
             })
             .checked_sub(confirmation_depth_k)
             .or({
-                if have_last_root_block {
+                if is_continuation {
                     None
                 } else {
                     // If not continuation, archive genesis block
                     Some(0)
                 }
-            });
+            })
+            .map(NumberFor::<Block>::from)
+            .filter(|blocks_to_archive_to| *blocks_to_archive_to >= blocks_to_archive_from);
 
         if let Some(blocks_to_archive_to) = blocks_to_archive_to {
             info!(
@@ -268,8 +479,8 @@ where
             let block_hashes_to_archive = block_hashes_to_archive(
                 client,
                 best_block_hash,
-                blocks_to_archive_from.into(),
-                blocks_to_archive_to.into(),
+                blocks_to_archive_from,
+                blocks_to_archive_to,
             );
             best_archived_block = block_hashes_to_archive.best_archived;
             let block_hashes_to_archive = block_hashes_to_archive.block_hashes;
@@ -308,23 +519,35 @@ where
                     .map(|archived_segment| archived_segment.root_block)
                     .collect();
 
+                if let Some(root_block) = new_root_blocks.last() {
+                    last_root_block.replace(*root_block);
+                }
+
                 older_archived_segments.extend(archived_segments);
 
                 if !new_root_blocks.is_empty() {
                     // Set list of expected root blocks for the block where we expect root block
                     // extrinsic to be included
                     subspace_link.root_blocks.lock().put(
-                        if block_number_to_archive.is_zero() {
-                            // Special case for genesis block whose root block should be included in
-                            // the first block in order for further validation to work properly.
-                            One::one()
-                        } else {
-                            block_number_to_archive + confirmation_depth_k.into() + One::one()
-                        },
+                        root_blocks_inclusion_block_number::<Block>(
+                            block_number_to_archive,
+                            confirmation_depth_k,
+                        ),
                         new_root_blocks,
                     );
                 }
             }
+
+            // Checkpoint is written once all already produced blocks were added rather than after
+            // each of them to avoid writing the whole archiver state many times in a row
+            if let Some(best_archived_block) = best_archived_block {
+                write_archiver_checkpoint::<Block, _>(
+                    client,
+                    &archiver,
+                    last_root_block,
+                    best_archived_block,
+                );
+            }
         }
     }
 
@@ -334,6 +557,7 @@ where
         older_archived_segments,
         best_archived_block: best_archived_block
             .expect("Must always set if there is no logical error; qed"),
+        last_root_block,
     }
 }
 
@@ -404,6 +628,7 @@ pub fn start_subspace_archiver<Block, Backend, Client>(
         mut archiver,
         older_archived_segments,
         best_archived_block: (mut best_archived_block_hash, mut best_archived_block_number),
+        mut last_root_block,
     } = initialize_archiver(
         best_block_hash,
         best_block_number,
@@ -508,6 +733,7 @@ pub fn start_subspace_archiver<Block, Backend, Client>(
                     for archived_segment in archiver.add_block(encoded_block, block_object_mappings)
                     {
                         let root_block = archived_segment.root_block;
+                        last_root_block.replace(root_block);
 
                         if is_authoring_blocks {
                             send_archived_segment_notification(
@@ -520,6 +746,13 @@ pub fn start_subspace_archiver<Block, Backend, Client>(
                         let _ = root_block_sender.send(root_block).await;
                     }
 
+                    write_archiver_checkpoint::<Block, _>(
+                        client.as_ref(),
+                        &archiver,
+                        last_root_block,
+                        (block_hash_to_archive, block_number_to_archive),
+                    );
+
                     finalize_block(
                         client.as_ref(),
                         telemetry.clone(),
@@ -0,0 +1,217 @@
+use crate::archiver::{load_archiver_checkpoint, write_archiver_checkpoint};
+use codec::Encode;
+use sc_client_api::AuxStore;
+use sp_blockchain::HeaderBackend;
+use sp_core::hashing::{twox_128, twox_64};
+use sp_runtime::generic::BlockId;
+use subspace_archiving::archiver::Archiver;
+use subspace_core_primitives::crypto::kzg;
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::objects::BlockObjectMapping;
+use subspace_core_primitives::{
+    ArchivedBlockProgress, Blake2b256Hash, LastArchivedBlock, RecordsRoot, RootBlock, SegmentIndex,
+    RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
+};
+use substrate_test_runtime::{Block as TestBlock, Hash};
+use substrate_test_runtime_client::{
+    DefaultTestClientBuilderExt, TestClient, TestClientBuilder, TestClientBuilderExt,
+};
+
+fn records_root(seed: u8) -> RecordsRoot {
+    let kzg = Kzg::new(kzg::test_public_parameters());
+    let mut data = [0u8; 64];
+    data[0] = seed;
+    kzg.commit(&kzg.poly(&data).unwrap()).unwrap()
+}
+
+/// Storage key under which `pallet-subspace` stores records root of the segment.
+fn records_root_storage_key(segment_index: SegmentIndex) -> Vec<u8> {
+    let encoded_segment_index = segment_index.encode();
+
+    let mut key = twox_128(b"Subspace").to_vec();
+    key.extend_from_slice(&twox_128(b"RecordsRoot"));
+    key.extend_from_slice(&twox_64(&encoded_segment_index));
+    key.extend_from_slice(&encoded_segment_index);
+    key
+}
+
+fn genesis_root_block(records_root: RecordsRoot) -> RootBlock {
+    RootBlock::V0 {
+        segment_index: 0,
+        records_root,
+        prev_root_block_hash: Blake2b256Hash::default(),
+        last_archived_block: LastArchivedBlock {
+            number: 0,
+            archived_progress: ArchivedBlockProgress::Complete,
+        },
+    }
+}
+
+/// Create client with records roots of provided segments included in the blockchain.
+fn create_client(records_roots: &[(SegmentIndex, RecordsRoot)]) -> TestClient {
+    records_roots
+        .iter()
+        .fold(
+            TestClientBuilder::new(),
+            |builder, (segment_index, records_root)| {
+                builder.add_extra_storage(
+                    records_root_storage_key(*segment_index),
+                    records_root.encode(),
+                )
+            },
+        )
+        .build()
+}
+
+fn archiver(last_root_block: Option<RootBlock>) -> Archiver {
+    let kzg = Kzg::new(kzg::test_public_parameters());
+
+    match last_root_block {
+        Some(last_root_block) => Archiver::with_initial_state(
+            RECORD_SIZE,
+            RECORDED_HISTORY_SEGMENT_SIZE,
+            kzg,
+            last_root_block,
+            &[],
+            BlockObjectMapping::default(),
+        )
+        .unwrap(),
+        None => Archiver::new(RECORD_SIZE, RECORDED_HISTORY_SEGMENT_SIZE, kzg).unwrap(),
+    }
+}
+
+fn write(client: &TestClient, last_root_block: Option<RootBlock>, best_archived_block_hash: Hash) {
+    write_archiver_checkpoint::<TestBlock, _>(
+        client,
+        &archiver(last_root_block),
+        last_root_block,
+        (best_archived_block_hash, 0),
+    );
+}
+
+fn load(client: &TestClient) -> Option<Option<RootBlock>> {
+    let best_block_id = BlockId::Hash(client.info().best_hash);
+
+    load_archiver_checkpoint::<TestBlock, _>(client, best_block_id)
+        .map(|archiver_checkpoint| archiver_checkpoint.last_root_block)
+}
+
+#[test]
+fn restores_from_checkpoint() {
+    let root_block = genesis_root_block(records_root(1));
+    let client = create_client(&[(0, root_block.records_root())]);
+    let genesis_hash = client.info().genesis_hash;
+
+    assert_eq!(load(&client), None);
+
+    write(&client, Some(root_block), genesis_hash);
+
+    let archiver_checkpoint =
+        load_archiver_checkpoint::<TestBlock, _>(&client, BlockId::Hash(genesis_hash)).unwrap();
+    assert_eq!(archiver_checkpoint.last_root_block, Some(root_block));
+    assert_eq!(archiver_checkpoint.best_archived_block, (genesis_hash, 0));
+    assert_eq!(
+        archiver_checkpoint.archiver_state,
+        archiver(Some(root_block)).export_state()
+    );
+}
+
+#[test]
+fn restores_from_checkpoint_without_segments() {
+    let client = create_client(&[]);
+    let genesis_hash = client.info().genesis_hash;
+
+    write(&client, None, genesis_hash);
+
+    assert_eq!(load(&client), Some(None));
+}
+
+#[test]
+fn ignores_corrupted_checkpoint() {
+    let root_block = genesis_root_block(records_root(1));
+    let client = create_client(&[(0, root_block.records_root())]);
+
+    client
+        .insert_aux(
+            &[(b"archiver_checkpoint".encode().as_slice(), &[1, 2, 3][..])],
+            &[],
+        )
+        .unwrap();
+
+    assert_eq!(load(&client), None);
+}
+
+#[test]
+fn ignores_checkpoint_with_unknown_block() {
+    let root_block = genesis_root_block(records_root(1));
+    let client = create_client(&[(0, root_block.records_root())]);
+
+    write(&client, Some(root_block), Hash::repeat_byte(1));
+
+    assert_eq!(load(&client), None);
+}
+
+#[test]
+fn ignores_checkpoint_with_inconsistent_archiver_state() {
+    let root_block = genesis_root_block(records_root(1));
+    let client = create_client(&[(0, root_block.records_root())]);
+    let genesis_hash = client.info().genesis_hash;
+
+    // Archiver state doesn't know about root block written alongside it
+    write_archiver_checkpoint::<TestBlock, _>(
+        &client,
+        &archiver(None),
+        Some(root_block),
+        (genesis_hash, 0),
+    );
+
+    assert_eq!(load(&client), None);
+}
+
+#[test]
+fn ignores_checkpoint_with_different_records_root() {
+    let client = create_client(&[(0, records_root(1))]);
+    let genesis_hash = client.info().genesis_hash;
+
+    write(
+        &client,
+        Some(genesis_root_block(records_root(2))),
+        genesis_hash,
+    );
+
+    assert_eq!(load(&client), None);
+}
+
+#[test]
+fn ignores_checkpoint_with_unconfirmed_root_block() {
+    let client = create_client(&[]);
+    let genesis_hash = client.info().genesis_hash;
+
+    write(
+        &client,
+        Some(genesis_root_block(records_root(1))),
+        genesis_hash,
+    );
+
+    assert_eq!(load(&client), None);
+}
+
+#[test]
+fn ignores_stale_checkpoint() {
+    let root_block = genesis_root_block(records_root(1));
+    // Root block of the next segment is already in the blockchain
+    let client = create_client(&[(0, root_block.records_root()), (1, records_root(2))]);
+    let genesis_hash = client.info().genesis_hash;
+
+    write(&client, Some(root_block), genesis_hash);
+
+    assert_eq!(load(&client), None);
+
+    // Same for checkpoint without any segments
+    let client = create_client(&[(0, root_block.records_root())]);
+    let genesis_hash = client.info().genesis_hash;
+
+    write(&client, None, genesis_hash);
+
+    assert_eq!(load(&client), None);
+}
@@ -21,7 +21,8 @@ use codec::{Decode, Encode};
 use sc_client_api::backend::AuxStore;
 use sp_blockchain::{Error as ClientError, Result as ClientResult};
 use sp_consensus_subspace::ChainConstants;
-use subspace_core_primitives::{BlockWeight, RecordsRoot, SegmentIndex};
+use subspace_archiving::archiver::ArchiverState;
+use subspace_core_primitives::{BlockWeight, RecordsRoot, RootBlock, SegmentIndex};
 
 fn load_decode<B, T>(backend: &B, key: &[u8]) -> ClientResult<Option<T>>
 where
@@ -112,3 +113,47 @@ where
 {
     load_decode(backend, chain_constants_key().as_slice())
 }
+
+/// Checkpoint of the archiver that allows to restore archiver on restart without searching the
+/// blockchain for the last archived block and archiving blocks again.
+#[derive(Debug, Encode, Decode)]
+pub(crate) struct ArchiverCheckpoint<Hash, Number> {
+    /// Exported state of the archiver
+    pub(crate) archiver_state: ArchiverState,
+    /// Root block of the last segment produced by the archiver, `None` if archiver didn't produce
+    /// any segments yet
+    pub(crate) last_root_block: Option<RootBlock>,
+    /// Hash and number of the last block added to the archiver
+    pub(crate) best_archived_block: (Hash, Number),
+}
+
+/// The aux storage key used to store the archiver checkpoint.
+fn archiver_checkpoint_key() -> Vec<u8> {
+    b"archiver_checkpoint".encode()
+}
+
+/// Write archiver checkpoint to aux storage.
+pub(crate) fn write_archiver_checkpoint<Hash, Number, F, R>(
+    archiver_checkpoint: &ArchiverCheckpoint<Hash, Number>,
+    write_aux: F,
+) -> R
+where
+    Hash: Encode,
+    Number: Encode,
+    F: FnOnce(&[(Vec<u8>, &[u8])]) -> R,
+{
+    let key = archiver_checkpoint_key();
+    archiver_checkpoint.using_encoded(|s| write_aux(&[(key, s)]))
+}
+
+/// Load archiver checkpoint.
+pub(crate) fn load_archiver_checkpoint<Hash, Number, Backend>(
+    backend: &Backend,
+) -> ClientResult<Option<ArchiverCheckpoint<Hash, Number>>>
+where
+    Hash: Decode,
+    Number: Decode,
+    Backend: AuxStore,
+{
+    load_decode(backend, archiver_checkpoint_key().as_slice())
+}
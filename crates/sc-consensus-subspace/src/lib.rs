@@ -78,7 +78,6 @@ use std::marker::PhantomData;
 use std::pin::Pin;
 use std::sync::Arc;
 use subspace_archiving::archiver::{ArchivedSegment, Archiver};
-use subspace_core_primitives::crypto::kzg;
 use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::objects::BlockObjectMapping;
 use subspace_core_primitives::{
@@ -640,7 +639,7 @@ where
             .runtime_api()
             .is_in_block_list(
                 &BlockId::Hash(*block.header.parent_hash()),
-                &pre_digest.solution.public_key,
+                pre_digest.solution.public_key(),
             )
             .or_else(|error| {
                 if matches!(block.state_action, StateAction::Skip) {
@@ -653,11 +652,11 @@ where
             warn!(
                 target: "subspace",
                 "Verifying block with solution provided by farmer in block list: {}",
-                pre_digest.solution.public_key
+                pre_digest.solution.public_key()
             );
 
             return Err(Error::<Block::Header>::FarmerInBlockList(
-                pre_digest.solution.public_key.clone(),
+                pre_digest.solution.public_key().clone(),
             )
             .into());
         }
@@ -703,7 +702,7 @@ where
                         slot_now,
                         slot,
                         &block.header,
-                        &verified_info.pre_digest.solution.public_key,
+                        verified_info.pre_digest.solution.public_key(),
                         &block.origin,
                     )
                     .await
@@ -830,7 +829,7 @@ where
         let pre_digest = &subspace_digest_items.pre_digest;
 
         if let Some(root_plot_public_key) = root_plot_public_key {
-            if &pre_digest.solution.public_key != root_plot_public_key {
+            if pre_digest.solution.public_key() != root_plot_public_key {
                 // Only root plot public key is allowed.
                 return Err(Error::OnlyRootPlotPublicKeyAllowed);
             }
@@ -841,7 +840,7 @@ where
         if self
             .client
             .runtime_api()
-            .is_in_block_list(&parent_block_id, &pre_digest.solution.public_key)
+            .is_in_block_list(&parent_block_id, pre_digest.solution.public_key())
             .or_else(|error| {
                 if skip_runtime_access {
                     Ok(false)
@@ -853,11 +852,11 @@ where
             warn!(
                 target: "subspace",
                 "Ignoring block with solution provided by farmer in block list: {}",
-                pre_digest.solution.public_key
+                pre_digest.solution.public_key()
             );
 
             return Err(Error::FarmerInBlockList(
-                pre_digest.solution.public_key.clone(),
+                pre_digest.solution.public_key().clone(),
             ));
         }
 
@@ -910,15 +909,15 @@ where
         }
 
         let sector_id = SectorId::new(
-            &(&pre_digest.solution.public_key).into(),
-            pre_digest.solution.sector_index,
+            &pre_digest.solution.public_key().into(),
+            pre_digest.solution.sector_index(),
         );
 
         // TODO: Derive `pre_digest.solution.piece_offset` from local challenge instead
 
         let piece_index = sector_id.derive_piece_index(
-            pre_digest.solution.piece_offset,
-            pre_digest.solution.total_pieces,
+            pre_digest.solution.piece_offset(),
+            pre_digest.solution.total_pieces(),
         );
         let position = u32::try_from(piece_index % u64::from(PIECES_IN_SEGMENT))
             .expect("Position within segment always fits into u32; qed");
@@ -1108,13 +1107,13 @@ where
             );
 
             let sector_id = SectorId::new(
-                &(&pre_digest.solution.public_key).into(),
-                pre_digest.solution.sector_index,
+                &pre_digest.solution.public_key().into(),
+                pre_digest.solution.sector_index(),
             );
 
             let local_challenge = sector_id.derive_local_challenge(&global_challenge);
 
-            let expanded_chunk = pre_digest.solution.chunk.expand(local_challenge);
+            let expanded_chunk = pre_digest.solution.chunk().expand(local_challenge);
 
             BlockWeight::from(
                 SolutionRange::MAX
@@ -1254,9 +1253,13 @@ where
 /// import-queue.
 ///
 /// Also returns a link object used to correctly instantiate the import queue and background worker.
+///
+/// `kzg_public_parameters` are serialized KZG public parameters, they must match the ones
+/// committed to in chain constants.
 #[allow(clippy::type_complexity)]
 pub fn block_import<Client, Block, I, CAW, CIDP>(
     config: Config,
+    kzg_public_parameters: &[u8],
     wrapped_block_import: I,
     client: Arc<Client>,
     can_author_with: CAW,
@@ -1281,12 +1284,15 @@ where
     let (imported_block_notification_sender, imported_block_notification_stream) =
         notification::channel("subspace_imported_block_notification_stream");
 
-    let confirmation_depth_k = get_chain_constants(client.as_ref())
-        .expect("Must always be able to get chain constants")
-        .confirmation_depth_k();
+    let chain_constants =
+        get_chain_constants(client.as_ref()).expect("Must always be able to get chain constants");
+    let confirmation_depth_k = chain_constants.confirmation_depth_k();
 
-    // TODO: Probably should have public parameters in chain constants instead
-    let kzg = Kzg::new(kzg::test_public_parameters());
+    let kzg = Kzg::try_from_serialized(
+        kzg_public_parameters,
+        &chain_constants.kzg_public_parameters(),
+    )
+    .map_err(|error| ClientError::Application(error.into()))?;
 
     let link = SubspaceLink {
         config,
@@ -182,7 +182,7 @@ where
 
         while let Some(solution) = solution_receiver.next().await {
             if let Some(root_plot_public_key) = &maybe_root_plot_public_key {
-                if &solution.public_key != root_plot_public_key {
+                if solution.public_key() != root_plot_public_key {
                     // Only root plot public key is allowed, no need to even try to claim block or
                     // vote.
                     continue;
@@ -192,23 +192,23 @@ where
             // TODO: We need also need to check for equivocation of farmers connected to *this node*
             //  during block import, currently farmers connected to this node are considered trusted
             if runtime_api
-                .is_in_block_list(&parent_block_id, &solution.public_key)
+                .is_in_block_list(&parent_block_id, solution.public_key())
                 .ok()?
             {
                 warn!(
                     target: "subspace",
                     "Ignoring solution for slot {} provided by farmer in block list: {}",
                     slot,
-                    solution.public_key,
+                    solution.public_key(),
                 );
 
                 continue;
             }
 
-            let sector_id = SectorId::new(&(&solution.public_key).into(), solution.sector_index);
+            let sector_id = SectorId::new(&solution.public_key().into(), solution.sector_index());
 
             let piece_index =
-                sector_id.derive_piece_index(solution.piece_offset, solution.total_pieces);
+                sector_id.derive_piece_index(solution.piece_offset(), solution.total_pieces());
             let segment_index: SegmentIndex = piece_index / SegmentIndex::from(PIECES_IN_SEGMENT);
             let position = u32::try_from(piece_index % u64::from(PIECES_IN_SEGMENT))
                 .expect("Position within segment always fits into u32; qed");
@@ -267,7 +267,7 @@ where
             } else {
                 let local_challenge = sector_id.derive_local_challenge(&global_challenge);
 
-                let expanded_chunk = solution.chunk.expand(local_challenge);
+                let expanded_chunk = solution.chunk().expand(local_challenge);
 
                 // If solution is of high enough quality and block pre-digest wasn't produced yet,
                 // block reward is claimed
@@ -307,7 +307,7 @@ where
         let signature = self
             .sign_reward(
                 H256::from_slice(header_hash.as_ref()),
-                &pre_digest.solution.public_key,
+                pre_digest.solution.public_key(),
             )
             .await?;
 
@@ -414,7 +414,7 @@ where
             solution: solution.clone(),
         };
 
-        let signature = match self.sign_reward(vote.hash(), &solution.public_key).await {
+        let signature = match self.sign_reward(vote.hash(), solution.public_key()).await {
             Ok(signature) => signature,
             Err(error) => {
                 error!(
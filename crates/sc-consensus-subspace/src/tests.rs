@@ -74,7 +74,8 @@ use subspace_core_primitives::crypto::kzg;
 use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::objects::BlockObjectMapping;
 use subspace_core_primitives::{
-    ChunkSignature, FlatPieces, Piece, Solution, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
+    ChunkSignature, FlatPieces, Piece, Solution, SolutionV0, RECORDED_HISTORY_SEGMENT_SIZE,
+    RECORD_SIZE,
 };
 use subspace_solving::{create_chunk_signature, REWARD_SIGNING_CONTEXT};
 use substrate_test_runtime::{Block as TestBlock, Hash};
@@ -340,6 +341,7 @@ impl TestNetFactory for SubspaceTestNet {
         let config = Config::get(&*client).expect("config available");
         let (block_import, link) = crate::block_import(
             config,
+            kzg::EMBEDDED_PUBLIC_PARAMETERS,
             client.clone(),
             client,
             AlwaysCanAuthor,
@@ -684,7 +686,7 @@ pub fn dummy_claim_slot(
 ) -> Option<(PreDigest<FarmerPublicKey, FarmerPublicKey>, FarmerPublicKey)> {
     Some((
         PreDigest {
-            solution: Solution {
+            solution: Solution::V0(SolutionV0 {
                 public_key: FarmerPublicKey::unchecked_from([0u8; 32]),
                 reward_address: FarmerPublicKey::unchecked_from([0u8; 32]),
                 sector_index: 0,
@@ -697,7 +699,7 @@ pub fn dummy_claim_slot(
                     output: [0; 32],
                     proof: [0; 64],
                 },
-            },
+            }),
             slot,
         },
         FarmerPublicKey::unchecked_from([0u8; 32]),
@@ -589,9 +589,9 @@ pub fn derive_next_global_randomness<Header: HeaderT>(
     }
 
     derive_randomness(
-        &PublicKey::from(&pre_digest.solution.public_key),
-        &pre_digest.solution.chunk,
-        &pre_digest.solution.chunk_signature,
+        &PublicKey::from(pre_digest.solution.public_key()),
+        &pre_digest.solution.chunk(),
+        &pre_digest.solution.chunk_signature(),
     )
     .map(Some)
     .map_err(|_err| Error::NextDigestDerivationError(ErrorDigestType::GlobalRandomness))
@@ -757,7 +757,7 @@ pub fn verify_next_digests<Header: HeaderT>(
             Some(updated_root_plot_public_key) => {
                 if number.is_one()
                     && root_plot_public_key.is_none()
-                    && &header_digests.pre_digest.solution.public_key
+                    && header_digests.pre_digest.solution.public_key()
                         == updated_root_plot_public_key
                 {
                     root_plot_public_key.replace(updated_root_plot_public_key.clone());
@@ -40,6 +40,8 @@ use sp_core::H256;
 use sp_io::hashing;
 use sp_runtime::{ConsensusEngineId, DigestItem};
 use sp_std::vec::Vec;
+use subspace_core_primitives::crypto::kzg;
+use subspace_core_primitives::crypto::kzg::PublicParametersInfo;
 use subspace_core_primitives::{
     BlockNumber, PublicKey, Randomness, RecordsRoot, RewardSignature, RootBlock, SegmentIndex,
     Solution, SolutionRange, PUBLIC_KEY_LENGTH, REWARD_SIGNATURE_LENGTH,
@@ -234,12 +236,12 @@ where
     }
 
     // both headers must have the same sector index
-    if first_pre_digest.solution.sector_index != second_pre_digest.solution.sector_index {
+    if first_pre_digest.solution.sector_index() != second_pre_digest.solution.sector_index() {
         return false;
     }
 
     // both headers must have been authored by the same farmer
-    if first_pre_digest.solution.public_key != second_pre_digest.solution.public_key {
+    if first_pre_digest.solution.public_key() != second_pre_digest.solution.public_key() {
         return false;
     }
 
@@ -288,6 +290,7 @@ impl Default for SolutionRanges {
 #[derive(Debug, Encode, Decode, MaxEncodedLen, PartialEq, Eq, Clone, Copy, TypeInfo)]
 pub enum ChainConstants {
     /// V0 of the chain constants.
+    #[codec(index = 0)]
     V0 {
         /// Depth `K` after which a block enters the recorded history.
         confirmation_depth_k: BlockNumber,
@@ -298,39 +301,80 @@ pub enum ChainConstants {
         /// Slot probability.
         slot_probability: (u64, u64),
     },
+    /// V1 of the chain constants, adds KZG public parameters.
+    #[codec(index = 1)]
+    V1 {
+        /// Depth `K` after which a block enters the recorded history.
+        confirmation_depth_k: BlockNumber,
+        /// Number of blocks between global randomness updates.
+        global_randomness_interval: BlockNumber,
+        /// Era duration in blocks.
+        era_duration: BlockNumber,
+        /// Slot probability.
+        slot_probability: (u64, u64),
+        /// KZG public parameters used for piece verification.
+        kzg_public_parameters: PublicParametersInfo,
+    },
 }
 
 impl ChainConstants {
     /// Depth `K` after which a block enters the recorded history.
     pub fn confirmation_depth_k(&self) -> BlockNumber {
-        let Self::V0 {
-            confirmation_depth_k,
-            ..
-        } = self;
-        *confirmation_depth_k
+        match self {
+            Self::V0 {
+                confirmation_depth_k,
+                ..
+            }
+            | Self::V1 {
+                confirmation_depth_k,
+                ..
+            } => *confirmation_depth_k,
+        }
     }
 
     /// Number of blocks between global randomness updates.
     pub fn global_randomness_interval(&self) -> BlockNumber {
-        let Self::V0 {
-            global_randomness_interval,
-            ..
-        } = self;
-        *global_randomness_interval
+        match self {
+            Self::V0 {
+                global_randomness_interval,
+                ..
+            }
+            | Self::V1 {
+                global_randomness_interval,
+                ..
+            } => *global_randomness_interval,
+        }
     }
 
     /// Era duration in blocks.
     pub fn era_duration(&self) -> BlockNumber {
-        let Self::V0 { era_duration, .. } = self;
-        *era_duration
+        match self {
+            Self::V0 { era_duration, .. } | Self::V1 { era_duration, .. } => *era_duration,
+        }
     }
 
     /// Slot probability.
     pub fn slot_probability(&self) -> (u64, u64) {
-        let Self::V0 {
-            slot_probability, ..
-        } = self;
-        *slot_probability
+        match self {
+            Self::V0 {
+                slot_probability, ..
+            }
+            | Self::V1 {
+                slot_probability, ..
+            } => *slot_probability,
+        }
+    }
+
+    /// KZG public parameters used for piece verification.
+    pub fn kzg_public_parameters(&self) -> PublicParametersInfo {
+        match self {
+            // Before KZG public parameters were added to chain constants embedded ones were used
+            Self::V0 { .. } => kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO,
+            Self::V1 {
+                kzg_public_parameters,
+                ..
+            } => *kzg_public_parameters,
+        }
     }
 }
 
@@ -502,7 +546,7 @@ where
     if check_reward_signature(
         pre_hash.as_ref(),
         &RewardSignature::from(&signature),
-        &PublicKey::from(&pre_digest.solution.public_key),
+        &PublicKey::from(pre_digest.solution.public_key()),
         reward_signing_context,
     )
     .is_err()
@@ -1,14 +1,17 @@
 use crate::{
-    is_equivocation_proof_valid, CompatibleDigestItem, EquivocationProof, FarmerPublicKey,
-    FarmerSignature,
+    is_equivocation_proof_valid, ChainConstants, CompatibleDigestItem, EquivocationProof,
+    FarmerPublicKey, FarmerSignature,
 };
+use codec::{Decode, Encode};
 use schnorrkel::Keypair;
 use sp_consensus_slots::Slot;
 use sp_core::crypto::UncheckedFrom;
 use sp_runtime::traits::BlakeTwo256;
 use sp_runtime::{Digest, DigestItem};
 use std::num::NonZeroU64;
-use subspace_core_primitives::{ChunkSignature, Solution};
+use subspace_core_primitives::crypto::kzg;
+use subspace_core_primitives::crypto::kzg::PublicParametersInfo;
+use subspace_core_primitives::{ChunkSignature, Solution, SolutionV0};
 use subspace_solving::REWARD_SIGNING_CONTEXT;
 
 type Header = sp_runtime::generic::Header<u32, BlakeTwo256>;
@@ -19,7 +22,7 @@ fn test_is_equivocation_proof_valid() {
     let keypair = Keypair::generate();
     let offender = FarmerPublicKey::unchecked_from(keypair.public.to_bytes());
     let slot = Slot::from(1);
-    let solution = Solution {
+    let solution = Solution::V0(SolutionV0 {
         public_key: offender.clone(),
         reward_address: (),
         sector_index: 0,
@@ -32,7 +35,7 @@ fn test_is_equivocation_proof_valid() {
             output: Default::default(),
             proof: [0u8; 64],
         },
-    };
+    });
 
     let mut first_header = Header {
         parent_hash: [0u8; 32].into(),
@@ -91,3 +94,37 @@ fn test_is_equivocation_proof_valid() {
 
     assert!(is_equivocation_proof_valid::<_, ()>(equivocation_proof));
 }
+
+#[test]
+fn chain_constants_compatibility() {
+    // Encoding of chain constants before KZG public parameters were added
+    let encoded_v0 = (0u8, 100u32, 256u32, 2016u32, (1u64, 6u64)).encode();
+    let chain_constants_v0 = ChainConstants::decode(&mut encoded_v0.as_slice()).unwrap();
+    assert_eq!(chain_constants_v0.encode(), encoded_v0);
+    assert_eq!(chain_constants_v0.confirmation_depth_k(), 100);
+    assert_eq!(chain_constants_v0.slot_probability(), (1, 6));
+    assert_eq!(
+        chain_constants_v0.kzg_public_parameters(),
+        kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO
+    );
+
+    let kzg_public_parameters = PublicParametersInfo {
+        max_degree: 128,
+        checksum: [1; 32],
+    };
+    let chain_constants_v1 = ChainConstants::V1 {
+        confirmation_depth_k: 100,
+        global_randomness_interval: 256,
+        era_duration: 2016,
+        slot_probability: (1, 6),
+        kzg_public_parameters,
+    };
+    assert_eq!(
+        ChainConstants::decode(&mut chain_constants_v1.encode().as_slice()).unwrap(),
+        chain_constants_v1
+    );
+    assert_eq!(
+        chain_constants_v1.kzg_public_parameters(),
+        kzg_public_parameters
+    );
+}
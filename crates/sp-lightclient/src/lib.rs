@@ -36,7 +36,7 @@ use sp_std::collections::btree_map::BTreeMap;
 use sp_std::marker::PhantomData;
 use sp_std::num::NonZeroU16;
 use subspace_core_primitives::crypto::kzg;
-use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::crypto::kzg::{Kzg, PublicParametersInfo};
 use subspace_core_primitives::{
     BlockWeight, PublicKey, Randomness, RecordsRoot, RewardSignature, SectorId, SegmentIndex,
     SolutionRange, PIECES_IN_SEGMENT,
@@ -80,6 +80,9 @@ pub struct ChainConstants<Header: HeaderT> {
 
     /// Space parameter for proof-of-replication in bits.
     pub space_l: NonZeroU16,
+
+    /// KZG public parameters used for piece verification.
+    pub kzg_public_parameters: PublicParametersInfo,
 }
 
 /// Defines the storage bound for the light client store.
@@ -253,6 +256,8 @@ pub enum ImportError<Header: HeaderT> {
     MissingRecordsRoot(SegmentIndex),
     /// Incorrect block author.
     IncorrectBlockAuthor(FarmerPublicKey),
+    /// Embedded KZG public parameters don't match the ones in chain constants.
+    UnexpectedKzgPublicParameters,
 }
 
 impl<Header: HeaderT> From<DigestError> for ImportError<Header> {
@@ -309,9 +314,9 @@ impl<Header: HeaderT, Store: Storage<Header>> HeaderImporter<Header, Store> {
         let constants = self.store.chain_constants();
         let mut maybe_root_plot_public_key = parent_header.maybe_root_plot_public_key;
         if let Some(root_plot_public_key) = &maybe_root_plot_public_key {
-            if root_plot_public_key != &header_digests.pre_digest.solution.public_key {
+            if root_plot_public_key != header_digests.pre_digest.solution.public_key() {
                 return Err(ImportError::IncorrectBlockAuthor(
-                    header_digests.pre_digest.solution.public_key,
+                    header_digests.pre_digest.solution.public_key().clone(),
                 ));
             }
         }
@@ -335,17 +340,17 @@ impl<Header: HeaderT, Store: Storage<Header>> HeaderImporter<Header, Store> {
         Self::verify_slot(&parent_header.header, &header_digests.pre_digest)?;
 
         // verify block signature
-        Self::verify_block_signature(&mut header, &header_digests.pre_digest.solution.public_key)?;
+        Self::verify_block_signature(&mut header, header_digests.pre_digest.solution.public_key())?;
 
         // verify solution
         let sector_id = SectorId::new(
-            &(&header_digests.pre_digest.solution.public_key).into(),
-            header_digests.pre_digest.solution.sector_index,
+            &header_digests.pre_digest.solution.public_key().into(),
+            header_digests.pre_digest.solution.sector_index(),
         );
 
         let piece_index = sector_id.derive_piece_index(
-            header_digests.pre_digest.solution.piece_offset,
-            header_digests.pre_digest.solution.total_pieces,
+            header_digests.pre_digest.solution.piece_offset(),
+            header_digests.pre_digest.solution.total_pieces(),
         );
         let position = u32::try_from(piece_index % u64::from(PIECES_IN_SEGMENT))
             .expect("Position within segment always fits into u32; qed");
@@ -354,8 +359,10 @@ impl<Header: HeaderT, Store: Storage<Header>> HeaderImporter<Header, Store> {
         let records_root =
             self.find_records_root_for_segment_index(segment_index, parent_header.header.hash())?;
 
-        // TODO: Probably should have public parameters in chain constants instead
-        let kzg = Kzg::new(kzg::test_public_parameters());
+        if constants.kzg_public_parameters != kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO {
+            return Err(ImportError::UnexpectedKzgPublicParameters);
+        }
+        let kzg = Kzg::new(kzg::embedded_public_parameters_unchecked());
 
         verify_solution(
             &header_digests.pre_digest.solution,
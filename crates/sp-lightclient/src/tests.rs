@@ -25,10 +25,10 @@ use subspace_core_primitives::crypto::kzg::{Kzg, Witness};
 use subspace_core_primitives::crypto::{blake2b_256_254_hash, kzg};
 use subspace_core_primitives::{
     Chunk, Piece, PublicKey, Randomness, RecordsRoot, SectorId, SegmentIndex, Solution,
-    SolutionRange, PIECE_SIZE, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
+    SolutionRange, SolutionV0, PIECE_SIZE, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
 };
 use subspace_solving::{
-    create_chunk_signature, derive_chunk_otp, derive_global_challenge, REWARD_SIGNING_CONTEXT,
+    create_chunk_signature, derive_global_challenge, encode_record, REWARD_SIGNING_CONTEXT,
 };
 use subspace_verification::derive_randomness;
 
@@ -50,6 +50,7 @@ fn default_test_constants() -> ChainConstants<Header> {
         slot_probability: (1, 6),
         storage_bound: Default::default(),
         space_l: NonZeroU16::new(20).unwrap(),
+        kzg_public_parameters: kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO,
     }
 }
 
@@ -128,22 +129,7 @@ fn valid_header(
         let (record, witness_bytes) = piece.split_at_mut(RECORD_SIZE as usize);
         let piece_witness = Witness::try_from_bytes((&*witness_bytes).try_into().unwrap()).unwrap();
         let piece_record_hash = blake2b_256_254_hash(record);
-        // TODO: Extract encoding into separate function reusable in
-        //  farmer and otherwise
-        record
-            .view_bits_mut::<Lsb0>()
-            .chunks_mut(space_l.get() as usize)
-            .enumerate()
-            .for_each(|(chunk_index, bits)| {
-                // Derive one-time pad
-                let mut otp = derive_chunk_otp(&sector_id, witness_bytes, chunk_index as u32);
-                // XOR chunk bit by bit with one-time pad
-                bits.iter_mut()
-                    .zip(otp.view_bits_mut::<Lsb0>().iter())
-                    .for_each(|(mut a, b)| {
-                        *a ^= *b;
-                    });
-            });
+        encode_record(record, &sector_id, witness_bytes, space_l);
 
         // TODO: We are skipping witness part of the piece or else it is not
         //  decodable
@@ -169,7 +155,7 @@ fn valid_header(
         let chunk_signature = create_chunk_signature(keypair, &chunk);
         let pre_digest = PreDigest {
             slot: slot.into(),
-            solution: Solution {
+            solution: Solution::V0(SolutionV0 {
                 public_key: FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
                 reward_address: FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
                 sector_index,
@@ -179,7 +165,7 @@ fn valid_header(
                 piece_witness,
                 chunk,
                 chunk_signature,
-            },
+            }),
         };
         let digests = vec![
             DigestItem::global_randomness(randomness),
@@ -359,8 +345,8 @@ fn add_headers_to_chain(
         let digests: SubspaceDigestItems<FarmerPublicKey, FarmerPublicKey, FarmerSignature> =
             extract_subspace_digest_items(&header).unwrap();
         let sector_id = SectorId::new(
-            &(&digests.pre_digest.solution.public_key).into(),
-            digests.pre_digest.solution.sector_index,
+            &digests.pre_digest.solution.public_key().into(),
+            digests.pre_digest.solution.sector_index(),
         );
         let new_weight =
             HeaderImporter::<Header, MockStorage>::calculate_block_weight(&sector_id, &digests);
@@ -712,8 +698,8 @@ fn test_reorg_to_heavier_smaller_chain() {
     let digests: SubspaceDigestItems<FarmerPublicKey, FarmerPublicKey, FarmerSignature> =
         extract_subspace_digest_items(&header).unwrap();
     let sector_id = SectorId::new(
-        &(&digests.pre_digest.solution.public_key).into(),
-        digests.pre_digest.solution.sector_index,
+        &digests.pre_digest.solution.public_key().into(),
+        digests.pre_digest.solution.sector_index(),
     );
     let new_weight =
         HeaderImporter::<Header, MockStorage>::calculate_block_weight(&sector_id, &digests);
@@ -792,9 +778,9 @@ fn test_next_global_randomness_digest() {
     remove_seal(&mut header);
     let pre_digest = extract_pre_digest(&header).unwrap();
     let randomness = derive_randomness(
-        &PublicKey::from(&pre_digest.solution.public_key),
-        &pre_digest.solution.chunk,
-        &pre_digest.solution.chunk_signature,
+        &PublicKey::from(pre_digest.solution.public_key()),
+        &pre_digest.solution.chunk(),
+        &pre_digest.solution.chunk_signature(),
     )
     .unwrap();
     let digests = header.digest_mut();
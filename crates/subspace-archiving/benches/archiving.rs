@@ -14,7 +14,14 @@ fn criterion_benchmark(c: &mut Criterion) {
     let kzg = Kzg::new(kzg::test_public_parameters());
     let mut archiver = Archiver::new(RECORD_SIZE, RECORDED_HISTORY_SEGMENT_SIZE, kzg).unwrap();
 
-    c.bench_function("segment-archiving", |b| {
+    let bench_name = if cfg!(feature = "parallel") {
+        "segment-archiving-parallel"
+    } else {
+        "segment-archiving"
+    };
+
+    // Run with and without `parallel` feature to compare
+    c.bench_function(bench_name, |b| {
         b.iter(|| {
             archiver.add_block(input.clone(), Default::default());
         })
@@ -18,15 +18,17 @@ mod record_shards;
 extern crate alloc;
 
 use crate::archiver::record_shards::RecordShards;
-use crate::utils::GF_16_ELEMENT_BYTES;
+use crate::utils::{Gf16Element, GF_16_ELEMENT_BYTES};
 use alloc::collections::VecDeque;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::cmp::Ordering;
 use parity_scale_codec::{Compact, CompactLen, Decode, Encode};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use reed_solomon_erasure::galois_16::ReedSolomon;
 use subspace_core_primitives::crypto::blake2b_256_254_hash;
-use subspace_core_primitives::crypto::kzg::{Commitment, Kzg, Witness};
+use subspace_core_primitives::crypto::kzg::{Commitment, Kzg, Opening, Witness};
 use subspace_core_primitives::objects::{
     BlockObject, BlockObjectMapping, PieceObject, PieceObjectMapping,
 };
@@ -166,6 +168,28 @@ pub enum ArchiverInstantiationError {
     },
 }
 
+/// Internal state of [`Archiver`] exported with [`Archiver::export_state()`], can be used to
+/// restore archiver with [`Archiver::from_exported_state()`] without archiving blocks again.
+#[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
+pub struct ArchiverState {
+    /// Items pending to be included into the next segment alongside with their object mappings
+    /// (that are not included in encoding of segment items themselves)
+    buffer: Vec<(SegmentItem, BlockObjectMapping)>,
+    /// An index of the current segment
+    segment_index: u64,
+    /// Hash of the root block of the previous segment
+    prev_root_block_hash: Blake2b256Hash,
+    /// Last archived block
+    last_archived_block: LastArchivedBlock,
+}
+
+impl ArchiverState {
+    /// Index of the segment archiver will produce next
+    pub fn segment_index(&self) -> u64 {
+        self.segment_index
+    }
+}
+
 /// Block archiver for Subspace blockchain.
 ///
 /// It takes new confirmed (at `K` depth) blocks and concatenates them into a buffer, buffer is
@@ -322,6 +346,75 @@ impl Archiver {
         Ok(archiver)
     }
 
+    /// Create a new instance of the archiver from the state previously exported with
+    /// [`Archiver::export_state()`], archiver will continue exactly where exported instance
+    /// stopped.
+    pub fn from_exported_state(
+        record_size: u32,
+        segment_size: u32,
+        kzg: Kzg,
+        state: ArchiverState,
+    ) -> Result<Self, ArchiverInstantiationError> {
+        let ArchiverState {
+            buffer,
+            segment_index,
+            prev_root_block_hash,
+            last_archived_block,
+        } = state;
+
+        let mut archiver = Self::new(record_size, segment_size, kzg)?;
+
+        archiver.buffer = buffer
+            .into_iter()
+            .map(|(segment_item, object_mapping)| match segment_item {
+                SegmentItem::Block { bytes, .. } => SegmentItem::Block {
+                    bytes,
+                    object_mapping,
+                },
+                SegmentItem::BlockStart { bytes, .. } => SegmentItem::BlockStart {
+                    bytes,
+                    object_mapping,
+                },
+                SegmentItem::BlockContinuation { bytes, .. } => SegmentItem::BlockContinuation {
+                    bytes,
+                    object_mapping,
+                },
+                SegmentItem::RootBlock(root_block) => SegmentItem::RootBlock(root_block),
+            })
+            .collect();
+        archiver.segment_index = segment_index;
+        archiver.prev_root_block_hash = prev_root_block_hash;
+        archiver.last_archived_block = last_archived_block;
+
+        Ok(archiver)
+    }
+
+    /// Export internal state of the archiver (blocks that were added, but not archived yet and
+    /// information about previously produced segments), see [`Archiver::from_exported_state()`].
+    pub fn export_state(&self) -> ArchiverState {
+        ArchiverState {
+            buffer: self
+                .buffer
+                .iter()
+                .map(|segment_item| {
+                    let object_mapping = match segment_item {
+                        SegmentItem::Block { object_mapping, .. }
+                        | SegmentItem::BlockStart { object_mapping, .. }
+                        | SegmentItem::BlockContinuation { object_mapping, .. } => {
+                            object_mapping.clone()
+                        }
+                        SegmentItem::RootBlock(_) => BlockObjectMapping::default(),
+                    };
+
+                    (segment_item.clone(), object_mapping)
+                })
+                .collect(),
+            segment_index: self.segment_index,
+            prev_root_block_hash: self.prev_root_block_hash,
+            last_archived_block: self.last_archived_block,
+        }
+    }
+
     /// Get last archived block if there was any
     pub fn last_archived_block_number(&self) -> Option<BlockNumber> {
         if self.last_archived_block != INITIAL_LAST_ARCHIVED_BLOCK {
@@ -640,22 +733,25 @@ impl Archiver {
 
         drop(segment);
 
-        let mut record_shards_slices = record_shards.as_mut_slices();
-
         // Apply erasure coding to to create parity shards/records
-        self.reed_solomon
-            .encode(&mut record_shards_slices)
-            .expect("Encoding is running with fixed parameters and should never fail; qed");
+        erasure_code(&self.reed_solomon, record_shards.as_mut_slices());
 
-        let mut pieces = FlatPieces::new(record_shards_slices.len());
-        drop(record_shards_slices);
+        let mut pieces = FlatPieces::new((self.data_shards + self.parity_shards) as usize);
 
-        let record_shards_hashes = record_shards
-            .as_bytes()
-            .as_ref()
-            .chunks_exact(self.record_size as usize)
-            .map(blake2b_256_254_hash)
-            .collect::<Vec<_>>();
+        let record_shards_hashes = {
+            let record_shards_bytes = record_shards.as_bytes();
+
+            #[cfg(feature = "parallel")]
+            let records = record_shards_bytes
+                .as_ref()
+                .par_chunks_exact(self.record_size as usize);
+            #[cfg(not(feature = "parallel"))]
+            let records = record_shards_bytes
+                .as_ref()
+                .chunks_exact(self.record_size as usize);
+
+            records.map(blake2b_256_254_hash).collect::<Vec<_>>()
+        };
         let data = {
             let mut data = Vec::with_capacity(
                 (self.data_shards + self.parity_shards) as usize * BLAKE2B_256_HASH_SIZE,
@@ -676,30 +772,27 @@ impl Archiver {
             .kzg
             .commit(&polynomial)
             .expect("Internally produced values must never fail; qed");
+        let witnesses = self
+            .kzg
+            .create_witnesses(&polynomial, self.data_shards + self.parity_shards)
+            .expect("We use the same indexes as during Merkle tree creation; qed");
 
         // Combine data and parity records back into flat vector of pieces along with corresponding
         // witnesses (Merkle proofs) created above.
         pieces
             .as_pieces_mut()
-            .enumerate()
             .zip(
                 record_shards
                     .as_bytes()
                     .as_ref()
                     .chunks_exact(self.record_size as usize),
             )
-            .for_each(|((position, piece), shard_chunk)| {
+            .zip(&witnesses)
+            .for_each(|((piece, shard_chunk), witness)| {
                 let (record_part, witness_part) = piece.split_at_mut(self.record_size as usize);
 
                 record_part.copy_from_slice(shard_chunk);
-                // TODO: Consider batch witness creation for improved performance
-                witness_part.copy_from_slice(
-                    &self
-                        .kzg
-                        .create_witness(&polynomial, position as u32)
-                        .expect("We use the same indexes as during Merkle tree creation; qed")
-                        .to_bytes(),
-                );
+                witness_part.copy_from_slice(&witness.to_bytes());
             });
 
         // Now produce root block
@@ -726,6 +819,47 @@ impl Archiver {
     }
 }
 
+// Apply erasure coding to data shards to create parity shards
+#[cfg(not(feature = "parallel"))]
+fn erasure_code(reed_solomon: &ReedSolomon, mut record_shards_slices: Vec<&mut [Gf16Element]>) {
+    reed_solomon
+        .encode(&mut record_shards_slices)
+        .expect("Encoding is running with fixed parameters and should never fail; qed");
+}
+
+// Apply erasure coding to data shards to create parity shards. Erasure coding works on each
+// position of shards independently, so shards are split into ranges of positions that are encoded
+// on different threads.
+#[cfg(feature = "parallel")]
+fn erasure_code(reed_solomon: &ReedSolomon, record_shards_slices: Vec<&mut [Gf16Element]>) {
+    let shard_elements = record_shards_slices
+        .first()
+        .map(|shard| shard.len())
+        .unwrap_or_default();
+    let num_threads = rayon::current_num_threads();
+    let elements_per_range = ((shard_elements + num_threads - 1) / num_threads).max(1);
+
+    let mut shard_ranges = Vec::<Vec<&mut [Gf16Element]>>::with_capacity(num_threads);
+    for shard in record_shards_slices {
+        for (range_index, shard_range) in shard.chunks_mut(elements_per_range).enumerate() {
+            match shard_ranges.get_mut(range_index) {
+                Some(shards) => {
+                    shards.push(shard_range);
+                }
+                None => {
+                    shard_ranges.push(vec![shard_range]);
+                }
+            }
+        }
+    }
+
+    shard_ranges.into_par_iter().for_each(|mut shards| {
+        reed_solomon
+            .encode(&mut shards)
+            .expect("Encoding is running with fixed parameters and should never fail; qed");
+    });
+}
+
 /// Validate witness embedded within a piece produced by archiver
 pub fn is_piece_valid(
     kzg: &Kzg,
@@ -757,6 +891,48 @@ pub fn is_piece_valid(
     )
 }
 
+/// Validate witnesses embedded within multiple pieces of the same segment produced by archiver.
+///
+/// `pieces` contains pieces along with their positions in the segment. This is much faster than
+/// calling [`is_piece_valid`] for each piece, but returns `false` if any of the pieces is invalid
+/// without telling which one.
+pub fn is_pieces_valid_batch(
+    kzg: &Kzg,
+    num_pieces_in_segment: u32,
+    pieces: &[(u32, &[u8])],
+    commitment: Commitment,
+    record_size: u32,
+) -> bool {
+    let mut leaf_hashes_and_witnesses = Vec::with_capacity(pieces.len());
+    for (position, piece) in pieces {
+        if piece.len() != (record_size + WITNESS_SIZE) as usize {
+            return false;
+        }
+
+        let (record, witness) = piece.split_at(record_size as usize);
+        let witness = match witness.try_into().map(Witness::try_from_bytes) {
+            Ok(Ok(witness)) => witness,
+            _ => {
+                return false;
+            }
+        };
+
+        leaf_hashes_and_witnesses.push((*position, crypto::blake2b_256_254_hash(record), witness));
+    }
+
+    let openings = leaf_hashes_and_witnesses
+        .iter()
+        .map(|(position, leaf_hash, witness)| Opening {
+            commitment: &commitment,
+            index: *position,
+            value: leaf_hash,
+            witness,
+        })
+        .collect::<Vec<_>>();
+
+    kzg.verify_batch(num_pieces_in_segment, &openings)
+}
+
 /// Validate witness for pieces record hash produced by archiver
 pub fn is_piece_record_hash_valid(
     kzg: &Kzg,
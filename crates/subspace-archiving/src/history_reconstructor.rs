@@ -0,0 +1,429 @@
+extern crate alloc;
+
+use crate::archiver::SegmentItem;
+use crate::reconstructor::{
+    decode_segment_items, ReconstructorError, ReconstructorInstantiationError,
+};
+use alloc::collections::{BTreeMap, BTreeSet};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::RangeInclusive;
+use reed_solomon_erasure::galois_16::ReedSolomon;
+use subspace_core_primitives::{
+    ArchivedBlockProgress, BlockNumber, Piece, PieceIndex, RootBlock, SegmentIndex,
+};
+
+/// History reconstructor-related error
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "thiserror", derive(thiserror::Error))]
+pub enum HistoryReconstructorError {
+    /// Failed to reconstruct segment from its pieces
+    #[cfg_attr(
+        feature = "thiserror",
+        error("Failed to reconstruct segment {segment_index}: {error}")
+    )]
+    SegmentReconstruction {
+        segment_index: SegmentIndex,
+        error: ReconstructorError,
+    },
+    /// Segment doesn't start with the root block of the previous segment
+    #[cfg_attr(
+        feature = "thiserror",
+        error("Segment {segment_index} doesn't start with root block of the previous segment")
+    )]
+    MissingRootBlock { segment_index: SegmentIndex },
+    /// Root block is not known for some of the segments preceding specified block, hence it is not
+    /// known which segments this block is archived in
+    #[cfg_attr(
+        feature = "thiserror",
+        error("Root blocks covering block {block_number} are not known")
+    )]
+    UnknownBlockLocation { block_number: BlockNumber },
+}
+
+/// Block that is split across multiple segments and waits for the rest of its fragments
+#[derive(Debug, Default, Clone)]
+struct PartialBlock {
+    /// Fragments of the block by segment index
+    fragments: BTreeMap<SegmentIndex, Vec<u8>>,
+    /// Segment that contains the beginning of the block, if known
+    first_segment_index: Option<SegmentIndex>,
+    /// Segment that contains the end of the block, if known
+    last_segment_index: Option<SegmentIndex>,
+}
+
+impl PartialBlock {
+    /// Assembles the block if all of its fragments are present
+    fn try_assemble(&self) -> Option<Vec<u8>> {
+        let first_segment_index = self.first_segment_index?;
+        let last_segment_index = self.last_segment_index?;
+
+        let mut block = Vec::new();
+        for segment_index in first_segment_index..=last_segment_index {
+            block.extend_from_slice(self.fragments.get(&segment_index)?);
+        }
+
+        Some(block)
+    }
+}
+
+/// Pieces of the segment that are still needed for its reconstruction
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MissingSegmentPieces {
+    /// Segment index
+    pub segment_index: SegmentIndex,
+    /// Number of additional pieces (any of the missing ones) needed for reconstruction
+    pub pieces_needed: u32,
+    /// Indexes of pieces of the segment that were not added yet
+    pub missing_pieces: Vec<PieceIndex>,
+}
+
+/// History reconstructor retrieves blocks from pieces of archived history that may come in any
+/// order and from any segments.
+///
+/// Unlike [`Reconstructor`](crate::reconstructor::Reconstructor), it accepts individual pieces and
+/// decodes every segment as soon as any half of its pieces is present, independently of other
+/// segments. Blocks that are split across segment boundaries are buffered until all of their
+/// fragments are decoded.
+#[derive(Debug, Clone)]
+pub struct HistoryReconstructor {
+    /// Configuration parameter defining the size of one record (data in one piece excluding witness
+    /// size)
+    record_size: u32,
+    /// Configuration parameter defining the size of one recorded history segment
+    segment_size: u32,
+    /// Number of pieces (both data and parity) in one segment
+    pieces_in_segment: u32,
+    /// Erasure coding data structure
+    reed_solomon: ReedSolomon,
+    /// Root blocks known so far, either added explicitly or found in decoded segments
+    root_blocks: BTreeMap<SegmentIndex, RootBlock>,
+    /// Pieces of segments that were not decoded yet
+    pending_segments: BTreeMap<SegmentIndex, Vec<Option<Piece>>>,
+    /// Segments that have enough pieces, but were not decoded yet
+    ready_segments: BTreeSet<SegmentIndex>,
+    /// Segments that were decoded already
+    decoded_segments: BTreeSet<SegmentIndex>,
+    /// Blocks split across segments that are waiting for the rest of their fragments
+    partial_blocks: BTreeMap<BlockNumber, PartialBlock>,
+    /// Blocks whose last fragment in a decoded segment might be the end of the block, which is only
+    /// known once the root block of that segment is known
+    unresolved_segment_ends: BTreeMap<SegmentIndex, BlockNumber>,
+}
+
+impl HistoryReconstructor {
+    pub fn new(
+        record_size: u32,
+        segment_size: u32,
+    ) -> Result<Self, ReconstructorInstantiationError> {
+        if segment_size <= record_size {
+            return Err(ReconstructorInstantiationError::SegmentSizeTooSmall);
+        }
+        if segment_size % record_size != 0 {
+            return Err(ReconstructorInstantiationError::SegmentSizesNotMultipleOfRecordSize);
+        }
+
+        let data_shards = segment_size / record_size;
+        let parity_shards = data_shards;
+        let reed_solomon = ReedSolomon::new(data_shards as usize, parity_shards as usize)
+            .expect("ReedSolomon must always be correctly instantiated");
+
+        Ok(Self {
+            record_size,
+            segment_size,
+            pieces_in_segment: data_shards + parity_shards,
+            reed_solomon,
+            root_blocks: BTreeMap::new(),
+            pending_segments: BTreeMap::new(),
+            ready_segments: BTreeSet::new(),
+            decoded_segments: BTreeSet::new(),
+            partial_blocks: BTreeMap::new(),
+            unresolved_segment_ends: BTreeMap::new(),
+        })
+    }
+
+    /// Add root block obtained from elsewhere (for instance from the blockchain), this allows to
+    /// find out which pieces are needed for a block range before corresponding segments are decoded
+    /// and to finish blocks that end exactly at the end of a segment.
+    ///
+    /// Returns blocks that were completed as the result.
+    pub fn add_root_block(&mut self, root_block: RootBlock) -> Vec<(BlockNumber, Vec<u8>)> {
+        let segment_index = root_block.segment_index();
+        self.root_blocks.insert(segment_index, root_block);
+
+        let mut blocks = Vec::new();
+        if let Some(block_number) = self.unresolved_segment_ends.remove(&segment_index) {
+            self.resolve_segment_end(segment_index, block_number, root_block, &mut blocks);
+        }
+
+        blocks
+    }
+
+    /// Add piece with specified index, pieces of already decoded segments are ignored.
+    ///
+    /// Returns `true` if segment of this piece has enough pieces for decoding now, decoding is
+    /// done with [`HistoryReconstructor::reconstruct`].
+    pub fn add_piece(&mut self, piece_index: PieceIndex, piece: Piece) -> bool {
+        let segment_index = piece_index / PieceIndex::from(self.pieces_in_segment);
+        let position = (piece_index % PieceIndex::from(self.pieces_in_segment)) as usize;
+
+        if self.decoded_segments.contains(&segment_index) {
+            return false;
+        }
+
+        let pieces = self
+            .pending_segments
+            .entry(segment_index)
+            .or_insert_with(|| vec![None; self.pieces_in_segment as usize]);
+        pieces[position].replace(piece);
+
+        let pieces_present = pieces.iter().filter(|piece| piece.is_some()).count();
+        if pieces_present >= self.reed_solomon.data_shard_count() {
+            self.ready_segments.insert(segment_index)
+        } else {
+            false
+        }
+    }
+
+    /// Decodes all segments that have enough pieces and returns blocks that were completed as the
+    /// result, blocks are sorted by block number.
+    pub fn reconstruct(
+        &mut self,
+    ) -> Result<Vec<(BlockNumber, Vec<u8>)>, HistoryReconstructorError> {
+        let mut blocks = Vec::new();
+
+        while let Some(segment_index) = self.ready_segments.iter().next().copied() {
+            self.ready_segments.remove(&segment_index);
+            let segment_pieces = self
+                .pending_segments
+                .remove(&segment_index)
+                .expect("Ready segments always have pending pieces; qed");
+
+            let items = decode_segment_items(
+                &self.reed_solomon,
+                self.record_size,
+                self.segment_size,
+                &segment_pieces,
+            )
+            .map_err(|error| HistoryReconstructorError::SegmentReconstruction {
+                segment_index,
+                error,
+            })?;
+
+            self.decoded_segments.insert(segment_index);
+            self.add_segment_items(segment_index, items, &mut blocks)?;
+        }
+
+        blocks.sort_by_key(|(block_number, _block)| *block_number);
+
+        Ok(blocks)
+    }
+
+    /// Returns pieces that are still needed to reconstruct all blocks in `block_range`, segments
+    /// that have enough pieces already are not included.
+    ///
+    /// Root blocks of all segments containing `block_range` (and preceding segments) need to be
+    /// known, either found in decoded segments or added with
+    /// [`HistoryReconstructor::add_root_block`].
+    pub fn missing_pieces(
+        &self,
+        block_range: RangeInclusive<BlockNumber>,
+    ) -> Result<Vec<MissingSegmentPieces>, HistoryReconstructorError> {
+        let mut missing_segment_pieces = Vec::new();
+        let mut first_block_number = 0;
+
+        for segment_index in 0.. {
+            let root_block = match self.root_blocks.get(&segment_index) {
+                Some(root_block) => root_block,
+                None => {
+                    return Err(HistoryReconstructorError::UnknownBlockLocation {
+                        block_number: (*block_range.start()).max(first_block_number),
+                    });
+                }
+            };
+            let last_archived_block = root_block.last_archived_block();
+
+            if first_block_number <= *block_range.end()
+                && last_archived_block.number >= *block_range.start()
+                && !self.decoded_segments.contains(&segment_index)
+                && !self.ready_segments.contains(&segment_index)
+            {
+                missing_segment_pieces.push(self.missing_segment_pieces(segment_index));
+            }
+
+            // Segments are checked until the one where the last block of the range ends
+            if last_archived_block.number > *block_range.end()
+                || (last_archived_block.number == *block_range.end()
+                    && last_archived_block.archived_progress == ArchivedBlockProgress::Complete)
+            {
+                break;
+            }
+
+            first_block_number = match last_archived_block.archived_progress {
+                ArchivedBlockProgress::Complete => last_archived_block.number + 1,
+                ArchivedBlockProgress::Partial(_) => last_archived_block.number,
+            };
+        }
+
+        Ok(missing_segment_pieces)
+    }
+
+    fn missing_segment_pieces(&self, segment_index: SegmentIndex) -> MissingSegmentPieces {
+        let first_piece_index = segment_index * PieceIndex::from(self.pieces_in_segment);
+        let required_pieces = self.reed_solomon.data_shard_count() as u32;
+
+        match self.pending_segments.get(&segment_index) {
+            Some(pieces) => {
+                let missing_pieces = pieces
+                    .iter()
+                    .zip(first_piece_index..)
+                    .filter_map(|(piece, piece_index)| piece.is_none().then_some(piece_index))
+                    .collect::<Vec<_>>();
+                let pieces_present = self.pieces_in_segment - missing_pieces.len() as u32;
+
+                MissingSegmentPieces {
+                    segment_index,
+                    pieces_needed: required_pieces.saturating_sub(pieces_present),
+                    missing_pieces,
+                }
+            }
+            None => MissingSegmentPieces {
+                segment_index,
+                pieces_needed: required_pieces,
+                missing_pieces: (first_piece_index..)
+                    .take(self.pieces_in_segment as usize)
+                    .collect(),
+            },
+        }
+    }
+
+    /// Processes items of decoded segment, completed blocks are pushed into `blocks`
+    fn add_segment_items(
+        &mut self,
+        segment_index: SegmentIndex,
+        items: Vec<SegmentItem>,
+        blocks: &mut Vec<(BlockNumber, Vec<u8>)>,
+    ) -> Result<(), HistoryReconstructorError> {
+        let mut items = items.into_iter().peekable();
+
+        // Every segment except the first one starts with the root block of the previous segment
+        let mut next_block_number = if segment_index == 0 {
+            0
+        } else {
+            let prev_root_block = match items.next() {
+                Some(SegmentItem::RootBlock(root_block))
+                    if root_block.segment_index() + 1 == segment_index =>
+                {
+                    root_block
+                }
+                _ => {
+                    return Err(HistoryReconstructorError::MissingRootBlock { segment_index });
+                }
+            };
+            blocks.extend(self.add_root_block(prev_root_block));
+
+            let last_archived_block = prev_root_block.last_archived_block();
+            match last_archived_block.archived_progress {
+                ArchivedBlockProgress::Complete => last_archived_block.number + 1,
+                ArchivedBlockProgress::Partial(_) => last_archived_block.number,
+            }
+        };
+
+        while let Some(item) = items.next() {
+            let is_last_item = items.peek().is_none();
+
+            match item {
+                SegmentItem::Block { bytes, .. } => {
+                    blocks.push((next_block_number, bytes));
+                    next_block_number += 1;
+                }
+                SegmentItem::BlockStart { bytes, .. } => {
+                    let partial_block = self.partial_blocks.entry(next_block_number).or_default();
+                    partial_block.fragments.insert(segment_index, bytes);
+                    partial_block.first_segment_index.replace(segment_index);
+
+                    self.try_complete_block(next_block_number, blocks);
+                }
+                SegmentItem::BlockContinuation { bytes, .. } => {
+                    self.partial_blocks
+                        .entry(next_block_number)
+                        .or_default()
+                        .fragments
+                        .insert(segment_index, bytes);
+
+                    if is_last_item {
+                        // Block may or may not end here, which is only known from the root block
+                        // of this segment
+                        match self.root_blocks.get(&segment_index).copied() {
+                            Some(root_block) => {
+                                self.resolve_segment_end(
+                                    segment_index,
+                                    next_block_number,
+                                    root_block,
+                                    blocks,
+                                );
+                            }
+                            None => {
+                                self.unresolved_segment_ends
+                                    .insert(segment_index, next_block_number);
+                            }
+                        }
+                    } else {
+                        self.partial_blocks
+                            .entry(next_block_number)
+                            .or_default()
+                            .last_segment_index
+                            .replace(segment_index);
+
+                        self.try_complete_block(next_block_number, blocks);
+                        next_block_number += 1;
+                    }
+                }
+                SegmentItem::RootBlock(_) => {
+                    return Err(HistoryReconstructorError::MissingRootBlock { segment_index });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether block continuation at the end of the segment is the end of the block using
+    /// root block of that segment
+    fn resolve_segment_end(
+        &mut self,
+        segment_index: SegmentIndex,
+        block_number: BlockNumber,
+        root_block: RootBlock,
+        blocks: &mut Vec<(BlockNumber, Vec<u8>)>,
+    ) {
+        let last_archived_block = root_block.last_archived_block();
+        if last_archived_block.number == block_number
+            && last_archived_block.archived_progress == ArchivedBlockProgress::Complete
+        {
+            self.partial_blocks
+                .entry(block_number)
+                .or_default()
+                .last_segment_index
+                .replace(segment_index);
+
+            self.try_complete_block(block_number, blocks);
+        }
+    }
+
+    fn try_complete_block(
+        &mut self,
+        block_number: BlockNumber,
+        blocks: &mut Vec<(BlockNumber, Vec<u8>)>,
+    ) {
+        let maybe_block = self
+            .partial_blocks
+            .get(&block_number)
+            .and_then(PartialBlock::try_assemble);
+
+        if let Some(block) = maybe_block {
+            self.partial_blocks.remove(&block_number);
+            blocks.push((block_number, block));
+        }
+    }
+}
@@ -18,5 +18,8 @@
 #![feature(drain_filter)]
 
 pub mod archiver;
+pub mod history_reconstructor;
+#[cfg(feature = "std")]
+pub mod object_fetcher;
 pub mod reconstructor;
 mod utils;
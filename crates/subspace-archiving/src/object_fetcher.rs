@@ -0,0 +1,538 @@
+//! Retrieval of objects stored in archived history of the blockchain.
+
+use crate::archiver::{is_piece_valid, is_pieces_valid_batch, Segment, SegmentItem};
+use async_trait::async_trait;
+use lru::LruCache;
+use parity_scale_codec::{Compact, CompactLen, Decode, Encode};
+use std::sync::Mutex;
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::objects::GlobalObject;
+use subspace_core_primitives::{Piece, PieceIndex, RecordsRoot, SegmentIndex};
+
+/// Default maximum size of one object in bytes
+pub const DEFAULT_MAX_OBJECT_SIZE: usize = 5 * 1024 * 1024;
+// Number of records roots of recent segments to keep in memory
+const RECORDS_ROOTS_CACHE_SIZE: usize = 1000;
+// Record must fit at least the last byte and 2 bytes of potential padding in the end of segment
+const MIN_RECORD_SIZE: u32 = 4;
+
+/// Error returned by [`ObjectPieceGetter`] implementations
+pub type ObjectPieceGetterError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// Something that can be used to get pieces and records roots needed for object retrieval
+#[async_trait]
+pub trait ObjectPieceGetter {
+    /// Get piece by its index, `Ok(None)` means piece is not available
+    async fn get_piece(
+        &self,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, ObjectPieceGetterError>;
+
+    /// Get records root of the segment, `Ok(None)` means records root is not known
+    async fn records_root(
+        &self,
+        segment_index: SegmentIndex,
+    ) -> Result<Option<RecordsRoot>, ObjectPieceGetterError>;
+}
+
+/// Object fetcher instantiation error
+#[derive(Debug, Copy, Clone, Eq, PartialEq, thiserror::Error)]
+pub enum ObjectFetcherInstantiationError {
+    /// Record size is too small
+    #[error("Record size is too small, must be at least {MIN_RECORD_SIZE} bytes")]
+    RecordSizeTooSmall,
+    /// Segment size is not bigger than record size
+    #[error("Segment size is not bigger than record size")]
+    SegmentSizeTooSmall,
+    /// Segment size is not a multiple of record size
+    #[error("Segment size is not a multiple of record size")]
+    SegmentSizesNotMultipleOfRecordSize,
+}
+
+/// Object fetching error
+#[derive(Debug, thiserror::Error)]
+pub enum ObjectFetchingError {
+    /// Failed to get piece
+    #[error("Failed to get piece {piece_index}: {error}")]
+    PieceGetter {
+        piece_index: PieceIndex,
+        error: ObjectPieceGetterError,
+    },
+    /// Piece not found
+    #[error("Piece {piece_index} not found")]
+    PieceNotFound { piece_index: PieceIndex },
+    /// Failed to get records root
+    #[error("Failed to get records root of segment {segment_index}: {error}")]
+    RecordsRootGetter {
+        segment_index: SegmentIndex,
+        error: ObjectPieceGetterError,
+    },
+    /// Records root not found
+    #[error("Records root of segment {segment_index} not found")]
+    RecordsRootNotFound { segment_index: SegmentIndex },
+    /// Piece doesn't match records root of its segment
+    #[error("Piece {piece_index} doesn't match records root of its segment")]
+    InvalidPiece { piece_index: PieceIndex },
+    /// Object offset is outside of the piece record
+    #[error("Offset {offset} is outside of the record of size {record_size}")]
+    InvalidOffset { offset: u32, record_size: u32 },
+    /// Invalid data length prefix
+    #[error("Invalid data length prefix found: 0x{0:02x}")]
+    InvalidDataLengthPrefix(u8),
+    /// Failed to decode object data length
+    #[error("Failed to decode object data length: {0}")]
+    DataLengthDecoding(parity_scale_codec::Error),
+    /// Failed to decode segment
+    #[error("Failed to decode segment {segment_index}: {error}")]
+    SegmentDecoding {
+        segment_index: SegmentIndex,
+        error: parity_scale_codec::Error,
+    },
+    /// No segment item at offset
+    #[error("Failed to find item at offset {offset_in_segment} in segment {segment_index}")]
+    SegmentItemNotFound {
+        segment_index: SegmentIndex,
+        offset_in_segment: u64,
+    },
+    /// Unexpected segment item at offset
+    #[error("Unexpected segment item at offset {offset_in_segment} in segment {segment_index}")]
+    UnexpectedSegmentItem {
+        segment_index: SegmentIndex,
+        offset_in_segment: u64,
+    },
+    /// Offset points to segment item metadata rather than its bytes
+    #[error(
+        "Offset {offset_in_segment} in segment {segment_index} doesn't point to segment item bytes"
+    )]
+    OffsetOutsideOfSegmentItemBytes {
+        segment_index: SegmentIndex,
+        offset_in_segment: u64,
+    },
+    /// Object is larger than allowed maximum
+    #[error("Object is larger than maximum object size {max_object_size} bytes")]
+    ObjectTooLarge { max_object_size: usize },
+}
+
+/// Object fetcher that assembles objects from pieces of archived history, verifying every piece
+/// against records root of its segment.
+#[derive(Debug)]
+pub struct ObjectFetcher<PG> {
+    piece_getter: PG,
+    kzg: Kzg,
+    record_size: u32,
+    /// Number of data + parity pieces in one segment
+    pieces_in_segment: u32,
+    max_object_size: usize,
+    /// Records roots of recent segments that were already retrieved, they never change once
+    /// segment is archived
+    records_roots: Mutex<LruCache<SegmentIndex, RecordsRoot>>,
+}
+
+impl<PG> ObjectFetcher<PG>
+where
+    PG: ObjectPieceGetter + Send + Sync,
+{
+    /// Create a new instance with specified record size, recorded history segment size and
+    /// maximum size of the object that can be fetched.
+    pub fn new(
+        piece_getter: PG,
+        kzg: Kzg,
+        record_size: u32,
+        segment_size: u32,
+        max_object_size: usize,
+    ) -> Result<Self, ObjectFetcherInstantiationError> {
+        if record_size < MIN_RECORD_SIZE {
+            return Err(ObjectFetcherInstantiationError::RecordSizeTooSmall);
+        }
+        if segment_size <= record_size {
+            return Err(ObjectFetcherInstantiationError::SegmentSizeTooSmall);
+        }
+        if segment_size % record_size != 0 {
+            return Err(ObjectFetcherInstantiationError::SegmentSizesNotMultipleOfRecordSize);
+        }
+
+        Ok(Self {
+            piece_getter,
+            kzg,
+            record_size,
+            pieces_in_segment: segment_size / record_size * 2,
+            max_object_size,
+            records_roots: Mutex::new(LruCache::new(RECORDS_ROOTS_CACHE_SIZE)),
+        })
+    }
+
+    /// Fetch object that starts at piece index and offset specified by `global_object` by reading
+    /// necessary pieces and putting necessary bytes together.
+    pub async fn fetch_object(
+        &self,
+        global_object: GlobalObject,
+    ) -> Result<Vec<u8>, ObjectFetchingError> {
+        let piece_index = global_object.piece_index();
+        let offset = global_object.offset();
+
+        if offset >= self.record_size {
+            return Err(ObjectFetchingError::InvalidOffset {
+                offset,
+                record_size: self.record_size,
+            });
+        }
+
+        // Try fast object assembling
+        if let Some(data) = self.fetch_object_fast(piece_index, offset).await? {
+            return Ok(data);
+        }
+
+        self.fetch_object_regular(piece_index, offset).await
+    }
+
+    /// Fast object fetching in case object doesn't cross piece (super fast) or segment (just
+    /// fast) boundary, returns `Ok(None)` if fast retrieval possibility is not guaranteed.
+    async fn fetch_object_fast(
+        &self,
+        piece_index: PieceIndex,
+        offset: u32,
+    ) -> Result<Option<Vec<u8>>, ObjectFetchingError> {
+        // We care if the offset is before the last 2 bytes of a piece because if not we might be
+        // able to do very fast object retrieval without assembling and processing the whole
+        // segment. `-2` is because last 2 bytes might contain padding if a piece is the last piece
+        // in the segment.
+        let before_last_two_bytes = offset <= self.record_size - 1 - 2;
+
+        // We care about whether piece index points to the last data piece in the segment because
+        // if not we might be able to do very fast object retrieval without assembling and
+        // processing the whole segment.
+        let last_data_piece_in_segment = {
+            let piece_position_in_segment = piece_index % u64::from(self.pieces_in_segment);
+            let last_piece_position_in_segment = u64::from(self.pieces_in_segment) / 2 - 1;
+
+            piece_position_in_segment >= last_piece_position_in_segment
+        };
+
+        // How much bytes are definitely available starting at `piece_index` and `offset` without
+        // crossing segment boundary
+        let bytes_available_in_segment = {
+            let data_shards = u64::from(self.pieces_in_segment / 2);
+            let piece_position = piece_index % u64::from(self.pieces_in_segment);
+
+            // `-2` is because last 2 bytes might contain padding if a piece is the last piece in
+            // the segment.
+            (data_shards.saturating_sub(piece_position) * u64::from(self.record_size))
+                .saturating_sub(u64::from(offset) + 2)
+        };
+
+        if last_data_piece_in_segment && !before_last_two_bytes {
+            // Fast retrieval possibility is not guaranteed
+            return Ok(None);
+        }
+
+        // Cache of read pieces that were already read, starting with piece at index `piece_index`
+        let mut read_records_data = Vec::<u8>::with_capacity(self.record_size as usize * 2);
+        let mut next_piece_index = piece_index;
+
+        let piece = self.read_piece(next_piece_index).await?;
+        next_piece_index += 1;
+        read_records_data.extend_from_slice(&piece[..self.record_size as usize]);
+
+        // Let's see how many bytes encode compact length encoding of the data, see
+        // https://docs.substrate.io/v3/advanced/scale-codec/#compactgeneral-integers for
+        // details.
+        let data_length_bytes_length: u32 = match read_records_data[offset as usize] % 4 {
+            0 => 1,
+            1 => 2,
+            2 => 4,
+            _ => {
+                return Err(ObjectFetchingError::InvalidDataLengthPrefix(
+                    read_records_data[offset as usize],
+                ));
+            }
+        };
+
+        // Same as `before_last_two_bytes`, but accounts for compact encoding of data length
+        let length_before_last_two_bytes =
+            offset + data_length_bytes_length < self.record_size - 1 - 2;
+        // Similar to `length_before_last_two_bytes`, but uses the whole record if needed
+        let length_before_record_end = offset + data_length_bytes_length < self.record_size - 1;
+
+        let data_length_result = if length_before_last_two_bytes {
+            Compact::<u32>::decode(&mut &read_records_data[offset as usize..])
+        } else if !last_data_piece_in_segment {
+            if !length_before_record_end {
+                // Need the next piece to read the length of data
+                let piece = self.read_piece(next_piece_index).await?;
+                next_piece_index += 1;
+                read_records_data.extend_from_slice(&piece[..self.record_size as usize]);
+            }
+
+            Compact::<u32>::decode(&mut &read_records_data[offset as usize..])
+        } else {
+            // Super fast read is not possible
+            return Ok(None);
+        };
+
+        let Compact(data_length) =
+            data_length_result.map_err(ObjectFetchingError::DataLengthDecoding)?;
+
+        if data_length as usize > self.max_object_size {
+            return Err(ObjectFetchingError::ObjectTooLarge {
+                max_object_size: self.max_object_size,
+            });
+        }
+
+        if u64::from(data_length_bytes_length + data_length) > bytes_available_in_segment {
+            // Not enough data without crossing segment boundary
+            return Ok(None);
+        }
+
+        let mut data =
+            read_records_data[offset as usize + data_length_bytes_length as usize..].to_vec();
+        drop(read_records_data);
+
+        // Read the rest of pieces with enough data, they are all in the same segment, so can be
+        // verified in one batch
+        let remaining_bytes = (data_length as usize).saturating_sub(data.len());
+        let remaining_pieces =
+            (remaining_bytes + self.record_size as usize - 1) / self.record_size as usize;
+        for piece in self
+            .read_pieces(next_piece_index, remaining_pieces as u32)
+            .await?
+        {
+            data.extend_from_slice(&piece[..self.record_size as usize]);
+        }
+
+        // Trim the excess
+        data.truncate(data_length as usize);
+
+        Ok(Some(data))
+    }
+
+    /// Fetch object that can cross segment boundary, which requires assembling and iterating over
+    /// full segments.
+    async fn fetch_object_regular(
+        &self,
+        piece_index: PieceIndex,
+        offset: u32,
+    ) -> Result<Vec<u8>, ObjectFetchingError> {
+        let segment_index = piece_index / u64::from(self.pieces_in_segment);
+        let piece_position_in_segment = piece_index % u64::from(self.pieces_in_segment);
+        let offset_in_segment =
+            piece_position_in_segment * u64::from(self.record_size) + u64::from(offset);
+
+        let mut data = {
+            let Segment::V0 { items } = self.read_segment(segment_index).await?;
+            // Unconditional progress is enum variant + compact encoding of number of elements
+            let mut progress = 1 + Compact::compact_len(&(items.len() as u64));
+            let segment_item = items
+                .into_iter()
+                .find(|item| {
+                    // Add number of bytes in encoded version of segment item
+                    progress += item.encoded_size();
+
+                    // Our data is within another segment item, which will have wrapping data
+                    // structure, hence strictly `>` here
+                    progress > offset_in_segment as usize
+                })
+                .ok_or(ObjectFetchingError::SegmentItemNotFound {
+                    segment_index,
+                    offset_in_segment,
+                })?;
+
+            match segment_item {
+                SegmentItem::Block { bytes, .. }
+                | SegmentItem::BlockStart { bytes, .. }
+                | SegmentItem::BlockContinuation { bytes, .. } => {
+                    // Rewind back progress to the beginning of the number of bytes
+                    progress -= bytes.len();
+                    // Get a chunk of the bytes starting at the position we care about
+                    let offset_in_bytes = (offset_in_segment as usize)
+                        .checked_sub(progress)
+                        .ok_or(ObjectFetchingError::OffsetOutsideOfSegmentItemBytes {
+                            segment_index,
+                            offset_in_segment,
+                        })?;
+                    Vec::from(&bytes[offset_in_bytes..])
+                }
+                _ => {
+                    return Err(ObjectFetchingError::UnexpectedSegmentItem {
+                        segment_index,
+                        offset_in_segment,
+                    });
+                }
+            }
+        };
+
+        if let Some(data) = self.decode_object(&data)? {
+            return Ok(data);
+        }
+
+        for segment_index in segment_index + 1.. {
+            let Segment::V0 { items } = self.read_segment(segment_index).await?;
+            for segment_item in items {
+                if let SegmentItem::BlockContinuation { bytes, .. } = segment_item {
+                    data.extend_from_slice(&bytes);
+
+                    if let Some(data) = self.decode_object(&data)? {
+                        return Ok(data);
+                    }
+                }
+            }
+
+            if data.len() >= self.max_object_size {
+                break;
+            }
+        }
+
+        Err(ObjectFetchingError::ObjectTooLarge {
+            max_object_size: self.max_object_size,
+        })
+    }
+
+    /// Try to decode length-prefixed object from the beginning of `data`, returns `Ok(None)` if
+    /// more data is needed.
+    fn decode_object(&self, data: &[u8]) -> Result<Option<Vec<u8>>, ObjectFetchingError> {
+        let data_length = match Compact::<u32>::decode(&mut &*data) {
+            Ok(Compact(data_length)) => data_length,
+            Err(_) => {
+                // Not enough bytes for data length yet
+                return Ok(None);
+            }
+        };
+
+        if data_length as usize > self.max_object_size {
+            return Err(ObjectFetchingError::ObjectTooLarge {
+                max_object_size: self.max_object_size,
+            });
+        }
+
+        Ok(Vec::<u8>::decode(&mut &*data).ok())
+    }
+
+    /// Read the whole segment by its index (just records, skipping witnesses)
+    async fn read_segment(
+        &self,
+        segment_index: SegmentIndex,
+    ) -> Result<Segment, ObjectFetchingError> {
+        let first_piece_in_segment = segment_index * SegmentIndex::from(self.pieces_in_segment);
+        let mut segment_bytes =
+            Vec::<u8>::with_capacity((self.pieces_in_segment / 2 * self.record_size) as usize);
+
+        for piece in self
+            .read_pieces(first_piece_in_segment, self.pieces_in_segment / 2)
+            .await?
+        {
+            segment_bytes.extend_from_slice(&piece[..self.record_size as usize]);
+        }
+
+        Segment::decode(&mut segment_bytes.as_slice()).map_err(|error| {
+            ObjectFetchingError::SegmentDecoding {
+                segment_index,
+                error,
+            }
+        })
+    }
+
+    /// Read the whole piece and verify it against records root of its segment
+    async fn read_piece(&self, piece_index: PieceIndex) -> Result<Piece, ObjectFetchingError> {
+        let mut pieces = self.read_pieces(piece_index, 1).await?;
+
+        Ok(pieces
+            .pop()
+            .expect("Exactly one piece was requested and read; qed"))
+    }
+
+    /// Read `count` consecutive pieces of the same segment starting with `first_piece_index` and
+    /// verify them against records root of the segment in one batch
+    async fn read_pieces(
+        &self,
+        first_piece_index: PieceIndex,
+        count: u32,
+    ) -> Result<Vec<Piece>, ObjectFetchingError> {
+        let mut pieces = Vec::with_capacity(count as usize);
+        for piece_index in (first_piece_index..).take(count as usize) {
+            let piece = self
+                .piece_getter
+                .get_piece(piece_index)
+                .await
+                .map_err(|error| ObjectFetchingError::PieceGetter { piece_index, error })?
+                .ok_or(ObjectFetchingError::PieceNotFound { piece_index })?;
+
+            pieces.push(piece);
+        }
+
+        if pieces.is_empty() {
+            return Ok(pieces);
+        }
+
+        let segment_index = first_piece_index / SegmentIndex::from(self.pieces_in_segment);
+        let first_position = (first_piece_index % PieceIndex::from(self.pieces_in_segment)) as u32;
+        let records_root = self.records_root(segment_index).await?;
+
+        let positions_and_pieces = pieces
+            .iter()
+            .zip(first_position..)
+            .map(|(piece, position)| (position, piece.as_ref()))
+            .collect::<Vec<_>>();
+
+        if is_pieces_valid_batch(
+            &self.kzg,
+            self.pieces_in_segment,
+            &positions_and_pieces,
+            records_root,
+            self.record_size,
+        ) {
+            return Ok(pieces);
+        }
+
+        // Batch verification doesn't tell which piece is invalid, find it for error reporting
+        let invalid_piece_index = positions_and_pieces
+            .into_iter()
+            .zip(first_piece_index..)
+            .find_map(|((position, piece), piece_index)| {
+                (!is_piece_valid(
+                    &self.kzg,
+                    self.pieces_in_segment,
+                    piece,
+                    records_root,
+                    position,
+                    self.record_size,
+                ))
+                .then_some(piece_index)
+            })
+            .unwrap_or(first_piece_index);
+
+        Err(ObjectFetchingError::InvalidPiece {
+            piece_index: invalid_piece_index,
+        })
+    }
+
+    /// Get records root of the segment, retrieved records roots are cached
+    async fn records_root(
+        &self,
+        segment_index: SegmentIndex,
+    ) -> Result<RecordsRoot, ObjectFetchingError> {
+        if let Some(records_root) = self
+            .records_roots
+            .lock()
+            .expect("Lock is never poisoned; qed")
+            .get(&segment_index)
+        {
+            return Ok(*records_root);
+        }
+
+        let records_root = self
+            .piece_getter
+            .records_root(segment_index)
+            .await
+            .map_err(|error| ObjectFetchingError::RecordsRootGetter {
+                segment_index,
+                error,
+            })?
+            .ok_or(ObjectFetchingError::RecordsRootNotFound { segment_index })?;
+
+        self.records_roots
+            .lock()
+            .expect("Lock is never poisoned; qed")
+            .put(segment_index, records_root);
+
+        Ok(records_root)
+    }
+}
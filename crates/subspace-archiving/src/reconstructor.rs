@@ -115,47 +115,12 @@ impl Reconstructor {
         &mut self,
         segment_pieces: &[Option<Piece>],
     ) -> Result<ReconstructedContents, ReconstructorError> {
-        let mut segment_data = Vec::with_capacity(self.segment_size as usize);
-        if !segment_pieces
-            .iter()
-            .take(self.reed_solomon.data_shard_count())
-            .all(|maybe_piece| {
-                if let Some(piece) = maybe_piece {
-                    segment_data.extend_from_slice(&piece[..self.record_size as usize]);
-                    true
-                } else {
-                    false
-                }
-            })
-        {
-            // If not all data pieces are available, need to reconstruct data shards using erasure
-            // coding.
-            let mut shards = segment_pieces
-                .iter()
-                .map(|maybe_piece| maybe_piece.as_ref().map(utils::slice_to_arrays))
-                .collect::<Vec<_>>();
-
-            self.reed_solomon
-                .reconstruct_data(&mut shards)
-                .map_err(ReconstructorError::DataShardsReconstruction)?;
-
-            segment_data.clear();
-            shards
-                .into_iter()
-                .take(self.reed_solomon.data_shard_count())
-                .for_each(|maybe_piece| {
-                    let piece = maybe_piece.expect(
-                        "All data shards are available after successful reconstruction; qed",
-                    );
-
-                    for chunk in piece.iter().take(self.record_size as usize / 2) {
-                        segment_data.extend_from_slice(chunk.as_ref());
-                    }
-                });
-        }
-
-        let Segment::V0 { items } = Segment::decode(&mut segment_data.as_ref())
-            .map_err(ReconstructorError::SegmentDecoding)?;
+        let items = decode_segment_items(
+            &self.reed_solomon,
+            self.record_size,
+            self.segment_size,
+            segment_pieces,
+        )?;
 
         let mut reconstructed_contents = ReconstructedContents::default();
         let mut next_block_number = 0;
@@ -251,3 +216,55 @@ impl Reconstructor {
         Ok(reconstructed_contents)
     }
 }
+
+/// Decodes items of a segment from its pieces, reconstructing data shards with erasure coding if
+/// some of them are missing (any half of all pieces are required to be present).
+pub(crate) fn decode_segment_items(
+    reed_solomon: &ReedSolomon,
+    record_size: u32,
+    segment_size: u32,
+    segment_pieces: &[Option<Piece>],
+) -> Result<Vec<SegmentItem>, ReconstructorError> {
+    let mut segment_data = Vec::with_capacity(segment_size as usize);
+    if !segment_pieces
+        .iter()
+        .take(reed_solomon.data_shard_count())
+        .all(|maybe_piece| {
+            if let Some(piece) = maybe_piece {
+                segment_data.extend_from_slice(&piece[..record_size as usize]);
+                true
+            } else {
+                false
+            }
+        })
+    {
+        // If not all data pieces are available, need to reconstruct data shards using erasure
+        // coding.
+        let mut shards = segment_pieces
+            .iter()
+            .map(|maybe_piece| maybe_piece.as_ref().map(utils::slice_to_arrays))
+            .collect::<Vec<_>>();
+
+        reed_solomon
+            .reconstruct_data(&mut shards)
+            .map_err(ReconstructorError::DataShardsReconstruction)?;
+
+        segment_data.clear();
+        shards
+            .into_iter()
+            .take(reed_solomon.data_shard_count())
+            .for_each(|maybe_piece| {
+                let piece = maybe_piece
+                    .expect("All data shards are available after successful reconstruction; qed");
+
+                for chunk in piece.iter().take(record_size as usize / 2) {
+                    segment_data.extend_from_slice(chunk.as_ref());
+                }
+            });
+    }
+
+    let Segment::V0 { items } =
+        Segment::decode(&mut segment_data.as_ref()).map_err(ReconstructorError::SegmentDecoding)?;
+
+    Ok(items)
+}
@@ -3,7 +3,9 @@ use std::assert_matches::assert_matches;
 use std::io::Write;
 use std::iter;
 use subspace_archiving::archiver;
-use subspace_archiving::archiver::{Archiver, ArchiverInstantiationError, SegmentItem};
+use subspace_archiving::archiver::{
+    Archiver, ArchiverInstantiationError, ArchiverState, SegmentItem,
+};
 use subspace_core_primitives::crypto::kzg::{Commitment, Kzg};
 use subspace_core_primitives::objects::{BlockObject, BlockObjectMapping, PieceObject};
 use subspace_core_primitives::{
@@ -154,12 +156,61 @@ fn archiver() {
             RECORD_SIZE,
         ));
     }
+    {
+        let pieces = first_archived_segment
+            .pieces
+            .as_pieces()
+            .enumerate()
+            .map(|(position, piece)| (position as u32, piece))
+            .collect::<Vec<_>>();
+
+        assert!(archiver::is_pieces_valid_batch(
+            &kzg,
+            PIECES_IN_SEGMENT,
+            &pieces,
+            first_archived_segment.root_block.records_root(),
+            RECORD_SIZE,
+        ));
+
+        // Pieces at wrong positions are detected
+        let mut shuffled_pieces = pieces.clone();
+        shuffled_pieces.swap(0, 1);
+        let shuffled_pieces = shuffled_pieces
+            .into_iter()
+            .enumerate()
+            .map(|(position, (_original_position, piece))| (position as u32, piece))
+            .collect::<Vec<_>>();
+        assert!(!archiver::is_pieces_valid_batch(
+            &kzg,
+            PIECES_IN_SEGMENT,
+            &shuffled_pieces,
+            first_archived_segment.root_block.records_root(),
+            RECORD_SIZE,
+        ));
+    }
+
+    // Exported state must survive encoding, including object mappings of buffered blocks
+    let exported_state =
+        ArchiverState::decode(&mut archiver.export_state().encode().as_slice()).unwrap();
 
     let block_2 = rand::random::<[u8; SEGMENT_SIZE as usize * 2]>().to_vec();
     // This should be big enough to produce two archived segments in one go
     let archived_segments = archiver.add_block(block_2.clone(), BlockObjectMapping::default());
     assert_eq!(archived_segments.len(), 2);
 
+    // Check that archiver restored from exported state before last block results in the same
+    // archived segments once last block is added
+    {
+        let mut archiver_from_exported_state =
+            Archiver::from_exported_state(RECORD_SIZE, SEGMENT_SIZE, kzg.clone(), exported_state)
+                .unwrap();
+
+        assert_eq!(
+            archiver_from_exported_state.add_block(block_2.clone(), BlockObjectMapping::default()),
+            archived_segments,
+        );
+    }
+
     // Check that initializing archiver with initial state before last block results in the same
     // archived segments once last block is added
     {
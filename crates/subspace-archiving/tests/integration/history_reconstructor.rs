@@ -0,0 +1,150 @@
+use std::assert_matches::assert_matches;
+use subspace_archiving::archiver::{ArchivedSegment, Archiver};
+use subspace_archiving::history_reconstructor::{
+    HistoryReconstructor, HistoryReconstructorError, MissingSegmentPieces,
+};
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::objects::BlockObjectMapping;
+use subspace_core_primitives::{Piece, PieceIndex, RECORD_SIZE};
+
+// This is data + parity shards
+const PIECES_IN_SEGMENT: u32 = 8;
+// In terms of source data that can be stored in the segment, not the size after archiving
+const SEGMENT_SIZE: u32 = RECORD_SIZE * PIECES_IN_SEGMENT / 2;
+
+fn archive_blocks() -> (Vec<Vec<u8>>, Vec<ArchivedSegment>) {
+    let kzg = Kzg::random(PIECES_IN_SEGMENT).unwrap();
+    let mut archiver = Archiver::new(RECORD_SIZE, SEGMENT_SIZE, kzg).unwrap();
+    let blocks = vec![
+        // Block that fits into the segment fully
+        rand::random::<[u8; SEGMENT_SIZE as usize / 2]>().to_vec(),
+        // Block that overflows into the next segment
+        rand::random::<[u8; SEGMENT_SIZE as usize]>().to_vec(),
+        // Block that also fits into the segment fully
+        rand::random::<[u8; SEGMENT_SIZE as usize / 4]>().to_vec(),
+        // Block that occupies multiple segments
+        rand::random::<[u8; SEGMENT_SIZE as usize * 3]>().to_vec(),
+        // Extra block
+        rand::random::<[u8; SEGMENT_SIZE as usize]>().to_vec(),
+    ];
+    let archived_segments = blocks
+        .iter()
+        .flat_map(|block| archiver.add_block(block.clone(), BlockObjectMapping::default()))
+        .collect::<Vec<_>>();
+
+    assert_eq!(archived_segments.len(), 5);
+
+    (blocks, archived_segments)
+}
+
+fn segment_pieces(
+    archived_segment: &ArchivedSegment,
+) -> impl Iterator<Item = (PieceIndex, Piece)> + '_ {
+    let first_piece_index =
+        archived_segment.root_block.segment_index() * PieceIndex::from(PIECES_IN_SEGMENT);
+
+    archived_segment
+        .pieces
+        .as_pieces()
+        .zip(first_piece_index..)
+        .map(|(piece, piece_index)| (piece_index, piece.try_into().unwrap()))
+}
+
+#[test]
+fn out_of_order() {
+    let (blocks, archived_segments) = archive_blocks();
+
+    let mut reconstructor = HistoryReconstructor::new(RECORD_SIZE, SEGMENT_SIZE).unwrap();
+    let mut reconstructed_blocks = Vec::new();
+
+    // Segments are added in reverse order with only half of the pieces each, alternating between
+    // data and parity pieces
+    for archived_segment in archived_segments.iter().rev() {
+        let use_parity = archived_segment.root_block.segment_index() % 2 == 1;
+        let mut segment_ready = false;
+
+        for (position, (piece_index, piece)) in segment_pieces(archived_segment).enumerate() {
+            if (position >= PIECES_IN_SEGMENT as usize / 2) == use_parity {
+                assert!(!segment_ready);
+                segment_ready = reconstructor.add_piece(piece_index, piece);
+            }
+        }
+
+        assert!(segment_ready);
+        reconstructed_blocks.extend(reconstructor.reconstruct().unwrap());
+    }
+
+    reconstructed_blocks.sort_by_key(|(block_number, _block)| *block_number);
+
+    // Last block is not archived fully
+    let expected_blocks = blocks
+        .into_iter()
+        .take(4)
+        .enumerate()
+        .map(|(block_number, block)| (block_number as u32, block))
+        .collect::<Vec<_>>();
+    assert_eq!(reconstructed_blocks, expected_blocks);
+}
+
+#[test]
+fn missing_pieces() {
+    let (_blocks, archived_segments) = archive_blocks();
+
+    let mut reconstructor = HistoryReconstructor::new(RECORD_SIZE, SEGMENT_SIZE).unwrap();
+
+    // Location of blocks is not known without root blocks
+    assert_matches!(
+        reconstructor.missing_pieces(0..=0),
+        Err(HistoryReconstructorError::UnknownBlockLocation { block_number: 0 })
+    );
+
+    for archived_segment in &archived_segments {
+        reconstructor.add_root_block(archived_segment.root_block);
+    }
+
+    // Second block starts in the first segment and ends in the second one
+    assert_eq!(
+        reconstructor.missing_pieces(1..=1).unwrap(),
+        vec![
+            MissingSegmentPieces {
+                segment_index: 0,
+                pieces_needed: 4,
+                missing_pieces: (0..8).collect(),
+            },
+            MissingSegmentPieces {
+                segment_index: 1,
+                pieces_needed: 4,
+                missing_pieces: (8..16).collect(),
+            },
+        ]
+    );
+
+    let mut pieces = segment_pieces(&archived_segments[0]);
+    for (piece_index, piece) in pieces.by_ref().take(2) {
+        assert!(!reconstructor.add_piece(piece_index, piece));
+    }
+
+    assert_eq!(
+        reconstructor.missing_pieces(1..=1).unwrap()[0],
+        MissingSegmentPieces {
+            segment_index: 0,
+            pieces_needed: 2,
+            missing_pieces: (2..8).collect(),
+        }
+    );
+
+    for (piece_index, piece) in pieces.take(2) {
+        reconstructor.add_piece(piece_index, piece);
+    }
+
+    // First segment has enough pieces now
+    assert_eq!(
+        reconstructor
+            .missing_pieces(1..=1)
+            .unwrap()
+            .into_iter()
+            .map(|missing_segment_pieces| missing_segment_pieces.segment_index)
+            .collect::<Vec<_>>(),
+        vec![1]
+    );
+}
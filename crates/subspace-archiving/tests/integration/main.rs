@@ -1,4 +1,6 @@
 #![feature(assert_matches)]
 
 mod archiver;
+mod history_reconstructor;
+mod object_fetcher;
 mod reconstructor;
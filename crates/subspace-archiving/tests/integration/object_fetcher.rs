@@ -0,0 +1,329 @@
+use async_trait::async_trait;
+use futures::executor::block_on;
+use parity_scale_codec::{Compact, Decode, Encode};
+use rand::Rng;
+use std::assert_matches::assert_matches;
+use std::collections::HashMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use subspace_archiving::archiver::Archiver;
+use subspace_archiving::object_fetcher::{
+    ObjectFetcher, ObjectFetcherInstantiationError, ObjectFetchingError, ObjectPieceGetter,
+    ObjectPieceGetterError, DEFAULT_MAX_OBJECT_SIZE,
+};
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::objects::{BlockObject, BlockObjectMapping, GlobalObject};
+use subspace_core_primitives::{
+    Blake2b256Hash, Piece, PieceIndex, RecordsRoot, SegmentIndex, RECORD_SIZE,
+};
+
+// This is data + parity shards
+const PIECES_IN_SEGMENT: u32 = 8;
+// In terms of source data that can be stored in the segment, not the size after archiving
+const SEGMENT_SIZE: u32 = RECORD_SIZE * PIECES_IN_SEGMENT / 2;
+
+#[derive(Default, Clone)]
+struct TestPieceGetter {
+    pieces: HashMap<PieceIndex, Piece>,
+    records_roots: HashMap<SegmentIndex, RecordsRoot>,
+    records_root_requests: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl ObjectPieceGetter for TestPieceGetter {
+    async fn get_piece(
+        &self,
+        piece_index: PieceIndex,
+    ) -> Result<Option<Piece>, ObjectPieceGetterError> {
+        Ok(self.pieces.get(&piece_index).cloned())
+    }
+
+    async fn records_root(
+        &self,
+        segment_index: SegmentIndex,
+    ) -> Result<Option<RecordsRoot>, ObjectPieceGetterError> {
+        self.records_root_requests.fetch_add(1, Ordering::SeqCst);
+
+        Ok(self.records_roots.get(&segment_index).copied())
+    }
+}
+
+fn create_block(size: usize, objects: &[(u32, u32)]) -> (Vec<u8>, BlockObjectMapping) {
+    let mut block = vec![0u8; size];
+    rand::thread_rng().fill(block.as_mut_slice());
+
+    for &(offset, length) in objects {
+        block[offset as usize..]
+            .as_mut()
+            .write_all(&Compact(length).encode())
+            .unwrap();
+    }
+
+    let object_mapping = BlockObjectMapping {
+        objects: objects
+            .iter()
+            .map(|&(offset, _length)| BlockObject::V0 {
+                hash: Blake2b256Hash::default(),
+                offset,
+            })
+            .collect(),
+    };
+
+    (block, object_mapping)
+}
+
+/// Archives a few blocks with objects that fit into one piece, cross piece boundary and cross
+/// segment boundary, returns piece getter and pairs of global objects with expected object data.
+fn archive_objects() -> (Kzg, TestPieceGetter, Vec<(GlobalObject, Vec<u8>)>) {
+    let kzg = Kzg::random(PIECES_IN_SEGMENT).unwrap();
+    let mut archiver = Archiver::new(RECORD_SIZE, SEGMENT_SIZE, kzg.clone()).unwrap();
+
+    let blocks = vec![
+        create_block(
+            SEGMENT_SIZE as usize / 2,
+            &[
+                // Within the first piece
+                (0, 100),
+                // Crosses piece boundary
+                (RECORD_SIZE - 50, 2048),
+            ],
+        ),
+        create_block(
+            SEGMENT_SIZE as usize,
+            &[
+                // Crosses segment boundary
+                (100, SEGMENT_SIZE / 2),
+            ],
+        ),
+        // Filler to make sure objects above are archived completely
+        create_block(SEGMENT_SIZE as usize * 2, &[]),
+    ];
+
+    let mut expected_objects = Vec::new();
+    for (block, object_mapping) in &blocks {
+        for block_object in &object_mapping.objects {
+            let mut data = &block[block_object.offset() as usize..];
+            expected_objects.push(Vec::<u8>::decode(&mut data).unwrap());
+        }
+    }
+
+    let mut piece_getter = TestPieceGetter::default();
+    let mut global_objects = Vec::new();
+    for (block, object_mapping) in blocks {
+        for archived_segment in archiver.add_block(block, object_mapping) {
+            let segment_index = archived_segment.root_block.segment_index();
+            let first_piece_index = segment_index * PieceIndex::from(PIECES_IN_SEGMENT);
+
+            piece_getter
+                .records_roots
+                .insert(segment_index, archived_segment.root_block.records_root());
+
+            for (position, piece) in archived_segment.pieces.as_pieces().enumerate() {
+                piece_getter.pieces.insert(
+                    first_piece_index + position as PieceIndex,
+                    piece.try_into().unwrap(),
+                );
+            }
+
+            for (position, piece_object_mapping) in
+                archived_segment.object_mapping.iter().enumerate()
+            {
+                for piece_object in &piece_object_mapping.objects {
+                    global_objects.push(GlobalObject::V0 {
+                        piece_index: first_piece_index + position as PieceIndex,
+                        offset: piece_object.offset(),
+                    });
+                }
+            }
+        }
+    }
+
+    assert_eq!(global_objects.len(), expected_objects.len());
+
+    (
+        kzg,
+        piece_getter,
+        global_objects.into_iter().zip(expected_objects).collect(),
+    )
+}
+
+#[test]
+fn object_fetcher() {
+    let (kzg, piece_getter, objects) = archive_objects();
+
+    let object_fetcher = ObjectFetcher::new(
+        piece_getter,
+        kzg,
+        RECORD_SIZE,
+        SEGMENT_SIZE,
+        DEFAULT_MAX_OBJECT_SIZE,
+    )
+    .unwrap();
+
+    for (global_object, expected_data) in objects {
+        let data = block_on(object_fetcher.fetch_object(global_object)).unwrap();
+        assert_eq!(data, expected_data);
+    }
+}
+
+#[test]
+fn object_fetcher_invalid_record_size() {
+    let kzg = Kzg::random(PIECES_IN_SEGMENT).unwrap();
+
+    for record_size in [1, 3] {
+        assert_eq!(
+            ObjectFetcher::new(
+                TestPieceGetter::default(),
+                kzg.clone(),
+                record_size,
+                record_size * PIECES_IN_SEGMENT / 2,
+                DEFAULT_MAX_OBJECT_SIZE,
+            )
+            .err(),
+            Some(ObjectFetcherInstantiationError::RecordSizeTooSmall)
+        );
+    }
+}
+
+#[test]
+fn object_fetcher_caches_records_roots() {
+    let (kzg, piece_getter, objects) = archive_objects();
+    let records_root_requests = Arc::clone(&piece_getter.records_root_requests);
+    let segments = piece_getter.records_roots.len();
+
+    let object_fetcher = ObjectFetcher::new(
+        piece_getter,
+        kzg,
+        RECORD_SIZE,
+        SEGMENT_SIZE,
+        DEFAULT_MAX_OBJECT_SIZE,
+    )
+    .unwrap();
+
+    for _ in 0..2 {
+        for (global_object, expected_data) in &objects {
+            let data = block_on(object_fetcher.fetch_object(*global_object)).unwrap();
+            assert_eq!(&data, expected_data);
+        }
+    }
+
+    // Each records root is requested at most once, no matter how many pieces were read
+    assert!(records_root_requests.load(Ordering::SeqCst) <= segments);
+}
+
+#[test]
+fn object_fetcher_invalid_offset() {
+    let (kzg, piece_getter, objects) = archive_objects();
+    let piece_index = objects[0].0.piece_index();
+
+    let object_fetcher = ObjectFetcher::new(
+        piece_getter,
+        kzg,
+        RECORD_SIZE,
+        SEGMENT_SIZE,
+        DEFAULT_MAX_OBJECT_SIZE,
+    )
+    .unwrap();
+
+    for offset in [RECORD_SIZE, RECORD_SIZE + 1, u32::MAX] {
+        assert_matches!(
+            block_on(object_fetcher.fetch_object(GlobalObject::V0 {
+                piece_index,
+                offset
+            })),
+            Err(ObjectFetchingError::InvalidOffset {
+                offset: invalid_offset,
+                record_size: RECORD_SIZE
+            }) if invalid_offset == offset
+        );
+    }
+}
+
+#[test]
+fn object_fetcher_max_object_size() {
+    let (kzg, piece_getter, objects) = archive_objects();
+
+    let object_fetcher =
+        ObjectFetcher::new(piece_getter, kzg, RECORD_SIZE, SEGMENT_SIZE, 1024).unwrap();
+
+    for (global_object, expected_data) in objects {
+        let result = block_on(object_fetcher.fetch_object(global_object));
+        if expected_data.len() <= 1024 {
+            assert_eq!(result.unwrap(), expected_data);
+        } else {
+            assert_matches!(
+                result,
+                Err(ObjectFetchingError::ObjectTooLarge {
+                    max_object_size: 1024
+                })
+            );
+        }
+    }
+}
+
+#[test]
+fn object_fetcher_invalid_pieces() {
+    let (kzg, piece_getter, objects) = archive_objects();
+    let global_object = objects[0].0;
+
+    {
+        let mut piece_getter = piece_getter.clone();
+        piece_getter
+            .pieces
+            .get_mut(&global_object.piece_index())
+            .unwrap()[0] ^= 1;
+
+        let object_fetcher = ObjectFetcher::new(
+            piece_getter,
+            kzg.clone(),
+            RECORD_SIZE,
+            SEGMENT_SIZE,
+            DEFAULT_MAX_OBJECT_SIZE,
+        )
+        .unwrap();
+
+        assert_matches!(
+            block_on(object_fetcher.fetch_object(global_object)),
+            Err(ObjectFetchingError::InvalidPiece { piece_index }) if piece_index == global_object.piece_index()
+        );
+    }
+
+    {
+        let mut piece_getter = piece_getter.clone();
+        piece_getter.pieces.remove(&global_object.piece_index());
+
+        let object_fetcher = ObjectFetcher::new(
+            piece_getter,
+            kzg.clone(),
+            RECORD_SIZE,
+            SEGMENT_SIZE,
+            DEFAULT_MAX_OBJECT_SIZE,
+        )
+        .unwrap();
+
+        assert_matches!(
+            block_on(object_fetcher.fetch_object(global_object)),
+            Err(ObjectFetchingError::PieceNotFound { piece_index }) if piece_index == global_object.piece_index()
+        );
+    }
+
+    {
+        let mut piece_getter = piece_getter;
+        piece_getter.records_roots.clear();
+
+        let object_fetcher = ObjectFetcher::new(
+            piece_getter,
+            kzg,
+            RECORD_SIZE,
+            SEGMENT_SIZE,
+            DEFAULT_MAX_OBJECT_SIZE,
+        )
+        .unwrap();
+
+        assert_matches!(
+            block_on(object_fetcher.fetch_object(global_object)),
+            Err(ObjectFetchingError::RecordsRootNotFound { segment_index: 0 })
+        );
+    }
+}
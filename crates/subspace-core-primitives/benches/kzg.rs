@@ -1,7 +1,7 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use dusk_bls12_381::BlsScalar;
 use dusk_bytes::Serializable;
-use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::crypto::kzg::{Kzg, Opening};
 
 fn criterion_benchmark(c: &mut Criterion) {
     let data = {
@@ -40,6 +40,16 @@ fn criterion_benchmark(c: &mut Criterion) {
         })
     });
 
+    c.bench_function("create-witnesses", |b| {
+        let polynomial = kzg.poly(&data).unwrap();
+        let num_values = (data.len() / BlsScalar::SIZE) as u32;
+
+        b.iter(|| {
+            kzg.create_witnesses(black_box(&polynomial), black_box(num_values))
+                .unwrap();
+        })
+    });
+
     c.bench_function("verify", |b| {
         let polynomial = kzg.poly(&data).unwrap();
         let commitment = kzg.commit(&polynomial).unwrap();
@@ -59,6 +69,30 @@ fn criterion_benchmark(c: &mut Criterion) {
             );
         })
     });
+
+    c.bench_function("verify-batch", |b| {
+        let polynomial = kzg.poly(&data).unwrap();
+        let commitment = kzg.commit(&polynomial).unwrap();
+        let values = data.chunks_exact(BlsScalar::SIZE);
+        let num_values = values.len() as u32;
+        let witnesses = (0..num_values)
+            .map(|index| kzg.create_witness(&polynomial, index).unwrap())
+            .collect::<Vec<_>>();
+        let openings = values
+            .zip(&witnesses)
+            .enumerate()
+            .map(|(index, (value, witness))| Opening {
+                commitment: &commitment,
+                index: index as u32,
+                value,
+                witness,
+            })
+            .collect::<Vec<_>>();
+
+        b.iter(|| {
+            kzg.verify_batch(black_box(num_values), black_box(&openings));
+        })
+    });
 }
 
 criterion_group!(benches, criterion_benchmark);
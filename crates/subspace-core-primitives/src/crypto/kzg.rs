@@ -7,8 +7,13 @@ mod tests;
 
 extern crate alloc;
 
+use crate::{Blake2b256Hash, BLAKE2B_256_HASH_SIZE};
+#[cfg(feature = "serde")]
+use ::serde::{Deserialize, Serialize};
 use alloc::vec::Vec;
-use dusk_bls12_381::{G1Affine, G2Affine, G2Prepared};
+use blake2_rfc::blake2b::{blake2b, Blake2b};
+use core::fmt;
+use dusk_bls12_381::{G1Affine, G1Projective, G2Affine, G2Prepared};
 pub use dusk_bytes;
 use dusk_bytes::{DeserializableSlice, Serializable};
 pub use dusk_plonk::commitment_scheme::kzg10::key::{CommitKey, OpeningKey};
@@ -18,16 +23,208 @@ use dusk_plonk::fft::domain::EvaluationDomain;
 use dusk_plonk::fft::evaluations::Evaluations;
 use dusk_plonk::fft::polynomial::Polynomial as PlonkPolynomial;
 use dusk_plonk::prelude::BlsScalar;
-use parity_scale_codec::{Decode, Encode, EncodeLike, Input};
+use parity_scale_codec::{Decode, Encode, EncodeLike, Input, MaxEncodedLen};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 use scale_info::{Type, TypeInfo};
 
-const TEST_PUBLIC_PARAMETERS: &[u8] = include_bytes!("kzg/test-public-parameters.bin");
-
-/// TODO: Test public parameters, must be replaced with proper public parameters later
+/// Serialized public parameters embedded into the binary, see [`encode_public_parameters()`] for
+/// the format.
+///
+/// TODO: These are generated from a known seed (see `test_public_parameters_generate()`), must be
+///  replaced with the output of a trusted setup ceremony before launch
+pub const EMBEDDED_PUBLIC_PARAMETERS: &[u8] = include_bytes!("kzg/public-parameters.bin");
+/// Information about [`EMBEDDED_PUBLIC_PARAMETERS`]
+pub const EMBEDDED_PUBLIC_PARAMETERS_INFO: PublicParametersInfo = PublicParametersInfo {
+    max_degree: 256,
+    checksum: [
+        0x4a, 0x17, 0x46, 0x6d, 0xe3, 0xf2, 0x1f, 0x5f, 0x58, 0xcf, 0x73, 0x90, 0x77, 0xab, 0x27,
+        0xdb, 0x12, 0x71, 0xf3, 0x4b, 0x46, 0x54, 0x5e, 0xb0, 0xf2, 0xbb, 0xb3, 0xf5, 0xc0, 0x5f,
+        0x75, 0xcc,
+    ],
+};
+// Magic bytes at the beginning of serialized public parameters
+const PUBLIC_PARAMETERS_MAGIC: [u8; 4] = *b"SKZG";
+// Version of the serialized public parameters format
+const PUBLIC_PARAMETERS_FORMAT_VERSION: u8 = 0;
+// Magic bytes, format version, max degree and checksum
+const PUBLIC_PARAMETERS_HEADER_SIZE: usize =
+    PUBLIC_PARAMETERS_MAGIC.len() + 1 + 4 + BLAKE2B_256_HASH_SIZE;
+// Domain separator for deriving coefficients of linear combination in batch verification
+const BATCH_VERIFICATION_CONTEXT: &[u8] = b"subspace-kzg-batch-verification";
+
+/// Public parameters for tests, these are the same as [`embedded_public_parameters()`]
 pub fn test_public_parameters() -> PublicParameters {
-    // SAFETY: Bytes were produced with below `test_public_parameters_generate()` and are guaranteed
-    // to be correct
-    unsafe { PublicParameters::from_slice_unchecked(TEST_PUBLIC_PARAMETERS) }
+    embedded_public_parameters()
+}
+
+/// Public parameters embedded into the binary, see [`EMBEDDED_PUBLIC_PARAMETERS`]
+pub fn embedded_public_parameters() -> PublicParameters {
+    decode_public_parameters(EMBEDDED_PUBLIC_PARAMETERS, &EMBEDDED_PUBLIC_PARAMETERS_INFO)
+        .expect("Embedded public parameters match embedded info; qed")
+}
+
+/// Same as [`embedded_public_parameters()`], but without checksum verification, which makes it
+/// cheap enough for hot paths like vote verification in the runtime.
+///
+/// Embedded public parameters are verified against [`EMBEDDED_PUBLIC_PARAMETERS_INFO`] in tests.
+pub fn embedded_public_parameters_unchecked() -> PublicParameters {
+    let (_info, raw_public_parameters) = read_public_parameters_header(EMBEDDED_PUBLIC_PARAMETERS)
+        .expect("Embedded public parameters have valid header; qed");
+
+    // SAFETY: Embedded public parameters are produced by `encode_public_parameters()` from valid
+    // public parameters, which is checked in tests
+    unsafe { PublicParameters::from_slice_unchecked(raw_public_parameters) }
+}
+
+/// Information about public parameters that is sufficient to check that the correct parameters
+/// are used, for instance by including it into chain constants.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Encode, Decode, MaxEncodedLen, TypeInfo)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub struct PublicParametersInfo {
+    /// Max degree of polynomial supported by public parameters
+    pub max_degree: u32,
+    /// BLAKE2b-256 checksum of raw public parameters
+    #[cfg_attr(feature = "serde", serde(with = "hex::serde"))]
+    pub checksum: Blake2b256Hash,
+}
+
+impl PublicParametersInfo {
+    /// Read information from the header of serialized public parameters without checking
+    /// parameters themselves.
+    pub fn from_serialized(bytes: &[u8]) -> Result<Self, PublicParametersError> {
+        read_public_parameters_header(bytes).map(|(info, _raw_public_parameters)| info)
+    }
+}
+
+/// Errors that happen during encoding or decoding of public parameters
+#[derive(Debug)]
+pub enum PublicParametersError {
+    /// Serialized public parameters are too short or don't start with expected magic bytes
+    InvalidHeader,
+    /// Unsupported version of serialized public parameters format
+    UnsupportedFormatVersion(u8),
+    /// Checksum doesn't match public parameters
+    ChecksumMismatch,
+    /// Public parameters are valid, but are not the expected ones
+    UnexpectedPublicParameters {
+        /// Expected public parameters
+        expected: PublicParametersInfo,
+        /// Actual public parameters
+        actual: PublicParametersInfo,
+    },
+    /// Public parameters don't support declared max degree
+    InvalidPublicParameters(Error),
+}
+
+impl fmt::Display for PublicParametersError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHeader => write!(f, "Invalid public parameters header"),
+            Self::UnsupportedFormatVersion(version) => {
+                write!(f, "Unsupported public parameters format version {version}")
+            }
+            Self::ChecksumMismatch => write!(f, "Public parameters checksum mismatch"),
+            Self::UnexpectedPublicParameters { expected, actual } => write!(
+                f,
+                "Unexpected public parameters: expected {expected:?}, found {actual:?}"
+            ),
+            Self::InvalidPublicParameters(error) => {
+                write!(f, "Invalid public parameters: {error:?}")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PublicParametersError {}
+
+/// Serialize public parameters for later use with [`decode_public_parameters()`].
+///
+/// Format is magic bytes `SKZG`, format version (`u8`), max degree (`u32`, little-endian),
+/// BLAKE2b-256 checksum of raw public parameters followed by raw public parameters themselves.
+pub fn encode_public_parameters(
+    public_parameters: &PublicParameters,
+    max_degree: u32,
+) -> Result<Vec<u8>, PublicParametersError> {
+    // Make sure public parameters actually support declared max degree
+    public_parameters
+        .trim(max_degree as usize)
+        .map_err(PublicParametersError::InvalidPublicParameters)?;
+
+    let raw_public_parameters = public_parameters.to_raw_var_bytes();
+    let checksum = crate::crypto::blake2b_256_hash(&raw_public_parameters);
+
+    let mut bytes = Vec::with_capacity(PUBLIC_PARAMETERS_HEADER_SIZE + raw_public_parameters.len());
+    bytes.extend_from_slice(&PUBLIC_PARAMETERS_MAGIC);
+    bytes.push(PUBLIC_PARAMETERS_FORMAT_VERSION);
+    bytes.extend_from_slice(&max_degree.to_le_bytes());
+    bytes.extend_from_slice(&checksum);
+    bytes.extend_from_slice(&raw_public_parameters);
+
+    Ok(bytes)
+}
+
+/// Deserialize public parameters produced by [`encode_public_parameters()`], checking that they
+/// are intact and match `expected` information.
+pub fn decode_public_parameters(
+    bytes: &[u8],
+    expected: &PublicParametersInfo,
+) -> Result<PublicParameters, PublicParametersError> {
+    let (info, raw_public_parameters) = read_public_parameters_header(bytes)?;
+
+    if crate::crypto::blake2b_256_hash(raw_public_parameters) != info.checksum {
+        return Err(PublicParametersError::ChecksumMismatch);
+    }
+    if info != *expected {
+        return Err(PublicParametersError::UnexpectedPublicParameters {
+            expected: *expected,
+            actual: info,
+        });
+    }
+
+    // SAFETY: Checksum of raw bytes matches expected checksum, which is only the case for bytes
+    // produced by `encode_public_parameters()` from valid public parameters
+    let public_parameters =
+        unsafe { PublicParameters::from_slice_unchecked(raw_public_parameters) };
+
+    public_parameters
+        .trim(info.max_degree as usize)
+        .map_err(PublicParametersError::InvalidPublicParameters)?;
+
+    Ok(public_parameters)
+}
+
+// Splits serialized public parameters into information from the header and raw public parameters
+fn read_public_parameters_header(
+    bytes: &[u8],
+) -> Result<(PublicParametersInfo, &[u8]), PublicParametersError> {
+    if bytes.len() < PUBLIC_PARAMETERS_HEADER_SIZE {
+        return Err(PublicParametersError::InvalidHeader);
+    }
+
+    let (magic, bytes) = bytes.split_at(PUBLIC_PARAMETERS_MAGIC.len());
+    if magic != PUBLIC_PARAMETERS_MAGIC.as_slice() {
+        return Err(PublicParametersError::InvalidHeader);
+    }
+    let (version, bytes) = bytes.split_at(1);
+    if version[0] != PUBLIC_PARAMETERS_FORMAT_VERSION {
+        return Err(PublicParametersError::UnsupportedFormatVersion(version[0]));
+    }
+    let (max_degree, bytes) = bytes.split_at(4);
+    let (checksum, raw_public_parameters) = bytes.split_at(BLAKE2B_256_HASH_SIZE);
+
+    let info = PublicParametersInfo {
+        max_degree: u32::from_le_bytes(
+            max_degree
+                .try_into()
+                .expect("Slice has correct length; qed"),
+        ),
+        checksum: checksum.try_into().expect("Slice has correct length; qed"),
+    };
+
+    Ok((info, raw_public_parameters))
 }
 
 #[cfg(test)]
@@ -243,6 +440,20 @@ impl TypeInfo for Witness {
     }
 }
 
+/// Claim that `value` is the evaluation at `index` of the polynomial matching `commitment`, used
+/// for batch verification
+#[derive(Debug, Copy, Clone)]
+pub struct Opening<'a> {
+    /// Commitment to polynomial
+    pub commitment: &'a Commitment,
+    /// Index of the value
+    pub index: u32,
+    /// Value at the index
+    pub value: &'a [u8],
+    /// Witness for polynomial evaluation at the index
+    pub witness: &'a Witness,
+}
+
 /// Wrapper data structure for working with KZG commitment scheme
 #[derive(Debug, Clone)]
 pub struct Kzg {
@@ -260,6 +471,15 @@ impl Kzg {
         Self { public_parameters }
     }
 
+    /// Create new instance from public parameters serialized with [`encode_public_parameters()`]
+    /// that match `expected` information (usually from chain constants).
+    pub fn try_from_serialized(
+        bytes: &[u8],
+        expected: &PublicParametersInfo,
+    ) -> Result<Self, PublicParametersError> {
+        decode_public_parameters(bytes, expected).map(Self::new)
+    }
+
     #[cfg(feature = "std")]
     /// For testing purposes only.
     ///
@@ -327,6 +547,47 @@ impl Kzg {
             )
             .ok_or(Error::MismatchedPolyLen)?;
 
+        self.create_witness_at_point(polynomial, point)
+    }
+
+    /// Computes `Witness`es of evaluation of `polynomial` at indices `0..num_witnesses`.
+    ///
+    /// Evaluation domain is created once and shared by all witnesses, with `parallel` feature
+    /// witnesses are created using multiple threads.
+    pub fn create_witnesses(
+        &self,
+        polynomial: &Polynomial,
+        num_witnesses: u32,
+    ) -> Result<Vec<Witness>, Error> {
+        let num_witnesses = usize::try_from(num_witnesses)
+            .expect("Always fits into usize on 32-bit+ platforms; qed");
+
+        // Generate all the x-axis points of the domain on which all the row polynomials reside
+        let eval_domain = EvaluationDomain::new(polynomial.0.degree())?;
+        let points = eval_domain
+            .elements()
+            .take(num_witnesses)
+            .collect::<Vec<_>>();
+        if points.len() != num_witnesses {
+            return Err(Error::MismatchedPolyLen);
+        }
+
+        #[cfg(feature = "parallel")]
+        let points = points.into_par_iter();
+        #[cfg(not(feature = "parallel"))]
+        let points = points.into_iter();
+
+        points
+            .map(|point| self.create_witness_at_point(polynomial, point))
+            .collect()
+    }
+
+    // Computes a `Witness` of evaluation of `polynomial` at `point` of the evaluation domain
+    fn create_witness_at_point(
+        &self,
+        polynomial: &Polynomial,
+        point: BlsScalar,
+    ) -> Result<Witness, Error> {
         // Computes `f(x) / x-z`, returning it as the witness poly
         let witness_poly = polynomial.0.ruffini(point);
 
@@ -346,33 +607,14 @@ impl Kzg {
         value: &[u8],
         witness: &Witness,
     ) -> bool {
-        let degree_of_polynomial = match num_values.checked_sub(1) {
-            Some(degree_of_polynomial) => degree_of_polynomial,
+        let point = match Self::evaluation_domain(num_values)
+            .and_then(|eval_domain| eval_domain.elements().nth(index as usize))
+        {
+            Some(point) => point,
             None => {
                 return false;
             }
         };
-
-        // Generate all the x-axis points of the domain on which all the row polynomials reside
-        let eval_domain = match EvaluationDomain::new(
-            degree_of_polynomial
-                .try_into()
-                .expect("Always fits into usize on 32-bit+ platforms; qed"),
-        ) {
-            Ok(eval_domain) => eval_domain,
-            Err(_error) => {
-                return false;
-            }
-        };
-        let point = eval_domain
-            .elements()
-            .nth(
-                index
-                    .try_into()
-                    .expect("Always fits into usize on 32-bit+ platforms; qed"),
-            )
-            // TODO: Remove unwrap
-            .unwrap();
         let value = match BlsScalar::from_slice(value) {
             Ok(value) => value,
             Err(_error) => {
@@ -398,4 +640,97 @@ impl Kzg {
 
         pairing == dusk_bls12_381::Gt::identity()
     }
+
+    /// Verifies multiple openings at once, each of them is a claim that `value` is the evaluation
+    /// at `index` of the polynomial created from `num_values` values matching the `commitment`.
+    ///
+    /// Openings may refer to different commitments. They are combined using a pseudo-random
+    /// linear combination with coefficients derived from all of the inputs, such that only two
+    /// pairings are computed regardless of the number of openings. Returns `false` if any of the
+    /// openings is invalid (use [`Kzg::verify`] to find out which one) and `true` for empty list of
+    /// openings.
+    pub fn verify_batch(&self, num_values: u32, openings: &[Opening<'_>]) -> bool {
+        if openings.is_empty() {
+            return true;
+        }
+
+        // All openings share the same evaluation domain, so its points are generated only once
+        let points = match Self::evaluation_domain(num_values) {
+            Some(eval_domain) => eval_domain.elements().collect::<Vec<_>>(),
+            None => {
+                return false;
+            }
+        };
+
+        // Each opening is checked with `e(C - v*G, H) == e(W, beta*H - z*H)`, which is equivalent
+        // to `e(C - v*G + z*W, H) == e(W, beta*H)`. Both sides are linear in `C`, `v` and `W`, so
+        // with random coefficients `r_i` all openings are checked at once with
+        // `e(sum(r_i * (C_i - v_i*G + z_i*W_i)), H) == e(sum(r_i * W_i), beta*H)`.
+        let seed = {
+            let mut state = Blake2b::new(BLAKE2B_256_HASH_SIZE);
+            state.update(BATCH_VERIFICATION_CONTEXT);
+            state.update(&num_values.to_le_bytes());
+            for opening in openings {
+                state.update(&opening.commitment.to_bytes());
+                state.update(&opening.index.to_le_bytes());
+                state.update(opening.value);
+                state.update(&opening.witness.to_bytes());
+            }
+            state.finalize()
+        };
+
+        let opening_key = &self.public_parameters.opening_key;
+        let mut combined_lhs = G1Projective::identity();
+        let mut combined_witness = G1Projective::identity();
+
+        for (position, opening) in openings.iter().enumerate() {
+            let point = match points.get(opening.index as usize) {
+                Some(&point) => point,
+                None => {
+                    return false;
+                }
+            };
+            let value = match BlsScalar::from_slice(opening.value) {
+                Ok(value) => value,
+                Err(_error) => {
+                    return false;
+                }
+            };
+            let coefficient = BlsScalar::from_bytes_wide(
+                blake2b(64, seed.as_bytes(), &(position as u64).to_le_bytes())
+                    .as_bytes()
+                    .try_into()
+                    .expect("Initialized with correct length; qed"),
+            );
+
+            let lhs = opening.commitment.0 - (opening_key.g * value) + (opening.witness.0 * point);
+            combined_lhs += lhs * coefficient;
+            combined_witness += opening.witness.0 * coefficient;
+        }
+
+        let combined_lhs = G1Affine::from(combined_lhs);
+        let combined_witness = G1Affine::from(-combined_witness);
+        let prepared_beta_h = G2Prepared::from(opening_key.beta_h);
+
+        let pairing = dusk_bls12_381::multi_miller_loop(&[
+            (&combined_lhs, &opening_key.prepared_h),
+            (&combined_witness, &prepared_beta_h),
+        ])
+        .final_exponentiation();
+
+        pairing == dusk_bls12_381::Gt::identity()
+    }
+
+    // Evaluation domain of the polynomial created from `num_values` values, its points are the
+    // x-axis points on which all the row polynomials reside, `None` if there is no such domain.
+    fn evaluation_domain(num_values: u32) -> Option<EvaluationDomain> {
+        let degree_of_polynomial = num_values.checked_sub(1)?;
+
+        EvaluationDomain::new(
+            degree_of_polynomial
+                .try_into()
+                .expect("Always fits into usize on 32-bit+ platforms; qed"),
+        )
+        .ok()
+    }
 }
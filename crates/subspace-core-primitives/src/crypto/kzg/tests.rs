@@ -1,5 +1,9 @@
 use crate::crypto::kzg::dusk_bytes::Serializable;
-use crate::crypto::kzg::{BlsScalar, Kzg};
+use crate::crypto::kzg::{
+    decode_public_parameters, embedded_public_parameters, embedded_public_parameters_unchecked,
+    encode_public_parameters, BlsScalar, Kzg, Opening, PublicParametersError, PublicParametersInfo,
+    EMBEDDED_PUBLIC_PARAMETERS, EMBEDDED_PUBLIC_PARAMETERS_INFO,
+};
 
 #[test]
 fn basic() {
@@ -32,4 +36,138 @@ fn basic() {
             "failed on index {index}"
         );
     }
+
+    let witnesses = kzg.create_witnesses(&polynomial, num_values).unwrap();
+    assert_eq!(witnesses.len(), num_values as usize);
+    for (index, witness) in witnesses.into_iter().enumerate() {
+        assert_eq!(
+            witness,
+            kzg.create_witness(&polynomial, index as u32).unwrap(),
+            "failed on index {index}"
+        );
+    }
+}
+
+#[test]
+fn batch_verification() {
+    let kzg = Kzg::random(256).unwrap();
+
+    let data = (0..2)
+        .map(|_| {
+            let mut data = rand::random::<[u8; 256]>();
+
+            // We can only store 254 bits, set last byte to zero because of that
+            data.chunks_exact_mut(BlsScalar::SIZE)
+                .flat_map(|chunk| chunk.iter_mut().last())
+                .for_each(|last_byte| *last_byte = 0);
+
+            data
+        })
+        .collect::<Vec<_>>();
+    let num_values = (data[0].len() / BlsScalar::SIZE) as u32;
+
+    let commitments_and_witnesses = data
+        .iter()
+        .map(|data| {
+            let polynomial = kzg.poly(data).unwrap();
+            let commitment = kzg.commit(&polynomial).unwrap();
+            let witnesses = (0..num_values)
+                .map(|index| kzg.create_witness(&polynomial, index).unwrap())
+                .collect::<Vec<_>>();
+
+            (commitment, witnesses)
+        })
+        .collect::<Vec<_>>();
+
+    let mut openings = data
+        .iter()
+        .zip(&commitments_and_witnesses)
+        .flat_map(|(data, (commitment, witnesses))| {
+            data.chunks_exact(BlsScalar::SIZE)
+                .zip(witnesses)
+                .enumerate()
+                .map(move |(index, (value, witness))| Opening {
+                    commitment,
+                    index: index as u32,
+                    value,
+                    witness,
+                })
+        })
+        .collect::<Vec<_>>();
+
+    assert!(kzg.verify_batch(num_values, &[]));
+    assert!(kzg.verify_batch(num_values, &openings[..1]));
+    assert!(kzg.verify_batch(num_values, &openings));
+
+    // Value that doesn't match the commitment
+    let mut invalid_openings = openings.clone();
+    invalid_openings[3].value = openings[4].value;
+    assert!(!kzg.verify_batch(num_values, &invalid_openings));
+
+    // Witness for a different index
+    let mut invalid_openings = openings.clone();
+    invalid_openings[3].witness = openings[4].witness;
+    assert!(!kzg.verify_batch(num_values, &invalid_openings));
+
+    // Opening against a different commitment
+    let last_opening = openings.len() - 1;
+    openings[last_opening].commitment = &commitments_and_witnesses[0].0;
+    assert!(!kzg.verify_batch(num_values, &openings));
+
+    // Index outside of the domain
+    openings[last_opening].index = u32::MAX;
+    assert!(!kzg.verify_batch(num_values, &openings));
+}
+
+#[test]
+fn public_parameters_encoding() {
+    let public_parameters = embedded_public_parameters();
+    let max_degree = EMBEDDED_PUBLIC_PARAMETERS_INFO.max_degree;
+
+    let encoded = encode_public_parameters(&public_parameters, max_degree).unwrap();
+    assert_eq!(encoded, EMBEDDED_PUBLIC_PARAMETERS);
+    assert_eq!(
+        PublicParametersInfo::from_serialized(&encoded).unwrap(),
+        EMBEDDED_PUBLIC_PARAMETERS_INFO
+    );
+
+    let decoded = decode_public_parameters(&encoded, &EMBEDDED_PUBLIC_PARAMETERS_INFO).unwrap();
+    assert_eq!(
+        decoded.to_raw_var_bytes(),
+        public_parameters.to_raw_var_bytes()
+    );
+    assert!(Kzg::try_from_serialized(&encoded, &EMBEDDED_PUBLIC_PARAMETERS_INFO).is_ok());
+    assert_eq!(
+        embedded_public_parameters_unchecked().to_raw_var_bytes(),
+        public_parameters.to_raw_var_bytes()
+    );
+
+    // Parameters can't claim support for higher degree than they actually support
+    assert!(matches!(
+        encode_public_parameters(&public_parameters, max_degree + 1),
+        Err(PublicParametersError::InvalidPublicParameters(_))
+    ));
+
+    let mut corrupted = encoded.clone();
+    *corrupted.last_mut().unwrap() ^= 1;
+    assert!(matches!(
+        decode_public_parameters(&corrupted, &EMBEDDED_PUBLIC_PARAMETERS_INFO),
+        Err(PublicParametersError::ChecksumMismatch)
+    ));
+
+    let mut wrong_magic = encoded.clone();
+    wrong_magic[0] ^= 1;
+    assert!(matches!(
+        decode_public_parameters(&wrong_magic, &EMBEDDED_PUBLIC_PARAMETERS_INFO),
+        Err(PublicParametersError::InvalidHeader)
+    ));
+
+    let unexpected_info = PublicParametersInfo {
+        checksum: [0; 32],
+        ..EMBEDDED_PUBLIC_PARAMETERS_INFO
+    };
+    assert!(matches!(
+        decode_public_parameters(&encoded, &unexpected_info),
+        Err(PublicParametersError::UnexpectedPublicParameters { .. })
+    ));
 }
@@ -39,8 +39,8 @@ use core::num::{NonZeroU16, NonZeroU64};
 use core::ops::{Deref, DerefMut};
 use derive_more::{Add, Display, Div, Mul, Rem, Sub};
 use num_traits::{WrappingAdd, WrappingSub};
-use parity_scale_codec::{Decode, Encode};
-use scale_info::TypeInfo;
+use parity_scale_codec::{Decode, Encode, EncodeLike, Input, Output};
+use scale_info::{Type, TypeInfo};
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
 
@@ -555,12 +555,11 @@ impl PieceIndexHash {
     }
 }
 
-// TODO: Versioned solution enum
-/// Farmer solution for slot challenge.
+/// Farmer solution for slot challenge, V0 layout of [`Solution`].
 #[derive(Clone, Debug, Eq, PartialEq, Encode, Decode, TypeInfo)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
-pub struct Solution<PublicKey, RewardAddress> {
+pub struct SolutionV0<PublicKey, RewardAddress> {
     /// Public key of the farmer that created the solution
     pub public_key: PublicKey,
     /// Address for receiving block reward
@@ -581,6 +580,139 @@ pub struct Solution<PublicKey, RewardAddress> {
     pub chunk_signature: ChunkSignature,
 }
 
+/// Farmer solution for slot challenge.
+///
+/// Solution is versioned, such that its format can be upgraded in the future. V0 is encoded
+/// exactly like [`SolutionV0`] without any version prefix to stay compatible with solutions that
+/// were produced before versioning was introduced, hence SCALE encoding is implemented manually.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "camelCase"))]
+pub enum Solution<PublicKey, RewardAddress> {
+    /// V0 of the solution
+    V0(SolutionV0<PublicKey, RewardAddress>),
+}
+
+impl<PublicKey, RewardAddress> Encode for Solution<PublicKey, RewardAddress>
+where
+    PublicKey: Encode,
+    RewardAddress: Encode,
+{
+    fn size_hint(&self) -> usize {
+        match self {
+            Self::V0(solution) => solution.size_hint(),
+        }
+    }
+
+    fn encode_to<O: Output + ?Sized>(&self, dest: &mut O) {
+        match self {
+            Self::V0(solution) => solution.encode_to(dest),
+        }
+    }
+}
+
+impl<PublicKey, RewardAddress> EncodeLike for Solution<PublicKey, RewardAddress>
+where
+    PublicKey: Encode,
+    RewardAddress: Encode,
+{
+}
+
+impl<PublicKey, RewardAddress> Decode for Solution<PublicKey, RewardAddress>
+where
+    PublicKey: Decode,
+    RewardAddress: Decode,
+{
+    fn decode<I: Input>(input: &mut I) -> Result<Self, parity_scale_codec::Error> {
+        SolutionV0::decode(input).map(Self::V0)
+    }
+}
+
+impl<PublicKey, RewardAddress> TypeInfo for Solution<PublicKey, RewardAddress>
+where
+    PublicKey: TypeInfo + 'static,
+    RewardAddress: TypeInfo + 'static,
+{
+    type Identity = SolutionV0<PublicKey, RewardAddress>;
+
+    fn type_info() -> Type {
+        SolutionV0::<PublicKey, RewardAddress>::type_info()
+    }
+}
+
+impl<PublicKey, RewardAddress> From<SolutionV0<PublicKey, RewardAddress>>
+    for Solution<PublicKey, RewardAddress>
+{
+    fn from(solution: SolutionV0<PublicKey, RewardAddress>) -> Self {
+        Self::V0(solution)
+    }
+}
+
+impl<PublicKey, RewardAddress> Solution<PublicKey, RewardAddress> {
+    /// Public key of the farmer that created the solution
+    pub fn public_key(&self) -> &PublicKey {
+        match self {
+            Self::V0(solution) => &solution.public_key,
+        }
+    }
+
+    /// Address for receiving block reward
+    pub fn reward_address(&self) -> &RewardAddress {
+        match self {
+            Self::V0(solution) => &solution.reward_address,
+        }
+    }
+
+    /// Index of the sector where solution was found
+    pub fn sector_index(&self) -> SectorIndex {
+        match self {
+            Self::V0(solution) => solution.sector_index,
+        }
+    }
+
+    /// Number of pieces in archived history at time of sector creation
+    pub fn total_pieces(&self) -> NonZeroU64 {
+        match self {
+            Self::V0(solution) => solution.total_pieces,
+        }
+    }
+
+    /// Pieces offset within sector
+    pub fn piece_offset(&self) -> PieceIndex {
+        match self {
+            Self::V0(solution) => solution.piece_offset,
+        }
+    }
+
+    /// Piece commitment that can use used to verify that piece was included in blockchain history
+    pub fn piece_record_hash(&self) -> Blake2b256Hash {
+        match self {
+            Self::V0(solution) => solution.piece_record_hash,
+        }
+    }
+
+    /// Witness for above piece commitment
+    pub fn piece_witness(&self) -> Witness {
+        match self {
+            Self::V0(solution) => solution.piece_witness,
+        }
+    }
+
+    /// Chunk (only `space_l` first bits should be used)
+    pub fn chunk(&self) -> Chunk {
+        match self {
+            Self::V0(solution) => solution.chunk,
+        }
+    }
+
+    /// VRF signature of expanded version of the above chunk
+    pub fn chunk_signature(&self) -> ChunkSignature {
+        match self {
+            Self::V0(solution) => solution.chunk_signature,
+        }
+    }
+}
+
 impl<PublicKey, RewardAddressA> Solution<PublicKey, RewardAddressA> {
     /// Transform solution with one reward address type into solution with another compatible
     /// reward address type.
@@ -591,27 +723,28 @@ impl<PublicKey, RewardAddressA> Solution<PublicKey, RewardAddressA> {
         RewardAddressA: Into<T>,
         T: Into<RewardAddressB>,
     {
-        let Solution {
-            public_key,
-            reward_address,
-            sector_index,
-            total_pieces,
-            piece_offset,
-            piece_record_hash: piece_commitment,
-            piece_witness,
-            chunk,
-            chunk_signature,
-        } = self;
-        Solution {
-            public_key,
-            reward_address: Into::<T>::into(reward_address).into(),
-            sector_index,
-            total_pieces,
-            piece_offset,
-            piece_record_hash: piece_commitment,
-            piece_witness,
-            chunk,
-            chunk_signature,
+        match self {
+            Self::V0(SolutionV0 {
+                public_key,
+                reward_address,
+                sector_index,
+                total_pieces,
+                piece_offset,
+                piece_record_hash,
+                piece_witness,
+                chunk,
+                chunk_signature,
+            }) => Solution::V0(SolutionV0 {
+                public_key,
+                reward_address: Into::<T>::into(reward_address).into(),
+                sector_index,
+                total_pieces,
+                piece_offset,
+                piece_record_hash,
+                piece_witness,
+                chunk,
+                chunk_signature,
+            }),
         }
     }
 }
@@ -623,7 +756,7 @@ where
 {
     /// Dummy solution for the genesis block
     pub fn genesis_solution(public_key: PublicKey, reward_address: RewardAddress) -> Self {
-        Self {
+        Self::V0(SolutionV0 {
             public_key,
             reward_address,
             sector_index: 0,
@@ -636,7 +769,7 @@ where
                 output: [0; 32],
                 proof: [0; 64],
             },
-        }
+        })
     }
 }
 
@@ -1,6 +1,24 @@
-use crate::U256;
+use crate::{PublicKey, Solution, SolutionV0, U256};
+use parity_scale_codec::{Decode, Encode};
 
 #[test]
 fn piece_distance_middle() {
     assert_eq!(U256::MIDDLE, U256::MAX / 2);
 }
+
+#[test]
+fn solution_encoding() {
+    let solution = Solution::<PublicKey, PublicKey>::genesis_solution(
+        PublicKey::from([1; 32]),
+        PublicKey::from([2; 32]),
+    );
+    let Solution::V0(solution_v0) = solution.clone();
+
+    // V0 is encoded exactly like the original layout of the solution, without version prefix
+    let encoded = solution.encode();
+    assert_eq!(encoded, solution_v0.encode());
+    assert_eq!(solution.encoded_size(), encoded.len());
+
+    assert_eq!(Solution::decode(&mut encoded.as_slice()).unwrap(), solution);
+    assert_eq!(Solution::from(solution_v0), solution);
+}
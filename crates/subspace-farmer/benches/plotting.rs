@@ -50,6 +50,7 @@ fn criterion_benchmark(c: &mut Criterion) {
         total_pieces: NonZeroU64::new(1).unwrap(),
         space_l: NonZeroU16::new(20).unwrap(),
         sector_expiration: 1,
+        kzg_public_parameters: kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO,
     };
     let piece_receiver = BenchPieceReceiver::new(piece);
 
@@ -62,6 +62,7 @@ pub fn criterion_benchmark(c: &mut Criterion) {
         total_pieces: NonZeroU64::new(1).unwrap(),
         space_l: NonZeroU16::new(20).unwrap(),
         sector_expiration: 1,
+        kzg_public_parameters: kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO,
     };
     let global_challenge = Blake2b256Hash::default();
     let solution_range = SolutionRange::MAX;
@@ -4,18 +4,24 @@ use anyhow::{anyhow, Result};
 use futures::stream::FuturesUnordered;
 use futures::{FutureExt, StreamExt};
 use parking_lot::Mutex;
-use std::collections::HashMap;
+use prometheus_endpoint::Registry;
+use std::collections::BTreeMap;
+use std::fs;
 use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::sync::Arc;
-use subspace_core_primitives::{PieceIndexHash, SectorIndex};
+use subspace_core_primitives::crypto::kzg;
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::{PieceIndex, PieceIndexHash, SectorIndex};
 use subspace_farmer::single_disk_plot::piece_reader::PieceReader;
 use subspace_farmer::single_disk_plot::{SingleDiskPlot, SingleDiskPlotOptions};
-use subspace_farmer::NodeRpcClient;
+use subspace_farmer::{NodeRpcClient, RpcClient};
 use subspace_networking::{
     create, peer_id, BootstrappedNetworkingParameters, Config, CustomRecordStore,
-    LimitedSizeRecordStorageWrapper, MemoryProviderStorage, Node, NodeRunner,
-    ParityDbRecordStorage, PieceByHashRequestHandler, PieceByHashResponse, PieceKey,
+    InboundRequestLimits, LimitedSizeRecordStorageWrapper, MemoryProviderStorage, Node, NodeRunner,
+    ParityDbRecordStorage, PeerInfo, PeerInfoProvider, PeerRole, PeerSyncStatus,
+    PieceByHashRequestHandler, PieceByHashResponse, PieceKey, PieceKeyRange,
+    PiecesByRangeRequestHandler, PiecesByRangeResponse, PIECES_BY_RANGE_PAGE_SIZE,
 };
 use tokio::runtime::Handle;
 use tracing::{debug, error, info, trace};
@@ -30,6 +36,7 @@ type ConfiguredRecordStore = CustomRecordStore<
 
 #[derive(Debug, Copy, Clone)]
 struct PieceDetails {
+    piece_index: PieceIndex,
     plot_offset: usize,
     sector_index: SectorIndex,
     piece_offset: u64,
@@ -38,7 +45,8 @@ struct PieceDetails {
 #[derive(Debug)]
 struct ReadersAndPieces {
     readers: Vec<PieceReader>,
-    pieces: HashMap<PieceIndexHash, PieceDetails>,
+    /// Pieces are sorted by piece index hash to serve pieces-by-range requests.
+    pieces: BTreeMap<PieceIndexHash, PieceDetails>,
 }
 
 /// Start farming by using multiple replica plot in specified path and connecting to WebSocket
@@ -62,12 +70,62 @@ pub(crate) async fn farm_multi_disk(
         plot_size: _,
         disk_concurrency,
         disable_farming,
+        metrics_endpoint,
+        kzg_public_parameters,
         dsn,
     } = farming_args;
 
     let readers_and_pieces = Arc::new(Mutex::new(None));
 
-    let (node, node_runner) = configure_dsn(base_path, dsn, &readers_and_pieces).await?;
+    let prometheus_registry = metrics_endpoint.map(|_| Registry::default());
+
+    info!("Connecting to node at {}", node_rpc_url);
+    let node_rpc_client = NodeRpcClient::new(&node_rpc_url).await?;
+
+    let farmer_protocol_info = node_rpc_client
+        .farmer_protocol_info()
+        .await
+        .map_err(|error| anyhow!(error))?;
+
+    let kzg = match kzg_public_parameters {
+        Some(kzg_public_parameters) => {
+            let kzg_public_parameters = fs::read(&kzg_public_parameters).map_err(|error| {
+                anyhow!(
+                    "Failed to read KZG public parameters from {}: {error}",
+                    kzg_public_parameters.display()
+                )
+            })?;
+
+            Kzg::try_from_serialized(
+                &kzg_public_parameters,
+                &farmer_protocol_info.kzg_public_parameters,
+            )
+            .map_err(|error| anyhow!("Invalid KZG public parameters: {error}"))?
+        }
+        None => {
+            if farmer_protocol_info.kzg_public_parameters != kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO {
+                return Err(anyhow!(
+                    "Node uses KZG public parameters {:?} that are different from farmer's {:?}",
+                    farmer_protocol_info.kzg_public_parameters,
+                    kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO
+                ));
+            }
+
+            Kzg::new(kzg::embedded_public_parameters())
+        }
+    };
+
+    let genesis_hash = farmer_protocol_info.genesis_hash;
+
+    let (node, node_runner) = configure_dsn(
+        base_path,
+        dsn,
+        &readers_and_pieces,
+        prometheus_registry.as_ref(),
+        genesis_hash,
+    )
+    .await?;
+
     let mut single_disk_plots = Vec::with_capacity(disk_farms.len());
 
     // TODO: Check plot and metadata sizes to ensure there is enough space for farmer to not
@@ -80,15 +138,13 @@ pub(crate) async fn farm_multi_disk(
             ));
         }
 
-        info!("Connecting to node at {}", node_rpc_url);
-        let rpc_client = NodeRpcClient::new(&node_rpc_url).await?;
-
         let single_disk_plot = SingleDiskPlot::new(SingleDiskPlotOptions {
             directory: disk_farm.directory,
             allocated_space: disk_farm.allocated_plotting_space,
-            rpc_client,
+            rpc_client: node_rpc_client.clone(),
             reward_address,
             dsn_node: node.clone(),
+            kzg: kzg.clone(),
         })?;
 
         single_disk_plots.push(single_disk_plot);
@@ -103,7 +159,7 @@ pub(crate) async fn farm_multi_disk(
     debug!("Collecting already plotted pieces");
 
     // Collect already plotted pieces
-    let plotted_pieces: HashMap<PieceIndexHash, PieceDetails> = single_disk_plots
+    let plotted_pieces: BTreeMap<PieceIndexHash, PieceDetails> = single_disk_plots
         .iter()
         .enumerate()
         .flat_map(|(plot_offset, single_disk_plot)| {
@@ -130,6 +186,7 @@ pub(crate) async fn farm_multi_disk(
                             (
                                 PieceIndexHash::from_index(piece_index),
                                 PieceDetails {
+                                    piece_index,
                                     plot_offset,
                                     sector_index: plotted_sector.sector_index,
                                     piece_offset: piece_offset as u64,
@@ -174,6 +231,7 @@ pub(crate) async fn farm_multi_disk(
                                     (
                                         PieceIndexHash::from_index(piece_index),
                                         PieceDetails {
+                                            piece_index,
                                             plot_offset,
                                             sector_index: plotted_sector.sector_index,
                                             piece_offset: piece_offset as u64,
@@ -218,6 +276,19 @@ pub(crate) async fn farm_multi_disk(
                 futures::future::pending().await
             }
         }).fuse() => {},
+
+        // Metrics endpoint future
+        result = Box::pin(async move {
+            match metrics_endpoint.zip(prometheus_registry) {
+                Some((metrics_endpoint, prometheus_registry)) => {
+                    prometheus_endpoint::init_prometheus(metrics_endpoint, prometheus_registry)
+                        .await
+                }
+                None => futures::future::pending().await,
+            }
+        }).fuse() => {
+            result.map_err(|error| anyhow!("Metrics endpoint failed: {error}"))?;
+        },
     );
 
     anyhow::Ok(())
@@ -230,8 +301,17 @@ async fn configure_dsn(
         listen_on,
         bootstrap_nodes,
         record_cache_size,
+        relay_servers,
+        upload_rate_limit,
+        piece_upload_rate_limit,
+        dsn_enable_mdns,
+        dsn_kademlia_client_mode,
+        dsn_pnet_psk,
+        dsn_gossipsub_message_signing,
     }: DsnArgs,
     readers_and_pieces: &Arc<Mutex<Option<ReadersAndPieces>>>,
+    prometheus_registry: Option<&Registry>,
+    genesis_hash: [u8; 32],
 ) -> Result<(Option<Node>, Option<NodeRunner<ConfiguredRecordStore>>), anyhow::Error> {
     if !enable_dsn {
         info!("No DSN configured.");
@@ -255,14 +335,22 @@ async fn configure_dsn(
     let handle = Handle::current();
     let default_config = Config::with_generated_keypair();
 
-    let config = Config::<ConfiguredRecordStore> {
-        listen_on,
-        allow_non_globals_in_dht: true,
-        networking_parameters_registry: BootstrappedNetworkingParameters::new(bootstrap_nodes)
-            .boxed(),
-        request_response_protocols: vec![PieceByHashRequestHandler::create(move |req| {
-            let result = if let PieceKey::Sector(piece_index_hash) = req.key {
-                let (mut reader, piece_details) = {
+    let pieces_by_range_request_handler = {
+        let weak_readers_and_pieces = weak_readers_and_pieces.clone();
+        let handle = handle.clone();
+
+        PiecesByRangeRequestHandler::create_with_limits(
+            InboundRequestLimits {
+                max_response_bytes_per_second: piece_upload_rate_limit,
+                ..PiecesByRangeRequestHandler::DEFAULT_INBOUND_LIMITS
+            },
+            move |request| {
+                if request.start > request.end {
+                    debug!(?request, "Incorrect pieces-by-range request - empty range.");
+                    return None;
+                }
+
+                let pieces_in_range = {
                     let readers_and_pieces = match weak_readers_and_pieces.upgrade() {
                         Some(readers_and_pieces) => readers_and_pieces,
                         None => {
@@ -274,55 +362,164 @@ async fn configure_dsn(
                     let readers_and_pieces = match readers_and_pieces.as_ref() {
                         Some(readers_and_pieces) => readers_and_pieces,
                         None => {
-                            debug!(
-                                ?piece_index_hash,
-                                "Readers and pieces are not initialized yet"
-                            );
+                            debug!(?request, "Readers and pieces are not initialized yet");
                             return None;
                         }
                     };
-                    let piece_details =
-                        match readers_and_pieces.pieces.get(&piece_index_hash).copied() {
-                            Some(piece_details) => piece_details,
-                            None => {
-                                trace!(
-                                    ?piece_index_hash,
-                                    "Piece is not stored in any of the local plots"
-                                );
-                                return None;
-                            }
-                        };
-                    let reader = readers_and_pieces
-                        .readers
-                        .get(piece_details.plot_offset)
-                        .cloned()
-                        .expect("Offsets strictly correspond to existing plots; qed");
-                    (reader, piece_details)
+
+                    readers_and_pieces
+                        .pieces
+                        .range(request.start..=request.end)
+                        // One more piece is necessary for the cursor of the next page
+                        .take(PIECES_BY_RANGE_PAGE_SIZE + 1)
+                        .map(|(piece_index_hash, piece_details)| {
+                            let reader = readers_and_pieces
+                                .readers
+                                .get(piece_details.plot_offset)
+                                .cloned()
+                                .expect("Offsets strictly correspond to existing plots; qed");
+
+                            (*piece_index_hash, (reader, *piece_details))
+                        })
+                        .collect::<Vec<_>>()
                 };
 
-                let handle = handle.clone();
-                tokio::task::block_in_place(move || {
-                    handle.block_on(
-                        reader.read_piece(piece_details.sector_index, piece_details.piece_offset),
-                    )
-                })
-            } else {
-                debug!(key=?req.key, "Incorrect piece request - unsupported key type.");
-
-                None
-            };
-
-            Some(PieceByHashResponse { piece: result })
-        })],
-        record_store: CustomRecordStore::new(
-            LimitedSizeRecordStorageWrapper::new(
-                ParityDbRecordStorage::new(&record_cache_db_path)
-                    .map_err(|err| anyhow::anyhow!(err.to_string()))?,
-                record_cache_size,
-                peer_id(&default_config.keypair),
+                Some(PiecesByRangeResponse::from_sorted_pieces(
+                    pieces_in_range,
+                    |(mut reader, piece_details): (PieceReader, PieceDetails)| {
+                        let piece =
+                            tokio::task::block_in_place(|| {
+                                handle.block_on(reader.read_piece(
+                                    piece_details.sector_index,
+                                    piece_details.piece_offset,
+                                ))
+                            })?;
+
+                        Some((piece_details.piece_index, piece))
+                    },
+                ))
+            },
+        )
+    };
+
+    let peer_info_provider: PeerInfoProvider = {
+        let weak_readers_and_pieces = weak_readers_and_pieces.clone();
+
+        Arc::new(move || {
+            let is_ready = weak_readers_and_pieces
+                .upgrade()
+                .map(|readers_and_pieces| readers_and_pieces.lock().is_some())
+                .unwrap_or_default();
+
+            PeerInfo {
+                status: if is_ready {
+                    PeerSyncStatus::Ready
+                } else {
+                    PeerSyncStatus::Unknown
+                },
+                // Farmer serves pieces it has plotted, which may belong to any part of key space
+                cached_key_ranges: vec![PieceKeyRange::full()],
+                genesis_hash: Some(genesis_hash),
+                ..PeerInfo::new(PeerRole::Farmer)
+            }
+        })
+    };
+
+    let record_storage = LimitedSizeRecordStorageWrapper::new(
+        ParityDbRecordStorage::new(&record_cache_db_path)
+            .map_err(|err| anyhow::anyhow!(err.to_string()))?,
+        record_cache_size,
+        peer_id(&default_config.keypair),
+    );
+    let record_storage = match prometheus_registry {
+        Some(prometheus_registry) => record_storage.with_metrics(prometheus_registry)?,
+        None => record_storage,
+    };
+
+    let config = Config::<ConfiguredRecordStore> {
+        listen_on,
+        allow_non_globals_in_dht: true,
+        networking_parameters_registry: BootstrappedNetworkingParameters::new(bootstrap_nodes)
+            .boxed(),
+        relay_servers,
+        request_response_protocols: vec![
+            PieceByHashRequestHandler::create_with_limits(
+                InboundRequestLimits {
+                    max_response_bytes_per_second: piece_upload_rate_limit,
+                    ..PieceByHashRequestHandler::DEFAULT_INBOUND_LIMITS
+                },
+                move |req| {
+                    let result = if let PieceKey::Sector(piece_index_hash) = req.key {
+                        let (mut reader, piece_details) = {
+                            let readers_and_pieces = match weak_readers_and_pieces.upgrade() {
+                                Some(readers_and_pieces) => readers_and_pieces,
+                                None => {
+                                    debug!("A readers and pieces are already dropped");
+                                    return None;
+                                }
+                            };
+                            let readers_and_pieces = readers_and_pieces.lock();
+                            let readers_and_pieces = match readers_and_pieces.as_ref() {
+                                Some(readers_and_pieces) => readers_and_pieces,
+                                None => {
+                                    debug!(
+                                        ?piece_index_hash,
+                                        "Readers and pieces are not initialized yet"
+                                    );
+                                    return None;
+                                }
+                            };
+                            let piece_details =
+                                match readers_and_pieces.pieces.get(&piece_index_hash).copied() {
+                                    Some(piece_details) => piece_details,
+                                    None => {
+                                        trace!(
+                                            ?piece_index_hash,
+                                            "Piece is not stored in any of the local plots"
+                                        );
+                                        return None;
+                                    }
+                                };
+                            let reader = readers_and_pieces
+                                .readers
+                                .get(piece_details.plot_offset)
+                                .cloned()
+                                .expect("Offsets strictly correspond to existing plots; qed");
+                            (reader, piece_details)
+                        };
+
+                        let handle = handle.clone();
+                        tokio::task::block_in_place(move || {
+                            handle.block_on(
+                                reader.read_piece(
+                                    piece_details.sector_index,
+                                    piece_details.piece_offset,
+                                ),
+                            )
+                        })
+                    } else {
+                        debug!(key=?req.key, "Incorrect piece request - unsupported key type.");
+
+                        None
+                    };
+
+                    Some(PieceByHashResponse { piece: result })
+                },
             ),
-            MemoryProviderStorage::default(),
-        ),
+            pieces_by_range_request_handler,
+        ],
+        upload_rate_limit,
+        enable_mdns: dsn_enable_mdns,
+        kademlia_mode: if dsn_kademlia_client_mode {
+            KademliaMode::Client
+        } else {
+            KademliaMode::Server
+        },
+        pnet_psk_path: dsn_pnet_psk,
+        gossipsub_message_signing: dsn_gossipsub_message_signing,
+        record_store: CustomRecordStore::new(record_storage, MemoryProviderStorage::default()),
+        prometheus_registry: prometheus_registry.cloned(),
+        peer_info_provider: Some(peer_info_provider),
         ..default_config
     };
 
@@ -10,7 +10,8 @@ use bytesize::ByteSize;
 use clap::{ArgEnum, Parser, ValueHint};
 use ss58::parse_ss58_reward_address;
 use std::fs;
-use std::num::NonZeroU16;
+use std::net::SocketAddr;
+use std::num::{NonZeroU16, NonZeroU32};
 use std::path::PathBuf;
 use std::str::FromStr;
 use subspace_core_primitives::PublicKey;
@@ -50,6 +51,14 @@ struct FarmingArgs {
     /// Disable farming
     #[clap(long)]
     disable_farming: bool,
+    /// Address of Prometheus metrics endpoint, for instance `127.0.0.1:9616`, metrics are
+    /// disabled if not specified
+    #[clap(long)]
+    metrics_endpoint: Option<SocketAddr>,
+    /// Path to the file with serialized KZG public parameters, must match parameters used by the
+    /// node, embedded parameters are used if not specified
+    #[clap(long)]
+    kzg_public_parameters: Option<PathBuf>,
     /// DSN parameters
     #[clap(flatten)]
     dsn: DsnArgs,
@@ -71,6 +80,32 @@ struct DsnArgs {
     /// Record cache size in items.
     #[clap(long, default_value_t = 32768)]
     record_cache_size: usize,
+    /// Multiaddrs of circuit relay servers to reserve relayed addresses with when farmer is
+    /// detected to be behind NAT, multiple are supported
+    #[clap(long, alias = "relay-server")]
+    relay_servers: Vec<Multiaddr>,
+    /// Upload rate limit of all DSN connections in bytes per second.
+    #[clap(long)]
+    upload_rate_limit: Option<NonZeroU32>,
+    /// Upload rate limit of serving pieces to other peers in bytes per second.
+    #[clap(long)]
+    piece_upload_rate_limit: Option<NonZeroU32>,
+    /// Discover other farmers and nodes in the local network with mDNS, useful for LAN clusters
+    /// and development setups.
+    #[clap(long)]
+    dsn_enable_mdns: bool,
+    /// Run Kademlia in client mode: query DHT without storing records of other peers and without
+    /// being added to their routing tables, useful on metered links.
+    #[clap(long)]
+    dsn_kademlia_client_mode: bool,
+    /// Path to the file with private network pre-shared key, the farmer will only connect to
+    /// peers that use the same key.
+    #[clap(long)]
+    dsn_pnet_psk: Option<PathBuf>,
+    /// Sign published gossipsub messages with the identity keypair of the farmer, so that peers
+    /// can attribute messages to their origin.
+    #[clap(long)]
+    dsn_gossipsub_message_signing: bool,
 }
 
 #[derive(Debug, Clone, Copy, ArgEnum)]
@@ -34,6 +34,7 @@
 pub mod file_ext;
 pub(crate) mod identity;
 pub(crate) mod object_mappings;
+pub mod piece_validator;
 pub mod reward_signing;
 pub mod rpc_client;
 pub mod single_disk_plot;
@@ -0,0 +1,91 @@
+//! Validation of pieces received from other DSN peers.
+
+use crate::RpcClient;
+use async_trait::async_trait;
+use lru::LruCache;
+use parking_lot::Mutex;
+use subspace_archiving::archiver::is_piece_valid;
+use subspace_core_primitives::crypto::kzg::Kzg;
+use subspace_core_primitives::{
+    Piece, PieceIndex, RecordsRoot, SegmentIndex, PIECES_IN_SEGMENT, RECORD_SIZE,
+};
+use subspace_networking::libp2p::PeerId;
+use subspace_networking::PieceValidator;
+use tracing::{error, warn};
+
+// Number of records roots of recent segments to keep in memory.
+const RECORDS_ROOTS_CACHE_SIZE: usize = 1000;
+
+/// Validates pieces against records roots of their segments obtained from the node.
+pub struct RecordsRootPieceValidator<RC> {
+    rpc_client: RC,
+    kzg: Kzg,
+    records_roots_cache: Mutex<LruCache<SegmentIndex, RecordsRoot>>,
+}
+
+impl<RC: RpcClient> RecordsRootPieceValidator<RC> {
+    /// Create new instance
+    pub fn new(rpc_client: RC, kzg: Kzg) -> Self {
+        Self {
+            rpc_client,
+            kzg,
+            records_roots_cache: Mutex::new(LruCache::new(RECORDS_ROOTS_CACHE_SIZE)),
+        }
+    }
+
+    async fn records_root(&self, segment_index: SegmentIndex) -> Option<RecordsRoot> {
+        let maybe_records_root = self.records_roots_cache.lock().get(&segment_index).copied();
+        if let Some(records_root) = maybe_records_root {
+            return Some(records_root);
+        }
+
+        let records_root = match self.rpc_client.records_roots(vec![segment_index]).await {
+            Ok(records_roots) => records_roots.into_iter().next().flatten()?,
+            Err(error) => {
+                error!(%segment_index, %error, "Failed to retrieve records root from node");
+                return None;
+            }
+        };
+
+        self.records_roots_cache
+            .lock()
+            .put(segment_index, records_root);
+
+        Some(records_root)
+    }
+}
+
+#[async_trait]
+impl<RC: RpcClient> PieceValidator for RecordsRootPieceValidator<RC> {
+    async fn validate_piece(
+        &self,
+        source_peer_id: PeerId,
+        piece_index: PieceIndex,
+        piece: &Piece,
+    ) -> bool {
+        let segment_index = piece_index / SegmentIndex::from(PIECES_IN_SEGMENT);
+        let position = (piece_index % PieceIndex::from(PIECES_IN_SEGMENT)) as u32;
+
+        let records_root = match self.records_root(segment_index).await {
+            Some(records_root) => records_root,
+            None => {
+                warn!(%piece_index, %segment_index, "No records root found for piece");
+                return false;
+            }
+        };
+
+        if !is_piece_valid(
+            &self.kzg,
+            PIECES_IN_SEGMENT,
+            piece,
+            records_root,
+            position,
+            RECORD_SIZE,
+        ) {
+            warn!(%piece_index, %source_peer_id, "Received invalid piece from peer");
+            return false;
+        }
+
+        true
+    }
+}
@@ -7,6 +7,7 @@ use std::num::{NonZeroU16, NonZeroU32, NonZeroU64};
 use std::pin::Pin;
 use std::sync::Arc;
 use subspace_archiving::archiver::ArchivedSegment;
+use subspace_core_primitives::crypto::kzg;
 use subspace_core_primitives::{Piece, PieceIndex, RecordsRoot, SegmentIndex};
 use subspace_rpc_primitives::{
     FarmerProtocolInfo, RewardSignatureResponse, RewardSigningInfo, SlotInfo, SolutionResponse,
@@ -37,6 +38,7 @@ pub const BENCH_FARMER_PROTOCOL_INFO: FarmerProtocolInfo = FarmerProtocolInfo {
     total_pieces: NonZeroU64::new(1).unwrap(),
     space_l: NonZeroU16::new(20).unwrap(),
     sector_expiration: 100,
+    kzg_public_parameters: kzg::EMBEDDED_PUBLIC_PARAMETERS_INFO,
 };
 
 impl BenchRpcClient {
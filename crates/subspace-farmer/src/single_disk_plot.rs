@@ -6,6 +6,7 @@ pub mod plotting;
 
 use crate::file_ext::FileExt;
 use crate::identity::Identity;
+use crate::piece_validator::RecordsRootPieceValidator;
 use crate::reward_signing::reward_signing;
 use crate::rpc_client;
 use crate::rpc_client::RpcClient;
@@ -37,11 +38,12 @@ use std::sync::Arc;
 use std::time::SystemTime;
 use std::{fmt, fs, io, thread};
 use std_semaphore::{Semaphore, SemaphoreGuard};
+use subspace_core_primitives::crypto::kzg::Kzg;
 use subspace_core_primitives::{
     plot_sector_size, PieceIndex, PublicKey, SectorId, SectorIndex, SegmentIndex, Solution,
     PIECE_SIZE,
 };
-use subspace_networking::Node;
+use subspace_networking::{Node, PiecesByRangeSyncer};
 use subspace_rpc_primitives::SolutionResponse;
 use thiserror::Error;
 use tokio::runtime::Handle;
@@ -287,6 +289,8 @@ pub struct SingleDiskPlotOptions<RC> {
     pub reward_address: PublicKey,
     /// Optional DSN Node.
     pub dsn_node: Option<Node>,
+    /// KZG instance used to validate pieces received from DSN peers
+    pub kzg: Kzg,
 }
 
 /// Errors happening when trying to create/open single disk plot
@@ -485,6 +489,7 @@ impl SingleDiskPlot {
             rpc_client,
             reward_address,
             dsn_node,
+            kzg,
         } = options;
 
         fs::create_dir_all(&directory)?;
@@ -666,6 +671,12 @@ impl SingleDiskPlot {
                 let piece_publisher = dsn_node.as_ref().map(|dsn_node| {
                     PieceSectorPublisher::new(dsn_node.clone(), shutting_down.clone())
                 });
+                let pieces_syncer = dsn_node.as_ref().map(|dsn_node| {
+                    PiecesByRangeSyncer::new(
+                        dsn_node.clone(),
+                        RecordsRootPieceValidator::new(rpc_client.clone(), kzg),
+                    )
+                });
 
                 move || {
                     let _tokio_handle_guard = handle.enter();
@@ -715,6 +726,7 @@ impl SingleDiskPlot {
                             let piece_receiver = MultiChannelPieceReceiver::new(
                                 rpc_client.clone(),
                                 dsn_node.clone(),
+                                pieces_syncer.as_ref(),
                                 &shutting_down,
                             );
 
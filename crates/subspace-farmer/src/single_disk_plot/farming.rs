@@ -8,10 +8,10 @@ use subspace_core_primitives::crypto::blake2b_256_254_hash;
 use subspace_core_primitives::crypto::kzg::Witness;
 use subspace_core_primitives::{
     Blake2b256Hash, Chunk, Piece, PublicKey, SectorId, SectorIndex, Solution, SolutionRange,
-    PIECE_SIZE,
+    SolutionV0, PIECE_SIZE,
 };
 use subspace_rpc_primitives::FarmerProtocolInfo;
-use subspace_solving::{create_chunk_signature, derive_chunk_otp};
+use subspace_solving::{create_chunk_signature, decode_record};
 use subspace_verification::is_within_solution_range;
 use tracing::error;
 
@@ -74,24 +74,14 @@ impl EligibleSector {
                 return Ok(None);
             }
         };
-        // TODO: Extract encoding into separate function reusable in
-        //  farmer and otherwise
-        record
-            .view_bits_mut::<Lsb0>()
-            .chunks_mut(farmer_protocol_info.space_l.get() as usize)
-            .enumerate()
-            .for_each(|(chunk_index, bits)| {
-                // Derive one-time pad
-                let mut otp = derive_chunk_otp(&self.sector_id, witness_bytes, chunk_index as u32);
-                // XOR chunk bit by bit with one-time pad
-                bits.iter_mut()
-                    .zip(otp.view_bits_mut::<Lsb0>().iter())
-                    .for_each(|(mut a, b)| {
-                        *a ^= *b;
-                    });
-            });
+        decode_record(
+            record,
+            &self.sector_id,
+            witness_bytes,
+            farmer_protocol_info.space_l,
+        );
 
-        Ok(Some(Solution {
+        Ok(Some(Solution::V0(SolutionV0 {
             public_key: PublicKey::from(keypair.public.to_bytes()),
             reward_address,
             sector_index: self.sector_index,
@@ -101,7 +91,7 @@ impl EligibleSector {
             piece_witness,
             chunk: self.chunk,
             chunk_signature: create_chunk_signature(keypair, &self.chunk),
-        }))
+        })))
     }
 }
 
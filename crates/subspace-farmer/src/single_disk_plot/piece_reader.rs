@@ -1,9 +1,8 @@
-use bitvec::prelude::*;
 use futures::channel::{mpsc, oneshot};
 use futures::SinkExt;
 use std::num::{NonZeroU16, NonZeroU32};
 use subspace_core_primitives::{Piece, PublicKey, SectorId, SectorIndex, PIECE_SIZE};
-use subspace_solving::derive_chunk_otp;
+use subspace_solving::decode_record;
 use tracing::warn;
 
 #[derive(Debug)]
@@ -103,22 +102,7 @@ pub(super) fn read_piece(
 
     // Decode piece
     let (record, witness_bytes) = piece.split_at_mut(record_size.get() as usize);
-    // TODO: Extract encoding into separate function reusable in farmer and
-    //  otherwise
-    record
-        .view_bits_mut::<Lsb0>()
-        .chunks_mut(space_l.get() as usize)
-        .enumerate()
-        .for_each(|(chunk_index, bits)| {
-            // Derive one-time pad
-            let mut otp = derive_chunk_otp(&sector_id, witness_bytes, chunk_index as u32);
-            // XOR chunk bit by bit with one-time pad
-            bits.iter_mut()
-                .zip(otp.view_bits_mut::<Lsb0>().iter())
-                .for_each(|(mut a, b)| {
-                    *a ^= *b;
-                });
-        });
+    decode_record(record, &sector_id, witness_bytes, space_l);
 
     Some(piece)
 }
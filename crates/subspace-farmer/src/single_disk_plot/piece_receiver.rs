@@ -1,5 +1,7 @@
+use crate::piece_validator::RecordsRootPieceValidator;
 use crate::RpcClient;
 use async_trait::async_trait;
+use futures::{stream, StreamExt};
 use parity_scale_codec::Decode;
 use std::collections::BTreeSet;
 use std::error::Error;
@@ -8,12 +10,21 @@ use std::time::Duration;
 use subspace_core_primitives::{Piece, PieceIndex, PieceIndexHash};
 use subspace_networking::libp2p::PeerId;
 use subspace_networking::utils::multihash::MultihashCode;
-use subspace_networking::{Node, PieceByHashRequest, PieceKey, ToMultihash};
+use subspace_networking::{
+    GenericRequest, Node, PeerSyncStatus, PieceByHashRequest, PieceKey, PiecesByRangeRequest,
+    PiecesByRangeSyncer, QueryOptions, ToMultihash,
+};
 use tokio::time::sleep;
 use tracing::{debug, error, info, trace, warn};
 
 /// Defines a duration between get_piece calls.
 const GET_PIECE_WAITING_DURATION_IN_SECS: u64 = 1;
+/// Defines a timeout of a single DHT query.
+const DSN_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Defines a timeout of a single piece request to a provider.
+const PIECE_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Defines how many connected peers are asked for a piece at most.
+const MAX_NEIGHBOURS_PER_PIECE: usize = 5;
 
 #[async_trait]
 pub trait PieceReceiver {
@@ -23,18 +34,28 @@ pub trait PieceReceiver {
     ) -> Result<Option<Piece>, Box<dyn Error + Send + Sync + 'static>>;
 }
 
+/// Syncer of pieces from neighbours that validates pieces against records roots from the node.
+pub(crate) type NeighbourPiecesSyncer<RC> = PiecesByRangeSyncer<RecordsRootPieceValidator<RC>>;
+
 // Temporary struct serving pieces from different providers using configuration arguments.
 pub(crate) struct MultiChannelPieceReceiver<'a, RC: RpcClient> {
     rpc_client: RC,
     dsn_node: Option<Node>,
+    pieces_syncer: Option<&'a NeighbourPiecesSyncer<RC>>,
     cancelled: &'a AtomicBool,
 }
 
 impl<'a, RC: RpcClient> MultiChannelPieceReceiver<'a, RC> {
-    pub(crate) fn new(rpc_client: RC, dsn_node: Option<Node>, cancelled: &'a AtomicBool) -> Self {
+    pub(crate) fn new(
+        rpc_client: RC,
+        dsn_node: Option<Node>,
+        pieces_syncer: Option<&'a NeighbourPiecesSyncer<RC>>,
+        cancelled: &'a AtomicBool,
+    ) -> Self {
         Self {
             rpc_client,
             dsn_node,
+            pieces_syncer,
             cancelled,
         }
     }
@@ -81,12 +102,72 @@ impl<'a, RC: RpcClient> MultiChannelPieceReceiver<'a, RC> {
         // }
     }
 
+    // Get from connected peers that advertise the piece key in their cached ranges, this avoids
+    // DHT lookup when a neighbour has the piece already.
+    async fn get_piece_from_neighbours(&self, piece_index: PieceIndex) -> Option<Piece> {
+        let (dsn_node, pieces_syncer) = match (&self.dsn_node, self.pieces_syncer) {
+            (Some(dsn_node), Some(pieces_syncer)) => (dsn_node, pieces_syncer),
+            _ => {
+                return None;
+            }
+        };
+
+        let piece_index_hash = PieceIndexHash::from_index(piece_index);
+
+        let peers_info = match dsn_node.connected_peers_info().await {
+            Ok(peers_info) => peers_info,
+            Err(error) => {
+                error!(%piece_index, %error, "Failed to get connected peers info");
+                return None;
+            }
+        };
+
+        let peers = peers_info
+            .into_iter()
+            .filter_map(|(peer_id, peer_info)| {
+                let has_piece = peer_info.status == PeerSyncStatus::Ready
+                    && peer_info.supports_protocol(PiecesByRangeRequest::PROTOCOL_NAME)
+                    && peer_info
+                        .cached_key_ranges
+                        .iter()
+                        .any(|key_range| key_range.contains(&piece_index_hash));
+
+                has_piece.then_some(peer_id)
+            })
+            .take(MAX_NEIGHBOURS_PER_PIECE)
+            .collect::<Vec<_>>();
+
+        if peers.is_empty() {
+            trace!(%piece_index, "No neighbours cache the piece");
+            return None;
+        }
+
+        // Peer without the piece returns an empty range, so peers are asked concurrently and
+        // separately, the first valid piece wins and the rest of requests are dropped.
+        let piece = stream::select_all(peers.into_iter().map(|peer_id| {
+            pieces_syncer
+                .sync_range(piece_index_hash, piece_index_hash, vec![peer_id])
+                .boxed()
+        }))
+        .next()
+        .await
+        .map(|(_piece_index, piece)| piece);
+
+        if piece.is_none() {
+            debug!(%piece_index, "Neighbours didn't return a valid piece");
+        }
+
+        piece
+    }
+
     // Get from piece cache (L2)
     async fn get_piece_from_cache(&self, piece_index: PieceIndex) -> Option<Piece> {
         if let Some(ref dsn_node) = self.dsn_node {
             let key = PieceIndexHash::from_index(piece_index).to_multihash();
 
-            let piece_result = dsn_node.get_value(key).await;
+            let piece_result = dsn_node
+                .get_value_with_options(key, QueryOptions::with_timeout(DSN_QUERY_TIMEOUT))
+                .await;
 
             match piece_result {
                 Ok(Some(piece)) => {
@@ -119,7 +200,9 @@ impl<'a, RC: RpcClient> MultiChannelPieceReceiver<'a, RC> {
             let key =
                 PieceIndexHash::from_index(piece_index).to_multihash_by_code(MultihashCode::Sector);
 
-            let piece_result = dsn_node.get_value(key).await;
+            let piece_result = dsn_node
+                .get_value_with_options(key, QueryOptions::with_timeout(DSN_QUERY_TIMEOUT))
+                .await;
 
             match piece_result {
                 Ok(Some(encoded_gset)) => {
@@ -146,13 +229,14 @@ impl<'a, RC: RpcClient> MultiChannelPieceReceiver<'a, RC> {
                     for peer_id in peer_set.into_iter() {
                         if let Ok(piece_provider_id) = PeerId::from_bytes(&peer_id) {
                             let request_result = dsn_node
-                                .send_generic_request(
+                                .send_generic_request_with_options(
                                     piece_provider_id,
                                     PieceByHashRequest {
                                         key: PieceKey::Sector(PieceIndexHash::from_index(
                                             piece_index,
                                         )),
                                     },
+                                    QueryOptions::with_timeout(PIECE_REQUEST_TIMEOUT),
                                 )
                                 .await;
 
@@ -205,6 +289,10 @@ impl<'a, RC: RpcClient> PieceReceiver for MultiChannelPieceReceiver<'a, RC> {
             loop {
                 self.check_cancellation()?;
 
+                if let Some(piece) = self.get_piece_from_neighbours(piece_index).await {
+                    return Ok(Some(piece));
+                }
+
                 if let Some(piece) = self.get_piece_from_cache(piece_index).await {
                     return Ok(Some(piece));
                 }
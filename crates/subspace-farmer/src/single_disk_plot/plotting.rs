@@ -1,7 +1,5 @@
 use crate::single_disk_plot::piece_receiver::PieceReceiver;
 use crate::single_disk_plot::{PlottingError, SectorMetadata};
-use bitvec::order::Lsb0;
-use bitvec::prelude::*;
 use parity_scale_codec::Encode;
 use std::io;
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -9,7 +7,7 @@ use subspace_core_primitives::{
     plot_sector_size, PieceIndex, PublicKey, SectorId, SectorIndex, PIECE_SIZE,
 };
 use subspace_rpc_primitives::FarmerProtocolInfo;
-use subspace_solving::derive_chunk_otp;
+use subspace_solving::encode_record;
 use thiserror::Error;
 use tracing::debug;
 
@@ -99,22 +97,12 @@ where
         // Encode piece
         let (record, witness_bytes) =
             piece.split_at_mut(farmer_protocol_info.record_size.get() as usize);
-        // TODO: Extract encoding into separate function reusable in
-        //  farmer and otherwise
-        record
-            .view_bits_mut::<Lsb0>()
-            .chunks_mut(farmer_protocol_info.space_l.get() as usize)
-            .enumerate()
-            .for_each(|(chunk_index, bits)| {
-                // Derive one-time pad
-                let mut otp = derive_chunk_otp(&sector_id, witness_bytes, chunk_index as u32);
-                // XOR chunk bit by bit with one-time pad
-                bits.iter_mut()
-                    .zip(otp.view_bits_mut::<Lsb0>().iter())
-                    .for_each(|(mut a, b)| {
-                        *a ^= *b;
-                    });
-            });
+        encode_record(
+            record,
+            &sector_id,
+            witness_bytes,
+            farmer_protocol_info.space_l,
+        );
 
         sector_output.write_all(&piece).map_err(PlottingError::Io)?;
     }
@@ -1,18 +1,30 @@
 pub(crate) mod custom_record_store;
+mod kademlia_mode;
 pub(crate) mod persistent_parameters;
 pub(crate) mod record_binary_heap;
 #[cfg(test)]
 mod tests;
 
+use crate::create::KademliaMode;
 use crate::request_responses::{
     Event as RequestResponseEvent, RequestHandler, RequestResponsesBehaviour,
 };
 use custom_record_store::CustomRecordStore;
 use derive_more::From;
-use libp2p::gossipsub::{Gossipsub, GossipsubConfig, GossipsubEvent, MessageAuthenticity};
+use kademlia_mode::KademliaWithMode;
+use libp2p::autonat::{Behaviour as Autonat, Config as AutonatConfig, Event as AutonatEvent};
+use libp2p::dcutr::behaviour::{Behaviour as Dcutr, Event as DcutrEvent};
+use libp2p::gossipsub::{
+    Gossipsub, GossipsubConfig, GossipsubEvent, MessageAuthenticity, PeerScoreParams,
+    PeerScoreThresholds,
+};
 use libp2p::identify::{Identify, IdentifyConfig, IdentifyEvent};
 use libp2p::kad::{Kademlia, KademliaConfig, KademliaEvent};
+use libp2p::mdns::{Mdns, MdnsEvent};
 use libp2p::ping::{Ping, PingEvent};
+use libp2p::relay::v2::client::{Client as RelayClient, Event as RelayClientEvent};
+use libp2p::relay::v2::relay::{Event as RelayServerEvent, Relay as RelayServer};
+use libp2p::swarm::behaviour::toggle::Toggle;
 use libp2p::{NetworkBehaviour, PeerId};
 
 pub(crate) struct BehaviorConfig<RecordStore = CustomRecordStore> {
@@ -22,12 +34,24 @@ pub(crate) struct BehaviorConfig<RecordStore = CustomRecordStore> {
     pub(crate) identify: IdentifyConfig,
     /// The configuration for the [`Kademlia`] behaviour.
     pub(crate) kademlia: KademliaConfig,
+    /// Defines whether Kademlia protocol is served to other peers.
+    pub(crate) kademlia_mode: KademliaMode,
     /// The configuration for the [`Gossipsub`] behaviour.
     pub(crate) gossipsub: GossipsubConfig,
+    /// Defines whether published gossipsub messages are signed or anonymous.
+    pub(crate) gossipsub_authenticity: MessageAuthenticity,
     /// Externally provided implementation of the custom record store for Kademlia DHT,
     pub(crate) record_store: RecordStore,
     /// The configuration for the [`RequestResponsesBehaviour`] protocol.
     pub(crate) request_response_protocols: Vec<Box<dyn RequestHandler>>,
+    /// The configuration for the [`Autonat`] behaviour, `None` disables reachability detection.
+    pub(crate) autonat: Option<AutonatConfig>,
+    /// Circuit relay client behaviour, created together with the relay transport.
+    pub(crate) relay_client: Option<RelayClient>,
+    /// Enables circuit relay server behaviour.
+    pub(crate) relay_server: bool,
+    /// mDNS behaviour for local peer discovery, `None` disables it.
+    pub(crate) mdns: Option<Mdns>,
 }
 
 #[derive(NetworkBehaviour)]
@@ -35,10 +59,15 @@ pub(crate) struct BehaviorConfig<RecordStore = CustomRecordStore> {
 #[behaviour(event_process = false)]
 pub(crate) struct Behavior<RecordStore> {
     pub(crate) identify: Identify,
-    pub(crate) kademlia: Kademlia<RecordStore>,
+    pub(crate) kademlia: KademliaWithMode<RecordStore>,
     pub(crate) gossipsub: Gossipsub,
     pub(crate) ping: Ping,
     pub(crate) request_response: RequestResponsesBehaviour,
+    pub(crate) autonat: Toggle<Autonat>,
+    pub(crate) relay_client: Toggle<RelayClient>,
+    pub(crate) relay_server: Toggle<RelayServer>,
+    pub(crate) dcutr: Toggle<Dcutr>,
+    pub(crate) mdns: Toggle<Mdns>,
 }
 
 impl<RecordStore> Behavior<RecordStore>
@@ -46,18 +75,36 @@ where
     RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
 {
     pub(crate) fn new(config: BehaviorConfig<RecordStore>) -> Self {
-        let kademlia = Kademlia::<RecordStore>::with_config(
-            config.peer_id,
-            config.record_store,
-            config.kademlia,
+        let kademlia = KademliaWithMode::new(
+            Kademlia::<RecordStore>::with_config(
+                config.peer_id,
+                config.record_store,
+                config.kademlia,
+            ),
+            config.kademlia_mode,
         );
 
-        let gossipsub = Gossipsub::new(
-            // TODO: Do we want message signing?
-            MessageAuthenticity::Anonymous,
-            config.gossipsub,
-        )
-        .expect("Correct configuration");
+        let mut gossipsub = Gossipsub::new(config.gossipsub_authenticity, config.gossipsub)
+            .expect("Correct configuration");
+        // Peer scoring is necessary for topic validators to penalize peers that send invalid
+        // messages.
+        gossipsub
+            .with_peer_score(
+                gossipsub_peer_score_params(),
+                PeerScoreThresholds::default(),
+            )
+            .expect("Correct configuration");
+
+        let autonat = config
+            .autonat
+            .map(|autonat_config| Autonat::new(config.peer_id, autonat_config));
+
+        // Hole punching only makes sense on top of relayed connections.
+        let dcutr = config.relay_client.is_some().then(Dcutr::new);
+
+        let relay_server = config
+            .relay_server
+            .then(|| RelayServer::new(config.peer_id, Default::default()));
 
         Self {
             identify: Identify::new(config.identify),
@@ -69,10 +116,24 @@ where
             )
             //TODO: Convert to an error.
             .expect("RequestResponse protocols registration failed."),
+            autonat: autonat.into(),
+            relay_client: config.relay_client.into(),
+            relay_server: relay_server.into(),
+            dcutr: dcutr.into(),
+            mdns: config.mdns.into(),
         }
     }
 }
 
+fn gossipsub_peer_score_params() -> PeerScoreParams {
+    PeerScoreParams {
+        // Multiple nodes behind the same IP address is a legitimate setup for farmers and local
+        // networks, connection limits take care of that instead.
+        ip_colocation_factor_weight: 0.0,
+        ..PeerScoreParams::default()
+    }
+}
+
 #[derive(Debug, From)]
 pub(crate) enum Event {
     Identify(IdentifyEvent),
@@ -80,4 +141,9 @@ pub(crate) enum Event {
     Gossipsub(GossipsubEvent),
     Ping(PingEvent),
     RequestResponse(RequestResponseEvent),
+    Autonat(AutonatEvent),
+    RelayClient(RelayClientEvent),
+    RelayServer(RelayServerEvent),
+    Dcutr(DcutrEvent),
+    Mdns(MdnsEvent),
 }
@@ -7,6 +7,7 @@ use libp2p::multihash::Multihash;
 use libp2p::PeerId;
 use parity_db::{ColumnOptions, Db, Options};
 use parity_scale_codec::{Decode, Encode};
+use prometheus_endpoint::{register, Gauge, PrometheusError, Registry, U64};
 use std::borrow::{Borrow, Cow};
 use std::collections::{BTreeSet, HashMap};
 use std::iter::IntoIterator;
@@ -17,6 +18,9 @@ use std::vec;
 use tracing::{debug, error, info, trace};
 
 const PARITY_DB_COLUMN_NAME: u8 = 0;
+// Number of records removed between progress reports when the record storage exceeds the size
+// limit on startup.
+const RECORDS_REMOVAL_BATCH_SIZE: usize = 1000;
 
 #[derive(Clone)]
 pub struct CustomRecordStore<
@@ -500,18 +504,21 @@ impl<'a> Iterator for ParityDbRecordIterator<'a> {
     type Item = Cow<'a, Record>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.next_entry().and_then(|(key, value)| {
+        // Records that can't be deserialized are skipped instead of terminating the iteration.
+        while let Some((key, value)) = self.next_entry() {
             let db_rec_result = ParityDbRecordStorage::convert_to_record(value);
 
             match db_rec_result {
-                Ok(db_rec) => Some(Cow::Owned(db_rec)),
+                Ok(db_rec) => {
+                    return Some(Cow::Owned(db_rec));
+                }
                 Err(err) => {
                     debug!(?key, ?err, "Parity DB record deserialization error");
-
-                    None
                 }
             }
-        })
+        }
+
+        None
     }
 }
 
@@ -521,17 +528,27 @@ pub struct LimitedSizeRecordStorageWrapper<RC = MemoryRecordStorage> {
     inner: RC,
     // Maintains a heap to limit total item number.
     heap: RecordBinaryHeap,
+    // Number of stored records, reported when metrics are enabled.
+    size_gauge: Option<Gauge<U64>>,
 }
 
 impl<RC: for<'a> RecordStorage<'a>> LimitedSizeRecordStorageWrapper<RC> {
-    pub fn new(record_store: RC, max_items_limit: NonZeroUsize, peer_id: PeerId) -> Self {
+    pub fn new(mut record_store: RC, max_items_limit: NonZeroUsize, peer_id: PeerId) -> Self {
         let mut heap = RecordBinaryHeap::new(peer_id, max_items_limit.get());
 
-        // Initial cache loading.
+        // Initial cache loading, the heap only keeps the closest keys, so its size is bounded by
+        // the limit regardless of the storage size.
         for rec in record_store.records() {
             let _ = heap.insert(rec.key.clone());
         }
 
+        // Storage may contain more records than the limit (e.g. the limit was decreased since the
+        // last start), records that didn't make it into the heap are removed.
+        let removed_records = Self::remove_records_beyond_limit(&mut record_store, &heap);
+        if removed_records > 0 {
+            info!(%removed_records, "Records exceeding record cache limit removed.");
+        }
+
         if heap.size() > 0 {
             info!(size = heap.size(), "Record cache loaded.");
         } else {
@@ -541,6 +558,56 @@ impl<RC: for<'a> RecordStorage<'a>> LimitedSizeRecordStorageWrapper<RC> {
         Self {
             inner: record_store,
             heap,
+            size_gauge: None,
+        }
+    }
+
+    // Removes records that are not present in the heap. Keys to remove are collected in a single
+    // pass over the storage and removed in batches afterwards, so progress is visible for large
+    // storages. Returns the number of removed records.
+    fn remove_records_beyond_limit(record_store: &mut RC, heap: &RecordBinaryHeap) -> usize {
+        let keys_to_remove = record_store
+            .records()
+            .map(|rec| rec.key.clone())
+            .filter(|key| !heap.should_include_key(key))
+            .collect::<Vec<_>>();
+
+        let mut removed_records = 0;
+
+        for batch in keys_to_remove.chunks(RECORDS_REMOVAL_BATCH_SIZE) {
+            for key in batch {
+                record_store.remove(key);
+            }
+
+            removed_records += batch.len();
+            debug!(
+                %removed_records,
+                total = keys_to_remove.len(),
+                "Removing records exceeding record cache limit."
+            );
+        }
+
+        removed_records
+    }
+
+    /// Registers record store size metric with provided Prometheus registry.
+    pub fn with_metrics(mut self, registry: &Registry) -> Result<Self, PrometheusError> {
+        let size_gauge = register(
+            Gauge::new(
+                "dsn_record_store_size",
+                "Number of records in the record store",
+            )?,
+            registry,
+        )?;
+        size_gauge.set(self.heap.size() as u64);
+        self.size_gauge.replace(size_gauge);
+
+        Ok(self)
+    }
+
+    fn update_size_metric(&self) {
+        if let Some(size_gauge) = &self.size_gauge {
+            size_gauge.set(self.heap.size() as u64);
         }
     }
 }
@@ -565,6 +632,8 @@ impl<'a, RC: RecordStorage<'a>> RecordStorage<'a> for LimitedSizeRecordStorageWr
             self.inner.remove(&key);
         }
 
+        self.update_size_metric();
+
         Ok(())
     }
 
@@ -572,6 +641,8 @@ impl<'a, RC: RecordStorage<'a>> RecordStorage<'a> for LimitedSizeRecordStorageWr
         self.inner.remove(key);
 
         self.heap.remove(key);
+
+        self.update_size_metric();
     }
 
     fn records(&'a self) -> Self::RecordsIter {
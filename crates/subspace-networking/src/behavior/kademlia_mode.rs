@@ -0,0 +1,339 @@
+//! Kademlia behaviour wrapper that implements client mode: Kademlia protocol is neither served on
+//! inbound streams nor advertised to other peers, so they don't add the node to their routing
+//! tables, while outgoing queries work as usual.
+
+use crate::create::KademliaMode;
+use libp2p::core::connection::ConnectionId;
+use libp2p::core::transport::ListenerId;
+use libp2p::core::upgrade::{DeniedUpgrade, EitherUpgrade};
+use libp2p::core::{ConnectedPoint, Multiaddr, PeerId};
+use libp2p::kad::protocol::KademliaProtocolConfig;
+use libp2p::kad::store::RecordStore;
+use libp2p::kad::{Kademlia, KademliaEvent};
+use libp2p::swarm::handler::{InboundUpgradeSend, OutboundUpgradeSend};
+use libp2p::swarm::{
+    ConnectionHandler, ConnectionHandlerEvent, ConnectionHandlerUpgrErr, DialError,
+    IntoConnectionHandler, KeepAlive, NetworkBehaviour, NetworkBehaviourAction, PollParameters,
+    SubstreamProtocol,
+};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::task::{Context, Poll};
+
+/// Inbound protocol of Kademlia connection handler, `DeniedUpgrade` is used when listening is not
+/// allowed.
+type KademliaInboundProtocol = EitherUpgrade<KademliaProtocolConfig, DeniedUpgrade>;
+
+/// [`Kademlia`] behaviour that doesn't accept inbound Kademlia streams in client mode.
+pub(crate) struct KademliaWithMode<RecordStore> {
+    kademlia: Kademlia<RecordStore>,
+    mode: KademliaMode,
+}
+
+impl<RecordStore> KademliaWithMode<RecordStore> {
+    pub(crate) fn new(kademlia: Kademlia<RecordStore>, mode: KademliaMode) -> Self {
+        Self { kademlia, mode }
+    }
+
+    fn wrap_handler<Proto>(&self, handler: Proto) -> KademliaModeHandlerProto<Proto> {
+        KademliaModeHandlerProto {
+            inner: handler,
+            mode: self.mode,
+        }
+    }
+}
+
+impl<RecordStore> Deref for KademliaWithMode<RecordStore> {
+    type Target = Kademlia<RecordStore>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.kademlia
+    }
+}
+
+impl<RecordStore> DerefMut for KademliaWithMode<RecordStore> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.kademlia
+    }
+}
+
+impl<Store> NetworkBehaviour for KademliaWithMode<Store>
+where
+    Store: for<'a> RecordStore<'a> + Send + 'static,
+{
+    type ConnectionHandler =
+        KademliaModeHandlerProto<<Kademlia<Store> as NetworkBehaviour>::ConnectionHandler>;
+    type OutEvent = KademliaEvent;
+
+    fn new_handler(&mut self) -> Self::ConnectionHandler {
+        let handler = self.kademlia.new_handler();
+
+        self.wrap_handler(handler)
+    }
+
+    fn addresses_of_peer(&mut self, peer_id: &PeerId) -> Vec<Multiaddr> {
+        self.kademlia.addresses_of_peer(peer_id)
+    }
+
+    fn inject_connection_established(
+        &mut self,
+        peer_id: &PeerId,
+        connection_id: &ConnectionId,
+        endpoint: &ConnectedPoint,
+        failed_addresses: Option<&Vec<Multiaddr>>,
+        other_established: usize,
+    ) {
+        self.kademlia.inject_connection_established(
+            peer_id,
+            connection_id,
+            endpoint,
+            failed_addresses,
+            other_established,
+        )
+    }
+
+    fn inject_connection_closed(
+        &mut self,
+        peer_id: &PeerId,
+        connection_id: &ConnectionId,
+        endpoint: &ConnectedPoint,
+        handler: <Self::ConnectionHandler as IntoConnectionHandler>::Handler,
+        remaining_established: usize,
+    ) {
+        self.kademlia.inject_connection_closed(
+            peer_id,
+            connection_id,
+            endpoint,
+            handler.inner,
+            remaining_established,
+        )
+    }
+
+    fn inject_address_change(
+        &mut self,
+        peer_id: &PeerId,
+        connection_id: &ConnectionId,
+        old: &ConnectedPoint,
+        new: &ConnectedPoint,
+    ) {
+        self.kademlia
+            .inject_address_change(peer_id, connection_id, old, new)
+    }
+
+    fn inject_event(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        event: <<Self::ConnectionHandler as IntoConnectionHandler>::Handler as ConnectionHandler>::OutEvent,
+    ) {
+        self.kademlia.inject_event(peer_id, connection_id, event)
+    }
+
+    fn inject_dial_failure(
+        &mut self,
+        peer_id: Option<PeerId>,
+        handler: Self::ConnectionHandler,
+        error: &DialError,
+    ) {
+        self.kademlia
+            .inject_dial_failure(peer_id, handler.inner, error)
+    }
+
+    fn inject_listen_failure(
+        &mut self,
+        local_addr: &Multiaddr,
+        send_back_addr: &Multiaddr,
+        handler: Self::ConnectionHandler,
+    ) {
+        self.kademlia
+            .inject_listen_failure(local_addr, send_back_addr, handler.inner)
+    }
+
+    fn inject_new_listener(&mut self, id: ListenerId) {
+        self.kademlia.inject_new_listener(id)
+    }
+
+    fn inject_new_listen_addr(&mut self, id: ListenerId, addr: &Multiaddr) {
+        self.kademlia.inject_new_listen_addr(id, addr)
+    }
+
+    fn inject_expired_listen_addr(&mut self, id: ListenerId, addr: &Multiaddr) {
+        self.kademlia.inject_expired_listen_addr(id, addr)
+    }
+
+    fn inject_listener_error(&mut self, id: ListenerId, err: &(dyn std::error::Error + 'static)) {
+        self.kademlia.inject_listener_error(id, err)
+    }
+
+    fn inject_listener_closed(&mut self, id: ListenerId, reason: Result<(), &io::Error>) {
+        self.kademlia.inject_listener_closed(id, reason)
+    }
+
+    fn inject_new_external_addr(&mut self, addr: &Multiaddr) {
+        self.kademlia.inject_new_external_addr(addr)
+    }
+
+    fn inject_expired_external_addr(&mut self, addr: &Multiaddr) {
+        self.kademlia.inject_expired_external_addr(addr)
+    }
+
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        params: &mut impl PollParameters,
+    ) -> Poll<NetworkBehaviourAction<Self::OutEvent, Self::ConnectionHandler>> {
+        let action = match self.kademlia.poll(cx, params) {
+            Poll::Ready(action) => action,
+            Poll::Pending => {
+                return Poll::Pending;
+            }
+        };
+
+        Poll::Ready(match action {
+            NetworkBehaviourAction::GenerateEvent(event) => {
+                NetworkBehaviourAction::GenerateEvent(event)
+            }
+            NetworkBehaviourAction::Dial { opts, handler } => NetworkBehaviourAction::Dial {
+                opts,
+                handler: self.wrap_handler(handler),
+            },
+            NetworkBehaviourAction::NotifyHandler {
+                peer_id,
+                handler,
+                event,
+            } => NetworkBehaviourAction::NotifyHandler {
+                peer_id,
+                handler,
+                event,
+            },
+            NetworkBehaviourAction::ReportObservedAddr { address, score } => {
+                NetworkBehaviourAction::ReportObservedAddr { address, score }
+            }
+            NetworkBehaviourAction::CloseConnection {
+                peer_id,
+                connection,
+            } => NetworkBehaviourAction::CloseConnection {
+                peer_id,
+                connection,
+            },
+        })
+    }
+}
+
+/// Prototype of [`KademliaModeHandler`].
+pub(crate) struct KademliaModeHandlerProto<Proto> {
+    inner: Proto,
+    mode: KademliaMode,
+}
+
+impl<Proto> IntoConnectionHandler for KademliaModeHandlerProto<Proto>
+where
+    Proto: IntoConnectionHandler,
+    Proto::Handler:
+        ConnectionHandler<InboundProtocol = KademliaInboundProtocol, InboundOpenInfo = ()>,
+{
+    type Handler = KademliaModeHandler<Proto::Handler>;
+
+    fn into_handler(self, remote_peer_id: &PeerId, endpoint: &ConnectedPoint) -> Self::Handler {
+        KademliaModeHandler {
+            inner: self.inner.into_handler(remote_peer_id, endpoint),
+            mode: self.mode,
+        }
+    }
+
+    // Used by identify to advertise supported protocols, Kademlia protocol is not advertised in
+    // client mode
+    fn inbound_protocol(&self) -> KademliaInboundProtocol {
+        if self.mode.is_client() {
+            EitherUpgrade::B(DeniedUpgrade)
+        } else {
+            self.inner.inbound_protocol()
+        }
+    }
+}
+
+/// Kademlia connection handler wrapper that denies inbound streams in client mode.
+pub(crate) struct KademliaModeHandler<Handler> {
+    inner: Handler,
+    mode: KademliaMode,
+}
+
+impl<Handler> ConnectionHandler for KademliaModeHandler<Handler>
+where
+    Handler: ConnectionHandler<InboundProtocol = KademliaInboundProtocol, InboundOpenInfo = ()>,
+{
+    type InEvent = Handler::InEvent;
+    type OutEvent = Handler::OutEvent;
+    type Error = Handler::Error;
+    type InboundProtocol = KademliaInboundProtocol;
+    type OutboundProtocol = Handler::OutboundProtocol;
+    type InboundOpenInfo = ();
+    type OutboundOpenInfo = Handler::OutboundOpenInfo;
+
+    fn listen_protocol(&self) -> SubstreamProtocol<Self::InboundProtocol, Self::InboundOpenInfo> {
+        if self.mode.is_client() {
+            SubstreamProtocol::new(EitherUpgrade::B(DeniedUpgrade), ())
+        } else {
+            self.inner.listen_protocol()
+        }
+    }
+
+    fn inject_fully_negotiated_inbound(
+        &mut self,
+        protocol: <Self::InboundProtocol as InboundUpgradeSend>::Output,
+        info: Self::InboundOpenInfo,
+    ) {
+        self.inner.inject_fully_negotiated_inbound(protocol, info)
+    }
+
+    fn inject_fully_negotiated_outbound(
+        &mut self,
+        protocol: <Self::OutboundProtocol as OutboundUpgradeSend>::Output,
+        info: Self::OutboundOpenInfo,
+    ) {
+        self.inner.inject_fully_negotiated_outbound(protocol, info)
+    }
+
+    fn inject_event(&mut self, event: Self::InEvent) {
+        self.inner.inject_event(event)
+    }
+
+    fn inject_address_change(&mut self, new_address: &Multiaddr) {
+        self.inner.inject_address_change(new_address)
+    }
+
+    fn inject_dial_upgrade_error(
+        &mut self,
+        info: Self::OutboundOpenInfo,
+        error: ConnectionHandlerUpgrErr<<Self::OutboundProtocol as OutboundUpgradeSend>::Error>,
+    ) {
+        self.inner.inject_dial_upgrade_error(info, error)
+    }
+
+    fn inject_listen_upgrade_error(
+        &mut self,
+        info: Self::InboundOpenInfo,
+        error: ConnectionHandlerUpgrErr<<Self::InboundProtocol as InboundUpgradeSend>::Error>,
+    ) {
+        self.inner.inject_listen_upgrade_error(info, error)
+    }
+
+    fn connection_keep_alive(&self) -> KeepAlive {
+        self.inner.connection_keep_alive()
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<
+        ConnectionHandlerEvent<
+            Self::OutboundProtocol,
+            Self::OutboundOpenInfo,
+            Self::OutEvent,
+            Self::Error,
+        >,
+    > {
+        self.inner.poll(cx)
+    }
+}
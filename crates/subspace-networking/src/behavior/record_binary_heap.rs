@@ -6,20 +6,19 @@ use std::collections::BinaryHeap;
 
 type KademliaBucketKey<T> = libp2p::kad::kbucket::Key<T, Sha256Hash>;
 
-// Helper structure. It wraps Kademlia distance to a given peer for heap-metrics.
+// Helper structure. It keeps Kademlia distance to a given peer for heap-metrics, distance is
+// calculated once to keep the memory footprint of each heap item small.
 #[derive(Debug, Clone)]
 struct RecordHeapKey {
-    peer_key: KademliaBucketKey<PeerId>,
-    key: KademliaBucketKey<Key>,
+    key: Key,
+    peer_distance: Distance,
 }
 
 impl RecordHeapKey {
-    fn peer_distance(&self) -> Distance {
-        self.key.distance(&self.peer_key)
-    }
+    fn new(peer_key: &KademliaBucketKey<PeerId>, key: Key) -> Self {
+        let peer_distance = KademliaBucketKey::new(key.clone()).distance(peer_key);
 
-    fn new(peer_key: KademliaBucketKey<PeerId>, key: KademliaBucketKey<Key>) -> Self {
-        Self { peer_key, key }
+        Self { key, peer_distance }
     }
 }
 
@@ -27,19 +26,19 @@ impl Eq for RecordHeapKey {}
 
 impl PartialEq<Self> for RecordHeapKey {
     fn eq(&self, other: &Self) -> bool {
-        self.peer_distance().eq(&other.peer_distance())
+        self.peer_distance.eq(&other.peer_distance)
     }
 }
 
 impl PartialOrd<Self> for RecordHeapKey {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.peer_distance().partial_cmp(&other.peer_distance())
+        self.peer_distance.partial_cmp(&other.peer_distance)
     }
 }
 
 impl Ord for RecordHeapKey {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.peer_distance().cmp(&other.peer_distance())
+        self.peer_distance.cmp(&other.peer_distance)
     }
 }
 
@@ -70,13 +69,13 @@ impl RecordBinaryHeap {
 
     /// Insert a key in the heap evicting (popping) if the size limit is exceeded.
     pub fn insert(&mut self, key: Key) -> Option<Key> {
-        let heap_key = RecordHeapKey::new(self.peer_key.clone(), KademliaBucketKey::new(key));
+        let heap_key = RecordHeapKey::new(&self.peer_key, key);
         self.max_heap.push(heap_key);
 
         if self.is_limit_exceeded() {
             let evicted = self.max_heap.pop();
 
-            return evicted.map(|key| key.key.preimage().clone());
+            return evicted.map(|heap_key| heap_key.key);
         }
 
         None
@@ -84,9 +83,24 @@ impl RecordBinaryHeap {
 
     /// Removes a key from the heap.
     pub fn remove(&mut self, key: &Key) {
-        self.max_heap.retain(|k| {
-            *k != RecordHeapKey::new(self.peer_key.clone(), KademliaBucketKey::new(key.clone()))
-        });
+        let heap_key = RecordHeapKey::new(&self.peer_key, key.clone());
+
+        self.max_heap.retain(|k| *k != heap_key);
+    }
+
+    /// Checks whether the key would be kept by the heap: the heap is not full yet or the key is
+    /// not further than the furthest key in the heap.
+    pub fn should_include_key(&self, key: &Key) -> bool {
+        if self.size() < self.limit {
+            return true;
+        }
+
+        match self.max_heap.peek() {
+            Some(furthest_heap_key) => {
+                RecordHeapKey::new(&self.peer_key, key.clone()) <= *furthest_heap_key
+            }
+            None => false,
+        }
     }
 
     fn is_limit_exceeded(&self) -> bool {
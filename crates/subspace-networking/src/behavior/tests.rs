@@ -1,18 +1,21 @@
 use super::persistent_parameters::remove_known_peer_addresses_internal;
 use crate::behavior::custom_record_store::{
-    CustomRecordStore, MemoryProviderStorage, NoRecordStorage,
+    CustomRecordStore, LimitedSizeRecordStorageWrapper, MemoryProviderStorage, MemoryRecordStorage,
+    NoRecordStorage, ParityDbRecordStorage, RecordStorage,
 };
 use crate::behavior::record_binary_heap::RecordBinaryHeap;
 use chrono::Duration;
 use libp2p::kad::kbucket::Sha256Hash;
 use libp2p::kad::record::Key;
 use libp2p::kad::store::RecordStore;
-use libp2p::kad::ProviderRecord;
+use libp2p::kad::{ProviderRecord, Record};
 use libp2p::multiaddr::Protocol;
 use libp2p::multihash::{Code, Multihash};
 use libp2p::{Multiaddr, PeerId};
 use lru::LruCache;
+use prometheus_endpoint::Registry;
 use std::collections::HashSet;
+use std::num::NonZeroUsize;
 
 #[tokio::test()]
 async fn test_address_timed_removal_from_known_peers_cache() {
@@ -214,3 +217,108 @@ fn binary_heap_eviction_works() {
         assert_eq!(evicted, key2);
     }
 }
+
+#[test]
+fn binary_heap_should_include_key_works() {
+    let peer_id =
+        PeerId::from_multihash(Multihash::wrap(Code::Identity.into(), [0u8].as_slice()).unwrap())
+            .unwrap();
+    let mut heap = RecordBinaryHeap::new(peer_id, 1);
+
+    let key1 = Key::from(vec![1]);
+    let key2 = Key::from(vec![2]);
+
+    assert!(heap.should_include_key(&key1));
+    assert!(heap.should_include_key(&key2));
+
+    heap.insert(key1.clone());
+    heap.insert(key2.clone());
+
+    // Only the closest key remains in the heap
+    assert_ne!(
+        heap.should_include_key(&key1),
+        heap.should_include_key(&key2)
+    );
+}
+
+#[test]
+fn limited_size_record_storage_limit_holds_across_restarts() {
+    type KademliaBucketKey<T> = libp2p::kad::kbucket::Key<T, Sha256Hash>;
+
+    let db_dir = tempfile::tempdir().unwrap();
+    let peer_id = PeerId::random();
+    let keys = (0..20u8).map(|i| Key::from(vec![i])).collect::<Vec<_>>();
+
+    let open_storage = |limit: usize| {
+        LimitedSizeRecordStorageWrapper::new(
+            ParityDbRecordStorage::new(db_dir.path()).unwrap(),
+            NonZeroUsize::new(limit).unwrap(),
+            peer_id,
+        )
+    };
+
+    {
+        let mut storage = open_storage(10);
+        for key in &keys[..10] {
+            storage.put(Record::new(key.clone(), vec![1])).unwrap();
+        }
+        assert_eq!(storage.records().count(), 10);
+    }
+
+    // Records stored before the restart are taken into account when new records are added
+    {
+        let mut storage = open_storage(10);
+        for key in &keys[10..] {
+            storage.put(Record::new(key.clone(), vec![1])).unwrap();
+        }
+        assert_eq!(storage.records().count(), 10);
+    }
+
+    // Decreased limit is enforced right after the restart, only the closest records are kept
+    let storage = open_storage(5);
+    let stored_keys = storage
+        .records()
+        .map(|rec| rec.key.clone())
+        .collect::<HashSet<_>>();
+
+    let peer_key = KademliaBucketKey::new(peer_id);
+    let mut sorted_keys = keys;
+    sorted_keys.sort_by_key(|key| KademliaBucketKey::new(key.clone()).distance(&peer_key));
+    let closest_keys = sorted_keys.into_iter().take(5).collect::<HashSet<_>>();
+
+    assert_eq!(stored_keys, closest_keys);
+}
+
+#[test]
+fn limited_size_record_storage_reports_size_metric() {
+    let registry = Registry::default();
+    let mut storage = LimitedSizeRecordStorageWrapper::new(
+        MemoryRecordStorage::default(),
+        NonZeroUsize::new(2).unwrap(),
+        PeerId::random(),
+    )
+    .with_metrics(&registry)
+    .unwrap();
+
+    let size = |registry: &Registry| {
+        registry
+            .gather()
+            .into_iter()
+            .find(|family| family.get_name() == "dsn_record_store_size")
+            .unwrap()
+            .get_metric()[0]
+            .get_gauge()
+            .get_value()
+    };
+
+    assert_eq!(size(&registry), 0.0);
+
+    for i in 0..3u8 {
+        storage
+            .put(Record::new(Key::from(vec![i]), vec![1]))
+            .unwrap();
+    }
+
+    // Size never exceeds the limit
+    assert_eq!(size(&registry), 2.0);
+}
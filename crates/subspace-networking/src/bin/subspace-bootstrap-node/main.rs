@@ -1,17 +1,47 @@
 //! Simple bootstrap node implementation
 
+#![feature(type_changing_struct_update)]
+
+#[cfg(feature = "bootstrap-node-rpc")]
+mod rpc;
+
+use anyhow::anyhow;
 use clap::Parser;
+use futures::{select, FutureExt};
 use libp2p::identity::sr25519::Keypair;
 use libp2p::Multiaddr;
+use prometheus_endpoint::Registry;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::num::NonZeroUsize;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use subspace_networking::libp2p::multiaddr::Protocol;
-use subspace_networking::{BootstrappedNetworkingParameters, Config};
+use subspace_networking::{
+    peer_id, Config, CustomRecordStore, LimitedSizeRecordStorageWrapper, MemoryProviderStorage,
+    NetworkingParametersManager, ParityDbRecordStorage, PeerInfo, PeerRole, RelayMode,
+};
 use tracing::info;
 
 // The default maximum incoming connections number for the peer.
 const MAX_ESTABLISHED_INCOMING_CONNECTIONS: u32 = 300;
 // The default maximum outgoing connections number for the peer.
 const MAX_ESTABLISHED_OUTGOING_CONNECTIONS: u32 = 300;
+// Name of the file with hex-encoded keypair inside base path.
+const KEYPAIR_FILE_NAME: &str = "keypair";
+// Name of the known peers DB directory inside base path.
+const NETWORKING_PARAMETERS_DB_DIR: &str = "known_peers_db";
+// Name of the record cache DB directory inside base path.
+const RECORD_CACHE_DB_DIR: &str = "records_cache_db";
+
+// Type alias for the record store used with enabled record cache.
+type RecordCacheStore = CustomRecordStore<
+    LimitedSizeRecordStorageWrapper<ParityDbRecordStorage>,
+    MemoryProviderStorage,
+>;
 
 #[derive(Debug, Parser)]
 #[clap(about, version)]
@@ -21,8 +51,14 @@ enum Command {
         /// Multiaddresses of bootstrap nodes to connect to on startup, multiple are supported
         #[clap(long, alias = "bootstrap-node")]
         bootstrap_nodes: Vec<Multiaddr>,
-        /// Keypair for node identity, can be obtained with `generate-keypair` command
-        keypair: String,
+        /// Directory for node data: keypair (created on the first start if missing), known peers
+        /// and record cache.
+        #[clap(long)]
+        base_path: PathBuf,
+        /// Hex-encoded keypair (as printed by `generate-keypair`) to import into base path, useful
+        /// for migrating existing nodes; ignored if base path already contains the same keypair.
+        #[clap(long)]
+        keypair: Option<String>,
         /// Multiaddr to listen on for subspace networking, multiple are supported
         #[clap(default_value = "/ip4/0.0.0.0/tcp/0")]
         listen_on: Vec<Multiaddr>,
@@ -35,6 +71,33 @@ enum Command {
         /// Defines max outgoing connections limit for the peer.
         #[clap(long)]
         out_peers: Option<u32>,
+        /// Enables circuit relay server, nodes behind NAT will be able to reserve relayed
+        /// addresses on this node.
+        #[clap(long)]
+        relay_server: bool,
+        /// Discovers peers in the local network with mDNS, useful for LAN clusters and development
+        /// setups.
+        #[clap(long)]
+        dsn_enable_mdns: bool,
+        /// Path to the file with private network pre-shared key, the node will only connect to
+        /// peers that use the same key.
+        #[clap(long)]
+        dsn_pnet_psk: Option<PathBuf>,
+        /// Stores DSN records put by other peers in a ParityDb-backed cache inside base path.
+        #[clap(long)]
+        dsn_record_cache: bool,
+        /// Maximum number of records in DSN record cache.
+        #[clap(long, default_value = "32768")]
+        dsn_record_cache_size: NonZeroUsize,
+        /// Address of Prometheus metrics endpoint, for instance `127.0.0.1:9616`, metrics are
+        /// disabled if not specified.
+        #[clap(long)]
+        metrics_endpoint: Option<SocketAddr>,
+        /// Address of local JSON-RPC endpoint reporting node status, for instance
+        /// `127.0.0.1:9944`, disabled if not specified.
+        #[cfg(feature = "bootstrap-node-rpc")]
+        #[clap(long)]
+        rpc_endpoint: Option<SocketAddr>,
     },
     /// Generate a new keypair
     GenerateKeypair,
@@ -51,16 +114,31 @@ async fn main() -> anyhow::Result<()> {
     match command {
         Command::Start {
             bootstrap_nodes,
+            base_path,
             keypair,
             listen_on,
             reserved_peers,
             in_peers,
             out_peers,
+            relay_server,
+            dsn_enable_mdns,
+            dsn_pnet_psk,
+            dsn_record_cache,
+            dsn_record_cache_size,
+            metrics_endpoint,
+            #[cfg(feature = "bootstrap-node-rpc")]
+            rpc_endpoint,
         } => {
+            fs::create_dir_all(&base_path)?;
+
+            let keypair = load_or_import_keypair(&base_path.join(KEYPAIR_FILE_NAME), keypair)?;
+            let prometheus_registry = metrics_endpoint.map(|_| Registry::default());
+
             let config = Config {
-                networking_parameters_registry: BootstrappedNetworkingParameters::new(
+                networking_parameters_registry: NetworkingParametersManager::new(
+                    &base_path.join(NETWORKING_PARAMETERS_DB_DIR),
                     bootstrap_nodes,
-                )
+                )?
                 .boxed(),
                 listen_on,
                 allow_non_globals_in_dht: true,
@@ -69,25 +147,61 @@ async fn main() -> anyhow::Result<()> {
                     .unwrap_or(MAX_ESTABLISHED_INCOMING_CONNECTIONS),
                 max_established_outgoing_connections: out_peers
                     .unwrap_or(MAX_ESTABLISHED_OUTGOING_CONNECTIONS),
-                ..Config::with_keypair(Keypair::decode(hex::decode(keypair)?.as_mut_slice())?)
+                relay_mode: if relay_server {
+                    RelayMode::Server
+                } else {
+                    RelayMode::NoRelay
+                },
+                enable_mdns: dsn_enable_mdns,
+                pnet_psk_path: dsn_pnet_psk,
+                prometheus_registry: prometheus_registry.clone(),
+                peer_info_provider: Some(Arc::new(move || {
+                    PeerInfo::new(if relay_server {
+                        PeerRole::Relay
+                    } else {
+                        PeerRole::BootstrapNode
+                    })
+                })),
+                ..Config::with_keypair(keypair)
             };
-            let (node, mut node_runner) = subspace_networking::create(config)
-                .await
-                .expect("Networking stack creation failed.");
-
-            node.on_new_listener(Arc::new({
-                let node_id = node.id();
-
-                move |multiaddr| {
-                    info!(
-                        "Listening on {}",
-                        multiaddr.clone().with(Protocol::P2p(node_id.into()))
-                    );
-                }
-            }))
-            .detach();
-
-            node_runner.run().await
+            let metrics = metrics_endpoint.zip(prometheus_registry);
+            #[cfg(not(feature = "bootstrap-node-rpc"))]
+            let rpc_endpoint = None;
+
+            if dsn_record_cache {
+                let record_cache_db_path = base_path.join(RECORD_CACHE_DB_DIR);
+
+                info!(
+                    ?record_cache_db_path,
+                    ?dsn_record_cache_size,
+                    "Record cache DB configured."
+                );
+
+                let record_storage = LimitedSizeRecordStorageWrapper::new(
+                    ParityDbRecordStorage::new(&record_cache_db_path)
+                        .map_err(|error| anyhow!(error.to_string()))?,
+                    dsn_record_cache_size,
+                    peer_id(&config.keypair),
+                );
+                let record_storage = match &metrics {
+                    Some((_, prometheus_registry)) => {
+                        record_storage.with_metrics(prometheus_registry)?
+                    }
+                    None => record_storage,
+                };
+
+                let config = Config::<RecordCacheStore> {
+                    record_store: CustomRecordStore::new(
+                        record_storage,
+                        MemoryProviderStorage::default(),
+                    ),
+                    ..config
+                };
+
+                run_node(config, metrics, rpc_endpoint).await?;
+            } else {
+                run_node(config, metrics, rpc_endpoint).await?;
+            }
         }
         Command::GenerateKeypair => {
             println!("{}", hex::encode(Keypair::generate().encode()))
@@ -96,3 +210,136 @@ async fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+fn decode_keypair(encoded_keypair: &str) -> anyhow::Result<Keypair> {
+    let mut encoded_keypair = hex::decode(encoded_keypair.trim())?;
+
+    Ok(Keypair::decode(encoded_keypair.as_mut_slice())?)
+}
+
+// Loads hex-encoded keypair from the file. If the file is missing, saves there the keypair to
+// import or a newly generated one.
+fn load_or_import_keypair(
+    path: &Path,
+    keypair_to_import: Option<String>,
+) -> anyhow::Result<Keypair> {
+    let keypair_to_import = keypair_to_import
+        .as_deref()
+        .map(decode_keypair)
+        .transpose()?;
+
+    if path.exists() {
+        let keypair = decode_keypair(&fs::read_to_string(path)?)?;
+
+        if let Some(keypair_to_import) = keypair_to_import {
+            if keypair_to_import.encode() != keypair.encode() {
+                return Err(anyhow!(
+                    "Keypair file {path:?} already contains a different keypair, remove it to \
+                    import the new one"
+                ));
+            }
+        }
+
+        return Ok(keypair);
+    }
+
+    let keypair = match keypair_to_import {
+        Some(keypair) => {
+            info!(?path, "Keypair imported.");
+
+            keypair
+        }
+        None => {
+            info!(?path, "New keypair generated.");
+
+            Keypair::generate()
+        }
+    };
+    write_keypair(path, &keypair)?;
+
+    Ok(keypair)
+}
+
+// Writes keypair readable by the owner only, through a temporary file so that an interrupted write
+// never leaves a truncated keypair behind.
+fn write_keypair(path: &Path, keypair: &Keypair) -> io::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+
+    // Permissions are only applied on file creation, don't reuse leftovers of a previous attempt
+    match fs::remove_file(&tmp_path) {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::NotFound => {}
+        Err(error) => {
+            return Err(error);
+        }
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(&tmp_path)?;
+    file.write_all(hex::encode(keypair.encode()).as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    fs::rename(tmp_path, path)
+}
+
+async fn run_node<RecordStore>(
+    config: Config<RecordStore>,
+    metrics: Option<(SocketAddr, Registry)>,
+    rpc_endpoint: Option<SocketAddr>,
+) -> anyhow::Result<()>
+where
+    RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
+{
+    let (node, mut node_runner) = subspace_networking::create(config)
+        .await
+        .map_err(|error| anyhow!("Networking stack creation failed: {error}"))?;
+
+    node.on_new_listener(Arc::new({
+        let node_id = node.id();
+
+        move |multiaddr| {
+            info!(
+                "Listening on {}",
+                multiaddr.clone().with(Protocol::P2p(node_id.into()))
+            );
+        }
+    }))
+    .detach();
+
+    #[cfg(not(feature = "bootstrap-node-rpc"))]
+    let _ = rpc_endpoint;
+    // Handle must be kept for as long as the node runs
+    #[cfg(feature = "bootstrap-node-rpc")]
+    let _rpc_server_handle = match rpc_endpoint {
+        Some(rpc_endpoint) => {
+            let rpc_server_handle = rpc::start_rpc_server(rpc_endpoint, node.clone()).await?;
+
+            info!(%rpc_endpoint, "RPC server started.");
+
+            Some(rpc_server_handle)
+        }
+        None => None,
+    };
+
+    match metrics {
+        Some((metrics_endpoint, prometheus_registry)) => {
+            select! {
+                _ = node_runner.run().fuse() => {},
+                result = prometheus_endpoint::init_prometheus(
+                    metrics_endpoint,
+                    prometheus_registry,
+                ).fuse() => {
+                    result.map_err(|error| anyhow!("Metrics endpoint failed: {error}"))?;
+                },
+            }
+        }
+        None => node_runner.run().await,
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+//! Local JSON-RPC endpoint for inspecting bootstrap node state.
+
+#[cfg(test)]
+mod tests;
+
+use jsonrpsee::core::Error as JsonRpseeError;
+use jsonrpsee::http_server::{HttpServerBuilder, HttpServerHandle};
+use jsonrpsee::RpcModule;
+use serde::Serialize;
+use std::net::SocketAddr;
+use subspace_networking::libp2p::{Multiaddr, PeerId};
+use subspace_networking::Node;
+
+/// Bootstrap node state returned by `bootstrapNode_status` RPC method.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct NodeStatus {
+    peer_id: PeerId,
+    connected_peers: Vec<PeerId>,
+    routing_table_size: usize,
+    listeners: Vec<Multiaddr>,
+}
+
+/// Starts JSON-RPC server on specified address.
+pub(crate) async fn start_rpc_server(
+    rpc_endpoint: SocketAddr,
+    node: Node,
+) -> anyhow::Result<HttpServerHandle> {
+    let module = rpc_module(node)?;
+    let server = HttpServerBuilder::default().build(rpc_endpoint).await?;
+
+    Ok(server.start(module)?)
+}
+
+fn rpc_module(node: Node) -> Result<RpcModule<Node>, JsonRpseeError> {
+    let mut module = RpcModule::new(node);
+
+    module.register_async_method("bootstrapNode_status", |_params, node| async move {
+        let connected_peers = node
+            .connected_peers()
+            .await
+            .map_err(|error| JsonRpseeError::Custom(error.to_string()))?;
+        let routing_table_size = node
+            .routing_table_size()
+            .await
+            .map_err(|error| JsonRpseeError::Custom(error.to_string()))?;
+
+        Ok(NodeStatus {
+            peer_id: node.id(),
+            connected_peers,
+            routing_table_size,
+            listeners: node.listeners(),
+        })
+    })?;
+
+    Ok(module)
+}
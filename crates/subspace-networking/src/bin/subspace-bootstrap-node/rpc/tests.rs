@@ -0,0 +1,99 @@
+use super::rpc_module;
+use futures::channel::oneshot;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use subspace_networking::libp2p::multiaddr::Protocol;
+use subspace_networking::{BootstrappedNetworkingParameters, Config};
+
+#[tokio::test]
+async fn status_reports_node_state() {
+    let config_1 = Config {
+        listen_on: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+        allow_non_globals_in_dht: true,
+        ..Config::with_generated_keypair()
+    };
+    let (node_1, mut node_runner_1) = subspace_networking::create(config_1).await.unwrap();
+
+    let (node_1_address_sender, node_1_address_receiver) = oneshot::channel();
+    let on_new_listener_handler = node_1.on_new_listener(Arc::new({
+        let node_1_address_sender = Mutex::new(Some(node_1_address_sender));
+
+        move |address| {
+            if let Some(node_1_address_sender) = node_1_address_sender.lock().take() {
+                node_1_address_sender.send(address.clone()).unwrap();
+            }
+        }
+    }));
+
+    tokio::spawn(async move {
+        node_runner_1.run().await;
+    });
+
+    let node_1_address = node_1_address_receiver.await.unwrap();
+    drop(on_new_listener_handler);
+
+    let config_2 = Config {
+        networking_parameters_registry: BootstrappedNetworkingParameters::new(vec![
+            node_1_address.with(Protocol::P2p(node_1.id().into()))
+        ])
+        .boxed(),
+        listen_on: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+        allow_non_globals_in_dht: true,
+        ..Config::with_generated_keypair()
+    };
+    let (node_2, mut node_runner_2) = subspace_networking::create(config_2).await.unwrap();
+
+    tokio::spawn(async move {
+        node_runner_2.run().await;
+    });
+
+    let module = rpc_module(node_2.clone()).unwrap();
+
+    // Connection to bootstrap node is established asynchronously
+    let mut status = Value::Null;
+    for _ in 0..50 {
+        let (response, _subscription) = module
+            .raw_json_request(r#"{"jsonrpc":"2.0","id":0,"method":"bootstrapNode_status"}"#)
+            .await
+            .unwrap();
+        let response = serde_json::from_str::<Value>(&response).unwrap();
+        status = response["result"].clone();
+
+        if status["connectedPeers"] != Value::Array(Vec::new()) {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(status["peerId"], node_2.id().to_string());
+    assert_eq!(
+        status["connectedPeers"],
+        serde_json::json!([node_1.id().to_string()])
+    );
+    assert_eq!(status["routingTableSize"], 1);
+    assert_eq!(
+        status["listeners"],
+        serde_json::to_value(node_2.listeners()).unwrap()
+    );
+}
+
+#[tokio::test]
+async fn unknown_method_is_rejected() {
+    let (node, _node_runner) = subspace_networking::create(Config::with_generated_keypair())
+        .await
+        .unwrap();
+
+    let module = rpc_module(node).unwrap();
+
+    let (response, _subscription) = module
+        .raw_json_request(r#"{"jsonrpc":"2.0","id":0,"method":"bootstrapNode_unknown"}"#)
+        .await
+        .unwrap();
+    let response = serde_json::from_str::<Value>(&response).unwrap();
+
+    assert!(response["result"].is_null());
+    assert!(!response["error"].is_null());
+}
@@ -1,37 +1,56 @@
+#[cfg(test)]
+mod tests;
+
 pub use crate::behavior::custom_record_store::ValueGetter;
 use crate::behavior::custom_record_store::{
     CustomRecordStore, MemoryProviderStorage, NoRecordStorage,
 };
 use crate::behavior::persistent_parameters::NetworkingParametersRegistry;
 use crate::behavior::{Behavior, BehaviorConfig};
+use crate::metrics::Metrics;
 use crate::node::{CircuitRelayClientError, Node};
 use crate::node_runner::{NodeRunner, NodeRunnerConfig};
+use crate::request_handlers::peer_info::{
+    PeerInfoProvider, PeerInfoRequest, PeerInfoRequestHandler, PeerInfoResponse,
+};
 use crate::request_responses::RequestHandler;
 use crate::shared::Shared;
 use crate::utils::convert_multiaddresses;
-use crate::BootstrappedNetworkingParameters;
+use crate::utils::rate_limiter::{RateLimitedStream, RateLimiter};
+use crate::{BootstrappedNetworkingParameters, GenericRequest};
 use futures::channel::mpsc;
+use futures::{AsyncRead, AsyncWrite};
+use libp2p::autonat::Config as AutonatConfig;
+use libp2p::bandwidth::{BandwidthLogging, BandwidthSinks};
+use libp2p::core::either::EitherOutput;
 use libp2p::core::muxing::StreamMuxerBox;
-use libp2p::core::transport::Boxed;
+use libp2p::core::transport::{Boxed, OptionalTransport};
 use libp2p::dns::TokioDnsConfig;
 use libp2p::gossipsub::{
-    GossipsubConfig, GossipsubConfigBuilder, GossipsubMessage, MessageId, ValidationMode,
+    GossipsubConfig, GossipsubConfigBuilder, GossipsubMessage, MessageAuthenticity, MessageId,
+    ValidationMode,
 };
 use libp2p::identify::IdentifyConfig;
 use libp2p::identity::Keypair;
 use libp2p::kad::{KademliaBucketInserts, KademliaCaching, KademliaConfig, KademliaStoreInserts};
+use libp2p::mdns::{Mdns, MdnsConfig};
 use libp2p::mplex::MplexConfig;
 use libp2p::multiaddr::Protocol;
 use libp2p::noise::NoiseConfig;
+use libp2p::pnet::{KeyParseError, PnetConfig, PnetOutput, PreSharedKey};
+use libp2p::relay::v2::client::transport::ClientTransport;
+use libp2p::relay::v2::client::Client as RelayClient;
 use libp2p::swarm::SwarmBuilder;
 use libp2p::tcp::{GenTcpConfig, TokioTcpTransport};
 use libp2p::websocket::WsConfig;
 use libp2p::yamux::{WindowUpdateMode, YamuxConfig};
 use libp2p::{core, identity, noise, Multiaddr, PeerId, Transport, TransportError};
-use std::num::NonZeroUsize;
+use prometheus_endpoint::{PrometheusError, Registry};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
-use std::{fmt, io};
+use std::{fmt, fs, io};
 use subspace_core_primitives::{crypto, PIECE_SIZE};
 use thiserror::Error;
 use tracing::info;
@@ -45,6 +64,12 @@ const SWARM_MAX_NEGOTIATING_INBOUND_STREAMS: usize = 100000;
 const SWARM_MAX_ESTABLISHED_INCOMING_CONNECTIONS: u32 = 50;
 // The default maximum incoming connection number for the swarm.
 const SWARM_MAX_ESTABLISHED_OUTGOING_CONNECTIONS: u32 = 50;
+// The default maximum incoming connections number from a single IP address.
+const SWARM_MAX_INCOMING_CONNECTIONS_PER_IP: u32 = 5;
+// The default maximum incoming connections number from a single subnet.
+const SWARM_MAX_INCOMING_CONNECTIONS_PER_SUBNET: u32 = 15;
+// The default maximum number of peers from a single subnet in a Kademlia k-bucket.
+const KADEMLIA_MAX_BUCKET_PEERS_PER_SUBNET: usize = 2;
 // Defines an expiration interval for item providers in Kademlia network.
 const KADEMLIA_PROVIDER_TTL_IN_SECS: Option<Duration> = Some(Duration::from_secs(86400)); /* 1 day */
 // Defines a republication interval for item providers in Kademlia network.
@@ -59,6 +84,12 @@ const KADEMLIA_RECORD_REPLICATION_FACTOR: NonZeroUsize =
 // Defines a replication factor for Kademlia on get_record operation.
 // "Good citizen" supports the network health.
 const KADEMLIA_CACHING_FACTOR_ON_GET_RECORDS: u16 = 3;
+// Defines a delay before the first AutoNAT probe, gives connections some time to be established.
+const AUTONAT_BOOT_DELAY: Duration = Duration::from_secs(15);
+// Defines an interval between AutoNAT probes after the reachability status was confirmed.
+const AUTONAT_REFRESH_INTERVAL: Duration = Duration::from_secs(15 * 60);
+// Defines an interval between AutoNAT probes while the reachability status is unknown.
+const AUTONAT_RETRY_INTERVAL: Duration = Duration::from_secs(90);
 
 /// Defines relay configuration for the Node
 #[derive(Clone, Debug)]
@@ -79,6 +110,30 @@ impl RelayMode {
     pub fn is_relay_server(&self) -> bool {
         matches!(self, RelayMode::Server)
     }
+
+    /// Defines whether the node has its relay client behavior enabled.
+    pub fn is_relay_client(&self) -> bool {
+        matches!(self, RelayMode::Client(_))
+    }
+}
+
+/// Defines Kademlia mode of the Node
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KademliaMode {
+    /// The node stores records put by other peers and is added to their routing tables.
+    #[default]
+    Server,
+    /// The node queries DHT as usual, but doesn't serve Kademlia protocol on incoming streams and
+    /// doesn't advertise it, so other peers neither add the node to their routing tables nor send
+    /// records to it, useful for short-lived and bandwidth-constrained nodes.
+    Client,
+}
+
+impl KademliaMode {
+    /// Defines whether the node runs Kademlia in client mode.
+    pub fn is_client(&self) -> bool {
+        matches!(self, KademliaMode::Client)
+    }
 }
 
 /// [`Node`] configuration.
@@ -99,6 +154,9 @@ pub struct Config<RecordStore = CustomRecordStore> {
     pub kademlia: KademliaConfig,
     /// The configuration for the Gossip behaviour.
     pub gossipsub: GossipsubConfig,
+    /// Sign published gossipsub messages with the identity keypair of the node. Incoming messages
+    /// are accepted either way, but signatures are verified when present.
+    pub gossipsub_message_signing: bool,
     /// Externally provided implementation of the custom record store for Kademlia DHT,
     pub record_store: RecordStore,
     /// Yamux multiplexing configuration.
@@ -119,6 +177,44 @@ pub struct Config<RecordStore = CustomRecordStore> {
     pub max_established_incoming_connections: u32,
     /// Outgoing swarm connection limit.
     pub max_established_outgoing_connections: u32,
+    /// Incoming connection limit per remote IP address. Reserved peers, relayed connections and
+    /// non-global addresses are exempt.
+    pub max_incoming_connections_per_ip: u32,
+    /// Incoming connection limit per remote subnet (/24 for IPv4, /64 for IPv6). Reserved peers,
+    /// relayed connections and non-global addresses are exempt.
+    pub max_incoming_connections_per_subnet: u32,
+    /// Maximum number of peers from a single subnet (/24 for IPv4, /64 for IPv6) in one Kademlia
+    /// k-bucket, addresses of other peers from that subnet are not added to the bucket. Reserved
+    /// peers and non-global addresses are exempt.
+    pub max_kademlia_bucket_peers_per_subnet: usize,
+    /// Defines relay configuration for the node.
+    pub relay_mode: RelayMode,
+    /// Defines Kademlia mode of the node.
+    pub kademlia_mode: KademliaMode,
+    /// Path to the file with private network pre-shared key (`/key/swarm/psk/1.0.0/` format),
+    /// when specified, the node only connects to peers with the same key.
+    ///
+    /// Standard libp2p private network handshake is used, key mismatch is not detected by the
+    /// handshake itself, such connections fail during the security upgrade that follows.
+    pub pnet_psk_path: Option<PathBuf>,
+    /// Known circuit relay servers. When AutoNAT detects that the node is behind NAT, it reserves
+    /// a relayed address with these servers and upgrades relayed connections with hole punching.
+    ///
+    /// Example: /ip4/1.2.3.4/tcp/30333/p2p/<server_peer_id>
+    pub relay_servers: Vec<Multiaddr>,
+    /// The configuration for the AutoNAT behaviour, `None` disables reachability detection.
+    pub autonat: Option<AutonatConfig>,
+    /// Upload rate limit (bytes per second) shared by all direct connections, `None` means no
+    /// limit. Per-protocol limits are configured with request handlers.
+    pub upload_rate_limit: Option<NonZeroU32>,
+    /// Prometheus registry to register networking metrics with, `None` disables metrics.
+    pub prometheus_registry: Option<Registry>,
+    /// Provides information about the local peer for peer-info protocol. When specified, the node
+    /// serves peer-info requests, queries connected peers and disconnects peers from other chains.
+    pub peer_info_provider: Option<PeerInfoProvider>,
+    /// Enables discovery of peers in the local network with mDNS, useful for LAN clusters and
+    /// development setups.
+    pub enable_mdns: bool,
 }
 
 impl fmt::Debug for Config {
@@ -163,8 +259,10 @@ impl Config {
 
         let gossipsub = GossipsubConfigBuilder::default()
             .protocol_id_prefix(GOSSIPSUB_PROTOCOL_PREFIX)
-            // TODO: Do we want message signing?
-            .validation_mode(ValidationMode::None)
+            // Signing is optional, but signatures must be valid when present.
+            .validation_mode(ValidationMode::Permissive)
+            // Messages are only propagated after topic validators (if any) accepted them.
+            .validate_messages()
             // To content-address message, we can take the hash of message and use it as an ID.
             .message_id_fn(|message: &GossipsubMessage| {
                 MessageId::from(crypto::blake2b_256_hash(&message.data))
@@ -176,6 +274,14 @@ impl Config {
         let keypair = identity::Keypair::Sr25519(keypair);
 
         let identify = IdentifyConfig::new("ipfs/0.1.0".to_string(), keypair.public());
+
+        let autonat = AutonatConfig {
+            boot_delay: AUTONAT_BOOT_DELAY,
+            refresh_interval: AUTONAT_REFRESH_INTERVAL,
+            retry_interval: AUTONAT_RETRY_INTERVAL,
+            ..AutonatConfig::default()
+        };
+
         Self {
             keypair,
             listen_on: vec![],
@@ -184,6 +290,7 @@ impl Config {
             identify,
             kademlia,
             gossipsub,
+            gossipsub_message_signing: false,
             record_store: CustomRecordStore::new(NoRecordStorage, MemoryProviderStorage::default()),
             allow_non_globals_in_dht: false,
             initial_random_query_interval: Duration::from_secs(1),
@@ -194,6 +301,18 @@ impl Config {
             reserved_peers: Vec::new(),
             max_established_incoming_connections: SWARM_MAX_ESTABLISHED_INCOMING_CONNECTIONS,
             max_established_outgoing_connections: SWARM_MAX_ESTABLISHED_OUTGOING_CONNECTIONS,
+            max_incoming_connections_per_ip: SWARM_MAX_INCOMING_CONNECTIONS_PER_IP,
+            max_incoming_connections_per_subnet: SWARM_MAX_INCOMING_CONNECTIONS_PER_SUBNET,
+            max_kademlia_bucket_peers_per_subnet: KADEMLIA_MAX_BUCKET_PEERS_PER_SUBNET,
+            relay_mode: RelayMode::NoRelay,
+            kademlia_mode: KademliaMode::Server,
+            pnet_psk_path: None,
+            relay_servers: Vec::new(),
+            autonat: Some(autonat),
+            upload_rate_limit: None,
+            prometheus_registry: None,
+            peer_info_provider: None,
+            enable_mdns: false,
         }
     }
 }
@@ -213,6 +332,12 @@ pub enum CreationError {
     /// Transport error when attempting to listen on multiaddr.
     #[error("Transport error when attempting to listen on multiaddr: {0}")]
     TransportError(#[from] TransportError<io::Error>),
+    /// Prometheus error.
+    #[error("Prometheus error: {0}")]
+    Prometheus(#[from] PrometheusError),
+    /// Private network pre-shared key parsing error.
+    #[error("Failed to parse private network pre-shared key: {0}")]
+    PnetKey(#[from] KeyParseError),
 }
 
 /// Converts public key from keypair to PeerId.
@@ -225,6 +350,48 @@ pub fn peer_id(keypair: &Keypair) -> PeerId {
 pub async fn create<RecordStore>(
     config: Config<RecordStore>,
 ) -> Result<(Node, NodeRunner<RecordStore>), CreationError>
+where
+    RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
+{
+    let (relay_transport, relay_client) = relay_transport_and_client(&config);
+
+    let pnet_psk = match &config.pnet_psk_path {
+        Some(pnet_psk_path) => {
+            let pnet_psk = fs::read_to_string(pnet_psk_path)?.parse::<PreSharedKey>()?;
+
+            info!(
+                ?pnet_psk_path,
+                fingerprint = %pnet_psk.fingerprint(),
+                "Private network pre-shared key loaded."
+            );
+
+            Some(pnet_psk)
+        }
+        None => None,
+    };
+
+    let (transport, bandwidth) = build_transport(
+        &config.keypair,
+        config.timeout,
+        config.yamux_config.clone(),
+        config.mplex_config.clone(),
+        relay_transport,
+        config.upload_rate_limit,
+        pnet_psk,
+    )?;
+
+    create_with_transport(config, transport, bandwidth, relay_client).await
+}
+
+// Creates node and node runner with the swarm running over provided transport. Transport related
+// configuration options are expected to be already applied to the transport, `relay_client` must be
+// paired with relay transport that is a part of `transport`.
+pub(crate) async fn create_with_transport<RecordStore>(
+    config: Config<RecordStore>,
+    transport: Boxed<(PeerId, StreamMuxerBox)>,
+    bandwidth: Arc<BandwidthSinks>,
+    relay_client: Option<RelayClient>,
+) -> Result<(Node, NodeRunner<RecordStore>), CreationError>
 where
     RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
 {
@@ -232,24 +399,65 @@ where
         keypair,
         listen_on,
         listen_on_fallback_to_random_port,
-        timeout,
+        timeout: _,
         identify,
         kademlia,
         gossipsub,
+        gossipsub_message_signing,
         record_store,
-        yamux_config,
-        mplex_config,
+        yamux_config: _,
+        mplex_config: _,
         allow_non_globals_in_dht,
         initial_random_query_interval,
         networking_parameters_registry,
-        request_response_protocols,
+        mut request_response_protocols,
         reserved_peers,
         max_established_incoming_connections,
         max_established_outgoing_connections,
+        max_incoming_connections_per_ip,
+        max_incoming_connections_per_subnet,
+        max_kademlia_bucket_peers_per_subnet,
+        relay_mode,
+        kademlia_mode,
+        pnet_psk_path: _,
+        relay_servers,
+        autonat,
+        upload_rate_limit: _,
+        prometheus_registry,
+        peer_info_provider,
+        enable_mdns,
     } = config;
     let local_peer_id = peer_id(&keypair);
 
-    let transport = build_transport(&keypair, timeout, yamux_config, mplex_config)?;
+    if let Some(peer_info_provider) = &peer_info_provider {
+        let protocols = request_response_protocols
+            .iter()
+            .map(|request_handler| request_handler.protocol_name().to_string())
+            .chain([PeerInfoRequest::PROTOCOL_NAME.to_string()])
+            .collect::<Vec<_>>();
+        let peer_info_provider = Arc::clone(peer_info_provider);
+
+        request_response_protocols.push(PeerInfoRequestHandler::create(move |_request| {
+            let mut peer_info = peer_info_provider();
+            peer_info.protocols = protocols.clone();
+
+            Some(PeerInfoResponse { peer_info })
+        }));
+    }
+
+    let metrics = prometheus_registry.as_ref().map(Metrics::new).transpose()?;
+
+    let gossipsub_authenticity = if gossipsub_message_signing {
+        MessageAuthenticity::Signed(keypair.clone())
+    } else {
+        MessageAuthenticity::Anonymous
+    };
+
+    let mdns = if enable_mdns {
+        Some(Mdns::new(MdnsConfig::default()).await?)
+    } else {
+        None
+    };
 
     // libp2p uses blocking API, hence we need to create a blocking task.
     let create_swarm_fut = tokio::task::spawn_blocking(move || {
@@ -257,9 +465,15 @@ where
             peer_id: local_peer_id,
             identify,
             kademlia,
+            kademlia_mode,
             gossipsub,
+            gossipsub_authenticity,
             record_store,
             request_response_protocols,
+            autonat,
+            relay_client,
+            relay_server: relay_mode.is_relay_server(),
+            mdns,
         });
 
         let mut swarm = SwarmBuilder::new(transport, behaviour, local_peer_id)
@@ -288,10 +502,30 @@ where
             }
         }
 
+        // Listen on a statically configured circuit relay address.
+        if let RelayMode::Client(relay_address) = &relay_mode {
+            swarm.listen_on(relay_address.clone())?;
+        }
+
+        let relay_servers = convert_multiaddresses(relay_servers);
+
+        if let Some(autonat) = swarm.behaviour_mut().autonat.as_mut() {
+            // Relay servers are expected to be publicly reachable and make good probe servers.
+            for (peer_id, address) in &relay_servers {
+                autonat.add_server(*peer_id, Some(address.clone()));
+            }
+        }
+
         // Create final structs
         let (command_sender, command_receiver) = mpsc::channel(1);
 
-        let shared = Arc::new(Shared::new(local_peer_id, command_sender));
+        let protocols_bandwidth = swarm.behaviour().request_response.protocols_bandwidth();
+        let shared = Arc::new(Shared::new(
+            local_peer_id,
+            command_sender,
+            bandwidth,
+            protocols_bandwidth,
+        ));
         let shared_weak = Arc::downgrade(&shared);
 
         let node = Node::new(shared);
@@ -305,6 +539,12 @@ where
             reserved_peers: convert_multiaddresses(reserved_peers).into_iter().collect(),
             max_established_incoming_connections,
             max_established_outgoing_connections,
+            max_incoming_connections_per_ip,
+            max_incoming_connections_per_subnet,
+            max_kademlia_bucket_peers_per_subnet,
+            relay_servers: relay_servers.into_iter().collect(),
+            metrics,
+            peer_info_provider,
         });
 
         Ok((node, node_runner))
@@ -316,14 +556,34 @@ where
     )
 }
 
-// Builds the transport stack that LibP2P will communicate over along with a relay client.
+// Creates relay client transport and behaviour when the node needs them, which is the case both for
+// a static relay and for automatic reservations.
+pub(crate) fn relay_transport_and_client<RecordStore>(
+    config: &Config<RecordStore>,
+) -> (Option<ClientTransport>, Option<RelayClient>) {
+    if config.relay_mode.is_relay_client() || !config.relay_servers.is_empty() {
+        let (relay_transport, relay_client) =
+            RelayClient::new_transport_and_behaviour(peer_id(&config.keypair));
+
+        (Some(relay_transport), Some(relay_client))
+    } else {
+        (None, None)
+    }
+}
+
+// Builds the transport stack that LibP2P will communicate over along with a relay client, returns
+// bandwidth sinks of direct connections along with it.
+#[allow(clippy::too_many_arguments)]
 fn build_transport(
     keypair: &identity::Keypair,
     timeout: Duration,
     yamux_config: YamuxConfig,
     mplex_config: MplexConfig,
-) -> Result<Boxed<(PeerId, StreamMuxerBox)>, CreationError> {
-    let transport = {
+    relay_transport: Option<ClientTransport>,
+    upload_rate_limit: Option<NonZeroU32>,
+    pnet_psk: Option<PreSharedKey>,
+) -> Result<(Boxed<(PeerId, StreamMuxerBox)>, Arc<BandwidthSinks>), CreationError> {
+    let (transport, bandwidth) = {
         let dns_tcp = TokioDnsConfig::system(TokioTcpTransport::new(
             GenTcpConfig::default().nodelay(true),
         ))?;
@@ -331,14 +591,39 @@ fn build_transport(
             GenTcpConfig::default().nodelay(true),
         ))?);
 
-        dns_tcp.or_transport(ws)
+        // Relayed connections are carried by direct connections to relay servers, so only direct
+        // connections are limited and accounted for to avoid doing it twice.
+        let upload_rate_limiter = upload_rate_limit.map(RateLimiter::new);
+        let (direct_transport, bandwidth) = BandwidthLogging::new(
+            dns_tcp
+                .or_transport(ws)
+                .map(move |stream, _| RateLimitedStream::new(stream, upload_rate_limiter.clone())),
+        );
+
+        let relay_transport = match relay_transport {
+            Some(relay_transport) => OptionalTransport::some(relay_transport),
+            None => OptionalTransport::none(),
+        };
+
+        (relay_transport.or_transport(direct_transport), bandwidth)
     };
 
+    // Relayed connections are covered as well, otherwise peers outside of private network would be
+    // able to connect through relay servers.
+    let transport = transport.and_then(move |socket, _| async move {
+        match pnet_psk {
+            Some(pnet_psk) => pnet_handshake(socket, pnet_psk)
+                .await
+                .map(EitherOutput::First),
+            None => Ok(EitherOutput::Second(socket)),
+        }
+    });
+
     let noise_keys = noise::Keypair::<noise::X25519Spec>::new()
         .into_authentic(keypair)
         .expect("Signing libp2p-noise static DH keypair failed.");
 
-    Ok(transport
+    let transport = transport
         .upgrade(core::upgrade::Version::V1Lazy)
         .authenticate(NoiseConfig::xx(noise_keys).into_authenticated())
         .multiplex(core::upgrade::SelectUpgrade::new(
@@ -346,5 +631,21 @@ fn build_transport(
             mplex_config,
         ))
         .timeout(timeout)
-        .boxed())
+        .boxed();
+
+    Ok((transport, bandwidth))
+}
+
+// Performs private network handshake, data is encrypted with pre-shared key afterwards.
+async fn pnet_handshake<Socket>(
+    socket: Socket,
+    pnet_psk: PreSharedKey,
+) -> io::Result<PnetOutput<Socket>>
+where
+    Socket: AsyncRead + AsyncWrite + Send + Unpin + 'static,
+{
+    PnetConfig::new(pnet_psk)
+        .handshake(socket)
+        .await
+        .map_err(|error| io::Error::new(io::ErrorKind::Other, error))
 }
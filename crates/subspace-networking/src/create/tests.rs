@@ -0,0 +1,83 @@
+use super::pnet_handshake;
+use futures::future::{join, poll_fn};
+use futures::{AsyncReadExt, AsyncWriteExt};
+use libp2p::core::transport::{MemoryTransport, TransportEvent};
+use libp2p::multiaddr::Protocol;
+use libp2p::pnet::PreSharedKey;
+use libp2p::{Multiaddr, Transport};
+use std::pin::Pin;
+
+// First message sent over a new connection by libp2p.
+const MESSAGE: &[u8] = b"/multistream/1.0.0";
+
+/// Establishes a pair of connected memory transport sockets.
+async fn memory_socket_pair() -> (
+    <MemoryTransport as Transport>::Output,
+    <MemoryTransport as Transport>::Output,
+) {
+    let address = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let mut listener = MemoryTransport::default();
+    listener.listen_on(address.clone()).unwrap();
+
+    let accept = async {
+        loop {
+            let event = poll_fn(|cx| Pin::new(&mut listener).poll(cx)).await;
+            if let TransportEvent::Incoming { upgrade, .. } = event {
+                return upgrade.await.unwrap();
+            }
+        }
+    };
+    let dial = async {
+        MemoryTransport::default()
+            .dial(address)
+            .unwrap()
+            .await
+            .unwrap()
+    };
+
+    let (listener_socket, dialer_socket) = join(accept, dial).await;
+
+    (dialer_socket, listener_socket)
+}
+
+// Sends a message from dialer to listener after private network handshake, returns the message
+// received by listener.
+async fn send_over_pnet(dialer_pnet_psk: PreSharedKey, listener_pnet_psk: PreSharedKey) -> Vec<u8> {
+    let (dialer_socket, listener_socket) = memory_socket_pair().await;
+
+    let (dialer_result, listener_result) = join(
+        pnet_handshake(dialer_socket, dialer_pnet_psk),
+        pnet_handshake(listener_socket, listener_pnet_psk),
+    )
+    .await;
+    let mut dialer_socket = dialer_result.unwrap();
+    let mut listener_socket = listener_result.unwrap();
+
+    dialer_socket.write_all(MESSAGE).await.unwrap();
+    dialer_socket.flush().await.unwrap();
+
+    let mut received_message = vec![0u8; MESSAGE.len()];
+    listener_socket
+        .read_exact(&mut received_message)
+        .await
+        .unwrap();
+
+    received_message
+}
+
+#[tokio::test]
+async fn pnet_handshake_with_same_key() {
+    let pnet_psk = PreSharedKey::new([1; 32]);
+
+    assert_eq!(send_over_pnet(pnet_psk, pnet_psk).await, MESSAGE);
+}
+
+#[tokio::test]
+async fn pnet_handshake_with_different_keys() {
+    // Standard private network handshake succeeds regardless of the keys, but the data can't be
+    // decrypted, so the following security upgrade fails.
+    assert_ne!(
+        send_over_pnet(PreSharedKey::new([1; 32]), PreSharedKey::new([2; 32])).await,
+        MESSAGE
+    );
+}
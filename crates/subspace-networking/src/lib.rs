@@ -15,23 +15,33 @@
 
 //! Networking functionality of Subspace Network, primarily used for DSN (Distributed Storage
 //! Network).
-#![feature(binary_heap_retain, const_option, ip, try_blocks)]
+#![feature(
+    binary_heap_retain,
+    const_option,
+    ip,
+    try_blocks,
+    type_changing_struct_update
+)]
 
 mod behavior;
 mod create;
+mod metrics;
 mod node;
 mod node_runner;
 mod request_handlers;
 mod request_responses;
 mod shared;
+#[cfg(any(test, feature = "simulator"))]
+pub mod simulator;
 pub mod utils;
 
 pub use crate::behavior::persistent_parameters::{
     BootstrappedNetworkingParameters, NetworkingParametersManager,
 };
 pub use crate::node::{
-    CircuitRelayClientError, GetClosestPeersError, Node, SendRequestError, SubscribeError,
-    TopicSubscription,
+    CheckConnectedPeersError, CircuitRelayClientError, GetClosestPeersError, GetNodeStateError,
+    GetProvidersError, GetValueError, InboundRequestsStats, Node, QueryOptions, SendRequestError,
+    SubscribeError, TopicSubscription, TopicValidator,
 };
 pub use crate::node_runner::NodeRunner;
 pub use behavior::custom_record_store::{
@@ -39,19 +49,23 @@ pub use behavior::custom_record_store::{
     MemoryProviderStorage, MemoryRecordStorage, NoRecordStorage, ParityDbRecordStorage,
     RecordStorage,
 };
-pub use create::{create, peer_id, Config, CreationError, RelayMode};
+pub use create::{create, peer_id, Config, CreationError, KademliaMode, RelayMode};
 pub use libp2p;
 pub use request_handlers::generic_request_handler::{GenericRequest, GenericRequestHandler};
 pub use request_handlers::object_mappings::{
     ObjectMappingsRequest, ObjectMappingsRequestHandler, ObjectMappingsResponse,
 };
 pub use request_handlers::peer_info::{
-    PeerInfo, PeerInfoRequest, PeerInfoRequestHandler, PeerInfoResponse, PeerSyncStatus,
+    PeerInfo, PeerInfoProvider, PeerInfoRequest, PeerInfoRequestHandler, PeerInfoResponse,
+    PeerRole, PeerSyncStatus, PieceKeyRange,
 };
 pub use request_handlers::piece_by_key::{
     PieceByHashRequest, PieceByHashRequestHandler, PieceByHashResponse, PieceKey,
 };
 pub use request_handlers::pieces_by_range::{
     PiecesByRangeRequest, PiecesByRangeRequestHandler, PiecesByRangeResponse, PiecesToPlot,
+    PIECES_BY_RANGE_PAGE_SIZE,
 };
+pub use request_responses::{InboundRequestLimits, ProtocolBandwidth};
 pub use utils::multihash::ToMultihash;
+pub use utils::pieces_by_range_syncer::{PieceValidator, PiecesByRangeSyncer};
@@ -0,0 +1,198 @@
+//! Prometheus metrics of the networking stack.
+
+use libp2p::kad::QueryResult;
+use prometheus_endpoint::{
+    exponential_buckets, register, Counter, CounterVec, Gauge, Histogram, HistogramOpts,
+    HistogramVec, Opts, PrometheusError, Registry, U64,
+};
+
+/// Metrics recorded by [`NodeRunner`](crate::NodeRunner).
+#[derive(Clone)]
+pub(crate) struct Metrics {
+    // libp2p metrics
+    pub(crate) connections_established: Counter<U64>,
+    pub(crate) connections_closed: Counter<U64>,
+    pub(crate) connected_peers: Gauge<U64>,
+    pub(crate) kademlia_queries: CounterVec<U64>,
+    pub(crate) kademlia_query_duration: HistogramVec,
+    pub(crate) kademlia_routing_table_size: Gauge<U64>,
+    pub(crate) gossipsub_messages_received: CounterVec<U64>,
+    pub(crate) gossipsub_messages_published: CounterVec<U64>,
+    pub(crate) gossipsub_subscribed_topics: Gauge<U64>,
+    pub(crate) identify_received: Counter<U64>,
+    pub(crate) ping_rtt: Histogram,
+    // Subspace-specific metrics
+    pub(crate) get_value: CounterVec<U64>,
+    pub(crate) put_value: CounterVec<U64>,
+    pub(crate) inbound_requests: CounterVec<U64>,
+    pub(crate) outbound_requests: CounterVec<U64>,
+    pub(crate) request_duration: HistogramVec,
+}
+
+impl Metrics {
+    pub(crate) fn new(registry: &Registry) -> Result<Self, PrometheusError> {
+        Ok(Self {
+            connections_established: register(
+                Counter::new(
+                    "dsn_connections_established_total",
+                    "Total number of established connections",
+                )?,
+                registry,
+            )?,
+            connections_closed: register(
+                Counter::new(
+                    "dsn_connections_closed_total",
+                    "Total number of closed connections",
+                )?,
+                registry,
+            )?,
+            connected_peers: register(
+                Gauge::new("dsn_connected_peers", "Number of connected peers")?,
+                registry,
+            )?,
+            kademlia_queries: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_kademlia_queries_total",
+                        "Total number of finished Kademlia queries",
+                    ),
+                    &["query", "result"],
+                )?,
+                registry,
+            )?,
+            kademlia_query_duration: register(
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "dsn_kademlia_query_duration_seconds",
+                        "Duration of Kademlia queries",
+                    )
+                    .buckets(exponential_buckets(0.1, 2.0, 10)?),
+                    &["query"],
+                )?,
+                registry,
+            )?,
+            kademlia_routing_table_size: register(
+                Gauge::new(
+                    "dsn_kademlia_routing_table_size",
+                    "Number of peers in Kademlia routing table",
+                )?,
+                registry,
+            )?,
+            gossipsub_messages_received: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_gossipsub_messages_received_total",
+                        "Total number of received gossipsub messages by validation result",
+                    ),
+                    &["acceptance"],
+                )?,
+                registry,
+            )?,
+            gossipsub_messages_published: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_gossipsub_messages_published_total",
+                        "Total number of published gossipsub messages",
+                    ),
+                    &["result"],
+                )?,
+                registry,
+            )?,
+            gossipsub_subscribed_topics: register(
+                Gauge::new(
+                    "dsn_gossipsub_subscribed_topics",
+                    "Number of subscribed gossipsub topics",
+                )?,
+                registry,
+            )?,
+            identify_received: register(
+                Counter::new(
+                    "dsn_identify_received_total",
+                    "Total number of received identify messages",
+                )?,
+                registry,
+            )?,
+            ping_rtt: register(
+                Histogram::with_opts(
+                    HistogramOpts::new("dsn_ping_rtt_seconds", "Round trip time of pings")
+                        .buckets(exponential_buckets(0.01, 2.0, 10)?),
+                )?,
+                registry,
+            )?,
+            get_value: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_get_value_total",
+                        "Total number of `get_value` requests",
+                    ),
+                    &["result"],
+                )?,
+                registry,
+            )?,
+            put_value: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_put_value_total",
+                        "Total number of `put_value` requests",
+                    ),
+                    &["result"],
+                )?,
+                registry,
+            )?,
+            inbound_requests: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_inbound_requests_total",
+                        "Total number of incoming request-response requests",
+                    ),
+                    &["protocol", "result"],
+                )?,
+                registry,
+            )?,
+            outbound_requests: register(
+                CounterVec::new(
+                    Opts::new(
+                        "dsn_outbound_requests_total",
+                        "Total number of outgoing request-response requests",
+                    ),
+                    &["protocol", "result"],
+                )?,
+                registry,
+            )?,
+            request_duration: register(
+                HistogramVec::new(
+                    HistogramOpts::new(
+                        "dsn_request_duration_seconds",
+                        "Duration of request-response requests",
+                    )
+                    .buckets(exponential_buckets(0.01, 2.0, 12)?),
+                    &["protocol", "direction"],
+                )?,
+                registry,
+            )?,
+        })
+    }
+}
+
+/// Returns query name and whether query was successful for metric labels.
+pub(crate) fn kademlia_query_labels(result: &QueryResult) -> (&'static str, bool) {
+    match result {
+        QueryResult::Bootstrap(result) => ("bootstrap", result.is_ok()),
+        QueryResult::GetClosestPeers(result) => ("get_closest_peers", result.is_ok()),
+        QueryResult::GetProviders(result) => ("get_providers", result.is_ok()),
+        QueryResult::StartProviding(result) => ("start_providing", result.is_ok()),
+        QueryResult::RepublishProvider(result) => ("republish_provider", result.is_ok()),
+        QueryResult::GetRecord(result) => ("get_record", result.is_ok()),
+        QueryResult::PutRecord(result) => ("put_record", result.is_ok()),
+        QueryResult::RepublishRecord(result) => ("republish_record", result.is_ok()),
+    }
+}
+
+/// Label value of the operation result.
+pub(crate) fn result_label(success: bool) -> &'static str {
+    if success {
+        "success"
+    } else {
+        "failure"
+    }
+}
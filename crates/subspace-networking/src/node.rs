@@ -1,23 +1,151 @@
 use crate::request_handlers::generic_request_handler::GenericRequest;
+use crate::request_handlers::peer_info::PeerInfo;
 use crate::request_responses;
+use crate::request_responses::ProtocolBandwidth;
 use crate::shared::{Command, CreatedSubscription, Shared};
 use bytes::Bytes;
 use event_listener_primitives::HandlerId;
 use futures::channel::mpsc::SendError;
 use futures::channel::{mpsc, oneshot};
-use futures::{SinkExt, Stream};
+use futures::{ready, Future, FutureExt, SinkExt, Stream};
+use libp2p::autonat::NatStatus;
 use libp2p::core::multihash::Multihash;
 use libp2p::gossipsub::error::SubscriptionError;
-use libp2p::gossipsub::Sha256Topic;
+use libp2p::gossipsub::{MessageAcceptance, Sha256Topic};
 use libp2p::{Multiaddr, PeerId};
 use parity_scale_codec::Decode;
+use std::collections::HashMap;
+use std::fmt;
+use std::pin::Pin;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::task::{Context, Poll};
 use std::time::Duration;
 use thiserror::Error;
-use tokio::time::sleep;
+use tokio::time::{sleep, timeout};
 use tracing::{error, trace};
 
+/// Validator of messages received on a topic.
+///
+/// Called with the peer that propagated the message and the message contents before the message is
+/// delivered to subscribers and propagated further. [`MessageAcceptance::Reject`] penalizes the
+/// propagating peer in gossipsub peer scoring, [`MessageAcceptance::Ignore`] drops the message
+/// without penalty. Validator is called on the networking event loop, so it must be cheap.
+#[derive(Clone)]
+pub struct TopicValidator(Arc<dyn Fn(&PeerId, &[u8]) -> MessageAcceptance + Send + Sync + 'static>);
+
+impl fmt::Debug for TopicValidator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TopicValidator").finish()
+    }
+}
+
+impl TopicValidator {
+    /// Create a new validator from a callback.
+    pub fn new<F>(validator: F) -> Self
+    where
+        F: Fn(&PeerId, &[u8]) -> MessageAcceptance + Send + Sync + 'static,
+    {
+        Self(Arc::new(validator))
+    }
+
+    pub(crate) fn validate(&self, propagation_source: &PeerId, data: &[u8]) -> MessageAcceptance {
+        (self.0)(propagation_source, data)
+    }
+}
+
+/// Options of a single DHT query or request.
+///
+/// When the caller stops waiting for the result (because of the timeout or because the future was
+/// dropped), the corresponding Kademlia query or pending request is cancelled by the node runner.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Maximum time to wait for the result, `None` means only Kademlia or request-response
+    /// protocol timeouts apply.
+    pub timeout: Option<Duration>,
+}
+
+impl QueryOptions {
+    /// Options with specified timeout.
+    pub fn with_timeout(timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+        }
+    }
+}
+
+// Awaits the future for at most the specified timeout (if any), returns `None` on timeout.
+async fn with_optional_timeout<F>(duration: Option<Duration>, future: F) -> Option<F::Output>
+where
+    F: Future,
+{
+    match duration {
+        Some(duration) => timeout(duration, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// Receiver of a query or request result. When dropped before the result is received (because of
+/// the timeout or because the caller's future was dropped), node runner is asked to cancel
+/// abandoned queries.
+struct QueryResultReceiver<T> {
+    receiver: Option<oneshot::Receiver<T>>,
+    command_sender: mpsc::Sender<Command>,
+}
+
+impl<T> QueryResultReceiver<T> {
+    fn new(receiver: oneshot::Receiver<T>, command_sender: &mpsc::Sender<Command>) -> Self {
+        Self {
+            receiver: Some(receiver),
+            command_sender: command_sender.clone(),
+        }
+    }
+}
+
+impl<T> Future for QueryResultReceiver<T> {
+    type Output = Result<T, oneshot::Canceled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = ready!(self
+            .receiver
+            .as_mut()
+            .expect("Only removed once the result is received or on drop; qed")
+            .poll_unpin(cx));
+
+        self.receiver.take();
+
+        Poll::Ready(result)
+    }
+}
+
+impl<T> Drop for QueryResultReceiver<T> {
+    fn drop(&mut self) {
+        if let Some(receiver) = self.receiver.take() {
+            // Receiver is dropped before the command is sent, so that node runner sees the query as
+            // abandoned.
+            drop(receiver);
+
+            // Every sender has a guaranteed slot in the channel, so a fresh sender doesn't fail
+            // because of the full channel. Doesn't matter if node runner is already dropped.
+            let _ = self
+                .command_sender
+                .clone()
+                .try_send(Command::CancelAbandonedQueries);
+        }
+    }
+}
+
+/// Statistics of incoming requests for a request-response protocol.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct InboundRequestsStats {
+    /// Number of requests that were successfully answered.
+    pub served: u64,
+    /// Number of requests refused due to per-peer limits.
+    pub rejected: u64,
+    /// Number of requests that failed for other reasons (network errors, missing responses).
+    pub failed: u64,
+}
+
 /// Topic subscription, will unsubscribe when last instance is dropped for a particular topic.
 #[derive(Debug)]
 #[pin_project::pin_project(PinnedDrop)]
@@ -75,6 +203,9 @@ pub enum GetValueError {
     /// Node runner was dropped
     #[error("Node runner was dropped")]
     NodeRunnerDropped,
+    /// Result wasn't received before the timeout.
+    #[error("Result wasn't received before the timeout")]
+    Timeout,
 }
 
 impl From<oneshot::Canceled> for GetValueError {
@@ -107,6 +238,9 @@ pub enum GetClosestPeersError {
     /// Node runner was dropped
     #[error("Node runner was dropped")]
     NodeRunnerDropped,
+    /// Result wasn't received before the timeout.
+    #[error("Result wasn't received before the timeout")]
+    Timeout,
 }
 
 impl From<oneshot::Canceled> for GetClosestPeersError {
@@ -120,6 +254,27 @@ pub enum CheckConnectedPeersError {
     /// Node runner was dropped, impossible to check connected peers.
     #[error("Node runner was dropped, impossible to check connected peers")]
     NodeRunnerDropped,
+    /// No peers were connected before the timeout.
+    #[error("No peers were connected before the timeout")]
+    Timeout,
+}
+
+/// Errors of requests for node runner state, like connected peers, their info or routing table
+/// size.
+#[derive(Debug, Error)]
+pub enum GetNodeStateError {
+    /// Failed to send command to the node runner
+    #[error("Failed to send command to the node runner: {0}")]
+    SendCommand(#[from] SendError),
+    /// Node runner was dropped
+    #[error("Node runner was dropped")]
+    NodeRunnerDropped,
+}
+
+impl From<oneshot::Canceled> for GetNodeStateError {
+    fn from(oneshot::Canceled: oneshot::Canceled) -> Self {
+        Self::NodeRunnerDropped
+    }
 }
 
 #[derive(Debug, Error)]
@@ -168,6 +323,9 @@ pub enum GetProvidersError {
     /// Node runner was dropped
     #[error("Node runner was dropped")]
     NodeRunnerDropped,
+    /// Result wasn't received before the timeout.
+    #[error("Result wasn't received before the timeout")]
+    Timeout,
     /// Failed to get providers.
     #[error("Failed to get providers.")]
     GetProviders,
@@ -225,6 +383,9 @@ pub enum SendRequestError {
     /// Node runner was dropped
     #[error("Node runner was dropped")]
     NodeRunnerDropped,
+    /// Result wasn't received before the timeout.
+    #[error("Result wasn't received before the timeout")]
+    Timeout,
     /// Underlying protocol returned an error, impossible to get response.
     #[error("Underlying protocol returned an error: {0}")]
     ProtocolFailure(#[from] request_responses::RequestFailure),
@@ -314,6 +475,16 @@ impl Node {
     }
 
     pub async fn get_value(&self, key: Multihash) -> Result<Option<Vec<u8>>, GetValueError> {
+        self.get_value_with_options(key, QueryOptions::default())
+            .await
+    }
+
+    /// Get value by multihash key using Kademlia DHT with specified query options.
+    pub async fn get_value_with_options(
+        &self,
+        key: Multihash,
+        options: QueryOptions,
+    ) -> Result<Option<Vec<u8>>, GetValueError> {
         let (result_sender, result_receiver) = oneshot::channel();
 
         self.shared
@@ -321,8 +492,12 @@ impl Node {
             .clone()
             .send(Command::GetValue { key, result_sender })
             .await?;
+        let result_receiver =
+            QueryResultReceiver::new(result_receiver, &self.shared.command_sender);
 
-        Ok(result_receiver.await?)
+        Ok(with_optional_timeout(options.timeout, result_receiver)
+            .await
+            .ok_or(GetValueError::Timeout)??)
     }
 
     pub async fn put_value(&self, key: Multihash, value: Vec<u8>) -> Result<bool, PutValueError> {
@@ -338,10 +513,28 @@ impl Node {
             })
             .await?;
 
-        Ok(result_receiver.await?)
+        Ok(QueryResultReceiver::new(result_receiver, &self.shared.command_sender).await?)
     }
 
     pub async fn subscribe(&self, topic: Sha256Topic) -> Result<TopicSubscription, SubscribeError> {
+        self.subscribe_internal(topic, None).await
+    }
+
+    /// Subscribe to a topic, messages will only be delivered to subscribers and propagated further
+    /// if they were accepted by validators of all logical subscriptions to this topic.
+    pub async fn subscribe_with_validator(
+        &self,
+        topic: Sha256Topic,
+        validator: TopicValidator,
+    ) -> Result<TopicSubscription, SubscribeError> {
+        self.subscribe_internal(topic, Some(validator)).await
+    }
+
+    async fn subscribe_internal(
+        &self,
+        topic: Sha256Topic,
+        validator: Option<TopicValidator>,
+    ) -> Result<TopicSubscription, SubscribeError> {
         let (result_sender, result_receiver) = oneshot::channel();
 
         self.shared
@@ -349,6 +542,7 @@ impl Node {
             .clone()
             .send(Command::Subscribe {
                 topic: topic.clone(),
+                validator,
                 result_sender,
             })
             .await?;
@@ -388,6 +582,20 @@ impl Node {
         peer_id: PeerId,
         request: Request,
     ) -> Result<Request::Response, SendRequestError>
+    where
+        Request: GenericRequest,
+    {
+        self.send_generic_request_with_options(peer_id, request, QueryOptions::default())
+            .await
+    }
+
+    /// Sends the generic request to the peer and awaits the result with specified options.
+    pub async fn send_generic_request_with_options<Request>(
+        &self,
+        peer_id: PeerId,
+        request: Request,
+        options: QueryOptions,
+    ) -> Result<Request::Response, SendRequestError>
     where
         Request: GenericRequest,
     {
@@ -400,8 +608,12 @@ impl Node {
         };
 
         self.shared.command_sender.clone().send(command).await?;
+        let result_receiver =
+            QueryResultReceiver::new(result_receiver, &self.shared.command_sender);
 
-        let result = result_receiver.await??;
+        let result = with_optional_timeout(options.timeout, result_receiver)
+            .await
+            .ok_or(SendRequestError::Timeout)???;
 
         Request::Response::decode(&mut result.as_slice()).map_err(Into::into)
     }
@@ -410,6 +622,16 @@ impl Node {
     pub async fn get_closest_peers(
         &self,
         key: Multihash,
+    ) -> Result<Vec<PeerId>, GetClosestPeersError> {
+        self.get_closest_peers_with_options(key, QueryOptions::default())
+            .await
+    }
+
+    /// Get closest peers by multihash key using Kademlia DHT with specified query options.
+    pub async fn get_closest_peers_with_options(
+        &self,
+        key: Multihash,
+        options: QueryOptions,
     ) -> Result<Vec<PeerId>, GetClosestPeersError> {
         trace!(?key, "Starting 'GetClosestPeers' request.");
 
@@ -420,17 +642,79 @@ impl Node {
             .clone()
             .send(Command::GetClosestPeers { key, result_sender })
             .await?;
+        let result_receiver =
+            QueryResultReceiver::new(result_receiver, &self.shared.command_sender);
 
-        let peers = result_receiver.await?;
+        let peers = with_optional_timeout(options.timeout, result_receiver)
+            .await
+            .ok_or(GetClosestPeersError::Timeout)??;
 
         trace!("Kademlia 'GetClosestPeers' returned {} peers", peers.len());
 
         Ok(peers)
     }
 
-    // TODO: add timeout
+    /// Returns currently connected peers.
+    pub async fn connected_peers(&self) -> Result<Vec<PeerId>, GetNodeStateError> {
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        self.shared
+            .command_sender
+            .clone()
+            .send(Command::GetConnectedPeers { result_sender })
+            .await?;
+
+        Ok(result_receiver.await?)
+    }
+
+    /// Returns the number of peers in Kademlia routing table.
+    pub async fn routing_table_size(&self) -> Result<usize, GetNodeStateError> {
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        self.shared
+            .command_sender
+            .clone()
+            .send(Command::GetRoutingTableSize { result_sender })
+            .await?;
+
+        Ok(result_receiver.await?)
+    }
+
+    /// Returns information advertised by connected peers over peer-info protocol.
+    ///
+    /// Peers that didn't respond (yet) or don't support the protocol are not included.
+    pub async fn connected_peers_info(
+        &self,
+    ) -> Result<HashMap<PeerId, PeerInfo>, GetNodeStateError> {
+        let (result_sender, result_receiver) = oneshot::channel();
+
+        self.shared
+            .command_sender
+            .clone()
+            .send(Command::GetPeersInfo { result_sender })
+            .await?;
+
+        Ok(result_receiver.await?)
+    }
+
     /// Waits for peers connection to the swarm and for Kademlia address registration.
     pub async fn wait_for_connected_peers(&self) -> Result<(), CheckConnectedPeersError> {
+        self.wait_for_connected_peers_with_options(QueryOptions::default())
+            .await
+    }
+
+    /// Waits for peers connection to the swarm and for Kademlia address registration for at most
+    /// the timeout from options.
+    pub async fn wait_for_connected_peers_with_options(
+        &self,
+        options: QueryOptions,
+    ) -> Result<(), CheckConnectedPeersError> {
+        with_optional_timeout(options.timeout, self.wait_for_connected_peers_inner())
+            .await
+            .ok_or(CheckConnectedPeersError::Timeout)?
+    }
+
+    async fn wait_for_connected_peers_inner(&self) -> Result<(), CheckConnectedPeersError> {
         loop {
             trace!("Starting 'CheckConnectedPeers' request.");
 
@@ -469,7 +753,7 @@ impl Node {
             .send(Command::StartAnnouncing { key, result_sender })
             .await?;
 
-        result_receiver
+        QueryResultReceiver::new(result_receiver, &self.shared.command_sender)
             .await?
             .then_some(())
             .ok_or(AnnounceError::Announce)
@@ -495,6 +779,16 @@ impl Node {
 
     /// Get item providers by its key. Initiate 'providers' Kademlia operation.
     pub async fn get_providers(&self, key: Multihash) -> Result<Vec<PeerId>, GetProvidersError> {
+        self.get_providers_with_options(key, QueryOptions::default())
+            .await
+    }
+
+    /// Get item providers by its key with specified query options.
+    pub async fn get_providers_with_options(
+        &self,
+        key: Multihash,
+        options: QueryOptions,
+    ) -> Result<Vec<PeerId>, GetProvidersError> {
         let (result_sender, result_receiver) = oneshot::channel();
 
         trace!(?key, "Starting 'get_providers' request.");
@@ -504,8 +798,14 @@ impl Node {
             .clone()
             .send(Command::GetProviders { key, result_sender })
             .await?;
+        let result_receiver =
+            QueryResultReceiver::new(result_receiver, &self.shared.command_sender);
+
+        let providers = with_optional_timeout(options.timeout, result_receiver)
+            .await
+            .ok_or(GetProvidersError::Timeout)??;
 
-        if let Some(providers) = result_receiver.await? {
+        if let Some(providers) = providers {
             trace!(
                 "Kademlia 'GetProviders' returned {} providers.",
                 providers.len()
@@ -531,4 +831,38 @@ impl Node {
     ) -> HandlerId {
         self.shared.handlers.new_listener.add(callback)
     }
+
+    /// Node's reachability status as detected by AutoNAT, `NatStatus::Unknown` until the first
+    /// successful probe (or when AutoNAT is disabled).
+    pub fn nat_status(&self) -> NatStatus {
+        self.shared.nat_status.lock().clone()
+    }
+
+    /// Callback is called when node's reachability status changes.
+    pub fn on_nat_status_change(
+        &self,
+        callback: Arc<dyn Fn(&NatStatus) + Send + Sync + 'static>,
+    ) -> HandlerId {
+        self.shared.handlers.nat_status_changed.add(callback)
+    }
+
+    /// Statistics of incoming requests by request-response protocol name.
+    pub fn inbound_requests_stats(&self) -> HashMap<String, InboundRequestsStats> {
+        self.shared.inbound_requests_stats.lock().clone()
+    }
+
+    /// Total number of bytes received over direct connections.
+    pub fn total_inbound_bytes(&self) -> u64 {
+        self.shared.bandwidth.total_inbound()
+    }
+
+    /// Total number of bytes sent over direct connections.
+    pub fn total_outbound_bytes(&self) -> u64 {
+        self.shared.bandwidth.total_outbound()
+    }
+
+    /// Bandwidth of request and response payloads by request-response protocol name.
+    pub fn protocols_bandwidth(&self) -> HashMap<String, ProtocolBandwidth> {
+        self.shared.protocols_bandwidth.lock().clone()
+    }
 }
@@ -1,33 +1,60 @@
+#[cfg(test)]
+mod tests;
+
 use crate::behavior::custom_record_store::CustomRecordStore;
 use crate::behavior::persistent_parameters::NetworkingParametersRegistry;
 use crate::behavior::{Behavior, Event};
-use crate::request_responses::{Event as RequestResponseEvent, IfDisconnected};
+use crate::metrics::{kademlia_query_labels, result_label, Metrics};
+use crate::node::TopicValidator;
+use crate::request_handlers::peer_info::{
+    PeerInfo, PeerInfoProvider, PeerInfoRequest, PeerInfoResponse,
+};
+use crate::request_responses::{
+    Event as RequestResponseEvent, IfDisconnected, RequestFailure, ResponseFailure,
+};
 use crate::shared::{Command, CreatedSubscription, Shared};
-use crate::utils;
+use crate::{utils, GenericRequest};
 use bytes::Bytes;
 use futures::channel::{mpsc, oneshot};
-use futures::future::Fuse;
+use futures::future::{BoxFuture, Fuse};
+use futures::stream::FuturesUnordered;
 use futures::{FutureExt, StreamExt};
+use libp2p::autonat::{Event as AutonatEvent, NatStatus};
+use libp2p::core::transport::ListenerId;
 use libp2p::core::ConnectedPoint;
-use libp2p::gossipsub::{GossipsubEvent, TopicHash};
+use libp2p::dcutr::behaviour::Event as DcutrEvent;
+use libp2p::gossipsub::{
+    GossipsubEvent, GossipsubMessage, MessageAcceptance, TopicHash, TopicScoreParams,
+};
 use libp2p::identify::IdentifyEvent;
 use libp2p::kad::{
     AddProviderError, AddProviderOk, GetClosestPeersError, GetClosestPeersOk, GetProvidersError,
-    GetProvidersOk, GetRecordError, GetRecordOk, InboundRequest, KademliaEvent, PutRecordOk,
-    QueryId, QueryResult, Quorum, Record,
+    GetProvidersOk, GetRecordError, GetRecordOk, InboundRequest, Kademlia, KademliaEvent,
+    PutRecordOk, QueryId, QueryResult, Quorum, Record,
 };
+use libp2p::mdns::MdnsEvent;
+use libp2p::multiaddr::Protocol;
+use libp2p::ping::{PingEvent, PingSuccess};
+use libp2p::relay::v2::client::Event as RelayClientEvent;
 use libp2p::swarm::dial_opts::DialOpts;
 use libp2p::swarm::{DialError, SwarmEvent};
 use libp2p::{futures, Multiaddr, PeerId, Swarm};
+use lru::LruCache;
 use nohash_hasher::IntMap;
+use parity_scale_codec::{Decode, Encode};
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
 use std::pin::Pin;
 use std::sync::Weak;
 use std::time::Duration;
 use tokio::time::Sleep;
 use tracing::{debug, error, trace, warn};
 
+// Number of peers from other chains remembered to avoid reconnecting to them, the least recently
+// rejected peers are forgotten first.
+const REJECTED_PEERS_CACHE_SIZE: usize = 1000;
+
 enum QueryResultSender {
     Value {
         sender: oneshot::Sender<Option<Vec<u8>>>,
@@ -46,6 +73,26 @@ enum QueryResultSender {
     },
 }
 
+impl QueryResultSender {
+    fn is_canceled(&self) -> bool {
+        match self {
+            QueryResultSender::Value { sender } => sender.is_canceled(),
+            QueryResultSender::ClosestPeers { sender } => sender.is_canceled(),
+            QueryResultSender::Providers { sender } => sender.is_canceled(),
+            QueryResultSender::Announce { sender } => sender.is_canceled(),
+            QueryResultSender::PutValue { sender } => sender.is_canceled(),
+        }
+    }
+}
+
+type PeerInfoResult = Result<Result<Vec<u8>, RequestFailure>, oneshot::Canceled>;
+
+/// Logical subscription to a topic.
+struct TopicSubscriber {
+    sender: mpsc::UnboundedSender<Bytes>,
+    validator: Option<TopicValidator>,
+}
+
 /// Runner for the Node.
 #[must_use = "Node does not function properly unless its runner is driven forward"]
 pub struct NodeRunner<RecordStore = CustomRecordStore>
@@ -65,7 +112,7 @@ where
     next_subscription_id: usize,
     /// Topic subscription senders for logical subscriptions (multiple logical subscriptions can be
     /// present for the same physical subscription).
-    topic_subscription_senders: HashMap<TopicHash, IntMap<usize, mpsc::UnboundedSender<Bytes>>>,
+    topic_subscription_senders: HashMap<TopicHash, IntMap<usize, TopicSubscriber>>,
     random_query_timeout: Pin<Box<Fuse<Sleep>>>,
     /// Defines a timeout between swarm attempts to dial known addresses
     peer_dialing_timeout: Pin<Box<Fuse<Sleep>>>,
@@ -77,6 +124,31 @@ where
     max_established_incoming_connections: u32,
     /// Outgoing swarm connection limit.
     max_established_outgoing_connections: u32,
+    /// Incoming connection limit per remote IP address.
+    max_incoming_connections_per_ip: u32,
+    /// Incoming connection limit per remote subnet.
+    max_incoming_connections_per_subnet: u32,
+    /// Maximum number of peers from a single subnet in one Kademlia k-bucket.
+    max_kademlia_bucket_peers_per_subnet: usize,
+    /// Number of established incoming connections by global remote IP address.
+    incoming_connections_per_ip: HashMap<IpAddr, u32>,
+    /// Number of established incoming connections by global remote subnet.
+    incoming_connections_per_subnet: HashMap<IpAddr, u32>,
+    /// Known circuit relay servers used for reservations when the node is behind NAT.
+    relay_servers: HashMap<PeerId, Multiaddr>,
+    /// Listeners on relayed addresses by relay server peer ID.
+    relay_listeners: HashMap<PeerId, ListenerId>,
+    /// Prometheus metrics, `None` if metrics are disabled.
+    metrics: Option<Metrics>,
+    /// Provides information about the local peer, `None` if peer-info protocol is disabled.
+    peer_info_provider: Option<PeerInfoProvider>,
+    /// Information advertised by connected peers.
+    peers_info: HashMap<PeerId, PeerInfo>,
+    /// Pending peer-info requests to recently connected peers.
+    peer_info_requests: FuturesUnordered<BoxFuture<'static, (PeerId, PeerInfoResult)>>,
+    /// Recently rejected peers that belong to a different chain, connections to them are closed
+    /// right away.
+    rejected_peers: LruCache<PeerId, ()>,
 }
 
 // Helper struct for NodeRunner configuration (clippy requirement).
@@ -93,6 +165,12 @@ where
     pub reserved_peers: HashMap<PeerId, Multiaddr>,
     pub max_established_incoming_connections: u32,
     pub max_established_outgoing_connections: u32,
+    pub max_incoming_connections_per_ip: u32,
+    pub max_incoming_connections_per_subnet: u32,
+    pub max_kademlia_bucket_peers_per_subnet: usize,
+    pub relay_servers: HashMap<PeerId, Multiaddr>,
+    pub metrics: Option<Metrics>,
+    pub peer_info_provider: Option<PeerInfoProvider>,
 }
 
 impl<RecordStore> NodeRunner<RecordStore>
@@ -110,6 +188,12 @@ where
             reserved_peers,
             max_established_incoming_connections,
             max_established_outgoing_connections,
+            max_incoming_connections_per_ip,
+            max_incoming_connections_per_subnet,
+            max_kademlia_bucket_peers_per_subnet,
+            relay_servers,
+            metrics,
+            peer_info_provider,
         }: NodeRunnerConfig<RecordStore>,
     ) -> Self {
         Self {
@@ -129,6 +213,18 @@ where
             reserved_peers,
             max_established_incoming_connections,
             max_established_outgoing_connections,
+            max_incoming_connections_per_ip,
+            max_incoming_connections_per_subnet,
+            max_kademlia_bucket_peers_per_subnet,
+            incoming_connections_per_ip: HashMap::default(),
+            incoming_connections_per_subnet: HashMap::default(),
+            relay_servers,
+            relay_listeners: HashMap::default(),
+            metrics,
+            peer_info_provider,
+            peers_info: HashMap::default(),
+            peer_info_requests: FuturesUnordered::new(),
+            rejected_peers: LruCache::new(REJECTED_PEERS_CACHE_SIZE),
         }
     }
 
@@ -157,6 +253,9 @@ where
                         break;
                     }
                 },
+                (peer_id, result) = self.peer_info_requests.select_next_some() => {
+                    self.handle_peer_info_result(peer_id, result);
+                },
                 _ = self.networking_parameters_registry.run().fuse() => {
                     trace!("Network parameters registry runner exited.")
                 },
@@ -216,7 +315,7 @@ where
             trace!(%local_peer_id, "Processing addresses batch: {:?}", addresses);
 
             for (peer_id, addr) in addresses {
-                if connected_peers.contains(&peer_id) {
+                if connected_peers.contains(&peer_id) || self.rejected_peers.contains(&peer_id) {
                     continue;
                 }
 
@@ -244,6 +343,89 @@ where
         }
     }
 
+    // Tracks a new incoming connection from global IP address, returns the number of incoming
+    // connections from that IP address and from its subnet.
+    fn add_incoming_connection(&mut self, ip: IpAddr) -> (u32, u32) {
+        let ip_connections = self.incoming_connections_per_ip.entry(ip).or_default();
+        *ip_connections += 1;
+
+        let subnet_connections = self
+            .incoming_connections_per_subnet
+            .entry(utils::ip_subnet(ip))
+            .or_default();
+        *subnet_connections += 1;
+
+        (*ip_connections, *subnet_connections)
+    }
+
+    // Checks whether numbers of incoming connections from IP address and its subnet returned by
+    // `add_incoming_connection` exceed the limits.
+    fn incoming_connections_from_ip_exceeded(
+        &self,
+        (ip_connections, subnet_connections): (u32, u32),
+    ) -> bool {
+        ip_connections > self.max_incoming_connections_per_ip
+            || subnet_connections > self.max_incoming_connections_per_subnet
+    }
+
+    fn remove_incoming_connection(&mut self, ip: IpAddr) {
+        for (connections, key) in [
+            (&mut self.incoming_connections_per_ip, ip),
+            (
+                &mut self.incoming_connections_per_subnet,
+                utils::ip_subnet(ip),
+            ),
+        ] {
+            if let Entry::Occupied(mut entry) = connections.entry(key) {
+                *entry.get_mut() -= 1;
+
+                if *entry.get() == 0 {
+                    entry.remove();
+                }
+            }
+        }
+    }
+
+    // Cancels Kademlia queries and pending requests that callers are no longer waiting for (due to
+    // timeout or dropped future), called when the caller drops result receiver.
+    fn cancel_abandoned_queries(&mut self) {
+        let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+
+        self.query_id_receivers.retain(|query_id, sender| {
+            if !sender.is_canceled() {
+                return true;
+            }
+
+            if let Some(mut query) = kademlia.query_mut(query_id) {
+                query.finish();
+            }
+
+            trace!(?query_id, "Abandoned Kademlia query cancelled.");
+
+            false
+        });
+
+        let cancelled_requests = self
+            .swarm
+            .behaviour_mut()
+            .request_response
+            .cancel_abandoned_requests();
+
+        if cancelled_requests > 0 {
+            trace!(%cancelled_requests, "Abandoned requests cancelled.");
+        }
+    }
+
+    // Number of peers in Kademlia routing table.
+    fn routing_table_size(&mut self) -> usize {
+        self.swarm
+            .behaviour_mut()
+            .kademlia
+            .kbuckets()
+            .map(|bucket| bucket.num_entries())
+            .sum()
+    }
+
     fn handle_random_query_interval(&mut self) {
         let random_peer_id = PeerId::random();
 
@@ -266,9 +448,27 @@ where
             SwarmEvent::Behaviour(Event::Gossipsub(event)) => {
                 self.handle_gossipsub_event(event).await;
             }
+            SwarmEvent::Behaviour(Event::Ping(event)) => {
+                self.handle_ping_event(event).await;
+            }
             SwarmEvent::Behaviour(Event::RequestResponse(event)) => {
                 self.handle_request_response_event(event).await;
             }
+            SwarmEvent::Behaviour(Event::Autonat(event)) => {
+                self.handle_autonat_event(event).await;
+            }
+            SwarmEvent::Behaviour(Event::RelayClient(event)) => {
+                self.handle_relay_client_event(event).await;
+            }
+            SwarmEvent::Behaviour(Event::RelayServer(event)) => {
+                trace!("Relay server event: {:?}", event);
+            }
+            SwarmEvent::Behaviour(Event::Dcutr(event)) => {
+                self.handle_dcutr_event(event).await;
+            }
+            SwarmEvent::Behaviour(Event::Mdns(event)) => {
+                self.handle_mdns_event(event).await;
+            }
             SwarmEvent::NewListenAddr { address, .. } => {
                 let shared = match self.shared_weak.upgrade() {
                     Some(shared) => shared,
@@ -288,6 +488,34 @@ where
                 let is_reserved_peer = self.reserved_peers.contains_key(&peer_id);
                 debug!(%peer_id, %is_reserved_peer, "Connection established [{num_established} from peer]");
 
+                // Counters are updated before any early return to stay consistent with
+                // `ConnectionClosed` events.
+                let incoming_connections_from_ip = match &endpoint {
+                    ConnectedPoint::Listener { send_back_addr, .. } => {
+                        utils::global_connection_ip(send_back_addr)
+                            .map(|ip| self.add_incoming_connection(ip))
+                    }
+                    ConnectedPoint::Dialer { .. } => None,
+                };
+
+                if self.rejected_peers.contains(&peer_id) {
+                    debug!(%peer_id, "Peer belongs to a different chain. Disconnecting ...");
+                    // Error here means: "peer was already disconnected"
+                    let _ = self.swarm.disconnect_peer_id(peer_id);
+                    return;
+                }
+
+                if num_established.get() == 1 {
+                    self.request_peer_info(peer_id);
+                }
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.connections_established.inc();
+                    metrics
+                        .connected_peers
+                        .set(self.swarm.connected_peers().count() as u64);
+                }
+
                 let (in_connections_number, out_connections_number) = {
                     let network_info = self.swarm.network_info();
                     let connections = network_info.connection_counters();
@@ -311,6 +539,25 @@ where
                             );
                             // Error here means: "peer was already disconnected"
                             let _ = self.swarm.disconnect_peer_id(peer_id);
+                        } else if let Some((ip_connections, subnet_connections)) =
+                            incoming_connections_from_ip
+                        {
+                            if !is_reserved_peer
+                                && self.incoming_connections_from_ip_exceeded((
+                                    ip_connections,
+                                    subnet_connections,
+                                ))
+                            {
+                                debug!(
+                                    %peer_id,
+                                    %ip_connections,
+                                    %subnet_connections,
+                                    "Incoming connections limit per IP address or subnet \
+                                    exceeded. Disconnecting in-peer ..."
+                                );
+                                // Error here means: "peer was already disconnected"
+                                let _ = self.swarm.disconnect_peer_id(peer_id);
+                            }
                         }
                     }
                     // Out connections
@@ -336,9 +583,38 @@ where
             SwarmEvent::ConnectionClosed {
                 peer_id,
                 num_established,
+                endpoint,
                 ..
             } => {
                 debug!("Connection closed with peer {peer_id} [{num_established} from peer]");
+
+                if let ConnectedPoint::Listener { send_back_addr, .. } = &endpoint {
+                    if let Some(ip) = utils::global_connection_ip(send_back_addr) {
+                        self.remove_incoming_connection(ip);
+                    }
+                }
+
+                if num_established == 0 {
+                    self.peers_info.remove(&peer_id);
+                }
+
+                if let Some(metrics) = &self.metrics {
+                    metrics.connections_closed.inc();
+                    metrics
+                        .connected_peers
+                        .set(self.swarm.connected_peers().count() as u64);
+                }
+            }
+            SwarmEvent::ListenerClosed {
+                listener_id,
+                reason,
+                ..
+            } => {
+                debug!(?listener_id, ?reason, "Listener closed.");
+
+                // Reservation will be requested again on the next reachability change.
+                self.relay_listeners
+                    .retain(|_, relay_listener_id| *relay_listener_id != listener_id);
             }
             SwarmEvent::OutgoingConnectionError { peer_id, error } => {
                 if let DialError::Transport(ref addresses) = error {
@@ -360,10 +636,81 @@ where
         }
     }
 
+    fn request_peer_info(&mut self, peer_id: PeerId) {
+        if self.peer_info_provider.is_none() {
+            return;
+        }
+
+        let (result_sender, result_receiver) = oneshot::channel();
+        self.swarm.behaviour_mut().request_response.send_request(
+            &peer_id,
+            PeerInfoRequest::PROTOCOL_NAME,
+            PeerInfoRequest.encode(),
+            result_sender,
+            IfDisconnected::ImmediateError,
+        );
+
+        self.peer_info_requests
+            .push(async move { (peer_id, result_receiver.await) }.boxed());
+    }
+
+    fn handle_peer_info_result(&mut self, peer_id: PeerId, result: PeerInfoResult) {
+        let peer_info = match result {
+            Ok(Ok(response)) => match PeerInfoResponse::decode(&mut response.as_slice()) {
+                Ok(PeerInfoResponse { peer_info }) => peer_info,
+                Err(error) => {
+                    debug!(%peer_id, ?error, "Failed to decode peer-info response.");
+                    return;
+                }
+            },
+            Ok(Err(error)) => {
+                // Peer might not support peer-info protocol.
+                debug!(%peer_id, %error, "Peer-info request failed.");
+                return;
+            }
+            Err(oneshot::Canceled) => {
+                return;
+            }
+        };
+
+        if !self.swarm.is_connected(&peer_id) {
+            return;
+        }
+
+        let local_peer_info = match &self.peer_info_provider {
+            Some(peer_info_provider) => peer_info_provider(),
+            None => {
+                return;
+            }
+        };
+
+        trace!(%peer_id, ?peer_info, "Peer-info received.");
+
+        if !local_peer_info.is_compatible_with(&peer_info) {
+            debug!(
+                %peer_id,
+                genesis_hash = ?peer_info.genesis_hash.map(hex::encode),
+                "Peer belongs to a different chain. Disconnecting ..."
+            );
+
+            self.rejected_peers.put(peer_id, ());
+            self.swarm.behaviour_mut().kademlia.remove_peer(&peer_id);
+            // Error here means: "peer was already disconnected"
+            let _ = self.swarm.disconnect_peer_id(peer_id);
+            return;
+        }
+
+        self.peers_info.insert(peer_id, peer_info);
+    }
+
     async fn handle_identify_event(&mut self, event: IdentifyEvent) {
         if let IdentifyEvent::Received { peer_id, mut info } = event {
             let local_peer_id = *self.swarm.local_peer_id();
 
+            if let Some(metrics) = &self.metrics {
+                metrics.identify_received.inc();
+            }
+
             if info.listen_addrs.len() > 30 {
                 debug!(
                     %local_peer_id,
@@ -374,7 +721,8 @@ where
                 info.listen_addrs.truncate(30);
             }
 
-            let kademlia = &mut self.swarm.behaviour_mut().kademlia;
+            let is_reserved_peer = self.reserved_peers.contains_key(&peer_id);
+            let kademlia = &mut *self.swarm.behaviour_mut().kademlia;
             let kademlia_enabled = info
                 .protocols
                 .iter()
@@ -393,6 +741,23 @@ where
                         continue;
                     }
 
+                    // Bucket diversity: prevents a single subnet from taking over k-buckets.
+                    if let Some(ip) = utils::global_connection_ip(&address) {
+                        if !is_reserved_peer
+                            && kbucket_peers_in_subnet(kademlia, &peer_id, utils::ip_subnet(ip))
+                                >= self.max_kademlia_bucket_peers_per_subnet
+                        {
+                            trace!(
+                                %local_peer_id,
+                                %peer_id,
+                                %address,
+                                "Ignoring self-reported address, too many peers from the same \
+                                subnet in Kademlia k-bucket.",
+                            );
+                            continue;
+                        }
+                    }
+
                     trace!(
                         %local_peer_id,
                         %peer_id,
@@ -416,6 +781,31 @@ where
     async fn handle_kademlia_event(&mut self, event: KademliaEvent) {
         trace!("Kademlia event: {:?}", event);
 
+        if let Some(metrics) = &self.metrics {
+            match &event {
+                KademliaEvent::OutboundQueryCompleted { result, stats, .. } => {
+                    let (query, success) = kademlia_query_labels(result);
+                    metrics
+                        .kademlia_queries
+                        .with_label_values(&[query, result_label(success)])
+                        .inc();
+                    if let Some(duration) = stats.duration() {
+                        metrics
+                            .kademlia_query_duration
+                            .with_label_values(&[query])
+                            .observe(duration.as_secs_f64());
+                    }
+                }
+                KademliaEvent::RoutingUpdated { .. } => {
+                    let routing_table_size = self.routing_table_size();
+                    metrics
+                        .kademlia_routing_table_size
+                        .set(routing_table_size as u64);
+                }
+                _ => {}
+            }
+        }
+
         match event {
             KademliaEvent::InboundRequest {
                 request: InboundRequest::AddProvider { record },
@@ -497,10 +887,24 @@ where
                                 records_len,
                             );
 
+                            if let Some(metrics) = &self.metrics {
+                                metrics
+                                    .get_value
+                                    .with_label_values(&[result_label(true)])
+                                    .inc();
+                            }
+
                             // Doesn't matter if receiver still waits for response.
                             let _ = sender.send(Some(record.value));
                         }
                         Err(error) => {
+                            if let Some(metrics) = &self.metrics {
+                                metrics
+                                    .get_value
+                                    .with_label_values(&[result_label(false)])
+                                    .inc();
+                            }
+
                             // Doesn't matter if receiver still waits for response.
                             let _ = sender.send(None);
 
@@ -599,6 +1003,13 @@ where
                 if let Some(QueryResultSender::PutValue { sender }) =
                     self.query_id_receivers.remove(&id)
                 {
+                    if let Some(metrics) = &self.metrics {
+                        metrics
+                            .put_value
+                            .with_label_values(&[result_label(result.is_ok())])
+                            .inc();
+                    }
+
                     match result {
                         Ok(PutRecordOk { key, .. }) => {
                             trace!("Put record query for {} succeeded", hex::encode(&key),);
@@ -620,21 +1031,309 @@ where
     }
 
     async fn handle_gossipsub_event(&mut self, event: GossipsubEvent) {
-        if let GossipsubEvent::Message { message, .. } = event {
-            if let Some(senders) = self.topic_subscription_senders.get(&message.topic) {
-                let bytes = Bytes::from(message.data);
+        if let GossipsubEvent::Message {
+            propagation_source,
+            message_id,
+            message,
+        } = event
+        {
+            let acceptance = self.validate_gossipsub_message(&propagation_source, &message);
+
+            if let Some(metrics) = &self.metrics {
+                let acceptance_label = match acceptance {
+                    MessageAcceptance::Accept => "accept",
+                    MessageAcceptance::Reject => "reject",
+                    MessageAcceptance::Ignore => "ignore",
+                };
+                metrics
+                    .gossipsub_messages_received
+                    .with_label_values(&[acceptance_label])
+                    .inc();
+            }
+
+            if matches!(acceptance, MessageAcceptance::Accept) {
+                if let Some(subscribers) = self.topic_subscription_senders.get(&message.topic) {
+                    let bytes = Bytes::from(message.data);
+
+                    for subscriber in subscribers.values() {
+                        // Doesn't matter if receiver is still listening for messages or not.
+                        let _ = subscriber.sender.unbounded_send(bytes.clone());
+                    }
+                }
+            } else {
+                debug!(
+                    %propagation_source,
+                    ?message_id,
+                    ?acceptance,
+                    "Gossipsub message was not accepted by topic validator."
+                );
+            }
+
+            if let Err(error) = self
+                .swarm
+                .behaviour_mut()
+                .gossipsub
+                .report_message_validation_result(&message_id, &propagation_source, acceptance)
+            {
+                warn!(
+                    ?error,
+                    "Failed to report gossipsub message validation result."
+                );
+            }
+        }
+    }
+
+    /// Message is rejected if any of the validators rejected it, ignored if any of the validators
+    /// ignored it and accepted otherwise.
+    fn validate_gossipsub_message(
+        &self,
+        propagation_source: &PeerId,
+        message: &GossipsubMessage,
+    ) -> MessageAcceptance {
+        let subscribers = match self.topic_subscription_senders.get(&message.topic) {
+            Some(subscribers) => subscribers,
+            None => {
+                // Not subscribed to this topic anymore.
+                return MessageAcceptance::Ignore;
+            }
+        };
 
-                for sender in senders.values() {
-                    // Doesn't matter if receiver is still listening for messages or not.
-                    let _ = sender.unbounded_send(bytes.clone());
+        let mut ignored = false;
+        for validator in subscribers
+            .values()
+            .filter_map(|subscriber| subscriber.validator.as_ref())
+        {
+            match validator.validate(propagation_source, &message.data) {
+                MessageAcceptance::Accept => {}
+                MessageAcceptance::Reject => {
+                    return MessageAcceptance::Reject;
+                }
+                MessageAcceptance::Ignore => {
+                    ignored = true;
                 }
             }
         }
+
+        if ignored {
+            MessageAcceptance::Ignore
+        } else {
+            MessageAcceptance::Accept
+        }
+    }
+
+    async fn handle_ping_event(&mut self, event: PingEvent) {
+        trace!("Ping event: {:?}", event);
+
+        if let (Some(metrics), Ok(PingSuccess::Ping { rtt })) = (&self.metrics, &event.result) {
+            metrics.ping_rtt.observe(rtt.as_secs_f64());
+        }
     }
 
     async fn handle_request_response_event(&mut self, event: RequestResponseEvent) {
-        // No actions on statistics events.
         trace!("Request response event: {:?}", event);
+
+        if let Some(metrics) = &self.metrics {
+            match &event {
+                RequestResponseEvent::InboundRequest {
+                    protocol, result, ..
+                } => {
+                    metrics
+                        .inbound_requests
+                        .with_label_values(&[protocol.as_ref(), result_label(result.is_ok())])
+                        .inc();
+                    if let Ok(duration) = result {
+                        metrics
+                            .request_duration
+                            .with_label_values(&[protocol.as_ref(), "inbound"])
+                            .observe(duration.as_secs_f64());
+                    }
+                }
+                RequestResponseEvent::RequestFinished {
+                    protocol,
+                    duration,
+                    result,
+                    ..
+                } => {
+                    metrics
+                        .outbound_requests
+                        .with_label_values(&[protocol.as_ref(), result_label(result.is_ok())])
+                        .inc();
+                    if result.is_ok() {
+                        metrics
+                            .request_duration
+                            .with_label_values(&[protocol.as_ref(), "outbound"])
+                            .observe(duration.as_secs_f64());
+                    }
+                }
+            }
+        }
+
+        if let RequestResponseEvent::InboundRequest {
+            peer,
+            protocol,
+            result,
+        } = event
+        {
+            let shared = match self.shared_weak.upgrade() {
+                Some(shared) => shared,
+                None => {
+                    return;
+                }
+            };
+
+            let mut inbound_requests_stats = shared.inbound_requests_stats.lock();
+            let stats = inbound_requests_stats
+                .entry(protocol.to_string())
+                .or_default();
+
+            match result {
+                Ok(_duration) => {
+                    stats.served += 1;
+                }
+                Err(
+                    failure @ (ResponseFailure::TooManyConcurrentRequests
+                    | ResponseFailure::TooManyRequestsPerSecond),
+                ) => {
+                    debug!(%peer, %protocol, %failure, "Inbound request rejected.");
+                    stats.rejected += 1;
+                }
+                Err(ResponseFailure::Network(_)) => {
+                    stats.failed += 1;
+                }
+            }
+        }
+    }
+
+    async fn handle_autonat_event(&mut self, event: AutonatEvent) {
+        trace!("AutoNAT event: {:?}", event);
+
+        if let AutonatEvent::StatusChanged { old, new } = event {
+            debug!(?old, ?new, "Reachability status changed.");
+
+            match &new {
+                NatStatus::Private => {
+                    self.reserve_relayed_addresses();
+                }
+                NatStatus::Public(_) => {
+                    self.remove_relayed_addresses();
+                }
+                NatStatus::Unknown => {}
+            }
+
+            let shared = match self.shared_weak.upgrade() {
+                Some(shared) => shared,
+                None => {
+                    return;
+                }
+            };
+            *shared.nat_status.lock() = new.clone();
+            shared.handlers.nat_status_changed.call_simple(&new);
+        }
+    }
+
+    async fn handle_relay_client_event(&mut self, event: RelayClientEvent) {
+        match event {
+            RelayClientEvent::ReservationReqAccepted {
+                relay_peer_id,
+                renewal,
+                ..
+            } => {
+                debug!(%relay_peer_id, %renewal, "Relay reservation accepted.");
+            }
+            RelayClientEvent::ReservationReqFailed {
+                relay_peer_id,
+                error,
+                ..
+            } => {
+                debug!(%relay_peer_id, ?error, "Relay reservation failed.");
+
+                // Allow another attempt on the next reachability change.
+                if let Some(listener_id) = self.relay_listeners.remove(&relay_peer_id) {
+                    self.swarm.remove_listener(listener_id);
+                }
+            }
+            other => {
+                trace!("Relay client event: {:?}", other);
+            }
+        }
+    }
+
+    async fn handle_dcutr_event(&mut self, event: DcutrEvent) {
+        match event {
+            DcutrEvent::DirectConnectionUpgradeSucceeded { remote_peer_id } => {
+                debug!(%remote_peer_id, "Relayed connection upgraded to a direct one.");
+            }
+            DcutrEvent::DirectConnectionUpgradeFailed {
+                remote_peer_id,
+                error,
+            } => {
+                debug!(%remote_peer_id, ?error, "Hole punching failed.");
+            }
+            other => {
+                trace!("DCUtR event: {:?}", other);
+            }
+        }
+    }
+
+    async fn handle_mdns_event(&mut self, event: MdnsEvent) {
+        match event {
+            MdnsEvent::Discovered(discovered) => {
+                for (peer_id, addr) in discovered {
+                    debug!(%peer_id, %addr, "Peer discovered with mDNS.");
+
+                    // Discovered peers follow the same path as other known peers: they are
+                    // dialed and added to Kademlia once identified.
+                    self.networking_parameters_registry
+                        .add_known_peer(peer_id, vec![addr.clone()])
+                        .await;
+
+                    if self.swarm.is_connected(&peer_id) || self.rejected_peers.contains(&peer_id) {
+                        continue;
+                    }
+
+                    self.dial_peer(peer_id, addr);
+                }
+            }
+            MdnsEvent::Expired(expired) => {
+                for (peer_id, addr) in expired {
+                    trace!(%peer_id, %addr, "mDNS record expired.");
+                }
+            }
+        }
+    }
+
+    // Listens on relayed addresses of all known relay servers that don't have a reservation yet.
+    fn reserve_relayed_addresses(&mut self) {
+        for (relay_peer_id, relay_address) in &self.relay_servers {
+            if self.relay_listeners.contains_key(relay_peer_id) {
+                continue;
+            }
+
+            let circuit_address = relay_address
+                .clone()
+                .with(Protocol::P2p((*relay_peer_id).into()))
+                .with(Protocol::P2pCircuit);
+
+            match self.swarm.listen_on(circuit_address.clone()) {
+                Ok(listener_id) => {
+                    debug!(%circuit_address, "Requested relay reservation.");
+
+                    self.relay_listeners.insert(*relay_peer_id, listener_id);
+                }
+                Err(error) => {
+                    warn!(%circuit_address, %error, "Failed to listen on relayed address.");
+                }
+            }
+        }
+    }
+
+    // Node is publicly reachable, relayed addresses are not necessary anymore.
+    fn remove_relayed_addresses(&mut self) {
+        for (relay_peer_id, listener_id) in self.relay_listeners.drain() {
+            debug!(%relay_peer_id, "Removing relayed address.");
+
+            self.swarm.remove_listener(listener_id);
+        }
     }
 
     async fn handle_command(&mut self, command: Command) {
@@ -688,11 +1387,25 @@ where
             }
             Command::Subscribe {
                 topic,
+                validator,
                 result_sender,
             } => {
                 let topic_hash = topic.hash();
                 let (sender, receiver) = mpsc::unbounded();
 
+                if validator.is_some() {
+                    // Invalid messages on validated topics should affect peer score.
+                    if let Err(error) = self
+                        .swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .set_topic_params(topic.clone(), validated_topic_score_params())
+                    {
+                        warn!(%topic, %error, "Failed to set topic score parameters.");
+                    }
+                }
+                let subscriber = TopicSubscriber { sender, validator };
+
                 // Unconditionally create subscription ID, code is simpler this way.
                 let subscription_id = self.next_subscription_id;
                 self.next_subscription_id += 1;
@@ -706,7 +1419,7 @@ where
                     Entry::Occupied(mut entry) => {
                         // In case subscription already exists, just add one more sender to it.
                         if result_sender.send(Ok(created_subscription)).is_ok() {
-                            entry.get_mut().insert(subscription_id, sender);
+                            entry.get_mut().insert(subscription_id, subscriber);
                         }
                     }
                     Entry::Vacant(entry) => {
@@ -715,7 +1428,15 @@ where
                         match self.swarm.behaviour_mut().gossipsub.subscribe(&topic) {
                             Ok(true) => {
                                 if result_sender.send(Ok(created_subscription)).is_ok() {
-                                    entry.insert(IntMap::from_iter([(subscription_id, sender)]));
+                                    entry
+                                        .insert(IntMap::from_iter([(subscription_id, subscriber)]));
+                                }
+
+                                if let Some(metrics) = &self.metrics {
+                                    metrics
+                                        .gossipsub_subscribed_topics
+                                        .set(self.swarm.behaviour().gossipsub.topics().count()
+                                            as u64);
                                 }
                             }
                             Ok(false) => {
@@ -748,6 +1469,12 @@ where
                         {
                             warn!("Failed to unsubscribe from topic {topic}: {error}");
                         }
+
+                        if let Some(metrics) = &self.metrics {
+                            metrics
+                                .gossipsub_subscribed_topics
+                                .set(self.swarm.behaviour().gossipsub.topics().count() as u64);
+                        }
                     }
                 } else {
                     error!(
@@ -761,14 +1488,22 @@ where
                 message,
                 result_sender,
             } => {
+                let result = self
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .publish(topic, message)
+                    .map(|_message_id| ());
+
+                if let Some(metrics) = &self.metrics {
+                    metrics
+                        .gossipsub_messages_published
+                        .with_label_values(&[result_label(result.is_ok())])
+                        .inc();
+                }
+
                 // Doesn't matter if receiver still waits for response.
-                let _ = result_sender.send(
-                    self.swarm
-                        .behaviour_mut()
-                        .gossipsub
-                        .publish(topic, message)
-                        .map(|_message_id| ()),
-                );
+                let _ = result_sender.send(result);
             }
             Command::GetClosestPeers { key, result_sender } => {
                 let query_id = self.swarm.behaviour_mut().kademlia.get_closest_peers(key);
@@ -794,6 +1529,15 @@ where
                     IfDisconnected::TryConnect,
                 );
             }
+            Command::GetPeersInfo { result_sender } => {
+                let _ = result_sender.send(self.peers_info.clone());
+            }
+            Command::GetConnectedPeers { result_sender } => {
+                let _ = result_sender.send(self.swarm.connected_peers().copied().collect());
+            }
+            Command::GetRoutingTableSize { result_sender } => {
+                let _ = result_sender.send(self.routing_table_size());
+            }
             Command::CheckConnectedPeers { result_sender } => {
                 let connected_peers_present = self.swarm.connected_peers().next().is_some();
 
@@ -836,6 +1580,9 @@ where
 
                 let _ = result_sender.send(true);
             }
+            Command::CancelAbandonedQueries => {
+                self.cancel_abandoned_queries();
+            }
             Command::GetProviders { key, result_sender } => {
                 let query_id = self
                     .swarm
@@ -853,3 +1600,46 @@ where
         }
     }
 }
+
+// Score parameters for topics with validators.
+fn validated_topic_score_params() -> TopicScoreParams {
+    TopicScoreParams {
+        topic_weight: 1.0,
+        // Validated topics (like archived segment announcements) can be quiet for a long time,
+        // peers shouldn't be penalized for not delivering messages.
+        mesh_message_deliveries_weight: 0.0,
+        mesh_failure_penalty_weight: 0.0,
+        // Few invalid messages are enough to graylist the peer, penalty decays slowly.
+        invalid_message_deliveries_weight: -10.0,
+        invalid_message_deliveries_decay: 0.9,
+        ..TopicScoreParams::default()
+    }
+}
+
+// Counts peers other than the specified one that have addresses in the subnet and are present in
+// the Kademlia k-bucket the specified peer belongs to.
+fn kbucket_peers_in_subnet<RecordStore>(
+    kademlia: &mut Kademlia<RecordStore>,
+    peer_id: &PeerId,
+    subnet: IpAddr,
+) -> usize
+where
+    RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
+{
+    let kbucket = match kademlia.kbucket(*peer_id) {
+        Some(kbucket) => kbucket,
+        None => {
+            return 0;
+        }
+    };
+
+    kbucket
+        .iter()
+        .filter(|entry| {
+            entry.node.key.preimage() != peer_id
+                && entry.node.value.iter().any(|address| {
+                    utils::global_connection_ip(address).map(utils::ip_subnet) == Some(subnet)
+                })
+        })
+        .count()
+}
@@ -0,0 +1,592 @@
+use crate::simulator::{create_simulated_node, SimulatedNetwork};
+use crate::{
+    create, BootstrappedNetworkingParameters, Config, CreationError, GenericRequest, KademliaMode,
+    Node, NodeRunner, PeerInfo, PeerInfoRequest, PeerRole, QueryOptions, RelayMode, TopicValidator,
+};
+use futures::channel::oneshot;
+use futures::StreamExt;
+use libp2p::autonat::{Event as AutonatEvent, NatStatus};
+use libp2p::gossipsub::{MessageAcceptance, Sha256Topic};
+use libp2p::multiaddr::Protocol;
+use libp2p::multihash::{Code, MultihashDigest};
+use libp2p::swarm::AddressScore;
+use libp2p::{Multiaddr, PeerId};
+use parking_lot::Mutex;
+use prometheus_endpoint::Registry;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Creates a node listening on a random local port with the rest of configuration specified by
+/// `config`, returns the node along with its full address once it is known.
+async fn start_node(config: Config) -> (Node, Multiaddr) {
+    let (node, address, _stop_sender, _node_runner_handle) = start_stoppable_node(config).await;
+
+    (node, address)
+}
+
+/// Same as [`start_node`], but node runner stops once stop sender is used and is returned from
+/// the join handle, so that its state can be inspected.
+async fn start_stoppable_node(
+    config: Config,
+) -> (Node, Multiaddr, oneshot::Sender<()>, JoinHandle<NodeRunner>) {
+    let config = Config {
+        listen_on: vec!["/ip4/127.0.0.1/tcp/0".parse().unwrap()],
+        allow_non_globals_in_dht: true,
+        ..config
+    };
+    let (node, mut node_runner) = create(config).await.unwrap();
+
+    let (address_sender, address_receiver) = oneshot::channel();
+    let on_new_listener_handler = node.on_new_listener(Arc::new({
+        let address_sender = Mutex::new(Some(address_sender));
+
+        move |address| {
+            if let Some(address_sender) = address_sender.lock().take() {
+                address_sender.send(address.clone()).unwrap();
+            }
+        }
+    }));
+
+    let (stop_sender, stop_receiver) = oneshot::channel();
+    let node_runner_handle = tokio::spawn(async move {
+        // Dropped stop sender disables the second branch and node runner keeps running
+        tokio::select! {
+            _ = node_runner.run() => {}
+            Ok(()) = stop_receiver => {}
+        }
+
+        node_runner
+    });
+
+    let address = address_receiver.await.unwrap();
+    drop(on_new_listener_handler);
+
+    let address = address.with(Protocol::P2p(node.id().into()));
+
+    (node, address, stop_sender, node_runner_handle)
+}
+
+/// Creates a node listening on a random memory port with AutoNAT disabled (AutoNAT servers only
+/// dial back IP addresses), returns the node along with its full address and node runner that is
+/// not running yet.
+async fn create_memory_node(config: Config) -> (Node, NodeRunner, Multiaddr) {
+    let address = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    let config = Config {
+        listen_on: vec![address.clone()],
+        allow_non_globals_in_dht: true,
+        autonat: None,
+        ..config
+    };
+    let (node, node_runner) = create_simulated_node(config, SimulatedNetwork::default(), 0)
+        .await
+        .unwrap();
+
+    let address = address.with(Protocol::P2p(node.id().into()));
+
+    (node, node_runner, address)
+}
+
+fn bootstrapped_config(bootstrap_address: &Multiaddr) -> Config {
+    Config {
+        networking_parameters_registry: BootstrappedNetworkingParameters::new(vec![
+            bootstrap_address.clone(),
+        ])
+        .boxed(),
+        ..Config::with_generated_keypair()
+    }
+}
+
+fn with_peer_info(config: Config, role: PeerRole, genesis_hash: [u8; 32]) -> Config {
+    Config {
+        peer_info_provider: Some(Arc::new(move || PeerInfo {
+            genesis_hash: Some(genesis_hash),
+            ..PeerInfo::new(role)
+        })),
+        ..config
+    }
+}
+
+/// Waits until routing table of the node has expected size or panics after a few seconds.
+async fn wait_for_routing_table_size(node: &Node, expected_size: usize) {
+    for _ in 0..50 {
+        if node.routing_table_size().await.unwrap() == expected_size {
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!(
+        "Routing table of {} doesn't have {} entries",
+        node.id(),
+        expected_size
+    );
+}
+
+/// Returns current value of the counter or gauge registered under the name.
+fn metric_value(registry: &Registry, name: &str) -> f64 {
+    let family = registry
+        .gather()
+        .into_iter()
+        .find(|family| family.get_name() == name)
+        .unwrap_or_else(|| panic!("Metric {name} is not registered"));
+    let metric = &family.get_metric()[0];
+
+    if metric.has_counter() {
+        metric.get_counter().get_value()
+    } else {
+        metric.get_gauge().get_value()
+    }
+}
+
+#[tokio::test]
+async fn kademlia_client_mode() {
+    let (bootstrap_node, bootstrap_address) = start_node(Config::with_generated_keypair()).await;
+
+    let (client_node, _) = start_node(Config {
+        kademlia_mode: KademliaMode::Client,
+        ..bootstrapped_config(&bootstrap_address)
+    })
+    .await;
+    client_node.wait_for_connected_peers().await.unwrap();
+
+    let (server_node, _) = start_node(bootstrapped_config(&bootstrap_address)).await;
+    server_node.wait_for_connected_peers().await.unwrap();
+
+    // Server node is added to the routing table of the bootstrap node, client node is not, even
+    // though it connected first
+    wait_for_routing_table_size(&bootstrap_node, 1).await;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(bootstrap_node.routing_table_size().await.unwrap(), 1);
+    assert!(bootstrap_node
+        .connected_peers()
+        .await
+        .unwrap()
+        .contains(&client_node.id()));
+
+    // Client node still uses DHT as usual
+    let key = Code::Identity.digest(&server_node.id().to_bytes());
+    let closest_peers = client_node.get_closest_peers(key).await.unwrap();
+    assert!(closest_peers.contains(&server_node.id()));
+    assert!(!closest_peers.contains(&client_node.id()));
+
+    // Server node doesn't learn about client node from DHT either
+    let key = Code::Identity.digest(&client_node.id().to_bytes());
+    let closest_peers = server_node.get_closest_peers(key).await.unwrap();
+    assert!(!closest_peers.contains(&client_node.id()));
+}
+
+#[tokio::test]
+async fn connection_metrics_are_recorded() {
+    let registry = Registry::default();
+    let (node, address) = start_node(Config {
+        prometheus_registry: Some(registry.clone()),
+        ..Config::with_generated_keypair()
+    })
+    .await;
+
+    assert_eq!(metric_value(&registry, "dsn_connected_peers"), 0.0);
+
+    let (other_node, _) = start_node(bootstrapped_config(&address)).await;
+    other_node.wait_for_connected_peers().await.unwrap();
+    node.wait_for_connected_peers().await.unwrap();
+
+    for _ in 0..50 {
+        if metric_value(&registry, "dsn_connected_peers") == 1.0 {
+            break;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    assert_eq!(metric_value(&registry, "dsn_connected_peers"), 1.0);
+    assert!(metric_value(&registry, "dsn_connections_established_total") >= 1.0);
+}
+
+#[tokio::test]
+async fn metrics_are_registered_only_once() {
+    let registry = Registry::default();
+
+    start_node(Config {
+        prometheus_registry: Some(registry.clone()),
+        ..Config::with_generated_keypair()
+    })
+    .await;
+
+    // Registering the same metrics twice is a configuration error rather than a silent no-op
+    let result = create(Config {
+        prometheus_registry: Some(registry),
+        ..Config::with_generated_keypair()
+    })
+    .await;
+
+    assert!(matches!(result, Err(CreationError::Prometheus(_))));
+}
+
+#[tokio::test]
+async fn peer_info_exchange() {
+    assert_eq!(
+        PeerInfoRequest::PROTOCOL_NAME,
+        "/subspace/sync/peer-info/0.2.0"
+    );
+
+    let genesis_hash = [1; 32];
+    let (node_1, address_1) = start_node(with_peer_info(
+        Config::with_generated_keypair(),
+        PeerRole::Node,
+        genesis_hash,
+    ))
+    .await;
+    let (node_2, _) = start_node(with_peer_info(
+        bootstrapped_config(&address_1),
+        PeerRole::Farmer,
+        genesis_hash,
+    ))
+    .await;
+
+    for _ in 0..50 {
+        if let Some(peer_info) = node_1
+            .connected_peers_info()
+            .await
+            .unwrap()
+            .get(&node_2.id())
+        {
+            assert_eq!(peer_info.role, PeerRole::Farmer);
+            assert_eq!(peer_info.genesis_hash, Some(genesis_hash));
+            assert!(peer_info.supports_protocol(PeerInfoRequest::PROTOCOL_NAME));
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!("Peer-info of {} was not received", node_2.id());
+}
+
+#[tokio::test]
+async fn peers_from_other_chains_are_rejected() {
+    let registry = Registry::default();
+    let (node_1, address_1) = start_node(with_peer_info(
+        Config {
+            prometheus_registry: Some(registry.clone()),
+            ..Config::with_generated_keypair()
+        },
+        PeerRole::Node,
+        [1; 32],
+    ))
+    .await;
+    let (node_2, _) = start_node(with_peer_info(
+        bootstrapped_config(&address_1),
+        PeerRole::Node,
+        [2; 32],
+    ))
+    .await;
+
+    // Peers do connect, but the connection is closed once peer-info is exchanged
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    assert!(metric_value(&registry, "dsn_connections_established_total") >= 1.0);
+    assert!(!node_1
+        .connected_peers()
+        .await
+        .unwrap()
+        .contains(&node_2.id()));
+    assert!(!node_1
+        .connected_peers_info()
+        .await
+        .unwrap()
+        .contains_key(&node_2.id()));
+    assert!(!node_2
+        .connected_peers_info()
+        .await
+        .unwrap()
+        .contains_key(&node_1.id()));
+}
+
+#[tokio::test]
+async fn incoming_connections_per_ip_and_subnet_are_limited() {
+    let (_node, mut node_runner) = create(Config::with_generated_keypair()).await.unwrap();
+    let ip = IpAddr::V4(Ipv4Addr::new(8, 8, 8, 8));
+
+    // 6th connection from the same IP address is over the limit
+    for connection in 1..=6 {
+        let connections = node_runner.add_incoming_connection(ip);
+        assert_eq!(
+            node_runner.incoming_connections_from_ip_exceeded(connections),
+            connection > 5,
+            "Connection {connection}"
+        );
+    }
+
+    // Closed connections free the slots
+    node_runner.remove_incoming_connection(ip);
+    node_runner.remove_incoming_connection(ip);
+    let connections = node_runner.add_incoming_connection(ip);
+    assert!(!node_runner.incoming_connections_from_ip_exceeded(connections));
+
+    // Connections from other IP addresses of the same /24 subnet count towards subnet limit
+    let mut exceeded = Vec::new();
+    for last_octet in 100..112 {
+        let connections =
+            node_runner.add_incoming_connection(IpAddr::V4(Ipv4Addr::new(8, 8, 8, last_octet)));
+        exceeded.push(node_runner.incoming_connections_from_ip_exceeded(connections));
+    }
+    // 5 connections from 8.8.8.8 plus 10 other connections reach the subnet limit of 15
+    assert!(exceeded[..10].iter().all(|exceeded| !exceeded));
+    assert!(exceeded[10..].iter().all(|exceeded| *exceeded));
+
+    // Other subnets are not affected
+    let connections = node_runner.add_incoming_connection(IpAddr::V4(Ipv4Addr::new(8, 8, 4, 4)));
+    assert!(!node_runner.incoming_connections_from_ip_exceeded(connections));
+}
+
+#[tokio::test]
+async fn gossipsub_rejected_messages_are_not_propagated_and_penalize_sender() {
+    let topic = Sha256Topic::new("test-topic");
+
+    // Validating node is the only connection between publisher and receiver, publisher is in
+    // Kademlia client mode, so receiver doesn't discover and connect to it directly
+    let (validating_node, validating_address, stop_sender, node_runner_handle) =
+        start_stoppable_node(Config::with_generated_keypair()).await;
+    let (publishing_node, _) = start_node(Config {
+        kademlia_mode: KademliaMode::Client,
+        ..bootstrapped_config(&validating_address)
+    })
+    .await;
+    let (receiving_node, _) = start_node(bootstrapped_config(&validating_address)).await;
+    publishing_node.wait_for_connected_peers().await.unwrap();
+    receiving_node.wait_for_connected_peers().await.unwrap();
+
+    let _validating_subscription = validating_node
+        .subscribe_with_validator(
+            topic.clone(),
+            TopicValidator::new(|_peer_id, data| {
+                if data == b"invalid" {
+                    MessageAcceptance::Reject
+                } else {
+                    MessageAcceptance::Accept
+                }
+            }),
+        )
+        .await
+        .unwrap();
+    let mut receiving_subscription = receiving_node.subscribe(topic.clone()).await.unwrap();
+
+    // Give subscriptions time to propagate and mesh to be formed
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    for message in [b"invalid".to_vec(), b"valid".to_vec()] {
+        for _ in 0..50 {
+            // Publishing fails with insufficient peers until subscription of validating node is
+            // known to the publisher
+            if publishing_node
+                .publish(topic.clone(), message.clone())
+                .await
+                .is_ok()
+            {
+                break;
+            }
+
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    // Rejected message is not propagated to the receiving node, only the valid one is
+    let message = tokio::time::timeout(Duration::from_secs(5), receiving_subscription.next())
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(message.as_ref(), b"valid");
+    assert!(
+        tokio::time::timeout(Duration::from_secs(1), receiving_subscription.next())
+            .await
+            .is_err()
+    );
+
+    stop_sender.send(()).unwrap();
+    let node_runner = node_runner_handle.await.unwrap();
+
+    let peer_score = node_runner
+        .swarm
+        .behaviour()
+        .gossipsub
+        .peer_score(&publishing_node.id())
+        .unwrap();
+    assert!(peer_score < 0.0, "Peer score {peer_score} is not negative");
+}
+
+#[tokio::test]
+async fn relayed_address_is_reserved_when_node_is_behind_nat() {
+    let (relay_node, mut relay_node_runner, relay_address) = create_memory_node(Config {
+        relay_mode: RelayMode::Server,
+        ..Config::with_generated_keypair()
+    })
+    .await;
+    // Relay server puts its external addresses into reservations
+    let mut relay_listen_address = relay_address.clone();
+    relay_listen_address.pop();
+    relay_node_runner
+        .swarm
+        .add_external_address(relay_listen_address, AddressScore::Infinite);
+    tokio::spawn(async move {
+        relay_node_runner.run().await;
+    });
+
+    let (node, mut node_runner, _) = create_memory_node(Config {
+        relay_servers: vec![relay_address.clone()],
+        ..Config::with_generated_keypair()
+    })
+    .await;
+
+    // The same happens when AutoNAT probes fail
+    node_runner
+        .handle_autonat_event(AutonatEvent::StatusChanged {
+            old: NatStatus::Unknown,
+            new: NatStatus::Private,
+        })
+        .await;
+    assert_eq!(node.nat_status(), NatStatus::Private);
+    assert!(node_runner.relay_listeners.contains_key(&relay_node.id()));
+
+    let (reserved_sender, reserved_receiver) = oneshot::channel();
+    let _on_new_listener_handler = node.on_new_listener(Arc::new({
+        let reserved_sender = Mutex::new(Some(reserved_sender));
+
+        move |address| {
+            if address
+                .iter()
+                .any(|protocol| protocol == Protocol::P2pCircuit)
+            {
+                if let Some(reserved_sender) = reserved_sender.lock().take() {
+                    reserved_sender.send(()).unwrap();
+                }
+            }
+        }
+    }));
+    tokio::spawn(async move {
+        node_runner.run().await;
+    });
+
+    // Relayed address appears once reservation is accepted by relay server
+    tokio::time::timeout(Duration::from_secs(10), reserved_receiver)
+        .await
+        .expect("Reservation must be accepted by relay server")
+        .unwrap();
+
+    // Peer that knows relay server (and hence has relay transport) connects through the circuit
+    let circuit_address = relay_address
+        .clone()
+        .with(Protocol::P2pCircuit)
+        .with(Protocol::P2p(node.id().into()));
+    let (remote_node, mut remote_node_runner, _) = create_memory_node(Config {
+        relay_servers: vec![relay_address],
+        reserved_peers: vec![circuit_address],
+        ..Config::with_generated_keypair()
+    })
+    .await;
+    tokio::spawn(async move {
+        remote_node_runner.run().await;
+    });
+
+    for _ in 0..100 {
+        if node
+            .connected_peers()
+            .await
+            .unwrap()
+            .contains(&remote_node.id())
+        {
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+
+    panic!(
+        "Relayed connection from {} was not established",
+        remote_node.id()
+    );
+}
+
+#[tokio::test]
+async fn relayed_addresses_are_reserved_once_and_removed_when_node_is_public() {
+    let relay_addresses = (0..2)
+        .map(|_| {
+            Multiaddr::empty()
+                .with(Protocol::Memory(rand::random::<u64>()))
+                .with(Protocol::P2p(PeerId::random().into()))
+        })
+        .collect::<Vec<_>>();
+    let (node, mut node_runner, _) = create_memory_node(Config {
+        relay_servers: relay_addresses,
+        ..Config::with_generated_keypair()
+    })
+    .await;
+    assert!(node_runner.relay_listeners.is_empty());
+
+    // Reservation is requested with every known relay server
+    node_runner.reserve_relayed_addresses();
+    let relay_listeners = node_runner.relay_listeners.clone();
+    assert_eq!(relay_listeners.len(), 2);
+
+    // Relay servers that were already asked for reservation are skipped
+    node_runner.reserve_relayed_addresses();
+    assert_eq!(node_runner.relay_listeners, relay_listeners);
+
+    // Relayed addresses are not necessary once the node is known to be publicly reachable
+    let public_address = Multiaddr::empty().with(Protocol::Memory(rand::random::<u64>()));
+    node_runner
+        .handle_autonat_event(AutonatEvent::StatusChanged {
+            old: NatStatus::Private,
+            new: NatStatus::Public(public_address.clone()),
+        })
+        .await;
+    assert!(node_runner.relay_listeners.is_empty());
+    assert_eq!(node.nat_status(), NatStatus::Public(public_address));
+
+    // Reservations are requested again if node ends up behind NAT later
+    node_runner.reserve_relayed_addresses();
+    assert_eq!(node_runner.relay_listeners.len(), 2);
+}
+
+#[tokio::test]
+async fn abandoned_query_is_cancelled_once_caller_stops_waiting() {
+    // Node that is never polled, so connection to it hangs on the security upgrade
+    let (stalled_node, _stalled_node_runner, stalled_node_address) =
+        create_memory_node(Config::with_generated_keypair()).await;
+
+    let (node, mut node_runner, _) = create_memory_node(Config::with_generated_keypair()).await;
+    let mut stalled_node_listen_address = stalled_node_address;
+    stalled_node_listen_address.pop();
+    node_runner
+        .swarm
+        .behaviour_mut()
+        .kademlia
+        .add_address(&stalled_node.id(), stalled_node_listen_address);
+
+    let (stop_sender, stop_receiver) = oneshot::channel();
+    let node_runner_handle = tokio::spawn(async move {
+        tokio::select! {
+            _ = node_runner.run() => {}
+            Ok(()) = stop_receiver => {}
+        }
+
+        node_runner
+    });
+
+    let result = node
+        .get_value_with_options(
+            Code::Sha2_256.digest(b"key"),
+            QueryOptions::with_timeout(Duration::from_millis(100)),
+        )
+        .await;
+    assert!(result.is_err());
+
+    // Cancellation is requested right away, give node runner a moment to process it
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    stop_sender.send(()).unwrap();
+    let node_runner = node_runner_handle.await.unwrap();
+
+    assert!(node_runner.query_id_receivers.is_empty());
+}
@@ -14,12 +14,15 @@
 // You should have received a copy of the GNU General Public License
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
-use crate::request_responses::{IncomingRequest, OutgoingResponse, ProtocolConfig, RequestHandler};
+use crate::request_responses::{
+    InboundRequestLimits, IncomingRequest, OutgoingResponse, ProtocolConfig, RequestHandler,
+};
 use async_trait::async_trait;
 use futures::channel::mpsc;
 use futures::prelude::*;
 use libp2p::PeerId;
 use parity_scale_codec::{Decode, Encode};
+use std::num::NonZeroUsize;
 use std::sync::Arc;
 use tracing::{debug, trace};
 
@@ -44,20 +47,56 @@ pub struct GenericRequestHandler<Request: GenericRequest> {
 }
 
 impl<Request: GenericRequest> GenericRequestHandler<Request> {
+    /// Default limits on incoming requests, limit of concurrent requests from a single peer leaves
+    /// room in the requests buffer for other peers.
+    pub const DEFAULT_INBOUND_LIMITS: InboundRequestLimits = InboundRequestLimits {
+        max_concurrent_requests_per_peer: Some(
+            NonZeroUsize::new(REQUESTS_BUFFER_SIZE / 5).expect("Manually set value should be > 0"),
+        ),
+        max_requests_per_second_per_peer: None,
+        max_response_bytes_per_second: None,
+    };
+
+    /// Creates a request handler with default per-peer limits on incoming requests.
     pub fn create<F>(request_handler: F) -> Box<dyn RequestHandler>
     where
         F: (Fn(&Request) -> Option<Request::Response>) + Send + Sync + 'static,
     {
+        Self::create_with_limits(Self::DEFAULT_INBOUND_LIMITS, request_handler)
+    }
+
+    /// Creates a request handler with custom limits on incoming requests.
+    pub fn create_with_limits<F>(
+        inbound_limits: InboundRequestLimits,
+        request_handler: F,
+    ) -> Box<dyn RequestHandler>
+    where
+        F: (Fn(&Request) -> Option<Request::Response>) + Send + Sync + 'static,
+    {
+        Box::new(Self::new(inbound_limits, Arc::new(request_handler)))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn new(
+        inbound_limits: InboundRequestLimits,
+        request_handler: Arc<
+            dyn (Fn(&Request) -> Option<<Request as GenericRequest>::Response>)
+                + Send
+                + Sync
+                + 'static,
+        >,
+    ) -> Self {
         let (request_sender, request_receiver) = mpsc::channel(REQUESTS_BUFFER_SIZE);
 
         let mut protocol_config = ProtocolConfig::new(Request::PROTOCOL_NAME);
         protocol_config.inbound_queue = Some(request_sender);
+        protocol_config.inbound_limits = inbound_limits;
 
-        Box::new(Self {
+        Self {
             request_receiver,
-            request_handler: Arc::new(request_handler),
+            request_handler,
             protocol_config,
-        })
+        }
     }
 
     // Invokes external protocol handler.
@@ -139,16 +178,10 @@ impl<Request: GenericRequest> RequestHandler for GenericRequestHandler<Request>
     }
 
     fn clone_box(&self) -> Box<dyn RequestHandler> {
-        let (request_sender, request_receiver) = mpsc::channel(REQUESTS_BUFFER_SIZE);
-
-        let mut protocol_config = ProtocolConfig::new(Request::PROTOCOL_NAME);
-        protocol_config.inbound_queue = Some(request_sender);
-
-        Box::new(Self {
-            request_receiver,
-            request_handler: Arc::clone(&self.request_handler),
-            protocol_config,
-        })
+        Box::new(Self::new(
+            self.protocol_config.inbound_limits,
+            Arc::clone(&self.request_handler),
+        ))
     }
 }
 
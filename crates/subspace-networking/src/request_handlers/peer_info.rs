@@ -1,5 +1,7 @@
 use crate::{GenericRequest, GenericRequestHandler};
 use parity_scale_codec::{Decode, Encode};
+use std::sync::Arc;
+use subspace_core_primitives::{PieceIndexHash, SegmentIndex, U256};
 
 /// Peer-info protocol request.
 #[derive(Debug, Clone, Eq, PartialEq, Encode, Decode)]
@@ -18,15 +20,96 @@ pub enum PeerSyncStatus {
     Syncing,
 }
 
+/// Defines the role of the peer in the network.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub enum PeerRole {
+    /// Blockchain node.
+    Node,
+    /// Farmer.
+    Farmer,
+    /// Bootstrap node.
+    BootstrapNode,
+    /// Bootstrap node with enabled circuit relay server.
+    Relay,
+}
+
+/// Inclusive range of piece index hashes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Encode, Decode)]
+pub struct PieceKeyRange {
+    /// Start of the range.
+    pub start: PieceIndexHash,
+    /// End of the range.
+    pub end: PieceIndexHash,
+}
+
+impl PieceKeyRange {
+    /// Range that covers the whole key space.
+    pub fn full() -> Self {
+        Self {
+            start: U256::zero().into(),
+            end: U256::MAX.into(),
+        }
+    }
+
+    /// Checks whether piece index hash belongs to the range.
+    pub fn contains(&self, piece_index_hash: &PieceIndexHash) -> bool {
+        self.start <= *piece_index_hash && *piece_index_hash <= self.end
+    }
+}
+
 /// Defines peer current state.
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
 pub struct PeerInfo {
     /// Synchronization status.
     pub status: PeerSyncStatus,
+    /// Role of the peer.
+    pub role: PeerRole,
+    /// Names of request-response protocols supported by the peer, filled by the networking stack.
+    pub protocols: Vec<String>,
+    /// Ranges of piece keys the peer caches pieces for.
+    pub cached_key_ranges: Vec<PieceKeyRange>,
+    /// Genesis hash of the chain the peer belongs to, `None` for chain-agnostic peers like
+    /// bootstrap nodes.
+    pub genesis_hash: Option<[u8; 32]>,
+    /// Index of the best archived segment known to the peer.
+    pub best_segment_index: Option<SegmentIndex>,
+}
+
+impl PeerInfo {
+    /// Creates peer info with specified role that doesn't advertise anything else.
+    pub fn new(role: PeerRole) -> Self {
+        Self {
+            status: PeerSyncStatus::NotSupported,
+            role,
+            protocols: Vec::new(),
+            cached_key_ranges: Vec::new(),
+            genesis_hash: None,
+            best_segment_index: None,
+        }
+    }
+
+    /// Checks whether peer advertised support of the request-response protocol.
+    pub fn supports_protocol(&self, protocol_name: &str) -> bool {
+        self.protocols
+            .iter()
+            .any(|protocol| protocol == protocol_name)
+    }
+
+    /// Checks whether peers belong to the same chain, chain-agnostic peers are compatible with
+    /// everyone.
+    pub fn is_compatible_with(&self, other: &PeerInfo) -> bool {
+        match (self.genesis_hash, other.genesis_hash) {
+            (Some(genesis_hash), Some(other_genesis_hash)) => genesis_hash == other_genesis_hash,
+            _ => true,
+        }
+    }
 }
 
+/// Returns up-to-date information about the local peer.
+pub type PeerInfoProvider = Arc<dyn Fn() -> PeerInfo + Send + Sync + 'static>;
+
 impl GenericRequest for PeerInfoRequest {
-    const PROTOCOL_NAME: &'static str = "/subspace/sync/peer-info/0.1.0";
+    const PROTOCOL_NAME: &'static str = "/subspace/sync/peer-info/0.2.0";
     const LOG_TARGET: &'static str = "peer-info-request-response-handler";
     type Response = PeerInfoResponse;
 }
@@ -1,8 +1,14 @@
+#[cfg(test)]
+mod tests;
+
 use parity_scale_codec::{Decode, Encode};
-use subspace_core_primitives::{FlatPieces, PieceIndex, PieceIndexHash};
+use subspace_core_primitives::{FlatPieces, Piece, PieceIndex, PieceIndexHash};
 
 use crate::{GenericRequest, GenericRequestHandler};
 
+/// Maximum number of pieces returned in a single pieces-by-range response (4 MiB of pieces).
+pub const PIECES_BY_RANGE_PAGE_SIZE: usize = 128;
+
 //TODO: A candidate for migrating to a separate crate.
 /// Collection of pieces that potentially need to be plotted
 #[derive(Debug, PartialEq, Eq, Clone, Encode, Decode)]
@@ -22,6 +28,13 @@ pub struct PiecesByRangeRequest {
     pub end: PieceIndexHash,
 }
 
+impl PiecesByRangeRequest {
+    /// Checks whether piece index hash belongs to the requested range, both ends are inclusive.
+    pub fn contains(&self, piece_index_hash: &PieceIndexHash) -> bool {
+        self.start <= *piece_index_hash && *piece_index_hash <= self.end
+    }
+}
+
 impl GenericRequest for PiecesByRangeRequest {
     const PROTOCOL_NAME: &'static str = "/subspace/sync/pieces-by-range/0.1.0";
     const LOG_TARGET: &'static str = "pieces-by-range-request-response-handler";
@@ -38,5 +51,45 @@ pub struct PiecesByRangeResponse {
     pub next_piece_index_hash: Option<PieceIndexHash>,
 }
 
+impl PiecesByRangeResponse {
+    /// Creates a single page of response from pieces of the requested range sorted by piece index
+    /// hash. At most [`PIECES_BY_RANGE_PAGE_SIZE`] pieces are considered, pieces that `read_piece`
+    /// failed to read are skipped. The cursor is the piece index hash that follows the last
+    /// considered piece, so `sorted_pieces` needs to contain one more piece than the page size (if
+    /// there is one in the range) for the cursor to be set.
+    pub fn from_sorted_pieces<I, D, F>(sorted_pieces: I, mut read_piece: F) -> Self
+    where
+        I: IntoIterator<Item = (PieceIndexHash, D)>,
+        F: FnMut(D) -> Option<(PieceIndex, Piece)>,
+    {
+        let mut sorted_pieces = sorted_pieces.into_iter();
+        let mut piece_indexes = Vec::new();
+        let mut pieces = Vec::new();
+
+        for (_piece_index_hash, piece_details) in
+            sorted_pieces.by_ref().take(PIECES_BY_RANGE_PAGE_SIZE)
+        {
+            if let Some((piece_index, piece)) = read_piece(piece_details) {
+                piece_indexes.push(piece_index);
+                pieces.extend_from_slice(&piece);
+            }
+        }
+
+        let next_piece_index_hash = sorted_pieces
+            .next()
+            .map(|(piece_index_hash, _piece_details)| piece_index_hash);
+
+        Self {
+            pieces: PiecesToPlot {
+                piece_indexes,
+                pieces: pieces
+                    .try_into()
+                    .expect("Pieces are always added as a whole; qed"),
+            },
+            next_piece_index_hash,
+        }
+    }
+}
+
 /// Create a new pieces-by-range request handler.
 pub type PiecesByRangeRequestHandler = GenericRequestHandler<PiecesByRangeRequest>;
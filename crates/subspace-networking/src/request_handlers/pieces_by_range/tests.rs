@@ -0,0 +1,118 @@
+use super::{PiecesByRangeRequest, PiecesByRangeResponse, PIECES_BY_RANGE_PAGE_SIZE};
+use std::collections::BTreeMap;
+use subspace_core_primitives::{Piece, PieceIndex, PieceIndexHash, U256};
+
+fn stored_pieces(count: u64) -> BTreeMap<PieceIndexHash, PieceIndex> {
+    (0..count)
+        .map(|piece_index| (PieceIndexHash::from_index(piece_index), piece_index))
+        .collect()
+}
+
+// Mirrors what request handlers do: collect one more piece than the page size for the cursor
+fn handle_request(
+    stored_pieces: &BTreeMap<PieceIndexHash, PieceIndex>,
+    request: PiecesByRangeRequest,
+    failed_piece_index: Option<PieceIndex>,
+) -> PiecesByRangeResponse {
+    let pieces_in_range = stored_pieces
+        .range(request.start..=request.end)
+        .take(PIECES_BY_RANGE_PAGE_SIZE + 1)
+        .map(|(piece_index_hash, piece_index)| (*piece_index_hash, *piece_index));
+
+    PiecesByRangeResponse::from_sorted_pieces(pieces_in_range, |piece_index| {
+        if Some(piece_index) == failed_piece_index {
+            None
+        } else {
+            Some((piece_index, Piece::default()))
+        }
+    })
+}
+
+#[test]
+fn pagination_covers_whole_range() {
+    let stored_pieces = stored_pieces(PIECES_BY_RANGE_PAGE_SIZE as u64 * 2 + 10);
+    let end = PieceIndexHash::from(U256::MAX);
+
+    let mut received_piece_indexes = Vec::new();
+    let mut cursor = Some(PieceIndexHash::from(U256::zero()));
+    let mut pages = 0;
+    while let Some(start) = cursor {
+        let response = handle_request(&stored_pieces, PiecesByRangeRequest { start, end }, None);
+        assert!(response.pieces.piece_indexes.len() <= PIECES_BY_RANGE_PAGE_SIZE);
+        assert_eq!(
+            response.pieces.pieces.count(),
+            response.pieces.piece_indexes.len()
+        );
+
+        received_piece_indexes.extend(response.pieces.piece_indexes);
+        cursor = response.next_piece_index_hash;
+        pages += 1;
+    }
+
+    assert_eq!(pages, 3);
+    assert_eq!(
+        received_piece_indexes,
+        stored_pieces.values().copied().collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn failed_piece_read_keeps_cursor() {
+    let stored_pieces = stored_pieces(PIECES_BY_RANGE_PAGE_SIZE as u64 + 5);
+    let sorted_piece_indexes = stored_pieces.values().copied().collect::<Vec<_>>();
+    let request = PiecesByRangeRequest {
+        start: PieceIndexHash::from(U256::zero()),
+        end: PieceIndexHash::from(U256::MAX),
+    };
+    let failed_piece_index = sorted_piece_indexes[3];
+
+    let response = handle_request(&stored_pieces, request, Some(failed_piece_index));
+
+    // Failed piece is skipped, but doesn't cause any other piece to be included in the page
+    assert_eq!(
+        response.pieces.piece_indexes.len(),
+        PIECES_BY_RANGE_PAGE_SIZE - 1
+    );
+    assert!(!response.pieces.piece_indexes.contains(&failed_piece_index));
+    // Cursor points to the first piece after the last considered one
+    assert_eq!(
+        response.next_piece_index_hash,
+        Some(PieceIndexHash::from_index(
+            sorted_piece_indexes[PIECES_BY_RANGE_PAGE_SIZE]
+        ))
+    );
+
+    // Failure of the last piece in the range doesn't break the last page either
+    let last_piece_index = *sorted_piece_indexes.last().unwrap();
+    let response = handle_request(
+        &stored_pieces,
+        PiecesByRangeRequest {
+            start: response.next_piece_index_hash.unwrap(),
+            ..request
+        },
+        Some(last_piece_index),
+    );
+    assert_eq!(response.pieces.piece_indexes.len(), 4);
+    assert_eq!(response.next_piece_index_hash, None);
+}
+
+#[test]
+fn range_is_respected() {
+    let stored_pieces = stored_pieces(20);
+    let sorted_piece_index_hashes = stored_pieces.keys().copied().collect::<Vec<_>>();
+    let request = PiecesByRangeRequest {
+        start: sorted_piece_index_hashes[5],
+        end: sorted_piece_index_hashes[9],
+    };
+
+    let response = handle_request(&stored_pieces, request, None);
+
+    assert_eq!(
+        response.pieces.piece_indexes,
+        stored_pieces
+            .range(request.start..=request.end)
+            .map(|(_piece_index_hash, piece_index)| *piece_index)
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(response.next_piece_index_hash, None);
+}
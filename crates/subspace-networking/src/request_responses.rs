@@ -37,6 +37,7 @@
 #[cfg(test)]
 mod tests;
 
+use crate::utils::rate_limiter::RateLimiter;
 use async_trait::async_trait;
 use futures::channel::{mpsc, oneshot};
 use futures::prelude::*;
@@ -53,16 +54,22 @@ use libp2p::swarm::{
     ConnectionHandler, IntoConnectionHandler, NetworkBehaviour, NetworkBehaviourAction,
     PollParameters,
 };
+use parking_lot::Mutex;
 use std::borrow::Cow;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::num::{NonZeroU32, NonZeroUsize};
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::{Duration, Instant};
 use std::{io, iter};
-use tracing::{debug, error, warn};
+use tracing::{debug, error, trace, warn};
 
 const LOG_TARGET: &str = "request-response-protocols";
+// Written instead of response length when request is refused due to inbound request limits, it is
+// never a valid length since it exceeds maximum response size.
+const RATE_LIMITED_RESPONSE_MARKER: usize = u32::MAX as usize;
 
 /// Defines a handler for the request-response protocol factory.
 #[async_trait]
@@ -130,6 +137,58 @@ pub struct ProtocolConfig {
     /// advertise support for this protocol, but any incoming request will lead to an error being
     /// sent back.
     pub inbound_queue: Option<mpsc::Sender<IncomingRequest>>,
+
+    /// Per-peer limits on incoming requests.
+    ///
+    /// Requests exceeding the limits are refused without being pushed to `inbound_queue`, so
+    /// that a single peer can't occupy the whole queue. The requester receives
+    /// [`RequestFailure::RateLimited`] in this case.
+    pub inbound_limits: InboundRequestLimits,
+}
+
+/// Limits on incoming requests from a single peer for a request-response protocol.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct InboundRequestLimits {
+    /// Maximum number of requests from a single peer processed at the same time, `None` means no
+    /// limit.
+    pub max_concurrent_requests_per_peer: Option<NonZeroUsize>,
+
+    /// Maximum average number of requests accepted from a single peer per second, the peer can
+    /// send up to this number of requests in a burst. `None` means no limit.
+    pub max_requests_per_second_per_peer: Option<NonZeroU32>,
+
+    /// Maximum number of response bytes sent per second to all peers, `None` means no limit.
+    ///
+    /// Responses exceeding the limit are delayed rather than refused.
+    pub max_response_bytes_per_second: Option<NonZeroU32>,
+}
+
+/// Number of request and response payload bytes transferred over a request-response protocol.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct ProtocolBandwidth {
+    /// Bytes received (incoming requests and responses to outgoing requests).
+    pub inbound: u64,
+    /// Bytes sent (outgoing requests and responses to incoming requests).
+    pub outbound: u64,
+}
+
+/// Bandwidth of request-response protocols by protocol name.
+pub(crate) type ProtocolsBandwidth = Arc<Mutex<HashMap<String, ProtocolBandwidth>>>;
+
+fn record_protocol_bandwidth(
+    protocols_bandwidth: &ProtocolsBandwidth,
+    protocol: &str,
+    inbound: usize,
+    outbound: usize,
+) {
+    let mut protocols_bandwidth = protocols_bandwidth.lock();
+    let protocol_bandwidth = match protocols_bandwidth.get_mut(protocol) {
+        Some(protocol_bandwidth) => protocol_bandwidth,
+        None => protocols_bandwidth.entry(protocol.to_string()).or_default(),
+    };
+
+    protocol_bandwidth.inbound += inbound as u64;
+    protocol_bandwidth.outbound += outbound as u64;
 }
 
 impl ProtocolConfig {
@@ -141,6 +200,7 @@ impl ProtocolConfig {
             max_response_size: 16 * 1024 * 1024,
             request_timeout: Duration::from_secs(15),
             inbound_queue: None,
+            inbound_limits: InboundRequestLimits::default(),
         }
     }
 }
@@ -261,6 +321,168 @@ impl IfDisconnected {
     }
 }
 
+/// Incoming requests of a single peer for a particular protocol.
+#[derive(Debug)]
+struct PeerInboundRequests {
+    /// Number of requests that are being processed.
+    in_flight: usize,
+    /// Token bucket of requests per second, `None` means no limit.
+    rate_limiter: Option<RateLimiter>,
+}
+
+impl PeerInboundRequests {
+    fn new(limits: &InboundRequestLimits) -> Self {
+        Self {
+            in_flight: 0,
+            // Each request takes one "byte" of the rate limiter
+            rate_limiter: limits
+                .max_requests_per_second_per_peer
+                .map(RateLimiter::new),
+        }
+    }
+}
+
+/// Enforces [`InboundRequestLimits`] for a particular protocol.
+#[derive(Debug)]
+struct InboundRequestsLimiter {
+    limits: InboundRequestLimits,
+    peers: HashMap<PeerId, PeerInboundRequests>,
+    response_rate_limiter: Option<RateLimiter>,
+}
+
+impl InboundRequestsLimiter {
+    fn new(limits: InboundRequestLimits) -> Self {
+        Self {
+            limits,
+            peers: HashMap::new(),
+            response_rate_limiter: limits.max_response_bytes_per_second.map(RateLimiter::new),
+        }
+    }
+
+    /// Accounts for a new incoming request, returns an error if the request must be refused.
+    fn try_start(&mut self, peer: PeerId) -> Result<(), ResponseFailure> {
+        let limits = &self.limits;
+        let peer_requests = self
+            .peers
+            .entry(peer)
+            .or_insert_with(|| PeerInboundRequests::new(limits));
+
+        if let Some(max_concurrent_requests) = self.limits.max_concurrent_requests_per_peer {
+            if peer_requests.in_flight >= max_concurrent_requests.get() {
+                return Err(ResponseFailure::TooManyConcurrentRequests);
+            }
+        }
+
+        if let Some(rate_limiter) = &peer_requests.rate_limiter {
+            if !rate_limiter.try_reserve(1) {
+                return Err(ResponseFailure::TooManyRequestsPerSecond);
+            }
+        }
+
+        peer_requests.in_flight += 1;
+
+        Ok(())
+    }
+
+    /// Accounts for a finished (successfully or not) request started with `try_start`.
+    fn finish(&mut self, peer: &PeerId) {
+        // Entry might be already removed if peer has disconnected.
+        if let Some(peer_requests) = self.peers.get_mut(peer) {
+            peer_requests.in_flight = peer_requests.in_flight.saturating_sub(1);
+        }
+    }
+
+    fn remove_peer(&mut self, peer: &PeerId) {
+        self.peers.remove(peer);
+    }
+}
+
+/// Enforces [`InboundRequestLimits`] of all protocols.
+#[derive(Debug, Default)]
+struct InboundRequestsLimiters {
+    /// Limiters by protocol name.
+    limiters: HashMap<Cow<'static, str>, InboundRequestsLimiter>,
+    /// Incoming requests accepted by limiters and being processed, with the sender peer.
+    in_flight: HashMap<ProtocolRequestId, PeerId>,
+    /// Incoming requests refused due to limits, with the reason to be reported once the refusal
+    /// was sent.
+    rejected: HashMap<ProtocolRequestId, ResponseFailure>,
+}
+
+impl InboundRequestsLimiters {
+    fn add_protocol(&mut self, protocol: Cow<'static, str>, limits: InboundRequestLimits) {
+        self.limiters
+            .insert(protocol, InboundRequestsLimiter::new(limits));
+    }
+
+    /// Accounts for a new incoming request, returns `false` if the request must be refused.
+    fn try_start(
+        &mut self,
+        protocol: &Cow<'static, str>,
+        request_id: RequestId,
+        peer: PeerId,
+    ) -> bool {
+        let limiter = match self.limiters.get_mut(protocol) {
+            Some(limiter) => limiter,
+            None => {
+                return true;
+            }
+        };
+
+        match limiter.try_start(peer) {
+            Ok(()) => {
+                self.in_flight
+                    .insert((protocol.clone(), request_id).into(), peer);
+                true
+            }
+            Err(failure) => {
+                debug!(
+                    target: LOG_TARGET,
+                    %peer,
+                    %request_id,
+                    %failure,
+                    "Refusing request on protocol {}",
+                    protocol,
+                );
+
+                self.rejected
+                    .insert((protocol.clone(), request_id).into(), failure);
+                false
+            }
+        }
+    }
+
+    /// Releases resources held by an incoming request, returns the failure if the request was
+    /// refused due to limits.
+    fn finish(
+        &mut self,
+        protocol: &Cow<'static, str>,
+        request_id: RequestId,
+    ) -> Option<ResponseFailure> {
+        let protocol_request_id = ProtocolRequestId::from((protocol.clone(), request_id));
+
+        if let Some(peer) = self.in_flight.remove(&protocol_request_id) {
+            if let Some(limiter) = self.limiters.get_mut(protocol) {
+                limiter.finish(&peer);
+            }
+        }
+
+        self.rejected.remove(&protocol_request_id)
+    }
+
+    fn remove_peer(&mut self, peer: &PeerId) {
+        for limiter in self.limiters.values_mut() {
+            limiter.remove_peer(peer);
+        }
+    }
+
+    fn response_rate_limiter(&self, protocol: &str) -> Option<RateLimiter> {
+        self.limiters
+            .get(protocol)
+            .and_then(|limiter| limiter.response_rate_limiter.clone())
+    }
+}
+
 /// Implementation of `NetworkBehaviour` that provides support for request-response protocols.
 #[allow(clippy::type_complexity)] // to preserve compatibility with copied implementation
 pub struct RequestResponsesBehaviour {
@@ -279,6 +501,10 @@ pub struct RequestResponsesBehaviour {
     pending_requests:
         HashMap<ProtocolRequestId, (Instant, oneshot::Sender<Result<Vec<u8>, RequestFailure>>)>,
 
+    /// Requests that were cancelled because local node is no longer interested in the result,
+    /// their responses and failures are ignored.
+    cancelled_requests: HashSet<ProtocolRequestId>,
+
     /// Whenever an incoming request arrives, a `Future` is added to this list and will yield the
     /// start time and the response to send back to the remote.
     pending_responses: stream::FuturesUnordered<
@@ -298,6 +524,12 @@ pub struct RequestResponsesBehaviour {
 
     /// Request-Response protocol handlers configured for this protocol factory.
     protocol_handlers: Vec<Box<dyn RequestHandler>>,
+
+    /// Per-peer limits on incoming requests.
+    inbound_limiters: InboundRequestsLimiters,
+
+    /// Bandwidth of request and response payloads by protocol.
+    protocols_bandwidth: ProtocolsBandwidth,
 }
 
 // This is a state of processing incoming request Message.
@@ -305,7 +537,7 @@ struct MessageRequest {
     peer: PeerId,
     request_id: RequestId,
     request: Vec<u8>,
-    channel: ResponseChannel<Result<Vec<u8>, ()>>,
+    channel: ResponseChannel<Result<Vec<u8>, Refusal>>,
     protocol: String,
     resp_builder: Option<mpsc::Sender<IncomingRequest>>,
 }
@@ -314,7 +546,7 @@ struct MessageRequest {
 struct RequestProcessingOutcome {
     request_id: RequestId,
     protocol: Cow<'static, str>,
-    inner_channel: ResponseChannel<Result<Vec<u8>, ()>>,
+    inner_channel: ResponseChannel<Result<Vec<u8>, Refusal>>,
     response: OutgoingResponse,
 }
 
@@ -326,6 +558,7 @@ impl RequestResponsesBehaviour {
     ) -> Result<Self, RegisterError> {
         let mut protocols = HashMap::new();
         let mut protocol_handlers = Vec::new();
+        let mut inbound_limiters = InboundRequestsLimiters::default();
         for handler in list {
             let config = handler.protocol_config();
 
@@ -354,6 +587,7 @@ impl RequestResponsesBehaviour {
                     return Err(RegisterError::DuplicateProtocol(e.key().clone()))
                 }
             };
+            inbound_limiters.add_protocol(Cow::Borrowed(config.name), config.inbound_limits);
 
             protocol_handlers.push(handler);
         }
@@ -361,14 +595,43 @@ impl RequestResponsesBehaviour {
         Ok(Self {
             protocols,
             pending_requests: Default::default(),
+            cancelled_requests: Default::default(),
             pending_responses: Default::default(),
             pending_responses_arrival_time: Default::default(),
             send_feedback: Default::default(),
             message_request: None,
             protocol_handlers,
+            inbound_limiters,
+            protocols_bandwidth: ProtocolsBandwidth::default(),
         })
     }
 
+    /// Cancels pending requests whose senders are no longer interested in the result, returns the
+    /// number of cancelled requests.
+    ///
+    /// Requests that were already sent can't be aborted, their responses are ignored instead.
+    pub(crate) fn cancel_abandoned_requests(&mut self) -> usize {
+        let cancelled_requests = &mut self.cancelled_requests;
+        let pending_requests_before = self.pending_requests.len();
+
+        self.pending_requests
+            .retain(|protocol_request_id, (_started, pending_response)| {
+                if pending_response.is_canceled() {
+                    cancelled_requests.insert(protocol_request_id.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+
+        pending_requests_before - self.pending_requests.len()
+    }
+
+    /// Bandwidth of request and response payloads by protocol, updated as requests are processed.
+    pub(crate) fn protocols_bandwidth(&self) -> ProtocolsBandwidth {
+        Arc::clone(&self.protocols_bandwidth)
+    }
+
     /// Initiates sending a request.
     ///
     /// If there is no established connection to the target peer, the behavior is determined by the
@@ -385,6 +648,12 @@ impl RequestResponsesBehaviour {
     ) {
         if let Some((protocol, _)) = self.protocols.get_mut(protocol_name) {
             if protocol.is_connected(target) || connect.should_connect() {
+                record_protocol_bandwidth(
+                    &self.protocols_bandwidth,
+                    protocol_name,
+                    0,
+                    request.len(),
+                );
                 let request_id = protocol.send_request(target, request);
                 let prev_req_id = self.pending_requests.insert(
                     (protocol_name.to_string().into(), request_id).into(),
@@ -467,6 +736,10 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
         handler: <Self::ConnectionHandler as IntoConnectionHandler>::Handler,
         remaining_established: usize,
     ) {
+        if remaining_established == 0 {
+            self.inbound_limiters.remove_peer(peer_id);
+        }
+
         for (p_name, event) in handler.into_iter() {
             if let Some((proto, _)) = self.protocols.get_mut(p_name.as_str()) {
                 proto.inject_connection_closed(
@@ -589,12 +862,19 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                     debug_assert!(false, "Received message on outbound-only protocol.");
                 }
 
+                let response_rate_limiter = self.inbound_limiters.response_rate_limiter(&protocol);
                 let protocol = Cow::from(protocol);
                 self.pending_responses.push(Box::pin(async move {
                     // The `tx` created above can be dropped if we are not capable of
                     // processing this request, which is reflected as a
                     // `InboundFailure::Omission` event.
                     if let Ok(response) = rx.await {
+                        if let (Some(response_rate_limiter), Ok(payload)) =
+                            (&response_rate_limiter, &response.result)
+                        {
+                            response_rate_limiter.throttle(payload.len()).await;
+                        }
+
                         Some(RequestProcessingOutcome {
                             request_id,
                             protocol,
@@ -630,6 +910,7 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
 
                 if let Ok(payload) = result {
                     if let Some((protocol, _)) = self.protocols.get_mut(&*protocol_name) {
+                        let payload_size = payload.len();
                         if protocol.send_response(inner_channel, Ok(payload)).is_err() {
                             // Note: Failure is handled further below when receiving
                             // `InboundFailure` event from `RequestResponse` behaviour.
@@ -641,9 +922,18 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                                 Dropping response",
                                 protocol_name,
                             );
-                        } else if let Some(sent_feedback) = sent_feedback {
-                            self.send_feedback
-                                .insert((protocol_name, request_id).into(), sent_feedback);
+                        } else {
+                            record_protocol_bandwidth(
+                                &self.protocols_bandwidth,
+                                &protocol_name,
+                                0,
+                                payload_size,
+                            );
+
+                            if let Some(sent_feedback) = sent_feedback {
+                                self.send_feedback
+                                    .insert((protocol_name, request_id).into(), sent_feedback);
+                            }
                         }
                     }
                 }
@@ -735,6 +1025,19 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                         } => {
                             self.pending_responses_arrival_time
                                 .insert((protocol.clone(), request_id).into(), Instant::now());
+                            record_protocol_bandwidth(
+                                &self.protocols_bandwidth,
+                                protocol,
+                                request.len(),
+                                0,
+                            );
+
+                            if !self.inbound_limiters.try_start(protocol, request_id, peer) {
+                                // Refusal is reported once `ResponseSent` or `InboundFailure`
+                                // arrives for this request.
+                                let _ = behaviour.send_response(channel, Err(Refusal::RateLimited));
+                                continue;
+                            }
 
                             // Save the Future-like state with params to poll `get_peer_reputation`
                             // and to continue processing the request once we get the reputation of
@@ -768,12 +1071,32 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                                 .remove(&(protocol.clone(), request_id).into())
                             {
                                 Some((started, pending_response)) => {
+                                    if let Ok(response) = &response {
+                                        record_protocol_bandwidth(
+                                            &self.protocols_bandwidth,
+                                            protocol,
+                                            response.len(),
+                                            0,
+                                        );
+                                    }
                                     let delivered = pending_response
-                                        .send(response.map_err(|()| RequestFailure::Refused))
+                                        .send(response.map_err(RequestFailure::from))
                                         .map_err(|_| RequestFailure::Obsolete);
                                     (started, delivered)
                                 }
                                 None => {
+                                    if self
+                                        .cancelled_requests
+                                        .remove(&(protocol.clone(), request_id).into())
+                                    {
+                                        trace!(
+                                            target: LOG_TARGET,
+                                            %request_id,
+                                            "Response to a cancelled request ignored.",
+                                        );
+                                        continue;
+                                    }
+
                                     warn!(
                                         target: LOG_TARGET,
                                         "Received `RequestResponseEvent::Message` with unexpected request id {:?}",
@@ -820,6 +1143,18 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                                     started
                                 }
                                 None => {
+                                    if self
+                                        .cancelled_requests
+                                        .remove(&(protocol.clone(), request_id).into())
+                                    {
+                                        trace!(
+                                            target: LOG_TARGET,
+                                            %request_id,
+                                            "Cancelled request failed.",
+                                        );
+                                        continue;
+                                    }
+
                                     warn!(
                                         target: LOG_TARGET,
                                         %request_id,
@@ -853,10 +1188,14 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                                 .remove(&(protocol.clone(), request_id).into());
                             self.send_feedback
                                 .remove(&(protocol.clone(), request_id).into());
+                            let failure = self
+                                .inbound_limiters
+                                .finish(protocol, request_id)
+                                .unwrap_or(ResponseFailure::Network(error));
                             let out = Event::InboundRequest {
                                 peer,
                                 protocol: protocol.clone(),
-                                result: Err(ResponseFailure::Network(error)),
+                                result: Err(failure),
                             };
                             return Poll::Ready(NetworkBehaviourAction::GenerateEvent(out));
                         }
@@ -882,10 +1221,15 @@ impl NetworkBehaviour for RequestResponsesBehaviour {
                                 let _ = send_feedback.send(());
                             }
 
+                            let result = match self.inbound_limiters.finish(protocol, request_id) {
+                                Some(failure) => Err(failure),
+                                None => Ok(arrival_time),
+                            };
+
                             let out = Event::InboundRequest {
                                 peer,
                                 protocol: protocol.clone(),
-                                result: Ok(arrival_time),
+                                result,
                             };
 
                             return Poll::Ready(NetworkBehaviourAction::GenerateEvent(out));
@@ -917,6 +1261,10 @@ pub enum RequestFailure {
     UnknownProtocol,
     #[error("Remote has closed the substream before answering, thereby signaling that it considers the request as valid, but refused to answer it.")]
     Refused,
+    #[error(
+        "Remote refused to answer because the local node exceeded its inbound request limits."
+    )]
+    RateLimited,
     #[error("The remote replied, but the local node is no longer interested in the response.")]
     Obsolete,
     /// Problem on the network.
@@ -924,12 +1272,37 @@ pub enum RequestFailure {
     Network(OutboundFailure),
 }
 
+impl From<Refusal> for RequestFailure {
+    fn from(refusal: Refusal) -> Self {
+        match refusal {
+            Refusal::Unspecified => Self::Refused,
+            Refusal::RateLimited => Self::RateLimited,
+        }
+    }
+}
+
+/// Reason of refusing to answer a request that is sent to the requester.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[doc(hidden)] // Needs to be public as a part of `GenericCodec`.
+pub enum Refusal {
+    /// Substream is closed without a response, the reason is not known to the requester.
+    Unspecified,
+    /// Requester exceeded inbound request limits of the responder.
+    RateLimited,
+}
+
 /// Error when processing a request sent by a remote.
 #[derive(Debug, thiserror::Error)]
 pub enum ResponseFailure {
     /// Problem on the network.
     #[error("Problem on the network: {0}")]
     Network(InboundFailure),
+    /// Request was refused because the peer exceeded the limit of concurrent requests.
+    #[error("Request refused: too many concurrent requests from the peer")]
+    TooManyConcurrentRequests,
+    /// Request was refused because the peer exceeded the limit of requests per second.
+    #[error("Request refused: too many requests per second from the peer")]
+    TooManyRequestsPerSecond,
 }
 
 /// Implements the libp2p [`RequestResponseCodec`] trait. Defines how streams of bytes are turned
@@ -945,7 +1318,7 @@ pub struct GenericCodec {
 impl RequestResponseCodec for GenericCodec {
     type Protocol = Vec<u8>;
     type Request = Vec<u8>;
-    type Response = Result<Vec<u8>, ()>;
+    type Response = Result<Vec<u8>, Refusal>;
 
     async fn read_request<T>(
         &mut self,
@@ -994,11 +1367,15 @@ impl RequestResponseCodec for GenericCodec {
             Err(unsigned_varint::io::ReadError::Io(err))
                 if matches!(err.kind(), io::ErrorKind::UnexpectedEof) =>
             {
-                return Ok(Err(()))
+                return Ok(Err(Refusal::Unspecified))
             }
             Err(err) => return Err(io::Error::new(io::ErrorKind::InvalidInput, err)),
         };
 
+        if length == RATE_LIMITED_RESPONSE_MARKER {
+            return Ok(Err(Refusal::RateLimited));
+        }
+
         if length > usize::try_from(self.max_response_size).unwrap_or(usize::MAX) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
@@ -1047,17 +1424,28 @@ impl RequestResponseCodec for GenericCodec {
     where
         T: AsyncWrite + Unpin + Send,
     {
-        // If `res` is an `Err`, we jump to closing the substream without writing anything on it.
-        if let Ok(res) = res {
-            // Write the length.
-            {
+        match res {
+            Ok(res) => {
+                // Write the length.
+                {
+                    let mut buffer = unsigned_varint::encode::usize_buffer();
+                    io.write_all(unsigned_varint::encode::usize(res.len(), &mut buffer))
+                        .await?;
+                }
+
+                // Write the payload.
+                io.write_all(&res).await?;
+            }
+            Err(Refusal::RateLimited) => {
                 let mut buffer = unsigned_varint::encode::usize_buffer();
-                io.write_all(unsigned_varint::encode::usize(res.len(), &mut buffer))
-                    .await?;
+                io.write_all(unsigned_varint::encode::usize(
+                    RATE_LIMITED_RESPONSE_MARKER,
+                    &mut buffer,
+                ))
+                .await?;
             }
-
-            // Write the payload.
-            io.write_all(&res).await?;
+            // We jump to closing the substream without writing anything on it.
+            Err(Refusal::Unspecified) => {}
         }
 
         io.close().await?;
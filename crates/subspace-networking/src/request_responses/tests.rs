@@ -1,6 +1,7 @@
 use crate::request_responses::{
-    Event, IfDisconnected, IncomingRequest, OutboundFailure, OutgoingResponse, ProtocolConfig,
-    RequestFailure, RequestHandler, RequestResponsesBehaviour,
+    Event, IfDisconnected, InboundRequestLimits, InboundRequestsLimiter, IncomingRequest,
+    OutboundFailure, OutgoingResponse, ProtocolConfig, RequestFailure, RequestHandler,
+    RequestResponsesBehaviour, ResponseFailure,
 };
 use async_trait::async_trait;
 use futures::channel::{mpsc, oneshot};
@@ -11,9 +12,10 @@ use libp2p::core::transport::{MemoryTransport, Transport};
 use libp2p::core::upgrade;
 use libp2p::identity::Keypair;
 use libp2p::swarm::{Swarm, SwarmEvent};
-use libp2p::{noise, Multiaddr};
+use libp2p::{noise, Multiaddr, PeerId};
 use std::iter;
-use std::time::Duration;
+use std::num::NonZeroU32;
+use std::time::{Duration, Instant};
 
 #[derive(Clone)]
 struct MockRunner(ProtocolConfig);
@@ -99,6 +101,7 @@ fn basic_request_response_works() {
                 max_response_size: 1024 * 1024,
                 request_timeout: Duration::from_secs(30),
                 inbound_queue: Some(tx),
+                inbound_limits: InboundRequestLimits::default(),
             };
 
             build_swarm(iter::once(protocol_config))
@@ -198,6 +201,7 @@ fn max_response_size_exceeded() {
                 max_response_size: 8, // <-- important for the test
                 request_timeout: Duration::from_secs(30),
                 inbound_queue: Some(tx),
+                inbound_limits: InboundRequestLimits::default(),
             };
 
             build_swarm(iter::once(protocol_config))
@@ -291,6 +295,7 @@ fn request_id_collision() {
                 max_response_size: 1024 * 1024,
                 request_timeout: Duration::from_secs(30),
                 inbound_queue: None,
+                inbound_limits: InboundRequestLimits::default(),
             },
             ProtocolConfig {
                 name: protocol_name_2,
@@ -298,6 +303,7 @@ fn request_id_collision() {
                 max_response_size: 1024 * 1024,
                 request_timeout: Duration::from_secs(30),
                 inbound_queue: None,
+                inbound_limits: InboundRequestLimits::default(),
             },
         ];
 
@@ -315,6 +321,7 @@ fn request_id_collision() {
                 max_response_size: 1024 * 1024,
                 request_timeout: Duration::from_secs(30),
                 inbound_queue: Some(tx_1),
+                inbound_limits: InboundRequestLimits::default(),
             },
             ProtocolConfig {
                 name: protocol_name_2,
@@ -322,6 +329,7 @@ fn request_id_collision() {
                 max_response_size: 1024 * 1024,
                 request_timeout: Duration::from_secs(30),
                 inbound_queue: Some(tx_2),
+                inbound_limits: InboundRequestLimits::default(),
             },
         ];
 
@@ -431,3 +439,276 @@ fn request_id_collision() {
         );
     });
 }
+
+#[test]
+fn inbound_requests_per_second_limit_refuses_excess_requests() {
+    let protocol_name = "/test/req-resp/1";
+    let mut pool = LocalPool::new();
+
+    // Build swarms whose behaviour is `RequestResponsesBehaviour`.
+    let mut swarms = (0..2)
+        .map(|_| {
+            let (tx, mut rx) = mpsc::channel::<IncomingRequest>(64);
+
+            pool.spawner()
+                .spawn_obj(
+                    async move {
+                        while let Some(rq) = rx.next().await {
+                            let _ = rq.pending_response.send(OutgoingResponse {
+                                result: Ok(b"this is a response".to_vec()),
+                                sent_feedback: None,
+                            });
+                        }
+                    }
+                    .boxed()
+                    .into(),
+                )
+                .unwrap();
+
+            let protocol_config = ProtocolConfig {
+                name: protocol_name,
+                max_request_size: 1024,
+                max_response_size: 1024 * 1024,
+                request_timeout: Duration::from_secs(30),
+                inbound_queue: Some(tx),
+                inbound_limits: InboundRequestLimits {
+                    max_concurrent_requests_per_peer: None,
+                    max_requests_per_second_per_peer: NonZeroU32::new(1),
+                    max_response_bytes_per_second: None,
+                },
+            };
+
+            build_swarm(iter::once(protocol_config))
+        })
+        .collect::<Vec<_>>();
+
+    // Ask `swarm[0]` to dial `swarm[1]`. There isn't any discovery mechanism in place in
+    // this test, so they wouldn't connect to each other.
+    {
+        let dial_addr = swarms[1].1.clone();
+        Swarm::dial(&mut swarms[0].0, dial_addr).unwrap();
+    }
+
+    let (mut swarm, _) = swarms.remove(0);
+    let (inbound_results_sender, mut inbound_results_receiver) = mpsc::unbounded();
+
+    // Running `swarm[0]` in the background.
+    pool.spawner()
+        .spawn_obj({
+            async move {
+                loop {
+                    if let SwarmEvent::Behaviour(Event::InboundRequest { result, .. }) =
+                        swarm.select_next_some().await
+                    {
+                        let _ = inbound_results_sender.unbounded_send(result);
+                    }
+                }
+            }
+            .boxed()
+            .into()
+        })
+        .unwrap();
+
+    // Remove and run the remaining swarm.
+    let (mut swarm, _) = swarms.remove(0);
+
+    pool.run_until(async move {
+        let mut response_receivers = Vec::new();
+        let mut finished_requests = 0;
+
+        while finished_requests < 2 {
+            match swarm.select_next_some().await {
+                SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                    for _ in 0..2 {
+                        let (sender, receiver) = oneshot::channel();
+                        swarm.behaviour_mut().send_request(
+                            &peer_id,
+                            protocol_name,
+                            b"this is a request".to_vec(),
+                            sender,
+                            IfDisconnected::ImmediateError,
+                        );
+                        response_receivers.push(receiver);
+                    }
+                }
+                SwarmEvent::Behaviour(Event::RequestFinished { .. }) => {
+                    finished_requests += 1;
+                }
+                _ => {}
+            }
+        }
+
+        let mut served = 0;
+        let mut rate_limited = 0;
+        for response_receiver in response_receivers {
+            match response_receiver.await.unwrap() {
+                Ok(response) => {
+                    assert_eq!(response, b"this is a response");
+                    served += 1;
+                }
+                // Requester learns that it was rate limited rather than getting a generic refusal
+                Err(RequestFailure::RateLimited) => {
+                    rate_limited += 1;
+                }
+                Err(error) => {
+                    panic!("Unexpected request failure: {error}");
+                }
+            }
+        }
+        assert_eq!(served, 1);
+        assert_eq!(rate_limited, 1);
+
+        let mut inbound_served = 0;
+        let mut inbound_rejected = 0;
+        for _ in 0..2 {
+            match inbound_results_receiver.next().await.unwrap() {
+                Ok(_) => {
+                    inbound_served += 1;
+                }
+                Err(ResponseFailure::TooManyRequestsPerSecond) => {
+                    inbound_rejected += 1;
+                }
+                Err(error) => {
+                    panic!("Unexpected response failure: {error}");
+                }
+            }
+        }
+        assert_eq!(inbound_served, 1);
+        assert_eq!(inbound_rejected, 1);
+    });
+}
+
+#[test]
+fn inbound_requests_per_second_limit_is_refilled_over_time() {
+    let mut limiter = InboundRequestsLimiter::new(InboundRequestLimits {
+        max_requests_per_second_per_peer: NonZeroU32::new(2),
+        ..InboundRequestLimits::default()
+    });
+    let peer = PeerId::random();
+
+    // Burst of requests up to the limit is allowed
+    assert!(limiter.try_start(peer).is_ok());
+    assert!(limiter.try_start(peer).is_ok());
+    assert!(matches!(
+        limiter.try_start(peer),
+        Err(ResponseFailure::TooManyRequestsPerSecond)
+    ));
+
+    // Other peers have their own limits
+    assert!(limiter.try_start(PeerId::random()).is_ok());
+
+    // One request is refilled every half a second rather than the whole limit at the end of a
+    // fixed window
+    std::thread::sleep(Duration::from_millis(600));
+    assert!(limiter.try_start(peer).is_ok());
+    assert!(matches!(
+        limiter.try_start(peer),
+        Err(ResponseFailure::TooManyRequestsPerSecond)
+    ));
+}
+
+#[tokio::test]
+async fn max_response_bytes_per_second_delays_responses() {
+    let protocol_name = "/test/req-resp/1";
+    let response = vec![0u8; 1000];
+
+    // Build swarms whose behaviour is `RequestResponsesBehaviour`.
+    let mut swarms = (0..2)
+        .map(|_| {
+            let (tx, mut rx) = mpsc::channel::<IncomingRequest>(64);
+
+            tokio::spawn({
+                let response = response.clone();
+
+                async move {
+                    while let Some(rq) = rx.next().await {
+                        let _ = rq.pending_response.send(OutgoingResponse {
+                            result: Ok(response.clone()),
+                            sent_feedback: None,
+                        });
+                    }
+                }
+            });
+
+            let protocol_config = ProtocolConfig {
+                name: protocol_name,
+                max_request_size: 1024,
+                max_response_size: 1024 * 1024,
+                request_timeout: Duration::from_secs(30),
+                inbound_queue: Some(tx),
+                inbound_limits: InboundRequestLimits {
+                    // One second worth of responses is sent without delay
+                    max_response_bytes_per_second: NonZeroU32::new(1000),
+                    ..InboundRequestLimits::default()
+                },
+            };
+
+            build_swarm(iter::once(protocol_config))
+        })
+        .collect::<Vec<_>>();
+
+    // Ask `swarm[0]` to dial `swarm[1]`. There isn't any discovery mechanism in place in
+    // this test, so they wouldn't connect to each other.
+    {
+        let dial_addr = swarms[1].1.clone();
+        Swarm::dial(&mut swarms[0].0, dial_addr).unwrap();
+    }
+
+    let (mut swarm, _) = swarms.remove(0);
+
+    // Running `swarm[0]` in the background.
+    tokio::spawn(async move {
+        loop {
+            if let SwarmEvent::Behaviour(Event::InboundRequest { result, .. }) =
+                swarm.select_next_some().await
+            {
+                result.unwrap();
+            }
+        }
+    });
+
+    // Remove and run the remaining swarm.
+    let (mut swarm, _) = swarms.remove(0);
+
+    let mut response_receivers = Vec::new();
+    let mut requests_sent_at = None;
+    let mut response_delays = Vec::new();
+
+    while response_delays.len() < 2 {
+        match swarm.select_next_some().await {
+            SwarmEvent::ConnectionEstablished { peer_id, .. } => {
+                for _ in 0..2 {
+                    let (sender, receiver) = oneshot::channel();
+                    swarm.behaviour_mut().send_request(
+                        &peer_id,
+                        protocol_name,
+                        b"this is a request".to_vec(),
+                        sender,
+                        IfDisconnected::ImmediateError,
+                    );
+                    response_receivers.push(receiver);
+                }
+                requests_sent_at.replace(Instant::now());
+            }
+            SwarmEvent::Behaviour(Event::RequestFinished { result, .. }) => {
+                result.unwrap();
+                response_delays.push(requests_sent_at.unwrap().elapsed());
+            }
+            _ => {}
+        }
+    }
+
+    for response_receiver in response_receivers {
+        assert_eq!(response_receiver.await.unwrap().unwrap(), response);
+    }
+
+    // First response fits into the burst, second one waits until bandwidth is available again
+    assert!(
+        response_delays[0] < Duration::from_millis(900),
+        "{response_delays:?}"
+    );
+    assert!(
+        response_delays[1] >= Duration::from_millis(900),
+        "{response_delays:?}"
+    );
+}
@@ -1,15 +1,20 @@
 //! Data structures shared between node and node runner, facilitating exchange and creation of
 //! queries, subscriptions, various events and shared information.
 
-use crate::request_responses::RequestFailure;
+use crate::node::{InboundRequestsStats, TopicValidator};
+use crate::request_handlers::peer_info::PeerInfo;
+use crate::request_responses::{ProtocolsBandwidth, RequestFailure};
 use bytes::Bytes;
 use event_listener_primitives::Bag;
 use futures::channel::{mpsc, oneshot};
+use libp2p::autonat::NatStatus;
+use libp2p::bandwidth::BandwidthSinks;
 use libp2p::core::multihash::Multihash;
 use libp2p::gossipsub::error::{PublishError, SubscriptionError};
 use libp2p::gossipsub::Sha256Topic;
 use libp2p::{Multiaddr, PeerId};
 use parking_lot::Mutex;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 #[derive(Debug)]
@@ -33,6 +38,7 @@ pub(crate) enum Command {
     },
     Subscribe {
         topic: Sha256Topic,
+        validator: Option<TopicValidator>,
         result_sender: oneshot::Sender<Result<CreatedSubscription, SubscriptionError>>,
     },
     Unsubscribe {
@@ -57,6 +63,15 @@ pub(crate) enum Command {
     CheckConnectedPeers {
         result_sender: oneshot::Sender<bool>,
     },
+    GetPeersInfo {
+        result_sender: oneshot::Sender<HashMap<PeerId, PeerInfo>>,
+    },
+    GetConnectedPeers {
+        result_sender: oneshot::Sender<Vec<PeerId>>,
+    },
+    GetRoutingTableSize {
+        result_sender: oneshot::Sender<usize>,
+    },
     StartAnnouncing {
         key: Multihash,
         result_sender: oneshot::Sender<bool>,
@@ -69,12 +84,16 @@ pub(crate) enum Command {
         key: Multihash,
         result_sender: oneshot::Sender<Option<Vec<PeerId>>>,
     },
+    /// Sent when a caller stops waiting for the result of a query or request.
+    CancelAbandonedQueries,
 }
 
 #[derive(Default, Debug)]
 pub(crate) struct Handlers {
     #[allow(clippy::type_complexity)]
     pub(crate) new_listener: Bag<Arc<dyn Fn(&Multiaddr) + Send + Sync + 'static>, Multiaddr>,
+    #[allow(clippy::type_complexity)]
+    pub(crate) nat_status_changed: Bag<Arc<dyn Fn(&NatStatus) + Send + Sync + 'static>, NatStatus>,
 }
 
 #[derive(Debug)]
@@ -83,16 +102,33 @@ pub(crate) struct Shared {
     pub(crate) id: PeerId,
     /// Addresses on which node is listening for incoming requests.
     pub(crate) listeners: Mutex<Vec<Multiaddr>>,
+    /// Reachability status of the node as detected by AutoNAT.
+    pub(crate) nat_status: Mutex<NatStatus>,
+    /// Statistics of incoming requests by request-response protocol name.
+    pub(crate) inbound_requests_stats: Mutex<HashMap<String, InboundRequestsStats>>,
+    /// Total bandwidth of direct connections.
+    pub(crate) bandwidth: Arc<BandwidthSinks>,
+    /// Bandwidth of request-response protocols.
+    pub(crate) protocols_bandwidth: ProtocolsBandwidth,
     /// Sender end of the channel for sending commands to the swarm.
     pub(crate) command_sender: mpsc::Sender<Command>,
 }
 
 impl Shared {
-    pub(crate) fn new(id: PeerId, command_sender: mpsc::Sender<Command>) -> Self {
+    pub(crate) fn new(
+        id: PeerId,
+        command_sender: mpsc::Sender<Command>,
+        bandwidth: Arc<BandwidthSinks>,
+        protocols_bandwidth: ProtocolsBandwidth,
+    ) -> Self {
         Self {
             handlers: Handlers::default(),
             id,
             listeners: Mutex::default(),
+            nat_status: Mutex::new(NatStatus::Unknown),
+            inbound_requests_stats: Mutex::default(),
+            bandwidth,
+            protocols_bandwidth,
             command_sender,
         }
     }
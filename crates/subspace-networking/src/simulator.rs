@@ -0,0 +1,530 @@
+//! In-process simulation of DSN with multiple nodes connected over memory transport.
+//!
+//! Simulated links may have latency, losses and network partitions, which makes it possible to
+//! test retrieval of pieces at scale in integration tests without real networking.
+//!
+//! Only available with `simulator` feature.
+
+#[cfg(test)]
+mod tests;
+mod transport;
+
+use crate::create::{create_with_transport, relay_transport_and_client};
+use crate::node::CheckConnectedPeersError;
+use crate::utils::multihash::MultihashCode;
+use crate::{
+    peer_id, BootstrappedNetworkingParameters, Config, CreationError, CustomRecordStore, Node,
+    NodeRunner, PieceByHashRequest, PieceKey, ToMultihash,
+};
+use futures::future::join_all;
+use libp2p::bandwidth::BandwidthLogging;
+use libp2p::core::transport::OptionalTransport;
+use libp2p::core::upgrade::{SelectUpgrade, Version};
+use libp2p::multiaddr::Protocol;
+use libp2p::noise::NoiseConfig;
+use libp2p::{noise, Multiaddr, PeerId, Transport};
+use parity_scale_codec::Decode;
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use subspace_core_primitives::{Piece, PieceIndex, PieceIndexHash};
+use tokio::task::JoinHandle;
+use tracing::debug;
+use transport::simulated_transport;
+
+// Delay of data retransmission after simulated loss.
+const RETRANSMISSION_DELAY: Duration = Duration::from_millis(200);
+// Delay between attempts to retrieve a piece in availability assertions.
+const PIECE_RETRIEVAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Conditions of a link between two simulated nodes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LinkConditions {
+    /// One-way latency of the link, applied to data sent in either direction, so every round trip
+    /// takes at least twice as long.
+    pub latency: Duration,
+    /// Probability (from 0.0 to 1.0) of losing a chunk of data, lost data is delivered after
+    /// retransmission delay like it happens with TCP.
+    pub loss_probability: f64,
+}
+
+impl LinkConditions {
+    // Delay of the next chunk of data sent over the link.
+    fn chunk_delay(&self) -> Duration {
+        if self.loss_probability > 0.0 && rand::random::<f64>() < self.loss_probability {
+            self.latency + RETRANSMISSION_DELAY
+        } else {
+            self.latency
+        }
+    }
+}
+
+/// Initial connectivity of simulated nodes, defines which nodes are used to bootstrap each node.
+#[derive(Debug, Clone)]
+pub enum Topology {
+    /// Every node knows all other nodes.
+    FullMesh,
+    /// Every node knows only the first node, which acts as a bootstrap node.
+    Star,
+    /// Every node knows only the previous node.
+    Line,
+    /// Node `a` knows node `b` for every `(a, b)` pair.
+    Custom(Vec<(usize, usize)>),
+}
+
+impl Topology {
+    fn known_nodes(&self, node: usize, nodes_number: usize) -> Vec<usize> {
+        match self {
+            Topology::FullMesh => (0..nodes_number).filter(|&other| other != node).collect(),
+            Topology::Star => {
+                if node == 0 {
+                    Vec::new()
+                } else {
+                    vec![0]
+                }
+            }
+            Topology::Line => node.checked_sub(1).into_iter().collect(),
+            Topology::Custom(links) => links
+                .iter()
+                .filter_map(|&(from, to)| (from == node).then_some(to))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct NetworkState {
+    /// Node index by memory port it listens on.
+    nodes_by_port: HashMap<u64, usize>,
+    default_link_conditions: LinkConditions,
+    /// Link conditions by ordered pair of node indexes.
+    link_conditions: HashMap<(usize, usize), LinkConditions>,
+    /// Partition group by node index, nodes from different groups can't communicate. Empty when
+    /// network is not partitioned.
+    partition_groups: HashMap<usize, usize>,
+}
+
+/// Shared state of the simulated network, cheap to clone.
+#[derive(Clone, Default)]
+pub(crate) struct SimulatedNetwork {
+    state: Arc<Mutex<NetworkState>>,
+}
+
+impl SimulatedNetwork {
+    fn register_node(&self, port: u64, node: usize) {
+        self.state.lock().nodes_by_port.insert(port, node);
+    }
+
+    fn node_by_address(&self, address: &Multiaddr) -> Option<usize> {
+        match address.iter().next() {
+            Some(Protocol::Memory(port)) => self.state.lock().nodes_by_port.get(&port).copied(),
+            _ => None,
+        }
+    }
+
+    fn link_conditions(&self, a: usize, b: usize) -> LinkConditions {
+        let state = self.state.lock();
+
+        state
+            .link_conditions
+            .get(&(a.min(b), a.max(b)))
+            .copied()
+            .unwrap_or(state.default_link_conditions)
+    }
+
+    fn set_default_link_conditions(&self, link_conditions: LinkConditions) {
+        self.state.lock().default_link_conditions = link_conditions;
+    }
+
+    fn set_link_conditions(&self, a: usize, b: usize, link_conditions: LinkConditions) {
+        self.state
+            .lock()
+            .link_conditions
+            .insert((a.min(b), a.max(b)), link_conditions);
+    }
+
+    fn is_partitioned(&self, a: usize, b: usize) -> bool {
+        let state = self.state.lock();
+
+        if state.partition_groups.is_empty() {
+            return false;
+        }
+
+        // Nodes that are not mentioned in any group form one more group together
+        let unlisted_group = usize::MAX;
+        state.partition_groups.get(&a).unwrap_or(&unlisted_group)
+            != state.partition_groups.get(&b).unwrap_or(&unlisted_group)
+    }
+
+    fn partition(&self, groups: &[&[usize]]) {
+        self.state.lock().partition_groups = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(group, nodes)| nodes.iter().map(move |&node| (node, group)))
+            .collect();
+    }
+
+    fn heal_partition(&self) {
+        self.state.lock().partition_groups.clear();
+    }
+}
+
+/// Builds node configuration of a simulated node from its index and default configuration.
+type ConfigBuilder<RecordStore> =
+    Box<dyn Fn(usize, Config) -> Config<RecordStore> + Send + Sync + 'static>;
+
+/// Builder of [`Simulator`].
+pub struct SimulatorBuilder<RecordStore = CustomRecordStore> {
+    nodes_number: usize,
+    topology: Topology,
+    link_conditions: LinkConditions,
+    config_builder: ConfigBuilder<RecordStore>,
+}
+
+impl SimulatorBuilder {
+    /// Creates builder of the network with specified number of nodes with default configuration,
+    /// star topology and ideal links.
+    pub fn new(nodes_number: usize) -> Self {
+        Self {
+            nodes_number,
+            topology: Topology::Star,
+            link_conditions: LinkConditions::default(),
+            config_builder: Box::new(|_node, config| config),
+        }
+    }
+}
+
+impl<RecordStore> SimulatorBuilder<RecordStore>
+where
+    RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
+{
+    /// Sets initial connectivity of nodes.
+    pub fn with_topology(mut self, topology: Topology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    /// Sets conditions of all links, can be changed for individual links later with
+    /// [`Simulator::set_link_conditions`].
+    pub fn with_link_conditions(mut self, link_conditions: LinkConditions) -> Self {
+        self.link_conditions = link_conditions;
+        self
+    }
+
+    /// Customizes configuration of nodes (record stores, request handlers, etc.). The builder is
+    /// called with node index and default configuration of the node with keypair, listen address
+    /// and bootstrap addresses already set.
+    pub fn with_node_config<NewRecordStore, F>(
+        self,
+        config_builder: F,
+    ) -> SimulatorBuilder<NewRecordStore>
+    where
+        F: Fn(usize, Config) -> Config<NewRecordStore> + Send + Sync + 'static,
+    {
+        SimulatorBuilder {
+            nodes_number: self.nodes_number,
+            topology: self.topology,
+            link_conditions: self.link_conditions,
+            config_builder: Box::new(config_builder),
+        }
+    }
+
+    /// Creates all nodes and runs them in background tasks, nodes are stopped when simulator is
+    /// dropped.
+    pub async fn build(self) -> Result<Simulator, CreationError> {
+        let network = SimulatedNetwork::default();
+        network.set_default_link_conditions(self.link_conditions);
+
+        let configs = (0..self.nodes_number)
+            .map(|_| Config::with_generated_keypair())
+            .collect::<Vec<_>>();
+        // Addresses are known in advance, so nodes can be bootstrapped from nodes created later
+        let addresses = configs
+            .iter()
+            .enumerate()
+            .map(|(node, config)| {
+                let port = rand::random::<u64>();
+                network.register_node(port, node);
+
+                Multiaddr::empty()
+                    .with(Protocol::Memory(port))
+                    .with(Protocol::P2p(peer_id(&config.keypair).into()))
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = Vec::with_capacity(self.nodes_number);
+        let mut node_runner_handles = Vec::with_capacity(self.nodes_number);
+
+        for (node_index, config) in configs.into_iter().enumerate() {
+            let bootstrap_addresses = self
+                .topology
+                .known_nodes(node_index, self.nodes_number)
+                .into_iter()
+                .map(|known_node| addresses[known_node].clone())
+                .collect();
+            let mut listen_address = addresses[node_index].clone();
+            // Remove `/p2p/<peer_id>` suffix
+            listen_address.pop();
+
+            let config = Config {
+                listen_on: vec![listen_address],
+                allow_non_globals_in_dht: true,
+                networking_parameters_registry: BootstrappedNetworkingParameters::new(
+                    bootstrap_addresses,
+                )
+                .boxed(),
+                // There is no NAT in simulated network
+                autonat: None,
+                ..config
+            };
+
+            let (node, mut node_runner) = create_simulated_node(
+                (self.config_builder)(node_index, config),
+                network.clone(),
+                node_index,
+            )
+            .await?;
+
+            node_runner_handles.push(tokio::spawn(async move {
+                node_runner.run().await;
+            }));
+            nodes.push(node);
+        }
+
+        Ok(Simulator {
+            nodes,
+            addresses,
+            network,
+            node_runner_handles,
+        })
+    }
+}
+
+/// Creates node that is connected to the simulated network only (directly or through relay
+/// servers), private network and upload rate limit options are ignored.
+pub(crate) async fn create_simulated_node<RecordStore>(
+    config: Config<RecordStore>,
+    network: SimulatedNetwork,
+    local_node: usize,
+) -> Result<(Node, NodeRunner<RecordStore>), CreationError>
+where
+    RecordStore: Send + Sync + for<'a> libp2p::kad::store::RecordStore<'a> + 'static,
+{
+    let (relay_transport, relay_client) = relay_transport_and_client(&config);
+    let relay_transport = match relay_transport {
+        Some(relay_transport) => OptionalTransport::some(relay_transport),
+        None => OptionalTransport::none(),
+    };
+
+    let (direct_transport, bandwidth) =
+        BandwidthLogging::new(simulated_transport(network, local_node));
+
+    let noise_keys = noise::Keypair::<noise::X25519Spec>::new()
+        .into_authentic(&config.keypair)
+        .expect("Signing libp2p-noise static DH keypair failed.");
+
+    let transport = relay_transport
+        .or_transport(direct_transport)
+        .upgrade(Version::V1Lazy)
+        .authenticate(NoiseConfig::xx(noise_keys).into_authenticated())
+        .multiplex(SelectUpgrade::new(
+            config.yamux_config.clone(),
+            config.mplex_config.clone(),
+        ))
+        .timeout(config.timeout)
+        .boxed();
+
+    create_with_transport(config, transport, bandwidth, relay_client).await
+}
+
+/// Result of piece retrieval by a simulated node.
+#[derive(Debug)]
+pub struct PieceRetrieval {
+    /// Retrieved piece, `None` if piece was not found.
+    pub piece: Option<Piece>,
+    /// Time spent on retrieval.
+    pub elapsed: Duration,
+}
+
+/// Simulated network of nodes running in the current process.
+pub struct Simulator {
+    nodes: Vec<Node>,
+    addresses: Vec<Multiaddr>,
+    network: SimulatedNetwork,
+    node_runner_handles: Vec<JoinHandle<()>>,
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        for node_runner_handle in &self.node_runner_handles {
+            node_runner_handle.abort();
+        }
+    }
+}
+
+impl Simulator {
+    /// Simulated nodes in the order of their indexes.
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    /// Simulated node with specified index.
+    pub fn node(&self, node_index: usize) -> &Node {
+        &self.nodes[node_index]
+    }
+
+    /// Address of the node with specified index (with `/p2p/<peer_id>` suffix).
+    pub fn address(&self, node_index: usize) -> &Multiaddr {
+        &self.addresses[node_index]
+    }
+
+    /// Changes conditions of the link between two nodes, applies to existing connections too.
+    pub fn set_link_conditions(&self, a: usize, b: usize, link_conditions: LinkConditions) {
+        self.network.set_link_conditions(a, b, link_conditions);
+    }
+
+    /// Splits network into partitions, nodes from different groups can't connect to each other and
+    /// existing connections between them break on the next read or write. Nodes that are not
+    /// mentioned form one more group together.
+    pub fn partition(&self, groups: &[&[usize]]) {
+        self.network.partition(groups);
+    }
+
+    /// Removes network partitions.
+    pub fn heal_partition(&self) {
+        self.network.heal_partition();
+    }
+
+    /// Waits for every node to connect to at least one peer.
+    pub async fn wait_for_connections(&self) -> Result<(), CheckConnectedPeersError> {
+        join_all(
+            self.nodes
+                .iter()
+                .map(|node| node.wait_for_connected_peers()),
+        )
+        .await
+        .into_iter()
+        .collect()
+    }
+
+    /// Retrieves piece by the node with specified index, first from piece cache and then from
+    /// farmers that announced the piece in their sectors.
+    pub async fn retrieve_piece(
+        &self,
+        node_index: usize,
+        piece_index: PieceIndex,
+    ) -> PieceRetrieval {
+        let started = Instant::now();
+        let node = self.node(node_index);
+        let piece_index_hash = PieceIndexHash::from_index(piece_index);
+
+        let piece = match node.get_value(piece_index_hash.to_multihash()).await {
+            Ok(Some(piece)) => Piece::try_from(piece).ok(),
+            Ok(None) => None,
+            Err(error) => {
+                debug!(%node_index, %piece_index, ?error, "Failed to get piece from cache.");
+                None
+            }
+        };
+
+        let piece = match piece {
+            Some(piece) => Some(piece),
+            None => retrieve_piece_from_sector_providers(node, piece_index_hash).await,
+        };
+
+        PieceRetrieval {
+            piece,
+            elapsed: started.elapsed(),
+        }
+    }
+
+    /// Asserts that the node with specified index retrieves the piece within the timeout, returns
+    /// the time it took.
+    ///
+    /// # Panics
+    ///
+    /// Panics if piece was not retrieved within the timeout.
+    pub async fn assert_piece_available(
+        &self,
+        node_index: usize,
+        piece_index: PieceIndex,
+        timeout: Duration,
+    ) -> Duration {
+        let started = Instant::now();
+
+        loop {
+            if self
+                .retrieve_piece(node_index, piece_index)
+                .await
+                .piece
+                .is_some()
+            {
+                return started.elapsed();
+            }
+
+            if started.elapsed() >= timeout {
+                panic!(
+                    "Piece {piece_index} is not available on node {node_index} after {timeout:?}"
+                );
+            }
+
+            tokio::time::sleep(PIECE_RETRIEVAL_RETRY_DELAY).await;
+        }
+    }
+
+    /// Asserts that every node retrieves the piece within the timeout, returns retrieval times in
+    /// the order of node indexes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if piece was not retrieved by any of the nodes within the timeout.
+    pub async fn assert_piece_available_everywhere(
+        &self,
+        piece_index: PieceIndex,
+        timeout: Duration,
+    ) -> Vec<Duration> {
+        join_all(
+            (0..self.nodes.len())
+                .map(|node_index| self.assert_piece_available(node_index, piece_index, timeout)),
+        )
+        .await
+    }
+}
+
+// Retrieves piece from farmers using the providers set stored under sector key.
+async fn retrieve_piece_from_sector_providers(
+    node: &Node,
+    piece_index_hash: PieceIndexHash,
+) -> Option<Piece> {
+    let key = piece_index_hash.to_multihash_by_code(MultihashCode::Sector);
+    let encoded_providers = node.get_value(key).await.ok()??;
+    let providers = BTreeSet::<Vec<u8>>::decode(&mut encoded_providers.as_slice()).ok()?;
+
+    for provider in providers {
+        let provider = match PeerId::from_bytes(&provider) {
+            Ok(provider) => provider,
+            Err(error) => {
+                debug!(?error, "Failed to decode sector provider.");
+                continue;
+            }
+        };
+
+        let request = PieceByHashRequest {
+            key: PieceKey::Sector(piece_index_hash),
+        };
+
+        match node.send_generic_request(provider, request).await {
+            Ok(response) => {
+                if let Some(piece) = response.piece {
+                    return Some(piece);
+                }
+            }
+            Err(error) => {
+                debug!(%provider, ?error, "Piece-by-hash request failed.");
+            }
+        }
+    }
+
+    None
+}
@@ -0,0 +1,377 @@
+use crate::simulator::{LinkConditions, SimulatedNetwork, SimulatorBuilder, Topology};
+use crate::utils::multihash::MultihashCode;
+use crate::{
+    CheckConnectedPeersError, Config, CustomRecordStore, MemoryProviderStorage,
+    MemoryRecordStorage, PieceByHashRequest, PieceByHashRequestHandler, PieceByHashResponse,
+    PieceKey, PieceValidator, PiecesByRangeRequestHandler, PiecesByRangeResponse,
+    PiecesByRangeSyncer, QueryOptions, SendRequestError, ToMultihash, PIECES_BY_RANGE_PAGE_SIZE,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use libp2p::PeerId;
+use parity_scale_codec::Encode;
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant};
+use subspace_core_primitives::{Piece, PieceIndex, PieceIndexHash, U256};
+
+type MemoryRecordStore = CustomRecordStore<MemoryRecordStorage, MemoryProviderStorage>;
+
+fn with_memory_record_store(_node: usize, config: Config) -> Config<MemoryRecordStore> {
+    Config {
+        record_store: CustomRecordStore::new(
+            MemoryRecordStorage::default(),
+            MemoryProviderStorage::default(),
+        ),
+        ..config
+    }
+}
+
+fn with_piece_by_hash_handler(_node: usize, config: Config) -> Config {
+    Config {
+        request_response_protocols: vec![PieceByHashRequestHandler::create(|_request| {
+            Some(PieceByHashResponse { piece: None })
+        })],
+        ..config
+    }
+}
+
+// Node 0 serves the piece from its sectors, while other nodes don't have it.
+fn with_sector_piece(node: usize, config: Config) -> Config<MemoryRecordStore> {
+    let config = with_memory_record_store(node, config);
+
+    Config {
+        request_response_protocols: vec![PieceByHashRequestHandler::create(move |request| {
+            match request.key {
+                PieceKey::Sector(_) if node == 0 => Some(PieceByHashResponse {
+                    piece: Some(Piece::default()),
+                }),
+                _ => Some(PieceByHashResponse { piece: None }),
+            }
+        })],
+        ..config
+    }
+}
+
+// Nodes 0 and 1 serve the same pieces by range, but pieces of node 1 are corrupted.
+fn with_pieces_by_range_handler(node: usize, config: Config) -> Config {
+    let stored_pieces = (0..PIECES_BY_RANGE_PAGE_SIZE as PieceIndex + 10)
+        .map(|piece_index| (PieceIndexHash::from_index(piece_index), piece_index))
+        .collect::<BTreeMap<_, _>>();
+
+    Config {
+        request_response_protocols: vec![PiecesByRangeRequestHandler::create(move |request| {
+            if node > 1 {
+                return None;
+            }
+
+            let pieces_in_range = stored_pieces
+                .range(request.start..=request.end)
+                .take(PIECES_BY_RANGE_PAGE_SIZE + 1)
+                .map(|(piece_index_hash, piece_index)| (*piece_index_hash, *piece_index));
+
+            Some(PiecesByRangeResponse::from_sorted_pieces(
+                pieces_in_range,
+                |piece_index| {
+                    let mut piece = Piece::default();
+                    if node == 1 {
+                        piece[0] = 1;
+                    }
+
+                    Some((piece_index, piece))
+                },
+            ))
+        })],
+        ..config
+    }
+}
+
+struct DefaultPieceValidator;
+
+#[async_trait]
+impl PieceValidator for DefaultPieceValidator {
+    async fn validate_piece(
+        &self,
+        _source_peer_id: PeerId,
+        _piece_index: PieceIndex,
+        piece: &Piece,
+    ) -> bool {
+        piece == &Piece::default()
+    }
+}
+
+#[test]
+fn topology_known_nodes() {
+    assert_eq!(Topology::FullMesh.known_nodes(1, 3), vec![0, 2]);
+    assert_eq!(Topology::Star.known_nodes(0, 3), Vec::<usize>::new());
+    assert_eq!(Topology::Star.known_nodes(2, 3), vec![0]);
+    assert_eq!(Topology::Line.known_nodes(0, 3), Vec::<usize>::new());
+    assert_eq!(Topology::Line.known_nodes(2, 3), vec![1]);
+    assert_eq!(
+        Topology::Custom(vec![(0, 2), (1, 0), (0, 1)]).known_nodes(0, 3),
+        vec![2, 1]
+    );
+}
+
+#[test]
+fn network_partitions() {
+    let network = SimulatedNetwork::default();
+    assert!(!network.is_partitioned(0, 1));
+
+    network.partition(&[&[0, 1], &[2]]);
+    assert!(!network.is_partitioned(0, 1));
+    assert!(network.is_partitioned(1, 2));
+    assert!(network.is_partitioned(0, 3));
+    // Nodes that are not mentioned are in the same group
+    assert!(!network.is_partitioned(3, 4));
+
+    network.heal_partition();
+    assert!(!network.is_partitioned(1, 2));
+}
+
+#[test]
+fn link_conditions_are_symmetric() {
+    let network = SimulatedNetwork::default();
+    let default_conditions = LinkConditions {
+        latency: Duration::from_millis(10),
+        loss_probability: 0.0,
+    };
+    let link_conditions = LinkConditions {
+        latency: Duration::from_millis(50),
+        loss_probability: 0.1,
+    };
+
+    network.set_default_link_conditions(default_conditions);
+    network.set_link_conditions(2, 1, link_conditions);
+
+    assert_eq!(network.link_conditions(1, 2), link_conditions);
+    assert_eq!(network.link_conditions(2, 1), link_conditions);
+    assert_eq!(network.link_conditions(0, 1), default_conditions);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn piece_is_available_across_simulated_network() {
+    let simulator = SimulatorBuilder::new(4)
+        .with_topology(Topology::Line)
+        .with_node_config(with_memory_record_store)
+        .build()
+        .await
+        .unwrap();
+
+    simulator.wait_for_connections().await.unwrap();
+
+    let piece_index = 1;
+    let piece = Piece::default();
+    simulator
+        .node(0)
+        .put_value(
+            PieceIndexHash::from_index(piece_index).to_multihash(),
+            piece.to_vec(),
+        )
+        .await
+        .unwrap();
+
+    simulator
+        .assert_piece_available_everywhere(piece_index, Duration::from_secs(30))
+        .await;
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn piece_is_available_at_scale() {
+    let nodes_number = 30;
+    let simulator = SimulatorBuilder::new(nodes_number)
+        .with_link_conditions(LinkConditions {
+            latency: Duration::from_millis(10),
+            loss_probability: 0.01,
+        })
+        .with_node_config(with_memory_record_store)
+        .build()
+        .await
+        .unwrap();
+
+    simulator.wait_for_connections().await.unwrap();
+
+    let piece_index = 1;
+    let piece = Piece::default();
+    simulator
+        .node(nodes_number - 1)
+        .put_value(
+            PieceIndexHash::from_index(piece_index).to_multihash(),
+            piece.to_vec(),
+        )
+        .await
+        .unwrap();
+
+    let retrieval_times = simulator
+        .assert_piece_available_everywhere(piece_index, Duration::from_secs(60))
+        .await;
+
+    assert_eq!(retrieval_times.len(), nodes_number);
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn piece_is_retrieved_from_sector_providers() {
+    let simulator = SimulatorBuilder::new(3)
+        .with_topology(Topology::FullMesh)
+        .with_node_config(with_sector_piece)
+        .build()
+        .await
+        .unwrap();
+
+    simulator.wait_for_connections().await.unwrap();
+
+    let piece_index = 1;
+    let piece_index_hash = PieceIndexHash::from_index(piece_index);
+
+    // Piece is not cached anywhere yet
+    assert!(simulator
+        .retrieve_piece(2, piece_index)
+        .await
+        .piece
+        .is_none());
+
+    let providers = BTreeSet::from([simulator.node(0).id().to_bytes()]);
+    simulator
+        .node(0)
+        .put_value(
+            piece_index_hash.to_multihash_by_code(MultihashCode::Sector),
+            providers.encode(),
+        )
+        .await
+        .unwrap();
+
+    let retrieval = simulator.retrieve_piece(2, piece_index).await;
+
+    assert_eq!(retrieval.piece, Some(Piece::default()));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn latency_is_applied_in_both_directions() {
+    let latency = Duration::from_millis(200);
+    let simulator = SimulatorBuilder::new(2)
+        .with_link_conditions(LinkConditions {
+            latency,
+            loss_probability: 0.0,
+        })
+        .with_node_config(with_piece_by_hash_handler)
+        .build()
+        .await
+        .unwrap();
+
+    simulator.wait_for_connections().await.unwrap();
+
+    // Requests are sent by both the dialer and the listener of the connection
+    for (requester, responder) in [(0, 1), (1, 0)] {
+        let started = Instant::now();
+        simulator
+            .node(requester)
+            .send_generic_request(
+                simulator.node(responder).id(),
+                PieceByHashRequest {
+                    key: PieceKey::PieceIndex(0),
+                },
+            )
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() >= latency * 2);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn requests_time_out() {
+    let simulator = SimulatorBuilder::new(2)
+        .with_node_config(with_piece_by_hash_handler)
+        .build()
+        .await
+        .unwrap();
+
+    simulator.wait_for_connections().await.unwrap();
+
+    simulator.set_link_conditions(
+        0,
+        1,
+        LinkConditions {
+            latency: Duration::from_secs(5),
+            loss_probability: 0.0,
+        },
+    );
+
+    let started = Instant::now();
+    let result = simulator
+        .node(0)
+        .send_generic_request_with_options(
+            simulator.node(1).id(),
+            PieceByHashRequest {
+                key: PieceKey::PieceIndex(0),
+            },
+            QueryOptions::with_timeout(Duration::from_millis(100)),
+        )
+        .await;
+
+    assert!(matches!(result, Err(SendRequestError::Timeout)));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn waiting_for_connected_peers_times_out() {
+    let simulator = SimulatorBuilder::new(1).build().await.unwrap();
+
+    let result = simulator
+        .node(0)
+        .wait_for_connected_peers_with_options(QueryOptions::with_timeout(Duration::from_millis(
+            100,
+        )))
+        .await;
+
+    assert!(matches!(result, Err(CheckConnectedPeersError::Timeout)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pieces_range_is_synced_from_valid_peers() {
+    let simulator = SimulatorBuilder::new(3)
+        .with_topology(Topology::FullMesh)
+        .with_node_config(with_pieces_by_range_handler)
+        .build()
+        .await
+        .unwrap();
+
+    simulator.wait_for_connections().await.unwrap();
+
+    let syncer = PiecesByRangeSyncer::new(simulator.node(2).clone(), DefaultPieceValidator)
+        .with_concurrency(NonZeroUsize::new(2).unwrap());
+
+    // The first sub-range starts with the peer that returns invalid pieces and has to fall back to
+    // the other one, pages of both sub-ranges are requested one by one.
+    let mut synced_piece_indexes = syncer
+        .sync_range(
+            U256::zero().into(),
+            U256::MAX.into(),
+            vec![simulator.node(1).id(), simulator.node(0).id()],
+        )
+        .map(|(piece_index, piece)| {
+            assert_eq!(piece, Piece::default());
+
+            piece_index
+        })
+        .collect::<Vec<_>>()
+        .await;
+    synced_piece_indexes.sort_unstable();
+
+    assert_eq!(
+        synced_piece_indexes,
+        (0..PIECES_BY_RANGE_PAGE_SIZE as PieceIndex + 10).collect::<Vec<_>>()
+    );
+
+    // Nothing is synced when all peers return invalid pieces.
+    let synced_pieces = syncer
+        .sync_range(
+            U256::zero().into(),
+            U256::MAX.into(),
+            vec![simulator.node(1).id()],
+        )
+        .collect::<Vec<_>>()
+        .await;
+
+    assert!(synced_pieces.is_empty());
+}
@@ -0,0 +1,303 @@
+//! In-memory transport with simulated network conditions.
+
+use crate::simulator::SimulatedNetwork;
+use futures::{future, ready, AsyncRead, AsyncWrite};
+use libp2p::core::transport::memory::Channel;
+use libp2p::core::transport::{Boxed, MemoryTransport};
+use libp2p::core::ConnectedPoint;
+use libp2p::Transport;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::time::{sleep_until, Instant, Sleep};
+
+// Maximum size of a chunk of data read from the underlying channel at once.
+const READ_CHUNK_SIZE: usize = 16 * 1024;
+// Maximum amount of sent data that is delayed before writes are back-pressured.
+const MAX_DELAYED_WRITES_SIZE: usize = 1024 * 1024;
+
+/// Memory transport of a simulated node.
+pub(crate) type SimulatedTransport = Boxed<SimulatedStream>;
+
+/// Creates memory transport for the node with specified index in the simulated network.
+pub(crate) fn simulated_transport(
+    network: SimulatedNetwork,
+    local_node: usize,
+) -> SimulatedTransport {
+    MemoryTransport::default()
+        .and_then(move |stream, endpoint| {
+            // Network conditions are applied on the dialer side only since the listener doesn't
+            // know which node dialed it, dialer delays data in both directions instead.
+            let link = match endpoint {
+                ConnectedPoint::Dialer { address, .. } => {
+                    network.node_by_address(&address).map(|remote_node| Link {
+                        network: network.clone(),
+                        local_node,
+                        remote_node,
+                    })
+                }
+                ConnectedPoint::Listener { .. } => None,
+            };
+
+            future::ready(match &link {
+                Some(link) if link.is_partitioned() => Err(partitioned_error()),
+                _ => Ok(SimulatedStream::new(stream, link)),
+            })
+        })
+        .boxed()
+}
+
+fn partitioned_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::ConnectionReset,
+        "Nodes are in different network partitions",
+    )
+}
+
+/// Connection between two simulated nodes.
+#[derive(Clone)]
+struct Link {
+    network: SimulatedNetwork,
+    local_node: usize,
+    remote_node: usize,
+}
+
+impl Link {
+    fn is_partitioned(&self) -> bool {
+        self.network
+            .is_partitioned(self.local_node, self.remote_node)
+    }
+
+    // Time the next chunk of data is delivered at, data is never delivered before the data that was
+    // sent earlier in the same direction.
+    fn deliver_at(&self, queue: &VecDeque<(Instant, Vec<u8>)>) -> Instant {
+        let conditions = self
+            .network
+            .link_conditions(self.local_node, self.remote_node);
+
+        (Instant::now() + conditions.chunk_delay()).max(
+            queue
+                .back()
+                .map(|(deliver_at, _chunk)| *deliver_at)
+                .unwrap_or_else(Instant::now),
+        )
+    }
+}
+
+/// Memory stream that delays data according to link conditions and breaks when nodes end up in
+/// different partitions.
+///
+/// Latency and losses are applied to both received and sent data, so every round trip is affected
+/// twice.
+#[pin_project::pin_project(project = SimulatedStreamProj)]
+pub(crate) struct SimulatedStream {
+    #[pin]
+    inner: Channel<Vec<u8>>,
+    /// `None` means data is passed through as is.
+    link: Option<Link>,
+    /// Received data along with the time it is delivered at.
+    delayed_reads: VecDeque<(Instant, Vec<u8>)>,
+    /// Sent data along with the time it is written into underlying channel at.
+    delayed_writes: VecDeque<(Instant, Vec<u8>)>,
+    delayed_writes_size: usize,
+    read_buffer: Vec<u8>,
+    inner_closed: bool,
+    read_delay: Option<Pin<Box<Sleep>>>,
+    write_delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl SimulatedStream {
+    fn new(inner: Channel<Vec<u8>>, link: Option<Link>) -> Self {
+        Self {
+            inner,
+            link,
+            delayed_reads: VecDeque::new(),
+            delayed_writes: VecDeque::new(),
+            delayed_writes_size: 0,
+            read_buffer: vec![0; READ_CHUNK_SIZE],
+            inner_closed: false,
+            read_delay: None,
+            write_delay: None,
+        }
+    }
+}
+
+// Waits for the delay to expire, registering current task for wake-up.
+fn poll_delay(
+    delay: &mut Option<Pin<Box<Sleep>>>,
+    deliver_at: Instant,
+    cx: &mut Context<'_>,
+) -> Poll<()> {
+    let delay = delay.get_or_insert_with(|| Box::pin(sleep_until(deliver_at)));
+    delay.as_mut().reset(deliver_at);
+    delay.as_mut().poll(cx)
+}
+
+impl SimulatedStreamProj<'_> {
+    // Writes delayed data whose time has come into underlying channel, resolves once everything
+    // is written.
+    fn poll_write_delayed(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            let deliver_at = match self.delayed_writes.front() {
+                Some((deliver_at, _chunk)) => *deliver_at,
+                None => {
+                    return Poll::Ready(Ok(()));
+                }
+            };
+
+            if deliver_at > Instant::now() {
+                ready!(poll_delay(self.write_delay, deliver_at, cx));
+                continue;
+            }
+
+            self.write_delay.take();
+
+            let (_deliver_at, chunk) = self
+                .delayed_writes
+                .front_mut()
+                .expect("Checked above that queue is not empty; qed");
+            let written = ready!(self.inner.as_mut().poll_write(cx, chunk))?;
+            chunk.drain(..written);
+            *self.delayed_writes_size -= written;
+
+            if chunk.is_empty() {
+                self.delayed_writes.pop_front();
+            }
+        }
+    }
+}
+
+impl AsyncRead for SimulatedStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+
+        let link = match this.link {
+            Some(link) => link.clone(),
+            None => {
+                return this.inner.poll_read(cx, buf);
+            }
+        };
+
+        if link.is_partitioned() {
+            return Poll::Ready(Err(partitioned_error()));
+        }
+
+        // Connection is polled for reading all the time, so delayed writes are driven from here
+        // too in case nobody flushes the stream
+        if let Poll::Ready(Err(error)) = this.poll_write_delayed(cx) {
+            return Poll::Ready(Err(error));
+        }
+
+        // Move everything that is already received into the queue of delayed data
+        while !*this.inner_closed {
+            match this
+                .inner
+                .as_mut()
+                .poll_read(cx, this.read_buffer.as_mut_slice())
+            {
+                Poll::Ready(Ok(0)) => {
+                    *this.inner_closed = true;
+                }
+                Poll::Ready(Ok(read)) => {
+                    let deliver_at = link.deliver_at(this.delayed_reads);
+                    this.delayed_reads
+                        .push_back((deliver_at, this.read_buffer[..read].to_vec()));
+                }
+                Poll::Ready(Err(error)) => {
+                    return Poll::Ready(Err(error));
+                }
+                Poll::Pending => {
+                    break;
+                }
+            }
+        }
+
+        loop {
+            let deliver_at = match this.delayed_reads.front() {
+                Some((deliver_at, _chunk)) => *deliver_at,
+                None => {
+                    return if *this.inner_closed {
+                        Poll::Ready(Ok(0))
+                    } else {
+                        Poll::Pending
+                    };
+                }
+            };
+
+            if deliver_at <= Instant::now() {
+                this.read_delay.take();
+
+                let (_deliver_at, chunk) = this
+                    .delayed_reads
+                    .front_mut()
+                    .expect("Checked above that queue is not empty; qed");
+                let read = chunk.len().min(buf.len());
+                buf[..read].copy_from_slice(&chunk[..read]);
+                chunk.drain(..read);
+
+                if chunk.is_empty() {
+                    this.delayed_reads.pop_front();
+                }
+
+                return Poll::Ready(Ok(read));
+            }
+
+            ready!(poll_delay(this.read_delay, deliver_at, cx));
+        }
+    }
+}
+
+impl AsyncWrite for SimulatedStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut this = self.project();
+
+        let link = match this.link {
+            Some(link) => link.clone(),
+            None => {
+                return this.inner.poll_write(cx, buf);
+            }
+        };
+
+        if link.is_partitioned() {
+            return Poll::Ready(Err(partitioned_error()));
+        }
+
+        if *this.delayed_writes_size >= MAX_DELAYED_WRITES_SIZE {
+            // Current task is woken up once some of the delayed data is written
+            ready!(this.poll_write_delayed(cx))?;
+        }
+
+        let deliver_at = link.deliver_at(this.delayed_writes);
+        this.delayed_writes.push_back((deliver_at, buf.to_vec()));
+        *this.delayed_writes_size += buf.len();
+
+        // Make sure current task is woken up when delayed data needs to be written
+        if let Poll::Ready(Err(error)) = this.poll_write_delayed(cx) {
+            return Poll::Ready(Err(error));
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        ready!(this.poll_write_delayed(cx))?;
+        this.inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut this = self.project();
+        ready!(this.poll_write_delayed(cx))?;
+        this.inner.poll_close(cx)
+    }
+}
@@ -1,10 +1,13 @@
 pub mod multihash;
+pub mod pieces_by_range_syncer;
+pub(crate) mod rate_limiter;
 #[cfg(test)]
 mod tests;
 
 use libp2p::multiaddr::Protocol;
 use libp2p::{Multiaddr, PeerId};
 use std::marker::PhantomData;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::num::NonZeroUsize;
 use tracing::warn;
 
@@ -18,6 +21,41 @@ pub(crate) fn is_global_address_or_dns(addr: &Multiaddr) -> bool {
     }
 }
 
+/// Returns remote IP address of a direct connection when it is global, `None` for relayed
+/// connections, non-IP and non-global addresses.
+pub(crate) fn global_connection_ip(addr: &Multiaddr) -> Option<IpAddr> {
+    if addr
+        .iter()
+        .any(|protocol| matches!(protocol, Protocol::P2pCircuit))
+    {
+        return None;
+    }
+
+    let ip = match addr.iter().next() {
+        Some(Protocol::Ip4(ip)) => IpAddr::V4(ip),
+        Some(Protocol::Ip6(ip)) => IpAddr::V6(ip),
+        _ => {
+            return None;
+        }
+    };
+
+    ip.is_global().then_some(ip)
+}
+
+/// Returns the subnet IP address belongs to: /24 for IPv4 and /64 for IPv6.
+pub(crate) fn ip_subnet(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            IpAddr::V4(Ipv4Addr::new(a, b, c, 0))
+        }
+        IpAddr::V6(ip) => {
+            let [a, b, c, d, ..] = ip.segments();
+            IpAddr::V6(Ipv6Addr::new(a, b, c, d, 0, 0, 0, 0))
+        }
+    }
+}
+
 // Generic collection batching helper.
 #[derive(Clone)]
 pub(crate) struct CollectionBatcher<T: Clone> {
@@ -0,0 +1,268 @@
+//! Client side of the pieces-by-range protocol.
+
+use crate::{Node, PiecesByRangeRequest, PiecesByRangeResponse, PiecesToPlot};
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream};
+use futures::{Stream, StreamExt};
+use libp2p::PeerId;
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use subspace_core_primitives::{Piece, PieceIndex, PieceIndexHash, U256};
+use tracing::{debug, trace, warn};
+
+// Number of sub-ranges synced concurrently by default.
+const DEFAULT_CONCURRENCY: NonZeroUsize =
+    NonZeroUsize::new(4).expect("Manually set value should be > 0");
+
+/// Validates pieces received from other peers.
+#[async_trait]
+pub trait PieceValidator: Send + Sync {
+    /// Returns `true` if the piece is a valid piece with specified index.
+    async fn validate_piece(
+        &self,
+        source_peer_id: PeerId,
+        piece_index: PieceIndex,
+        piece: &Piece,
+    ) -> bool;
+}
+
+/// Downloads pieces of a key-space range from multiple peers using pieces-by-range protocol.
+pub struct PiecesByRangeSyncer<PV> {
+    node: Node,
+    piece_validator: Arc<PV>,
+    concurrency: NonZeroUsize,
+}
+
+impl<PV> PiecesByRangeSyncer<PV>
+where
+    PV: PieceValidator + 'static,
+{
+    /// Creates new syncer, every received piece is checked with provided validator.
+    pub fn new(node: Node, piece_validator: PV) -> Self {
+        Self {
+            node,
+            piece_validator: Arc::new(piece_validator),
+            concurrency: DEFAULT_CONCURRENCY,
+        }
+    }
+
+    /// Sets the number of sub-ranges (and peers) the range is synced from concurrently.
+    pub fn with_concurrency(mut self, concurrency: NonZeroUsize) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Streams validated pieces of the range `start..=end` (sorted by piece index hash within each
+    /// sub-range) from provided peers.
+    ///
+    /// The range is split into sub-ranges that are synced concurrently starting with different
+    /// peers. The next page of a sub-range is only requested after all pieces of the previous page
+    /// were consumed. Peers that failed to respond or returned invalid pieces aren't used for the
+    /// sub-range anymore, sub-range is abandoned when there are no peers left.
+    pub fn sync_range(
+        &self,
+        start: PieceIndexHash,
+        end: PieceIndexHash,
+        peers: Vec<PeerId>,
+    ) -> impl Stream<Item = (PieceIndex, Piece)> + Send + 'static {
+        if peers.is_empty() {
+            warn!("No peers provided to sync pieces range from.");
+
+            return stream::select_all(Vec::new());
+        }
+
+        let sub_range_syncs = split_range(start, end, self.concurrency)
+            .into_iter()
+            .enumerate()
+            .map(|(sub_range_index, (start, end))| {
+                let mut peers = VecDeque::from(peers.clone());
+                peers.rotate_left(sub_range_index % peers.len());
+
+                SubRangeSync {
+                    node: self.node.clone(),
+                    piece_validator: Arc::clone(&self.piece_validator),
+                    cursor: Some(start),
+                    end,
+                    peers,
+                    buffered: VecDeque::new(),
+                }
+                .into_stream()
+            })
+            .collect::<Vec<_>>();
+
+        stream::select_all(sub_range_syncs)
+    }
+}
+
+/// Sync state of a single sub-range.
+struct SubRangeSync<PV> {
+    node: Node,
+    piece_validator: Arc<PV>,
+    /// Start of the next page, `None` when the whole sub-range was received.
+    cursor: Option<PieceIndexHash>,
+    end: PieceIndexHash,
+    /// Peers to request pages from, the first one is used until it fails.
+    peers: VecDeque<PeerId>,
+    /// Pieces of the last page that were not consumed yet.
+    buffered: VecDeque<(PieceIndex, Piece)>,
+}
+
+impl<PV> SubRangeSync<PV>
+where
+    PV: PieceValidator + 'static,
+{
+    fn into_stream(self) -> BoxStream<'static, (PieceIndex, Piece)> {
+        stream::unfold(self, |mut sub_range_sync| async move {
+            let piece = sub_range_sync.next_piece().await?;
+
+            Some((piece, sub_range_sync))
+        })
+        .boxed()
+    }
+
+    async fn next_piece(&mut self) -> Option<(PieceIndex, Piece)> {
+        loop {
+            if let Some(piece) = self.buffered.pop_front() {
+                return Some(piece);
+            }
+
+            let start = self.cursor?;
+            self.request_page(start).await?;
+        }
+    }
+
+    // Requests the page starting at `start` from known peers until one of them returns a valid
+    // response, returns `None` if there are no peers left.
+    async fn request_page(&mut self, start: PieceIndexHash) -> Option<()> {
+        let request = PiecesByRangeRequest {
+            start,
+            end: self.end,
+        };
+
+        while let Some(peer_id) = self.peers.front().copied() {
+            match self.node.send_generic_request(peer_id, request).await {
+                Ok(response) => {
+                    if let Some((pieces, next_piece_index_hash)) =
+                        self.validate_response(peer_id, &request, response).await
+                    {
+                        trace!(
+                            %peer_id,
+                            ?request,
+                            pieces = pieces.len(),
+                            "Pieces-by-range page received."
+                        );
+
+                        self.buffered.extend(pieces);
+                        self.cursor = next_piece_index_hash;
+
+                        return Some(());
+                    }
+
+                    warn!(%peer_id, ?request, "Peer returned invalid pieces-by-range response.");
+                }
+                Err(error) => {
+                    debug!(%peer_id, ?request, %error, "Pieces-by-range request failed.");
+                }
+            }
+
+            self.peers.pop_front();
+        }
+
+        warn!(?request, "No peers left to sync pieces range from.");
+
+        None
+    }
+
+    // Checks that pieces are sorted, belong to the requested page and are valid, the cursor must
+    // move forward.
+    async fn validate_response(
+        &self,
+        peer_id: PeerId,
+        request: &PiecesByRangeRequest,
+        response: PiecesByRangeResponse,
+    ) -> Option<(Vec<(PieceIndex, Piece)>, Option<PieceIndexHash>)> {
+        let PiecesByRangeResponse {
+            pieces:
+                PiecesToPlot {
+                    piece_indexes,
+                    pieces,
+                },
+            next_piece_index_hash,
+        } = response;
+
+        if piece_indexes.len() != pieces.count() {
+            return None;
+        }
+
+        if let Some(next_piece_index_hash) = next_piece_index_hash {
+            if next_piece_index_hash <= request.start || !request.contains(&next_piece_index_hash) {
+                return None;
+            }
+        }
+
+        let mut last_piece_index_hash = None;
+        let mut validated_pieces = Vec::with_capacity(piece_indexes.len());
+
+        for (piece_index, piece) in piece_indexes.into_iter().zip(pieces.as_pieces()) {
+            let piece_index_hash = PieceIndexHash::from_index(piece_index);
+
+            if !request.contains(&piece_index_hash)
+                || last_piece_index_hash >= Some(piece_index_hash)
+                || next_piece_index_hash
+                    .map(|next_piece_index_hash| piece_index_hash >= next_piece_index_hash)
+                    .unwrap_or_default()
+            {
+                return None;
+            }
+            last_piece_index_hash.replace(piece_index_hash);
+
+            let piece = Piece::try_from(piece).ok()?;
+
+            if !self
+                .piece_validator
+                .validate_piece(peer_id, piece_index, &piece)
+                .await
+            {
+                return None;
+            }
+
+            validated_pieces.push((piece_index, piece));
+        }
+
+        Some((validated_pieces, next_piece_index_hash))
+    }
+}
+
+/// Splits the range `start..=end` into at most `parts` adjacent sub-ranges of similar size.
+pub(crate) fn split_range(
+    start: PieceIndexHash,
+    end: PieceIndexHash,
+    parts: NonZeroUsize,
+) -> Vec<(PieceIndexHash, PieceIndexHash)> {
+    let start = U256::from(start);
+    let end = U256::from(end);
+
+    if start > end {
+        return Vec::new();
+    }
+
+    let step = ((end - start) / U256::from(parts.get() as u64)).saturating_add(&U256::one());
+
+    let mut sub_ranges = Vec::with_capacity(parts.get());
+    let mut sub_range_start = start;
+    loop {
+        let sub_range_end = sub_range_start
+            .saturating_add(&(step - U256::one()))
+            .min(end);
+        sub_ranges.push((sub_range_start.into(), sub_range_end.into()));
+
+        if sub_range_end == end {
+            break;
+        }
+
+        sub_range_start = sub_range_end + U256::one();
+    }
+
+    sub_ranges
+}
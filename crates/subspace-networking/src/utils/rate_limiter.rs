@@ -0,0 +1,142 @@
+//! Bandwidth rate limiting primitives.
+
+use futures::{ready, AsyncRead, AsyncWrite};
+use parking_lot::Mutex;
+use std::future::Future;
+use std::io;
+use std::num::NonZeroU32;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::time::Sleep;
+
+// Amount of traffic (expressed as time at the configured rate) allowed to be sent without delay.
+const BURST_DURATION: Duration = Duration::from_secs(1);
+
+/// Rate limiter shared by multiple users (connections, protocols), cheap to clone.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimiter {
+    bytes_per_second: NonZeroU32,
+    /// The moment when all reserved bytes are considered sent at the configured rate.
+    next_free: Arc<Mutex<Instant>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(bytes_per_second: NonZeroU32) -> Self {
+        Self {
+            bytes_per_second,
+            next_free: Arc::new(Mutex::new(Instant::now())),
+        }
+    }
+
+    /// Reserves bandwidth for specified number of bytes, returns for how long user should wait
+    /// before sending more data.
+    pub(crate) fn reserve(&self, bytes: usize) -> Duration {
+        let now = Instant::now();
+        let mut next_free = self.next_free.lock();
+
+        *next_free = self.next_free_after(*next_free, now, bytes);
+
+        next_free
+            .saturating_duration_since(now)
+            .saturating_sub(BURST_DURATION)
+    }
+
+    /// Reserves bandwidth for specified number of bytes only if it is available without waiting,
+    /// which makes the limiter a token bucket with capacity of one second worth of bytes. Returns
+    /// `false` and reserves nothing otherwise.
+    pub(crate) fn try_reserve(&self, bytes: usize) -> bool {
+        let now = Instant::now();
+        let mut next_free = self.next_free.lock();
+
+        let new_next_free = self.next_free_after(*next_free, now, bytes);
+
+        if new_next_free.saturating_duration_since(now) > BURST_DURATION {
+            return false;
+        }
+
+        *next_free = new_next_free;
+
+        true
+    }
+
+    fn next_free_after(&self, next_free: Instant, now: Instant, bytes: usize) -> Instant {
+        next_free.max(now)
+            + Duration::from_secs_f64(bytes as f64 / f64::from(self.bytes_per_second.get()))
+    }
+
+    /// Reserves bandwidth for specified number of bytes and waits if necessary.
+    pub(crate) async fn throttle(&self, bytes: usize) {
+        let delay = self.reserve(bytes);
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Stream wrapper that limits the rate of writes, reads are not affected.
+#[pin_project::pin_project]
+pub(crate) struct RateLimitedStream<S> {
+    #[pin]
+    inner: S,
+    limiter: Option<RateLimiter>,
+    delay: Option<Pin<Box<Sleep>>>,
+}
+
+impl<S> RateLimitedStream<S> {
+    /// Wraps the stream, `None` as limiter leaves the stream unlimited.
+    pub(crate) fn new(inner: S, limiter: Option<RateLimiter>) -> Self {
+        Self {
+            inner,
+            limiter,
+            delay: None,
+        }
+    }
+}
+
+impl<S: AsyncRead> AsyncRead for RateLimitedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.project().inner.poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite> AsyncWrite for RateLimitedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.project();
+
+        if let Some(delay) = this.delay.as_mut() {
+            ready!(delay.as_mut().poll(cx));
+            this.delay.take();
+        }
+
+        let written = ready!(this.inner.poll_write(cx, buf))?;
+
+        if let Some(limiter) = this.limiter.as_ref() {
+            let delay = limiter.reserve(written);
+
+            if !delay.is_zero() {
+                this.delay.replace(Box::pin(tokio::time::sleep(delay)));
+            }
+        }
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.project().inner.poll_close(cx)
+    }
+}
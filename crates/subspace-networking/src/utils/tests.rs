@@ -1,5 +1,12 @@
-use super::CollectionBatcher;
-use std::num::NonZeroUsize;
+use super::pieces_by_range_syncer::split_range;
+use super::rate_limiter::{RateLimitedStream, RateLimiter};
+use super::{global_connection_ip, ip_subnet, CollectionBatcher};
+use futures::AsyncWriteExt;
+use libp2p::Multiaddr;
+use std::net::IpAddr;
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::time::{Duration, Instant};
+use subspace_core_primitives::{PieceIndexHash, U256};
 
 #[test]
 fn test_empty_collection() {
@@ -60,3 +67,145 @@ fn test_batching() {
     assert_eq!(batcher.next_batch(collection.clone()), vec![3, 4, 5, 6]);
     assert_eq!(batcher.next_batch(collection), vec![7, 1, 2, 3]);
 }
+
+#[test]
+fn test_rate_limiter_allows_burst_and_delays_excess() {
+    let rate_limiter = RateLimiter::new(NonZeroU32::new(1000).unwrap());
+
+    // One second worth of traffic is allowed without delay.
+    assert_eq!(rate_limiter.reserve(1000), Duration::ZERO);
+
+    // Excess traffic must wait for the corresponding amount of time.
+    let delay = rate_limiter.reserve(500);
+    assert!(delay > Duration::from_millis(400), "{delay:?}");
+    assert!(delay <= Duration::from_millis(500), "{delay:?}");
+}
+
+#[test]
+fn test_rate_limiter_try_reserve() {
+    let rate_limiter = RateLimiter::new(NonZeroU32::new(1000).unwrap());
+
+    // One second worth of traffic fits into the bucket.
+    assert!(rate_limiter.try_reserve(600));
+    assert!(rate_limiter.try_reserve(400));
+
+    // Reservation over the capacity is refused and doesn't consume anything.
+    assert!(!rate_limiter.try_reserve(100));
+    assert!(!rate_limiter.try_reserve(100));
+
+    // Bucket is refilled over time.
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(rate_limiter.try_reserve(100));
+}
+
+#[tokio::test]
+async fn test_rate_limited_stream_throttles_writes() {
+    let chunk = [0u8; 500];
+
+    let mut stream = RateLimitedStream::new(
+        Vec::new(),
+        Some(RateLimiter::new(NonZeroU32::new(1000).unwrap())),
+    );
+    let started = Instant::now();
+    for _ in 0..4 {
+        stream.write_all(&chunk).await.unwrap();
+    }
+    // First second worth of data is written without delay, the rest waits for bandwidth.
+    assert!(
+        started.elapsed() >= Duration::from_millis(400),
+        "{:?}",
+        started.elapsed()
+    );
+
+    let mut stream = RateLimitedStream::new(Vec::new(), None);
+    let started = Instant::now();
+    for _ in 0..4 {
+        stream.write_all(&chunk).await.unwrap();
+    }
+    // Stream without limiter is not throttled.
+    assert!(
+        started.elapsed() < Duration::from_millis(400),
+        "{:?}",
+        started.elapsed()
+    );
+}
+
+#[test]
+fn test_split_range_covers_whole_range() {
+    let hash = |number: U256| PieceIndexHash::from(number);
+    let start = hash(U256::from(10u64));
+    let end = hash(U256::from(20u64));
+
+    let sub_ranges = split_range(start, end, NonZeroUsize::new(3).unwrap());
+    assert_eq!(
+        sub_ranges,
+        vec![
+            (hash(U256::from(10u64)), hash(U256::from(13u64))),
+            (hash(U256::from(14u64)), hash(U256::from(17u64))),
+            (hash(U256::from(18u64)), hash(U256::from(20u64))),
+        ]
+    );
+
+    // Single element range can't be split any further.
+    assert_eq!(
+        split_range(start, start, NonZeroUsize::new(3).unwrap()),
+        vec![(start, start)]
+    );
+
+    // Whole key space doesn't overflow.
+    let sub_ranges = split_range(
+        hash(U256::zero()),
+        hash(U256::MAX),
+        NonZeroUsize::new(4).unwrap(),
+    );
+    assert_eq!(sub_ranges.len(), 4);
+    assert_eq!(sub_ranges.last().unwrap().1, hash(U256::MAX));
+
+    assert!(split_range(end, start, NonZeroUsize::new(3).unwrap()).is_empty());
+}
+
+#[test]
+fn test_global_connection_ip() {
+    let address: Multiaddr = "/ip4/8.8.8.8/tcp/30333".parse().unwrap();
+    assert_eq!(
+        global_connection_ip(&address),
+        Some("8.8.8.8".parse().unwrap())
+    );
+
+    let address: Multiaddr = "/ip6/2001:4860:4860::8888/tcp/30333".parse().unwrap();
+    assert_eq!(
+        global_connection_ip(&address),
+        Some("2001:4860:4860::8888".parse().unwrap())
+    );
+
+    let address: Multiaddr = "/ip4/127.0.0.1/tcp/30333".parse().unwrap();
+    assert_eq!(global_connection_ip(&address), None);
+
+    let address: Multiaddr = "/ip4/192.168.1.1/tcp/30333".parse().unwrap();
+    assert_eq!(global_connection_ip(&address), None);
+
+    let address: Multiaddr = "/dns/example.com/tcp/30333".parse().unwrap();
+    assert_eq!(global_connection_ip(&address), None);
+
+    let address: Multiaddr =
+        "/ip4/8.8.8.8/tcp/30333/p2p/12D3KooWGBWx9gyUFTVQcKMTenQMSyE2ad9m7c9fpjS4NMjoDien/p2p-circuit"
+            .parse()
+            .unwrap();
+    assert_eq!(global_connection_ip(&address), None);
+}
+
+#[test]
+fn test_ip_subnet() {
+    assert_eq!(
+        ip_subnet("8.8.8.8".parse().unwrap()),
+        "8.8.8.0".parse::<IpAddr>().unwrap()
+    );
+    assert_eq!(
+        ip_subnet("8.8.4.4".parse().unwrap()),
+        "8.8.4.0".parse::<IpAddr>().unwrap()
+    );
+    assert_eq!(
+        ip_subnet("2001:4860:4860:1:2:3:4:8888".parse().unwrap()),
+        "2001:4860:4860:1::".parse::<IpAddr>().unwrap()
+    );
+}
@@ -113,7 +113,7 @@ fn main() -> Result<(), Error> {
                     import_queue,
                     task_manager,
                     ..
-                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
                 Ok((
                     cmd.run(client, import_queue).map_err(Error::SubstrateCli),
                     task_manager,
@@ -128,7 +128,7 @@ fn main() -> Result<(), Error> {
                     client,
                     task_manager,
                     ..
-                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
                 Ok((
                     cmd.run(client, config.database)
                         .map_err(Error::SubstrateCli),
@@ -144,7 +144,7 @@ fn main() -> Result<(), Error> {
                     client,
                     task_manager,
                     ..
-                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
                 Ok((
                     cmd.run(client, config.chain_spec)
                         .map_err(Error::SubstrateCli),
@@ -161,7 +161,7 @@ fn main() -> Result<(), Error> {
                     import_queue,
                     task_manager,
                     ..
-                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
                 Ok((
                     cmd.run(client, import_queue).map_err(Error::SubstrateCli),
                     task_manager,
@@ -177,7 +177,35 @@ fn main() -> Result<(), Error> {
                     import_queue,
                     task_manager,
                     ..
-                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
+                Ok((
+                    cmd.run(client, import_queue).map_err(Error::SubstrateCli),
+                    task_manager,
+                ))
+            })?;
+        }
+        Some(Subcommand::ExportSegments(cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            set_default_ss58_version(&runner.config().chain_spec);
+            runner.async_run(|config| {
+                let PartialComponents {
+                    client,
+                    task_manager,
+                    ..
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
+                Ok((cmd.run(client).map_err(Error::SubstrateCli), task_manager))
+            })?;
+        }
+        Some(Subcommand::ImportBlocksFromSegments(cmd)) => {
+            let runner = cli.create_runner(cmd)?;
+            set_default_ss58_version(&runner.config().chain_spec);
+            runner.async_run(|config| {
+                let PartialComponents {
+                    client,
+                    import_queue,
+                    task_manager,
+                    ..
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
                 Ok((
                     cmd.run(client, import_queue).map_err(Error::SubstrateCli),
                     task_manager,
@@ -242,7 +270,7 @@ fn main() -> Result<(), Error> {
                     backend,
                     task_manager,
                     ..
-                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config, None)?;
                 Ok((
                     cmd.run(client, backend, None).map_err(Error::SubstrateCli),
                     task_manager,
@@ -273,14 +301,18 @@ fn main() -> Result<(), Error> {
                     }
                     BenchmarkCmd::Block(cmd) => {
                         let PartialComponents { client, .. } =
-                            subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                            subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(
+                                &config, None,
+                            )?;
 
                         cmd.run(client)
                     }
                     BenchmarkCmd::Storage(cmd) => {
                         let PartialComponents {
                             client, backend, ..
-                        } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+                        } = subspace_service::new_partial::<RuntimeApi, ExecutorDispatch>(
+                            &config, None,
+                        )?;
                         let db = backend.expose_db();
                         let storage = backend.expose_storage();
 
@@ -366,14 +398,31 @@ fn main() -> Result<(), Error> {
                             keypair: network_keypair,
                             dsn_listen_on: cli.dsn_listen_on,
                             dsn_bootstrap_node: cli.dsn_bootstrap_node,
+                            upload_rate_limit: cli.dsn_upload_rate_limit,
+                            piece_upload_rate_limit: cli.dsn_piece_upload_rate_limit,
+                            enable_mdns: cli.dsn_enable_mdns,
+                            pnet_psk_path: cli.dsn_pnet_psk,
+                            gossipsub_message_signing: cli.dsn_gossipsub_message_signing,
                         })
                     };
 
+                    let kzg_public_parameters = cli
+                        .kzg_public_parameters
+                        .as_ref()
+                        .map(std::fs::read)
+                        .transpose()
+                        .map_err(|error| {
+                            sc_service::Error::Other(format!(
+                                "Failed to read KZG public parameters: {error}"
+                            ))
+                        })?;
+
                     let primary_chain_config = SubspaceConfiguration {
                         base: primary_chain_config,
                         // Secondary node needs slots notifications for bundle production.
                         force_new_slot_notifications: !cli.secondary_chain_args.is_empty(),
                         dsn_config,
+                        kzg_public_parameters,
                     };
 
                     subspace_service::new_full::<RuntimeApi, ExecutorDispatch>(
@@ -0,0 +1,181 @@
+// Copyright (C) 2022 Subspace Labs, Inc.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::import_blocks_from_segments::trusted_records_root;
+use crate::segment_files::{last_segment_file_index, read_segment_file, write_segment_file};
+use clap::Parser;
+use log::info;
+use parity_scale_codec::Encode;
+use sc_cli::{CliConfiguration, SharedParams};
+use sc_client_api::{BlockBackend, HeaderBackend};
+use sp_api::ProvideRuntimeApi;
+use sp_consensus_subspace::{FarmerPublicKey, SubspaceApi};
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::Block as BlockT;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use subspace_archiving::archiver::Archiver;
+use subspace_core_primitives::crypto::kzg::{embedded_public_parameters, Kzg};
+use subspace_core_primitives::objects::BlockObjectMapping;
+use subspace_core_primitives::{BlockNumber, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE};
+
+/// The `export-segments` command used to export archived history of the blockchain as segment
+/// files that can be imported later with `import-blocks-from-segments` command.
+#[derive(Debug, Parser)]
+pub struct ExportSegmentsCmd {
+    /// Directory where segment files will be written, will be created if doesn't exist
+    #[clap(value_name = "DIR")]
+    pub output: PathBuf,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+}
+
+impl ExportSegmentsCmd {
+    /// Run the export-segments command
+    pub async fn run<B, C>(&self, client: Arc<C>) -> sc_cli::Result<()>
+    where
+        C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+        C::Api: SubspaceApi<B, FarmerPublicKey>,
+        B: BlockT,
+    {
+        export_segments(&self.output, client.as_ref()).map_err(Into::into)
+    }
+}
+
+impl CliConfiguration for ExportSegmentsCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+}
+
+/// Archives all finalized blocks (blocks are finalized once they are archived by the node) and
+/// writes produced segments into `output` directory.
+///
+/// Segment files already present in `output` are not produced again, archiving continues from the
+/// state described by the root block of the last of them.
+fn export_segments<B, C>(output: &Path, client: &C) -> Result<(), sc_service::Error>
+where
+    C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B>,
+    C::Api: SubspaceApi<B, FarmerPublicKey>,
+    B: BlockT,
+{
+    fs::create_dir_all(output)?;
+
+    let kzg = Kzg::new(embedded_public_parameters());
+    let pieces_in_segment = RECORDED_HISTORY_SEGMENT_SIZE / RECORD_SIZE * 2;
+
+    let (mut archiver, first_block_number) = match last_segment_file_index(output)? {
+        Some(segment_index) => {
+            let (last_root_block, _pieces) =
+                read_segment_file(output, segment_index, pieces_in_segment)
+                    .map_err(|error| {
+                        sc_service::Error::Other(format!(
+                            "Failed to read segment file for segment {segment_index}: {error}"
+                        ))
+                    })?
+                    .expect("Segment file was just found in the directory; qed");
+
+            if let Some(records_root) = trusted_records_root(client, segment_index)? {
+                if records_root != last_root_block.records_root() {
+                    return Err(sc_service::Error::Other(format!(
+                        "Segment file for segment {segment_index} doesn't match records root in \
+                        the blockchain, existing segment files belong to a different chain"
+                    )));
+                }
+            }
+
+            let last_archived_block_number = last_root_block.last_archived_block().number;
+            let last_archived_block = client
+                .block(&BlockId::Number(last_archived_block_number.into()))?
+                .ok_or_else(|| {
+                    sc_service::Error::Other(format!(
+                        "Last archived block {last_archived_block_number} of segment \
+                        {segment_index} not found"
+                    ))
+                })?;
+
+            info!(
+                "Continuing export after segment {} (last archived block {})",
+                segment_index, last_archived_block_number
+            );
+
+            let archiver = Archiver::with_initial_state(
+                RECORD_SIZE,
+                RECORDED_HISTORY_SEGMENT_SIZE,
+                kzg,
+                last_root_block,
+                &last_archived_block.encode(),
+                BlockObjectMapping::default(),
+            )
+            .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+
+            (archiver, last_archived_block_number + 1)
+        }
+        None => {
+            let archiver = Archiver::new(RECORD_SIZE, RECORDED_HISTORY_SEGMENT_SIZE, kzg)
+                .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+
+            (archiver, 0)
+        }
+    };
+
+    let finalized_block_number = client.info().finalized_number;
+    let finalized_block_number =
+        TryInto::<BlockNumber>::try_into(finalized_block_number).map_err(|_| {
+            sc_service::Error::Other(format!(
+                "Finalized block number {finalized_block_number} can't be converted into \
+                BlockNumber"
+            ))
+        })?;
+
+    let mut exported_segments = 0;
+
+    for block_number in first_block_number..=finalized_block_number {
+        let block = client
+            .block(&BlockId::Number(block_number.into()))?
+            .ok_or_else(|| {
+                sc_service::Error::Other(format!("Finalized block {block_number} not found"))
+            })?;
+
+        // Object mappings do not affect archived segments, hence they are not needed here
+        for archived_segment in archiver.add_block(block.encode(), BlockObjectMapping::default()) {
+            write_segment_file(
+                output,
+                &archived_segment.root_block,
+                &archived_segment.pieces,
+            )
+            .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+
+            exported_segments += 1;
+
+            info!(
+                "Exported segment {} (last archived block {})",
+                archived_segment.root_block.segment_index(),
+                archived_segment.root_block.last_archived_block().number
+            );
+        }
+    }
+
+    info!(
+        "🎉 Exported {} segments up to finalized block #{}, exiting",
+        exported_segments, finalized_block_number
+    );
+
+    Ok(())
+}
@@ -15,26 +15,37 @@
 // along with this program. If not, see <https://www.gnu.org/licenses/>.
 
 use clap::Parser;
-use futures::stream::FuturesOrdered;
-use futures::StreamExt;
-use log::info;
+use futures::stream::FuturesUnordered;
+use futures::{stream, StreamExt};
+use log::{debug, info};
 use parity_scale_codec::Encode;
 use sc_cli::{CliConfiguration, ImportParams, SharedParams};
 use sc_client_api::{BlockBackend, HeaderBackend};
 use sc_consensus::{BlockImportError, BlockImportStatus, IncomingBlock, Link};
 use sc_service::ImportQueue;
+use sp_api::ProvideRuntimeApi;
 use sp_consensus::BlockOrigin;
+use sp_consensus_subspace::{FarmerPublicKey, SubspaceApi};
 use sp_runtime::generic::BlockId;
 use sp_runtime::traits::{Block as BlockT, Header, NumberFor};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::task::Poll;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use subspace_archiving::reconstructor::Reconstructor;
-use subspace_core_primitives::{Piece, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE};
+use subspace_core_primitives::{
+    BlockNumber, Piece, PieceIndex, SegmentIndex, PIECES_IN_SEGMENT, RECORDED_HISTORY_SEGMENT_SIZE,
+    RECORD_SIZE,
+};
 use subspace_networking::libp2p::Multiaddr;
 use subspace_networking::utils::multihash;
-use subspace_networking::{BootstrappedNetworkingParameters, Config};
+use subspace_networking::{BootstrappedNetworkingParameters, Config, KademliaMode, Node};
 
-type PieceIndex = u64;
+/// Delay before the first retry of piece retrieval, doubled with every following retry
+const PIECE_RETRY_INITIAL_DELAY: Duration = Duration::from_secs(1);
+/// Upper bound for the delay between piece retrieval retries
+const PIECE_RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
 
 /// The `import-blocks-from-network` command used to import blocks from Subspace Network DSN.
 #[derive(Debug, Parser)]
@@ -43,6 +54,21 @@ pub struct ImportBlocksFromDsnCmd {
     #[clap(long)]
     pub bootstrap_node: Vec<Multiaddr>,
 
+    /// Number of segments to download concurrently ahead of the one being imported
+    #[clap(long, default_value_t = 4)]
+    pub max_concurrent_segments: usize,
+
+    /// Number of times retrieval of each individual piece is retried before falling back to
+    /// other pieces of the segment
+    #[clap(long, default_value_t = 3)]
+    pub piece_retries: u16,
+
+    /// Index of the last segment to import, also used to estimate remaining time of the import.
+    ///
+    /// Import continues until no more segments are found in DSN if not specified.
+    #[clap(long)]
+    pub last_segment_index: Option<SegmentIndex>,
+
     /// The default number of 64KB pages to ever allocate for Wasm execution.
     ///
     /// Don't alter this unless you know what you're doing.
@@ -62,13 +88,24 @@ impl ImportBlocksFromDsnCmd {
     /// Run the import-blocks command
     pub async fn run<B, C, IQ>(&self, client: Arc<C>, import_queue: IQ) -> sc_cli::Result<()>
     where
-        C: HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+        C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+        C::Api: SubspaceApi<B, FarmerPublicKey>,
         B: BlockT + for<'de> serde::Deserialize<'de>,
         IQ: sc_service::ImportQueue<B> + 'static,
     {
-        import_blocks(self.bootstrap_node.clone(), client, import_queue, false)
-            .await
-            .map_err(Into::into)
+        import_blocks(
+            self.bootstrap_node.clone(),
+            DownloadOptions {
+                max_concurrent_segments: self.max_concurrent_segments.max(1),
+                piece_retries: self.piece_retries,
+                last_segment_index: self.last_segment_index,
+            },
+            client,
+            import_queue,
+            false,
+        )
+        .await
+        .map_err(Into::into)
     }
 }
 
@@ -82,18 +119,90 @@ impl CliConfiguration for ImportBlocksFromDsnCmd {
     }
 }
 
+/// Options of segments downloading from DSN
+#[derive(Debug, Copy, Clone)]
+struct DownloadOptions {
+    max_concurrent_segments: usize,
+    piece_retries: u16,
+    last_segment_index: Option<SegmentIndex>,
+}
+
+/// Aborts Tokio task on drop, so that segment downloads don't outlive the import
+struct AbortingJoinHandle<T>(tokio::task::JoinHandle<T>);
+
+impl<T> Drop for AbortingJoinHandle<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+impl<T> Future for AbortingJoinHandle<T> {
+    type Output = Result<T, tokio::task::JoinError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx)
+    }
+}
+
+/// Progress of the import, reported after every imported segment
+struct ImportProgress {
+    started_at: Instant,
+    last_segment_index: Option<SegmentIndex>,
+    imported_segments: u64,
+}
+
+impl ImportProgress {
+    fn new(last_segment_index: Option<SegmentIndex>) -> Self {
+        Self {
+            started_at: Instant::now(),
+            last_segment_index,
+            imported_segments: 0,
+        }
+    }
+
+    fn segment_imported(&mut self, segment_index: SegmentIndex, imported_blocks: u64) {
+        self.imported_segments += 1;
+
+        let elapsed = self.started_at.elapsed().as_secs_f64();
+        let segments_per_second = self.imported_segments as f64 / elapsed;
+        let blocks_per_second = imported_blocks as f64 / elapsed;
+
+        let maybe_eta = self.last_segment_index.and_then(|last_segment_index| {
+            let remaining_segments = last_segment_index.saturating_sub(segment_index);
+            (segments_per_second > 0.0).then(|| {
+                Duration::from_secs((remaining_segments as f64 / segments_per_second) as u64)
+            })
+        });
+
+        match maybe_eta {
+            Some(eta) => {
+                info!(
+                    "Imported segment {}, {} blocks total ({:.2} segments/s, {:.2} blocks/s), ETA {:?}",
+                    segment_index, imported_blocks, segments_per_second, blocks_per_second, eta
+                );
+            }
+            None => {
+                info!(
+                    "Imported segment {}, {} blocks total ({:.2} segments/s, {:.2} blocks/s)",
+                    segment_index, imported_blocks, segments_per_second, blocks_per_second
+                );
+            }
+        }
+    }
+}
+
 struct WaitLinkError<B: BlockT> {
     error: BlockImportError,
     hash: B::Hash,
 }
 
-struct WaitLink<B: BlockT> {
+pub(crate) struct WaitLink<B: BlockT> {
     imported_blocks: u64,
     error: Option<WaitLinkError<B>>,
 }
 
 impl<B: BlockT> WaitLink<B> {
-    fn new() -> Self {
+    pub(crate) fn new() -> Self {
         Self {
             imported_blocks: 0,
             error: None,
@@ -111,7 +220,6 @@ impl<B: BlockT> Link<B> for WaitLink<B> {
             B::Hash,
         )>,
     ) {
-        println!("Imported {imported} blocks");
         self.imported_blocks += imported as u64;
 
         for result in results {
@@ -124,14 +232,19 @@ impl<B: BlockT> Link<B> for WaitLink<B> {
 }
 
 /// Starts the process of importing blocks.
+///
+/// Blocks from segments whose root blocks are already known to the client are not downloaded
+/// again, which allows to resume interrupted import.
 async fn import_blocks<B, IQ, C>(
     bootstrap_nodes: Vec<Multiaddr>,
+    download_options: DownloadOptions,
     client: Arc<C>,
     mut import_queue: IQ,
     force: bool,
 ) -> Result<(), sc_service::Error>
 where
-    C: HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+    C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+    C::Api: SubspaceApi<B, FarmerPublicKey>,
     B: BlockT + for<'de> serde::Deserialize<'de>,
     IQ: ImportQueue<B> + 'static,
 {
@@ -139,6 +252,8 @@ where
         networking_parameters_registry: BootstrappedNetworkingParameters::new(bootstrap_nodes)
             .boxed(),
         allow_non_globals_in_dht: true,
+        // Short-lived node, other peers should not add it to their routing tables
+        kademlia_mode: KademliaMode::Client,
         ..Config::with_generated_keypair()
     })
     .await
@@ -154,115 +269,283 @@ where
     let mut reconstructor = Reconstructor::new(RECORD_SIZE, RECORDED_HISTORY_SEGMENT_SIZE)
         .map_err(|error| sc_service::Error::Other(error.to_string()))?;
 
-    let pieces_in_segment = u64::from(RECORDED_HISTORY_SEGMENT_SIZE / RECORD_SIZE * 2);
+    let first_segment_index = first_unknown_segment_index(client.as_ref())?;
+    if first_segment_index > 0 {
+        info!(
+            "Root blocks of {} segments are already known, skipping them",
+            first_segment_index
+        );
+    }
 
-    // TODO: Check latest known root block on chain and skip downloading of corresponding segments
-    // Collection is intentional to make sure downloading starts right away and not lazily
-    for segment_index in 0.. {
-        let source_pieces_results = (0..pieces_in_segment / 2)
-            .map(|piece_position| {
-                let piece_index: PieceIndex = segment_index * pieces_in_segment + piece_position;
+    let DownloadOptions {
+        max_concurrent_segments,
+        piece_retries,
+        last_segment_index,
+    } = download_options;
+    let mut progress = ImportProgress::new(last_segment_index);
+
+    // Downloads are spawned as separate tasks so that upcoming segments are being downloaded while
+    // blocks of the current segment are imported, downloads that are still in progress are aborted
+    // when import stops early
+    let mut segments = stream::iter(first_segment_index..=last_segment_index.unwrap_or(u64::MAX))
+        .map(|segment_index| {
+            let node = node.clone();
+
+            AbortingJoinHandle(tokio::spawn(async move {
+                let maybe_pieces = download_segment(&node, segment_index, piece_retries).await;
+
+                (segment_index, maybe_pieces)
+            }))
+        })
+        .buffered(max_concurrent_segments);
 
-                node.get_value(multihash::create_multihash_by_piece_index(piece_index))
-            })
-            .collect::<FuturesOrdered<_>>()
-            .collect::<Vec<_>>()
-            .await;
+    while let Some(download_result) = segments.next().await {
+        let (segment_index, maybe_pieces) =
+            download_result.map_err(|error| sc_service::Error::Other(error.to_string()))?;
 
-        let mut pieces = vec![None::<Piece>; pieces_in_segment as usize];
-        let mut found_one_piece = false;
+        let pieces = match maybe_pieces? {
+            Some(pieces) => pieces,
+            None => {
+                info!("Found no pieces for segment index {}", segment_index);
+                break;
+            }
+        };
 
-        for (source_piece_result, piece) in source_pieces_results.into_iter().zip(pieces.iter_mut())
-        {
-            let maybe_piece =
-                source_piece_result.map_err(|error| sc_service::Error::Other(error.to_string()))?;
+        let reconstructed_contents = reconstructor
+            .add_segment(pieces.as_ref())
+            .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+
+        import_reconstructed_blocks(
+            reconstructed_contents.blocks,
+            client.as_ref(),
+            &mut import_queue,
+            &mut link,
+            best_block_number,
+            &mut imported_blocks,
+            force,
+        )
+        .await?;
+
+        progress.segment_imported(segment_index, imported_blocks);
+    }
+
+    info!(
+        "🎉 Imported {} blocks, best #{}, exiting",
+        imported_blocks,
+        client.info().best_number
+    );
+
+    Ok(())
+}
 
-            if let Some(piece_vec) = maybe_piece {
-                found_one_piece = true;
+/// Index of the first segment whose root block is not known to the client yet, all blocks from
+/// earlier segments were already imported.
+fn first_unknown_segment_index<B, C>(client: &C) -> Result<SegmentIndex, sc_service::Error>
+where
+    B: BlockT,
+    C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+    C::Api: SubspaceApi<B, FarmerPublicKey>,
+{
+    let best_block_id = BlockId::Hash(client.info().best_hash);
+    let runtime_api = client.runtime_api();
+
+    let mut segment_index = 0;
+    while runtime_api
+        .records_root(&best_block_id, segment_index)
+        .map_err(|error| sc_service::Error::Other(error.to_string()))?
+        .is_some()
+    {
+        segment_index += 1;
+    }
 
-                piece.replace(piece_vec.as_slice().try_into()?);
+    Ok(segment_index)
+}
+
+/// Downloads enough pieces of the segment for its reconstruction (any half of all pieces).
+///
+/// Source pieces are requested first since they don't need erasure decoding, parity pieces are
+/// only requested in place of source pieces that could not be retrieved. Returns `Ok(None)` if none
+/// of the source pieces were found, which means that segment is not archived yet.
+async fn download_segment(
+    node: &Node,
+    segment_index: SegmentIndex,
+    piece_retries: u16,
+) -> Result<Option<Vec<Option<Piece>>>, sc_service::Error> {
+    let first_piece_index = segment_index * PieceIndex::from(PIECES_IN_SEGMENT);
+    let required_pieces = PIECES_IN_SEGMENT / 2;
+
+    let mut pieces = vec![None::<Piece>; PIECES_IN_SEGMENT as usize];
+    let mut pieces_found = 0;
+    let mut pieces_missing = 0;
+
+    let request_piece = |position: u32| async move {
+        let piece_index = first_piece_index + PieceIndex::from(position);
+
+        (position, get_piece(node, piece_index, piece_retries).await)
+    };
+
+    let mut piece_requests = (0..required_pieces)
+        .map(request_piece)
+        .collect::<FuturesUnordered<_>>();
+    let mut next_position = required_pieces;
+
+    while let Some((position, maybe_piece)) = piece_requests.next().await {
+        match maybe_piece {
+            Some(piece) => {
+                pieces[position as usize].replace(piece);
+                pieces_found += 1;
+
+                if pieces_found == required_pieces {
+                    break;
+                }
             }
-        }
+            None => {
+                pieces_missing += 1;
+
+                if pieces_found == 0 && pieces_missing == required_pieces {
+                    return Ok(None);
+                }
 
-        if !found_one_piece {
-            info!("Found no pieces for segment index {}", segment_index);
-            break;
+                if next_position < PIECES_IN_SEGMENT {
+                    let position = next_position;
+                    next_position += 1;
+
+                    piece_requests.push(request_piece(position));
+                }
+            }
         }
+    }
 
-        let reconstructed_contents = reconstructor
-            .add_segment(pieces.as_ref())
-            .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+    if pieces_found < required_pieces {
+        return Err(sc_service::Error::Other(format!(
+            "Only {pieces_found} out of {required_pieces} required pieces of segment \
+            {segment_index} were found"
+        )));
+    }
 
-        for (block_number, block_bytes) in reconstructed_contents.blocks {
-            {
-                let block_number = block_number.into();
-                if block_number <= best_block_number {
-                    if block_number == 0u32.into() {
-                        let block = client
-                            .block(&BlockId::Number(block_number))?
-                            .expect("Block before best block number must always be found; qed");
-
-                        if block.encode() != block_bytes {
-                            return Err(sc_service::Error::Other(
-                                "Wrong genesis block, block import failed".to_string(),
-                            ));
-                        }
-                    }
+    Ok(Some(pieces))
+}
 
-                    continue;
+/// Get piece from DSN, retrying up to `retries` times with exponential backoff if piece is not
+/// found or invalid.
+async fn get_piece(node: &Node, piece_index: PieceIndex, retries: u16) -> Option<Piece> {
+    let mut retry_delay = PIECE_RETRY_INITIAL_DELAY;
+
+    for attempt in 0..=retries {
+        if attempt > 0 {
+            tokio::time::sleep(retry_delay).await;
+            retry_delay = (retry_delay * 2).min(PIECE_RETRY_MAX_DELAY);
+        }
+
+        match node
+            .get_value(multihash::create_multihash_by_piece_index(piece_index))
+            .await
+        {
+            Ok(Some(piece_bytes)) => match Piece::try_from(piece_bytes.as_slice()) {
+                Ok(piece) => {
+                    return Some(piece);
+                }
+                Err(error) => {
+                    debug!(
+                        "Invalid piece {} received on attempt {}: {}",
+                        piece_index, attempt, error
+                    );
                 }
+            },
+            Ok(None) => {
+                debug!("Piece {} not found on attempt {}", piece_index, attempt);
             }
-
-            let block = B::decode(&mut block_bytes.as_slice())
-                .map_err(|error| sc_service::Error::Other(error.to_string()))?;
-
-            let (header, extrinsics) = block.deconstruct();
-            let hash = header.hash();
-
-            // import queue handles verification and importing it into the client.
-            import_queue.import_blocks(
-                BlockOrigin::NetworkInitialSync,
-                vec![IncomingBlock::<B> {
-                    hash,
-                    header: Some(header),
-                    body: Some(extrinsics),
-                    indexed_body: None,
-                    justifications: None,
-                    origin: None,
-                    allow_missing_state: false,
-                    import_existing: force,
-                    state: None,
-                    skip_execution: false,
-                }],
-            );
-
-            imported_blocks += 1;
-
-            if imported_blocks % 1000 == 0 {
-                info!("Imported block {}", block_number);
+            Err(error) => {
+                debug!(
+                    "Failed to get piece {} on attempt {}: {}",
+                    piece_index, attempt, error
+                );
             }
         }
+    }
 
-        futures::future::poll_fn(|ctx| {
-            import_queue.poll_actions(ctx, &mut link);
+    None
+}
 
-            Poll::Ready(())
-        })
-        .await;
+/// Sends blocks reconstructed from a segment that are not yet known to the client to the import
+/// queue and waits for them to be processed.
+pub(crate) async fn import_reconstructed_blocks<B, IQ, C>(
+    blocks: Vec<(BlockNumber, Vec<u8>)>,
+    client: &C,
+    import_queue: &mut IQ,
+    link: &mut WaitLink<B>,
+    best_block_number: NumberFor<B>,
+    imported_blocks: &mut u64,
+    force: bool,
+) -> Result<(), sc_service::Error>
+where
+    C: HeaderBackend<B> + BlockBackend<B>,
+    B: BlockT,
+    IQ: ImportQueue<B>,
+{
+    for (block_number, block_bytes) in blocks {
+        {
+            let block_number = block_number.into();
+            if block_number <= best_block_number {
+                if block_number == 0u32.into() {
+                    let block = client
+                        .block(&BlockId::Number(block_number))?
+                        .expect("Block before best block number must always be found; qed");
+
+                    if block.encode() != block_bytes {
+                        return Err(sc_service::Error::Other(
+                            "Wrong genesis block, block import failed".to_string(),
+                        ));
+                    }
+                }
 
-        if let Some(WaitLinkError { error, hash }) = &link.error {
-            return Err(sc_service::Error::Other(format!(
-                "Stopping block import after #{} blocks on {} because of an error: {}",
-                link.imported_blocks, hash, error
-            )));
+                continue;
+            }
+        }
+
+        let block = B::decode(&mut block_bytes.as_slice())
+            .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+
+        let (header, extrinsics) = block.deconstruct();
+        let hash = header.hash();
+
+        // import queue handles verification and importing it into the client.
+        import_queue.import_blocks(
+            BlockOrigin::NetworkInitialSync,
+            vec![IncomingBlock::<B> {
+                hash,
+                header: Some(header),
+                body: Some(extrinsics),
+                indexed_body: None,
+                justifications: None,
+                origin: None,
+                allow_missing_state: false,
+                import_existing: force,
+                state: None,
+                skip_execution: false,
+            }],
+        );
+
+        *imported_blocks += 1;
+
+        if *imported_blocks % 1000 == 0 {
+            info!("Imported block {}", block_number);
         }
     }
 
-    info!(
-        "🎉 Imported {} blocks, best #{}, exiting",
-        imported_blocks,
-        client.info().best_number
-    );
+    futures::future::poll_fn(|ctx| {
+        import_queue.poll_actions(ctx, link);
+
+        Poll::Ready(())
+    })
+    .await;
+
+    if let Some(WaitLinkError { error, hash }) = &link.error {
+        return Err(sc_service::Error::Other(format!(
+            "Stopping block import after #{} blocks on {} because of an error: {}",
+            link.imported_blocks, hash, error
+        )));
+    }
 
     Ok(())
 }
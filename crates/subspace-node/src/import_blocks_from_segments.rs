@@ -0,0 +1,229 @@
+// Copyright (C) 2022 Subspace Labs, Inc.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+use crate::import_blocks_from_dsn::{import_reconstructed_blocks, WaitLink};
+use crate::segment_files::read_segment_file;
+use clap::Parser;
+use log::info;
+use sc_cli::{CliConfiguration, ImportParams, SharedParams};
+use sc_client_api::{BlockBackend, HeaderBackend};
+use sc_service::ImportQueue;
+use sp_api::ProvideRuntimeApi;
+use sp_consensus_subspace::{FarmerPublicKey, SubspaceApi};
+use sp_runtime::generic::BlockId;
+use sp_runtime::traits::{Block as BlockT, NumberFor};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use subspace_archiving::archiver::is_pieces_valid_batch;
+use subspace_archiving::reconstructor::Reconstructor;
+use subspace_core_primitives::crypto::kzg::{embedded_public_parameters, Kzg};
+use subspace_core_primitives::{
+    Piece, RecordsRoot, RootBlock, SegmentIndex, RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
+};
+
+/// The `import-blocks-from-segments` command used to import blocks from segment files produced by
+/// `export-segments` command.
+#[derive(Debug, Parser)]
+pub struct ImportBlocksFromSegmentsCmd {
+    /// Directory with segment files
+    #[clap(value_name = "DIR")]
+    pub input: PathBuf,
+
+    /// The default number of 64KB pages to ever allocate for Wasm execution.
+    ///
+    /// Don't alter this unless you know what you're doing.
+    #[clap(long, value_name = "COUNT")]
+    pub default_heap_pages: Option<u32>,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub shared_params: SharedParams,
+
+    #[allow(missing_docs)]
+    #[clap(flatten)]
+    pub import_params: ImportParams,
+}
+
+impl ImportBlocksFromSegmentsCmd {
+    /// Run the import-blocks-from-segments command
+    pub async fn run<B, C, IQ>(&self, client: Arc<C>, import_queue: IQ) -> sc_cli::Result<()>
+    where
+        C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+        C::Api: SubspaceApi<B, FarmerPublicKey>,
+        B: BlockT,
+        IQ: ImportQueue<B> + 'static,
+    {
+        import_blocks(&self.input, client, import_queue, false)
+            .await
+            .map_err(Into::into)
+    }
+}
+
+impl CliConfiguration for ImportBlocksFromSegmentsCmd {
+    fn shared_params(&self) -> &SharedParams {
+        &self.shared_params
+    }
+
+    fn import_params(&self) -> Option<&ImportParams> {
+        Some(&self.import_params)
+    }
+}
+
+/// Records root of the segment according to the blockchain known to the client, `Ok(None)` if root
+/// block of the segment is not included in the blockchain yet.
+pub(crate) fn trusted_records_root<B, C>(
+    client: &C,
+    segment_index: SegmentIndex,
+) -> Result<Option<RecordsRoot>, sc_service::Error>
+where
+    B: BlockT,
+    C: ProvideRuntimeApi<B> + HeaderBackend<B>,
+    C::Api: SubspaceApi<B, FarmerPublicKey>,
+{
+    client
+        .runtime_api()
+        .records_root(&BlockId::Hash(client.info().best_hash), segment_index)
+        .map_err(|error| sc_service::Error::Other(error.to_string()))
+}
+
+/// Starts the process of importing blocks from segment files.
+///
+/// Segment files are not trusted on their own: root block of every segment must either match
+/// records root in the blockchain known to the client or follow the previous segment that was
+/// already checked. The very first segment is anchored by the genesis block of the client, which
+/// is the first block reconstructed from it.
+///
+/// Segments that only contain blocks already known to the client are skipped, which allows to
+/// resume interrupted import.
+async fn import_blocks<B, IQ, C>(
+    input: &Path,
+    client: Arc<C>,
+    mut import_queue: IQ,
+    force: bool,
+) -> Result<(), sc_service::Error>
+where
+    C: ProvideRuntimeApi<B> + HeaderBackend<B> + BlockBackend<B> + Send + Sync + 'static,
+    C::Api: SubspaceApi<B, FarmerPublicKey>,
+    B: BlockT,
+    IQ: ImportQueue<B> + 'static,
+{
+    let best_block_number = client.info().best_number;
+    let mut link = WaitLink::new();
+    let mut imported_blocks = 0;
+    let mut reconstructor = Reconstructor::new(RECORD_SIZE, RECORDED_HISTORY_SEGMENT_SIZE)
+        .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+    let kzg = Kzg::new(embedded_public_parameters());
+
+    let pieces_in_segment = RECORDED_HISTORY_SEGMENT_SIZE / RECORD_SIZE * 2;
+
+    let mut prev_root_block = None::<RootBlock>;
+
+    for segment_index in 0.. {
+        let (root_block, pieces) = match read_segment_file(input, segment_index, pieces_in_segment)
+            .map_err(|error| {
+                sc_service::Error::Other(format!(
+                    "Failed to read segment file for segment {segment_index}: {error}"
+                ))
+            })? {
+            Some(segment) => segment,
+            None => {
+                info!("Found no segment file for segment index {}", segment_index);
+                break;
+            }
+        };
+
+        if root_block.segment_index() != segment_index {
+            return Err(sc_service::Error::Other(format!(
+                "Segment file for segment {segment_index} contains segment {}",
+                root_block.segment_index()
+            )));
+        }
+
+        if let Some(prev_root_block) = &prev_root_block {
+            if root_block.prev_root_block_hash() != prev_root_block.hash() {
+                return Err(sc_service::Error::Other(format!(
+                    "Root block of segment {segment_index} doesn't follow previous segment"
+                )));
+            }
+        }
+        prev_root_block.replace(root_block);
+
+        // Best block moves forward during import, so records roots of segments imported earlier
+        // become available too
+        if let Some(records_root) = trusted_records_root(client.as_ref(), segment_index)? {
+            if records_root != root_block.records_root() {
+                return Err(sc_service::Error::Other(format!(
+                    "Root block of segment {segment_index} doesn't match records root in the \
+                    blockchain"
+                )));
+            }
+        }
+
+        // Earlier segments only contain blocks that were imported already, no need to process them.
+        // Block that is partially contained in this segment might continue in the next one, so
+        // segment is only skipped if its last archived block is before the best block.
+        let last_archived_block_number: NumberFor<B> =
+            root_block.last_archived_block().number.into();
+        if last_archived_block_number < best_block_number {
+            continue;
+        }
+
+        let pieces_with_positions = pieces
+            .as_pieces()
+            .enumerate()
+            .map(|(position, piece)| (position as u32, piece))
+            .collect::<Vec<_>>();
+        if !is_pieces_valid_batch(
+            &kzg,
+            pieces_in_segment,
+            &pieces_with_positions,
+            root_block.records_root(),
+            RECORD_SIZE,
+        ) {
+            return Err(sc_service::Error::Other(format!(
+                "Pieces of segment {segment_index} don't match its root block"
+            )));
+        }
+
+        let pieces = pieces
+            .as_pieces()
+            .map(|piece| Piece::try_from(piece).map(Some))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let reconstructed_contents = reconstructor
+            .add_segment(&pieces)
+            .map_err(|error| sc_service::Error::Other(error.to_string()))?;
+
+        import_reconstructed_blocks(
+            reconstructed_contents.blocks,
+            client.as_ref(),
+            &mut import_queue,
+            &mut link,
+            best_block_number,
+            &mut imported_blocks,
+            force,
+        )
+        .await?;
+    }
+
+    info!(
+        "🎉 Imported {} blocks, best #{}, exiting",
+        imported_blocks,
+        client.info().best_number
+    );
+
+    Ok(())
+}
@@ -18,10 +18,15 @@
 
 mod chain_spec;
 mod chain_spec_utils;
+mod export_segments;
 mod import_blocks_from_dsn;
+mod import_blocks_from_segments;
 mod secondary_chain;
+mod segment_files;
 
+pub use crate::export_segments::ExportSegmentsCmd;
 pub use crate::import_blocks_from_dsn::ImportBlocksFromDsnCmd;
+pub use crate::import_blocks_from_segments::ImportBlocksFromSegmentsCmd;
 pub use crate::secondary_chain::cli::SecondaryChainCli;
 use clap::Parser;
 use sc_cli::{RunCmd, SubstrateCli};
@@ -31,6 +36,8 @@ use sc_subspace_chain_specs::ConsensusChainSpec;
 use sc_telemetry::serde_json;
 use serde_json::Value;
 use std::io::Write;
+use std::num::NonZeroU32;
+use std::path::PathBuf;
 use std::{fs, io};
 use subspace_networking::libp2p::Multiaddr;
 
@@ -141,6 +148,12 @@ pub enum Subcommand {
     /// Import blocks from Subspace Network DSN.
     ImportBlocksFromDsn(ImportBlocksFromDsnCmd),
 
+    /// Export archived history as segment files.
+    ExportSegments(ExportSegmentsCmd),
+
+    /// Import blocks from segment files produced by `export-segments`.
+    ImportBlocksFromSegments(ImportBlocksFromSegmentsCmd),
+
     /// Remove the whole chain.
     PurgeChain(PurgeChainCmd),
 
@@ -184,6 +197,34 @@ pub struct Cli {
     #[clap(long)]
     pub dsn_bootstrap_node: Vec<Multiaddr>,
 
+    /// DSN configuration arguments: upload rate limit of all DSN connections in bytes per second
+    #[clap(long)]
+    pub dsn_upload_rate_limit: Option<NonZeroU32>,
+
+    /// DSN configuration arguments: upload rate limit of serving pieces in bytes per second
+    #[clap(long)]
+    pub dsn_piece_upload_rate_limit: Option<NonZeroU32>,
+
+    /// DSN configuration arguments: discover peers in the local network with mDNS, useful for LAN
+    /// clusters and development setups
+    #[clap(long)]
+    pub dsn_enable_mdns: bool,
+
+    /// DSN configuration arguments: path to the file with private network pre-shared key, DSN
+    /// only connects to peers that use the same key
+    #[clap(long)]
+    pub dsn_pnet_psk: Option<PathBuf>,
+
+    /// DSN configuration arguments: sign published gossipsub messages with the identity keypair
+    /// of the node, so that peers can attribute messages to their origin
+    #[clap(long)]
+    pub dsn_gossipsub_message_signing: bool,
+
+    /// Path to the file with serialized KZG public parameters, must match parameters committed to
+    /// in chain constants, embedded parameters are used if not specified
+    #[clap(long)]
+    pub kzg_public_parameters: Option<PathBuf>,
+
     /// Secondary chain arguments
     ///
     /// The command-line arguments provided first will be passed to the embedded primary node,
@@ -0,0 +1,177 @@
+// Copyright (C) 2022 Subspace Labs, Inc.
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+// GNU General Public License for more details.
+
+// You should have received a copy of the GNU General Public License
+// along with this program. If not, see <https://www.gnu.org/licenses/>.
+
+//! File format for archived segments used by `export-segments` and `import-blocks-from-segments`
+//! commands.
+//!
+//! Every segment is stored in a separate file named `segment-{segment_index}.bin` (segment index
+//! is padded with zeroes to 20 digits) that has following layout:
+//! * 4 bytes of magic `SSEG`
+//! * 1 byte of format version, currently `0`
+//! * 32 bytes of BLAKE2b-256 checksum of the rest of the file
+//! * SCALE-encoded [`RootBlock`] of the segment
+//! * all pieces of the segment (both data and parity), [`PIECE_SIZE`] bytes each
+
+#[cfg(test)]
+mod tests;
+
+use parity_scale_codec::{Decode, Encode};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::{fs, io};
+use subspace_core_primitives::crypto::blake2b_256_hash;
+use subspace_core_primitives::{
+    FlatPieces, RootBlock, SegmentIndex, BLAKE2B_256_HASH_SIZE, PIECE_SIZE,
+};
+
+/// Magic bytes at the beginning of every segment file
+const SEGMENT_FILE_MAGIC: &[u8; 4] = b"SSEG";
+/// Current version of the segment file format
+const SEGMENT_FILE_VERSION: u8 = 0;
+/// Size of the segment file header: magic, version and checksum
+const SEGMENT_FILE_HEADER_SIZE: usize = SEGMENT_FILE_MAGIC.len() + 1 + BLAKE2B_256_HASH_SIZE;
+
+/// Errors that might happen during reading or writing of segment files
+#[derive(Debug, thiserror::Error)]
+pub(crate) enum SegmentFileError {
+    /// I/O error
+    #[error("I/O error: {0}")]
+    Io(#[from] io::Error),
+    /// Invalid segment file header
+    #[error("Invalid segment file header")]
+    InvalidHeader,
+    /// Unsupported segment file format version
+    #[error("Unsupported segment file format version {0}")]
+    UnsupportedFormatVersion(u8),
+    /// Checksum mismatch, file is corrupted
+    #[error("Segment file checksum mismatch")]
+    ChecksumMismatch,
+    /// Failed to decode root block
+    #[error("Failed to decode root block: {0}")]
+    RootBlockDecoding(parity_scale_codec::Error),
+    /// Unexpected number of pieces in segment file
+    #[error("Expected {expected} pieces in segment file, found {actual} bytes of pieces")]
+    UnexpectedPiecesSize { expected: u32, actual: usize },
+}
+
+/// Path of the file for segment with specified index in `directory`
+pub(crate) fn segment_file_path(directory: &Path, segment_index: SegmentIndex) -> PathBuf {
+    directory.join(format!("segment-{segment_index:020}.bin"))
+}
+
+/// Encode segment into segment file contents
+pub(crate) fn encode_segment_file(root_block: &RootBlock, pieces: &FlatPieces) -> Vec<u8> {
+    let mut payload = root_block.encode();
+    payload.extend_from_slice(pieces);
+
+    let mut bytes = Vec::with_capacity(SEGMENT_FILE_HEADER_SIZE + payload.len());
+    bytes.extend_from_slice(SEGMENT_FILE_MAGIC);
+    bytes.push(SEGMENT_FILE_VERSION);
+    bytes.extend_from_slice(&blake2b_256_hash(&payload));
+    bytes.extend_from_slice(&payload);
+
+    bytes
+}
+
+/// Decode segment file contents, checking that it contains `pieces_in_segment` pieces
+pub(crate) fn decode_segment_file(
+    bytes: &[u8],
+    pieces_in_segment: u32,
+) -> Result<(RootBlock, FlatPieces), SegmentFileError> {
+    if bytes.len() < SEGMENT_FILE_HEADER_SIZE || !bytes.starts_with(SEGMENT_FILE_MAGIC) {
+        return Err(SegmentFileError::InvalidHeader);
+    }
+
+    let (header, payload) = bytes.split_at(SEGMENT_FILE_HEADER_SIZE);
+    let version = header[SEGMENT_FILE_MAGIC.len()];
+    if version != SEGMENT_FILE_VERSION {
+        return Err(SegmentFileError::UnsupportedFormatVersion(version));
+    }
+
+    let checksum = &header[SEGMENT_FILE_MAGIC.len() + 1..];
+    if checksum != blake2b_256_hash(payload).as_slice() {
+        return Err(SegmentFileError::ChecksumMismatch);
+    }
+
+    let mut pieces = payload;
+    let root_block = RootBlock::decode(&mut pieces).map_err(SegmentFileError::RootBlockDecoding)?;
+
+    if pieces.len() != pieces_in_segment as usize * PIECE_SIZE {
+        return Err(SegmentFileError::UnexpectedPiecesSize {
+            expected: pieces_in_segment,
+            actual: pieces.len(),
+        });
+    }
+
+    let pieces = FlatPieces::try_from(pieces.to_vec())
+        .expect("Length was checked to be a multiple of piece size above; qed");
+
+    Ok((root_block, pieces))
+}
+
+/// Write segment file into `directory`, file is written atomically to make sure partially written
+/// files are never observed.
+pub(crate) fn write_segment_file(
+    directory: &Path,
+    root_block: &RootBlock,
+    pieces: &FlatPieces,
+) -> Result<(), SegmentFileError> {
+    let path = segment_file_path(directory, root_block.segment_index());
+    let tmp_path = path.with_extension("tmp");
+
+    fs::write(&tmp_path, encode_segment_file(root_block, pieces))?;
+    fs::rename(&tmp_path, &path)?;
+
+    Ok(())
+}
+
+/// Read segment file for segment with specified index from `directory`, returns `Ok(None)` if file
+/// doesn't exist.
+pub(crate) fn read_segment_file(
+    directory: &Path,
+    segment_index: SegmentIndex,
+    pieces_in_segment: u32,
+) -> Result<Option<(RootBlock, FlatPieces)>, SegmentFileError> {
+    let bytes = match fs::read(segment_file_path(directory, segment_index)) {
+        Ok(bytes) => bytes,
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            return Ok(None);
+        }
+        Err(error) => {
+            return Err(error.into());
+        }
+    };
+
+    decode_segment_file(&bytes, pieces_in_segment).map(Some)
+}
+
+/// Index of the last segment file in `directory` among files of consecutive segments starting with
+/// segment `0`, returns `Ok(None)` if there is no file for segment `0`.
+pub(crate) fn last_segment_file_index(
+    directory: &Path,
+) -> Result<Option<SegmentIndex>, SegmentFileError> {
+    let mut last_segment_index = None;
+
+    for segment_index in 0.. {
+        if !segment_file_path(directory, segment_index).try_exists()? {
+            break;
+        }
+
+        last_segment_index.replace(segment_index);
+    }
+
+    Ok(last_segment_index)
+}
@@ -0,0 +1,144 @@
+use crate::segment_files::{
+    decode_segment_file, encode_segment_file, last_segment_file_index, read_segment_file,
+    write_segment_file, SegmentFileError,
+};
+use std::path::Path;
+use subspace_core_primitives::{
+    ArchivedBlockProgress, FlatPieces, LastArchivedBlock, RootBlock, PIECE_SIZE,
+};
+
+const PIECES_IN_SEGMENT: u32 = 4;
+
+fn test_segment() -> (RootBlock, FlatPieces) {
+    let root_block = RootBlock::V0 {
+        segment_index: 1,
+        records_root: Default::default(),
+        prev_root_block_hash: [1; 32],
+        last_archived_block: LastArchivedBlock {
+            number: 10,
+            archived_progress: ArchivedBlockProgress::Partial(100),
+        },
+    };
+    let pieces = FlatPieces::try_from(
+        (0..PIECES_IN_SEGMENT as usize * PIECE_SIZE)
+            .map(|i| i as u8)
+            .collect::<Vec<u8>>(),
+    )
+    .unwrap();
+
+    (root_block, pieces)
+}
+
+#[test]
+fn segment_file_round_trip() {
+    let (root_block, pieces) = test_segment();
+    let bytes = encode_segment_file(&root_block, &pieces);
+
+    let (decoded_root_block, decoded_pieces) =
+        decode_segment_file(&bytes, PIECES_IN_SEGMENT).unwrap();
+    assert_eq!(decoded_root_block, root_block);
+    assert_eq!(decoded_pieces, pieces);
+}
+
+#[test]
+fn segment_file_invalid() {
+    let (root_block, pieces) = test_segment();
+    let bytes = encode_segment_file(&root_block, &pieces);
+
+    assert!(matches!(
+        decode_segment_file(&bytes[..3], PIECES_IN_SEGMENT),
+        Err(SegmentFileError::InvalidHeader)
+    ));
+
+    {
+        let mut bytes = bytes.clone();
+        bytes[0] = b'X';
+        assert!(matches!(
+            decode_segment_file(&bytes, PIECES_IN_SEGMENT),
+            Err(SegmentFileError::InvalidHeader)
+        ));
+    }
+
+    {
+        let mut bytes = bytes.clone();
+        bytes[4] = 1;
+        assert!(matches!(
+            decode_segment_file(&bytes, PIECES_IN_SEGMENT),
+            Err(SegmentFileError::UnsupportedFormatVersion(1))
+        ));
+    }
+
+    {
+        let mut bytes = bytes.clone();
+        *bytes.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            decode_segment_file(&bytes, PIECES_IN_SEGMENT),
+            Err(SegmentFileError::ChecksumMismatch)
+        ));
+    }
+
+    assert!(matches!(
+        decode_segment_file(&bytes, PIECES_IN_SEGMENT * 2),
+        Err(SegmentFileError::UnexpectedPiecesSize { .. })
+    ));
+}
+
+#[test]
+fn missing_segment_file() {
+    assert!(matches!(
+        read_segment_file(
+            Path::new("/definitely/does/not/exist"),
+            0,
+            PIECES_IN_SEGMENT
+        ),
+        Ok(None)
+    ));
+}
+
+#[test]
+fn last_segment_file() {
+    let directory = tempfile::tempdir().unwrap();
+    let (root_block, pieces) = test_segment();
+
+    assert!(matches!(
+        last_segment_file_index(directory.path()),
+        Ok(None)
+    ));
+
+    let write_segment = |segment_index| {
+        let root_block = match root_block {
+            RootBlock::V0 {
+                records_root,
+                prev_root_block_hash,
+                last_archived_block,
+                ..
+            } => RootBlock::V0 {
+                segment_index,
+                records_root,
+                prev_root_block_hash,
+                last_archived_block,
+            },
+        };
+        write_segment_file(directory.path(), &root_block, &pieces).unwrap();
+    };
+
+    // Segment `0` must be present
+    write_segment(1);
+    assert!(matches!(
+        last_segment_file_index(directory.path()),
+        Ok(None)
+    ));
+
+    write_segment(0);
+    assert!(matches!(
+        last_segment_file_index(directory.path()),
+        Ok(Some(1))
+    ));
+
+    // Only consecutive segments are considered
+    write_segment(3);
+    assert!(matches!(
+        last_segment_file_index(directory.path()),
+        Ok(Some(1))
+    ));
+}
@@ -17,6 +17,7 @@
 
 use serde::{Deserialize, Serialize};
 use std::num::{NonZeroU16, NonZeroU32, NonZeroU64};
+use subspace_core_primitives::crypto::kzg::PublicParametersInfo;
 use subspace_core_primitives::{
     Blake2b256Hash, PublicKey, RewardSignature, SegmentIndex, SlotNumber, Solution, SolutionRange,
 };
@@ -41,6 +42,8 @@ pub struct FarmerProtocolInfo {
     pub space_l: NonZeroU16,
     /// Number of segments after which sector expires
     pub sector_expiration: SegmentIndex,
+    /// KZG public parameters used for piece verification
+    pub kzg_public_parameters: PublicParametersInfo,
 }
 
 /// Information about new slot that just arrived
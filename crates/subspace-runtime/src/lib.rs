@@ -89,7 +89,7 @@ pub const VERSION: RuntimeVersion = RuntimeVersion {
     spec_name: create_runtime_str!("subspace"),
     impl_name: create_runtime_str!("subspace"),
     authoring_version: 0,
-    spec_version: 4,
+    spec_version: 5,
     impl_version: 0,
     apis: RUNTIME_API_VERSIONS,
     transaction_version: 0,
@@ -605,9 +605,9 @@ fn extrinsics_shuffling_seed<Block: BlockT>(header: Block::Header) -> Randomness
 
         let seed: &[u8] = b"extrinsics-shuffling-seed";
         let randomness = derive_randomness(
-            &Into::<PublicKey>::into(&pre_digest.solution.public_key),
-            &pre_digest.solution.chunk,
-            &pre_digest.solution.chunk_signature,
+            &Into::<PublicKey>::into(pre_digest.solution.public_key()),
+            &pre_digest.solution.chunk(),
+            &pre_digest.solution.chunk_signature(),
         )
         .expect("Tag signature is verified by the client and must always be valid; qed");
         let mut data = Vec::with_capacity(seed.len() + randomness.len());
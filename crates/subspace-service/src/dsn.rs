@@ -2,17 +2,27 @@ mod piece_record_store;
 
 use crate::dsn::piece_record_store::{AuxRecordStorage, SegmentIndexGetter};
 use futures::StreamExt;
+use parking_lot::Mutex;
 use sc_consensus_subspace::{ArchivedSegmentNotification, SubspaceLink};
-use sc_piece_cache::AuxPieceCache;
+use sc_piece_cache::{AuxPieceCache, PieceCache, MAX_SEGMENTS_NUMBER_IN_CACHE};
 use sp_core::traits::SpawnEssentialNamed;
 use sp_runtime::traits::Block as BlockT;
+use std::collections::BTreeMap;
+use std::num::NonZeroU32;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::sync::Arc;
-use subspace_core_primitives::{Piece, PieceIndex, PieceIndexHash, PIECES_IN_SEGMENT};
+use subspace_core_primitives::{
+    Piece, PieceIndex, PieceIndexHash, SegmentIndex, PIECES_IN_SEGMENT,
+};
 use subspace_networking::libp2p::{identity, Multiaddr};
 use subspace_networking::{
-    BootstrappedNetworkingParameters, CreationError, CustomRecordStore, MemoryProviderStorage,
-    PieceByHashRequestHandler, PieceByHashResponse, PieceKey, ToMultihash,
+    BootstrappedNetworkingParameters, CreationError, CustomRecordStore, InboundRequestLimits,
+    MemoryProviderStorage, PeerInfo, PeerRole, PeerSyncStatus, PieceByHashRequestHandler,
+    PieceByHashResponse, PieceKey, PieceKeyRange, PiecesByRangeRequestHandler,
+    PiecesByRangeResponse, ToMultihash, PIECES_BY_RANGE_PAGE_SIZE,
 };
+use substrate_prometheus_endpoint::Registry as PrometheusRegistry;
 use tracing::{debug, info, trace, Instrument};
 
 pub type PieceGetter = Arc<dyn (Fn(&PieceIndex) -> Option<Piece>) + Send + Sync + 'static>;
@@ -28,6 +38,65 @@ pub struct DsnConfig {
 
     /// Identity keypair of a node used for authenticated connections.
     pub keypair: identity::Keypair,
+
+    /// Upload rate limit of all DSN connections in bytes per second.
+    pub upload_rate_limit: Option<NonZeroU32>,
+
+    /// Upload rate limit of serving pieces in bytes per second.
+    pub piece_upload_rate_limit: Option<NonZeroU32>,
+
+    /// Discover peers in the local network with mDNS.
+    pub enable_mdns: bool,
+
+    /// Path to the file with private network pre-shared key.
+    pub pnet_psk_path: Option<PathBuf>,
+
+    /// Sign published gossipsub messages with the identity keypair.
+    pub gossipsub_message_signing: bool,
+}
+
+/// Index of pieces in the piece cache by piece index hash, used to serve pieces-by-range requests.
+#[derive(Debug, Default)]
+struct PieceCacheIndex {
+    /// Segments whose pieces are indexed
+    segment_indexes: Option<RangeInclusive<SegmentIndex>>,
+    pieces: BTreeMap<PieceIndexHash, PieceIndex>,
+}
+
+impl PieceCacheIndex {
+    /// Updates index to contain pieces of the same recent segments as the piece cache.
+    fn update(&mut self, last_segment_index: SegmentIndex) {
+        let first_segment_index = last_segment_index.saturating_sub(MAX_SEGMENTS_NUMBER_IN_CACHE);
+        let segment_indexes = first_segment_index..=last_segment_index;
+
+        if self.segment_indexes.as_ref() == Some(&segment_indexes) {
+            return;
+        }
+
+        let first_piece_index = first_segment_index * PieceIndex::from(PIECES_IN_SEGMENT);
+        self.pieces
+            .retain(|_piece_index_hash, piece_index| *piece_index >= first_piece_index);
+
+        for segment_index in segment_indexes.clone() {
+            let already_indexed = self
+                .segment_indexes
+                .as_ref()
+                .map(|indexed_segment_indexes| indexed_segment_indexes.contains(&segment_index))
+                .unwrap_or_default();
+            if already_indexed {
+                continue;
+            }
+
+            let first_piece_index = segment_index * PieceIndex::from(PIECES_IN_SEGMENT);
+            self.pieces.extend(
+                (first_piece_index..)
+                    .take(PIECES_IN_SEGMENT as usize)
+                    .map(|piece_index| (PieceIndexHash::from_index(piece_index), piece_index)),
+            );
+        }
+
+        self.segment_indexes.replace(segment_indexes);
+    }
 }
 
 /// Start an archiver that will listen for archived segments and send it to DSN network using
@@ -39,6 +108,8 @@ pub async fn start_dsn_node<Block, Spawner, AS: sc_client_api::AuxStore + Sync +
     piece_cache: AuxPieceCache<AS>,
     piece_getter: PieceGetter,
     segment_index_getter: SegmentIndexGetter,
+    prometheus_registry: Option<PrometheusRegistry>,
+    genesis_hash: [u8; 32],
 ) -> Result<(), CreationError>
 where
     Block: BlockT,
@@ -47,6 +118,61 @@ where
     let span = tracing::info_span!(sc_tracing::logging::PREFIX_LOG_SPAN, name = "DSN");
     let _enter = span.enter();
 
+    let pieces_by_range_request_handler = {
+        let piece_cache = piece_cache.clone();
+        let segment_index_getter = Arc::clone(&segment_index_getter);
+        let piece_cache_index = Mutex::new(PieceCacheIndex::default());
+
+        PiecesByRangeRequestHandler::create_with_limits(
+            InboundRequestLimits {
+                max_response_bytes_per_second: dsn_config.piece_upload_rate_limit,
+                ..PiecesByRangeRequestHandler::DEFAULT_INBOUND_LIMITS
+            },
+            move |request| {
+                if request.start > request.end {
+                    debug!(?request, "Incorrect pieces-by-range request - empty range.");
+                    return None;
+                }
+
+                let pieces_in_range = {
+                    let mut piece_cache_index = piece_cache_index.lock();
+                    // Only pieces of the most recent segments are present in the cache.
+                    piece_cache_index.update(segment_index_getter());
+
+                    piece_cache_index
+                        .pieces
+                        .range(request.start..=request.end)
+                        // One more piece is necessary for the cursor of the next page
+                        .take(PIECES_BY_RANGE_PAGE_SIZE + 1)
+                        .map(|(piece_index_hash, piece_index)| (*piece_index_hash, *piece_index))
+                        .collect::<Vec<_>>()
+                };
+
+                Some(PiecesByRangeResponse::from_sorted_pieces(
+                    pieces_in_range,
+                    |piece_index| {
+                        let piece = piece_cache.get_piece(piece_index).ok().flatten()?;
+
+                        Some((piece_index, piece))
+                    },
+                ))
+            },
+        )
+    };
+
+    let peer_info_provider = {
+        let segment_index_getter = Arc::clone(&segment_index_getter);
+
+        Arc::new(move || PeerInfo {
+            status: PeerSyncStatus::Ready,
+            // TODO: Advertise actual key ranges once the piece cache is limited by key space
+            cached_key_ranges: vec![PieceKeyRange::full()],
+            genesis_hash: Some(genesis_hash),
+            best_segment_index: Some(segment_index_getter()),
+            ..PeerInfo::new(PeerRole::Node)
+        })
+    };
+
     let record_storage = AuxRecordStorage::new(piece_cache, segment_index_getter);
 
     trace!("Subspace networking starting.");
@@ -61,17 +187,32 @@ where
             dsn_config.dsn_bootstrap_node,
         )
         .boxed(),
-        request_response_protocols: vec![PieceByHashRequestHandler::create(move |req| {
-            let result = if let PieceKey::PieceIndex(idx) = req.key {
-                piece_getter(&idx)
-            } else {
-                debug!(key=?req.key, "Incorrect piece request - unsupported key type.");
+        request_response_protocols: vec![
+            PieceByHashRequestHandler::create_with_limits(
+                InboundRequestLimits {
+                    max_response_bytes_per_second: dsn_config.piece_upload_rate_limit,
+                    ..PieceByHashRequestHandler::DEFAULT_INBOUND_LIMITS
+                },
+                move |req| {
+                    let result = if let PieceKey::PieceIndex(idx) = req.key {
+                        piece_getter(&idx)
+                    } else {
+                        debug!(key=?req.key, "Incorrect piece request - unsupported key type.");
 
-                None
-            };
+                        None
+                    };
 
-            Some(PieceByHashResponse { piece: result })
-        })],
+                    Some(PieceByHashResponse { piece: result })
+                },
+            ),
+            pieces_by_range_request_handler,
+        ],
+        upload_rate_limit: dsn_config.upload_rate_limit,
+        enable_mdns: dsn_config.enable_mdns,
+        pnet_psk_path: dsn_config.pnet_psk_path,
+        gossipsub_message_signing: dsn_config.gossipsub_message_signing,
+        prometheus_registry,
+        peer_info_provider: Some(peer_info_provider),
         record_store: CustomRecordStore::new(record_storage, MemoryProviderStorage::default()),
         ..subspace_networking::Config::with_generated_keypair()
     };
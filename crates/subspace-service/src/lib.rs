@@ -59,6 +59,7 @@ use sp_runtime::traits::{Block as BlockT, BlockIdTo};
 use sp_session::SessionKeys;
 use sp_transaction_pool::runtime_api::TaggedTransactionQueue;
 use std::sync::Arc;
+use subspace_core_primitives::crypto::kzg;
 use subspace_core_primitives::PIECES_IN_SEGMENT;
 use subspace_fraud_proof::VerifyFraudProof;
 use subspace_runtime_primitives::opaque::Block;
@@ -127,6 +128,8 @@ pub struct SubspaceConfiguration {
     pub force_new_slot_notifications: bool,
     /// Subspace networking configuration (for DSN). Will not be started if set to `None`.
     pub dsn_config: Option<DsnConfig>,
+    /// Serialized KZG public parameters, embedded ones are used if set to `None`.
+    pub kzg_public_parameters: Option<Vec<u8>>,
 }
 
 impl From<Configuration> for SubspaceConfiguration {
@@ -135,14 +138,18 @@ impl From<Configuration> for SubspaceConfiguration {
             base,
             force_new_slot_notifications: false,
             dsn_config: None,
+            kzg_public_parameters: None,
         }
     }
 }
 
 /// Creates `PartialComponents` for Subspace client.
+///
+/// Embedded KZG public parameters are used unless `kzg_public_parameters` are specified.
 #[allow(clippy::type_complexity)]
 pub fn new_partial<RuntimeApi, ExecutorDispatch>(
     config: &Configuration,
+    kzg_public_parameters: Option<&[u8]>,
 ) -> Result<
     PartialComponents<
         FullClient<RuntimeApi, ExecutorDispatch>,
@@ -237,6 +244,7 @@ where
 
     let (block_import, subspace_link) = sc_consensus_subspace::block_import(
         sc_consensus_subspace::Config::get(&*client)?,
+        kzg_public_parameters.unwrap_or(kzg::EMBEDDED_PUBLIC_PARAMETERS),
         fraud_proof_block_import,
         client.clone(),
         CanAuthorWithNativeVersion::new(client.executor().clone()),
@@ -409,7 +417,10 @@ where
         select_chain,
         transaction_pool,
         other: (block_import, subspace_link, piece_cache, mut telemetry),
-    } = new_partial::<RuntimeApi, ExecutorDispatch>(&config)?;
+    } = new_partial::<RuntimeApi, ExecutorDispatch>(
+        &config,
+        config.kzg_public_parameters.as_deref(),
+    )?;
 
     if let Some(dsn_config) = config.dsn_config.clone() {
         let piece_cache = piece_cache.clone();
@@ -433,6 +444,8 @@ where
                     (total_pieces.get() / PIECES_IN_SEGMENT as u64).saturating_sub(1)
                 }
             }),
+            config.prometheus_registry().cloned(),
+            client.info().genesis_hash.into(),
         )
         .await?;
     }
@@ -20,6 +20,13 @@
 #![warn(rust_2018_idioms, missing_debug_implementations, missing_docs)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(test)]
+mod tests;
+
+extern crate alloc;
+
+use alloc::vec;
+use core::num::NonZeroU16;
 use merlin::Transcript;
 use schnorrkel::vrf::{VRFInOut, VRFOutput, VRFProof};
 use schnorrkel::{Keypair, PublicKey, SignatureResult};
@@ -27,6 +34,10 @@ use subspace_core_primitives::crypto::blake2b_256_hash_list;
 use subspace_core_primitives::{Blake2b256Hash, Chunk, ChunkSignature, Randomness, SectorId};
 
 const CHUNK_SIGNATURE_LABEL: &[u8] = b"subspace_chunk_signature";
+// Number of bits in one-time pad produced by `derive_chunk_otp()`
+const CHUNK_OTP_BITS: usize = u64::BITS as usize;
+// Size of the word one-time pads are written into and records are XOR-ed with
+const WORD_SIZE: usize = core::mem::size_of::<u128>();
 
 /// Signing context used for creating reward signatures by farmers.
 pub const REWARD_SIGNING_CONTEXT: &[u8] = b"subspace_reward";
@@ -87,3 +98,91 @@ pub fn derive_chunk_otp(
         hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7],
     ]
 }
+
+/// Encode record in place by XOR-ing each `space_l`-bit chunk (bits are in LSB0 order) with
+/// one-time pad derived with [`derive_chunk_otp()`], the result can be decoded with
+/// [`decode_record()`].
+///
+/// Only first 64 bits of each chunk are encoded if `space_l` is larger than that and the last
+/// chunk is encoded partially if record size is not multiple of `space_l`.
+pub fn encode_record(
+    record: &mut [u8],
+    sector_id: &SectorId,
+    piece_witness_bytes: &[u8],
+    space_l: NonZeroU16,
+) {
+    xor_record_with_chunk_otps(record, sector_id, piece_witness_bytes, space_l);
+}
+
+/// Decode record in place that was previously encoded with [`encode_record()`].
+pub fn decode_record(
+    record: &mut [u8],
+    sector_id: &SectorId,
+    piece_witness_bytes: &[u8],
+    space_l: NonZeroU16,
+) {
+    xor_record_with_chunk_otps(record, sector_id, piece_witness_bytes, space_l);
+}
+
+// One-time pads of all chunks are combined into a single pad for the whole record one word at a
+// time, after which the record is XOR-ed with the pad in 128-bit words instead of bit by bit.
+// One-time pads are still derived with one BLAKE2b hash per chunk, there is no explicit SIMD code
+// here.
+fn xor_record_with_chunk_otps(
+    record: &mut [u8],
+    sector_id: &SectorId,
+    piece_witness_bytes: &[u8],
+    space_l: NonZeroU16,
+) {
+    let space_l = usize::from(space_l.get());
+    let record_bits = record.len() * u8::BITS as usize;
+    // Extra word at the end such that writing a word at any chunk offset never goes out of bounds
+    let mut pad = vec![0u8; record.len() + WORD_SIZE];
+
+    for (chunk_index, chunk_offset) in (0..record_bits).step_by(space_l).enumerate() {
+        let otp_bits = space_l.min(CHUNK_OTP_BITS).min(record_bits - chunk_offset);
+        let otp = u64::from_le_bytes(derive_chunk_otp(
+            sector_id,
+            piece_witness_bytes,
+            chunk_index as u32,
+        ));
+        let otp = if otp_bits == CHUNK_OTP_BITS {
+            otp
+        } else {
+            otp & ((1 << otp_bits) - 1)
+        };
+
+        let pad_word = &mut pad[chunk_offset / u8::BITS as usize..][..WORD_SIZE];
+        let otp_word = u128::from(otp) << (chunk_offset % u8::BITS as usize);
+        pad_word.copy_from_slice(
+            &(u128::from_le_bytes(
+                (&*pad_word)
+                    .try_into()
+                    .expect("Slice has exactly one word; qed"),
+            ) | otp_word)
+                .to_le_bytes(),
+        );
+    }
+
+    let mut record_words = record.chunks_exact_mut(WORD_SIZE);
+    let mut pad_words = pad.chunks_exact(WORD_SIZE);
+    for (record_word, pad_word) in (&mut record_words).zip(&mut pad_words) {
+        let word = u128::from_le_bytes(
+            (&*record_word)
+                .try_into()
+                .expect("Slice has exactly one word; qed"),
+        ) ^ u128::from_le_bytes(
+            pad_word
+                .try_into()
+                .expect("Slice has exactly one word; qed"),
+        );
+        record_word.copy_from_slice(&word.to_le_bytes());
+    }
+    record_words
+        .into_remainder()
+        .iter_mut()
+        .zip(pad_words.next().unwrap_or_default())
+        .for_each(|(record_byte, pad_byte)| {
+            *record_byte ^= pad_byte;
+        });
+}
@@ -0,0 +1,106 @@
+use crate::{decode_record, derive_chunk_otp, encode_record};
+use bitvec::prelude::*;
+use quickcheck::{Arbitrary, Gen, QuickCheck};
+use std::num::NonZeroU16;
+use subspace_core_primitives::{PublicKey, SectorId};
+
+// Record size and `space_l` used by the protocol
+const PROTOCOL_RECORD_SIZE: usize = 3840;
+const PROTOCOL_SPACE_L: u16 = 20;
+
+// Bit-by-bit encoding that was used before word-level implementation
+fn encode_record_reference(
+    record: &mut [u8],
+    sector_id: &SectorId,
+    piece_witness_bytes: &[u8],
+    space_l: NonZeroU16,
+) {
+    record
+        .view_bits_mut::<Lsb0>()
+        .chunks_mut(space_l.get() as usize)
+        .enumerate()
+        .for_each(|(chunk_index, bits)| {
+            // Derive one-time pad
+            let mut otp = derive_chunk_otp(sector_id, piece_witness_bytes, chunk_index as u32);
+            // XOR chunk bit by bit with one-time pad
+            bits.iter_mut()
+                .zip(otp.view_bits_mut::<Lsb0>().iter())
+                .for_each(|(mut a, b)| {
+                    *a ^= *b;
+                });
+        });
+}
+
+#[derive(Debug, Clone)]
+struct EncodingInput {
+    sector_id: SectorId,
+    piece_witness_bytes: Vec<u8>,
+    space_l: NonZeroU16,
+    record: Vec<u8>,
+}
+
+impl Arbitrary for EncodingInput {
+    fn arbitrary(g: &mut Gen) -> Self {
+        let public_key = PublicKey::from([0u8; 32].map(|_| u8::arbitrary(g)));
+
+        Self {
+            sector_id: SectorId::new(&public_key, u64::arbitrary(g)),
+            piece_witness_bytes: [0u8; 48].map(|_| u8::arbitrary(g)).to_vec(),
+            space_l: NonZeroU16::new(u16::arbitrary(g) % 80 + 1)
+                .expect("Always in 1..=80 range; qed"),
+            record: Vec::arbitrary(g),
+        }
+    }
+}
+
+fn encoding_matches_reference_and_round_trips(input: EncodingInput) -> bool {
+    let EncodingInput {
+        sector_id,
+        piece_witness_bytes,
+        space_l,
+        record,
+    } = input;
+
+    let mut encoded = record.clone();
+    encode_record(&mut encoded, &sector_id, &piece_witness_bytes, space_l);
+
+    let mut encoded_reference = record.clone();
+    encode_record_reference(
+        &mut encoded_reference,
+        &sector_id,
+        &piece_witness_bytes,
+        space_l,
+    );
+
+    let mut decoded = encoded.clone();
+    decode_record(&mut decoded, &sector_id, &piece_witness_bytes, space_l);
+
+    encoded == encoded_reference && decoded == record
+}
+
+#[test]
+fn record_encoding_matches_reference() {
+    QuickCheck::new()
+        .tests(1000)
+        .gen(Gen::new(300))
+        .quickcheck(encoding_matches_reference_and_round_trips as fn(EncodingInput) -> bool);
+}
+
+#[test]
+fn record_encoding_matches_reference_for_protocol_parameters() {
+    fn prop(input: EncodingInput) -> bool {
+        let mut g = Gen::new(PROTOCOL_RECORD_SIZE);
+
+        encoding_matches_reference_and_round_trips(EncodingInput {
+            space_l: NonZeroU16::new(PROTOCOL_SPACE_L).expect("Not zero; qed"),
+            record: (0..PROTOCOL_RECORD_SIZE)
+                .map(|_| u8::arbitrary(&mut g))
+                .collect(),
+            ..input
+        })
+    }
+
+    QuickCheck::new()
+        .tests(10)
+        .quickcheck(prop as fn(EncodingInput) -> bool);
+}
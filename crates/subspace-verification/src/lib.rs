@@ -78,9 +78,9 @@ where
     if !archiver::is_piece_record_hash_valid(
         kzg,
         pieces_in_segment,
-        &solution.piece_record_hash,
+        &solution.piece_record_hash(),
         records_root,
-        &solution.piece_witness,
+        &solution.piece_witness(),
         position,
     ) {
         return Err(Error::InvalidPiece);
@@ -140,22 +140,22 @@ where
         piece_check_params,
     } = params;
 
-    let public_key = PublicKey::from(&solution.public_key);
+    let public_key = PublicKey::from(solution.public_key());
 
-    let sector_id = SectorId::new(&public_key, solution.sector_index);
+    let sector_id = SectorId::new(&public_key, solution.sector_index());
 
     let local_challenge =
         sector_id.derive_local_challenge(&derive_global_challenge(global_randomness, slot));
 
-    let expanded_chunk = solution.chunk.expand(local_challenge);
+    let expanded_chunk = solution.chunk().expand(local_challenge);
 
     if !is_within_solution_range(local_challenge, expanded_chunk, solution_range) {
         return Err(Error::OutsideSolutionRange);
     }
 
     if let Err(error) = verify_chunk_signature(
-        &solution.chunk,
-        &solution.chunk_signature,
+        &solution.chunk(),
+        &solution.chunk_signature(),
         &schnorrkel::PublicKey::from_bytes(public_key.as_ref())
             .expect("Always correct length; qed"),
     ) {
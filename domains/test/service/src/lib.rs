@@ -119,6 +119,7 @@ async fn run_executor(
             // Always enable the slot notification.
             force_new_slot_notifications: true,
             dsn_config: None,
+            kzg_public_parameters: None,
         };
 
         subspace_service::new_full::<
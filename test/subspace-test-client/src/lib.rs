@@ -36,12 +36,12 @@ use subspace_core_primitives::crypto::kzg::{Kzg, Witness};
 use subspace_core_primitives::crypto::{blake2b_256_254_hash, kzg};
 use subspace_core_primitives::objects::BlockObjectMapping;
 use subspace_core_primitives::{
-    Chunk, Piece, PieceIndex, PublicKey, SectorId, Solution, PIECE_SIZE,
+    Chunk, Piece, PieceIndex, PublicKey, SectorId, Solution, SolutionV0, PIECE_SIZE,
     RECORDED_HISTORY_SEGMENT_SIZE, RECORD_SIZE,
 };
 use subspace_runtime_primitives::opaque::Block;
 use subspace_service::{FullClient, NewFull};
-use subspace_solving::{create_chunk_signature, derive_chunk_otp, REWARD_SIGNING_CONTEXT};
+use subspace_solving::{create_chunk_signature, encode_record, REWARD_SIGNING_CONTEXT};
 use zeroize::Zeroizing;
 
 /// Subspace native executor instance.
@@ -172,22 +172,7 @@ async fn start_farming<Client>(
                 Witness::try_from_bytes((&*witness_bytes).try_into().unwrap()).unwrap();
             let piece_record_hash = blake2b_256_254_hash(record);
 
-            // TODO: Extract encoding into separate function reusable in
-            //  farmer and otherwise
-            record
-                .view_bits_mut::<Lsb0>()
-                .chunks_mut(space_l.get() as usize)
-                .enumerate()
-                .for_each(|(chunk_index, bits)| {
-                    // Derive one-time pad
-                    let mut otp = derive_chunk_otp(&sector_id, witness_bytes, chunk_index as u32);
-                    // XOR chunk bit by bit with one-time pad
-                    bits.iter_mut()
-                        .zip(otp.view_bits_mut::<Lsb0>().iter())
-                        .for_each(|(mut a, b)| {
-                            *a ^= *b;
-                        });
-                });
+            encode_record(record, &sector_id, witness_bytes, space_l);
 
             // TODO: We are skipping witness part of the piece or else it is not
             //  decodable
@@ -208,7 +193,7 @@ async fn start_farming<Client>(
             let chunk_signature = create_chunk_signature(&keypair, &chunk);
 
             let _ = solution_sender
-                .send(Solution {
+                .send(Solution::V0(SolutionV0 {
                     public_key: FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
                     reward_address: FarmerPublicKey::unchecked_from(keypair.public.to_bytes()),
                     sector_index,
@@ -218,7 +203,7 @@ async fn start_farming<Client>(
                     piece_witness,
                     chunk,
                     chunk_signature,
-                })
+                }))
                 .await;
         }
     }
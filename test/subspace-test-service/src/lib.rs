@@ -187,6 +187,7 @@ pub async fn run_validator_node(
             base: primary_chain_config,
             force_new_slot_notifications: true,
             dsn_config: None,
+            kzg_public_parameters: None,
         };
 
         subspace_service::new_full::<subspace_test_runtime::RuntimeApi, TestExecutorDispatch>(